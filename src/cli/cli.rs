@@ -1,6 +1,8 @@
+#[cfg(feature = "encryption")]
+use crate::cli::commands::DecryptCommand;
 #[cfg(feature = "tui")]
 use crate::cli::commands::TuiCommand;
-use crate::cli::commands::{DiscoverCommand, ReceiveCommand, SendCommand};
+use crate::cli::commands::{BenchCommand, DiscoverCommand, ReceiveCommand, SendCommand};
 use clap::{Parser, Subcommand};
 
 #[derive(Parser, Debug)]
@@ -17,4 +19,9 @@ pub enum Commands {
     Send(SendCommand),
     #[cfg(feature = "tui")]
     Tui(TuiCommand),
+    #[cfg(feature = "encryption")]
+    Decrypt(DecryptCommand),
+    /// Diagnostics: time a generated upload to a peer.
+    #[command(hide = true)]
+    Bench(BenchCommand),
 }
@@ -0,0 +1,167 @@
+use super::send::{build_client_for_target, default_sender, resolve_target};
+use crate::DeviceInfo;
+use crate::client::LocalSendClient;
+use crate::protocol::{FileId, FileMetadata};
+use clap::Parser;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "bench",
+    about = "Measure LAN throughput by sending a generated payload"
+)]
+pub struct BenchCommand {
+    /// Target device, in any form `send` accepts.
+    target: String,
+
+    /// Payload size, e.g. `64MiB` or `500MB`.
+    #[arg(short, long, default_value = "64MiB", value_parser = parse_payload_size)]
+    size: u64,
+
+    #[arg(short, long)]
+    pin: Option<String>,
+}
+
+fn parse_payload_size(input: &str) -> Result<u64, String> {
+    crate::core::parse_size(input).map_err(|error| error.to_string())
+}
+
+/// Timings of one [`run_bench`] transfer.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct BenchReport {
+    pub bytes: u64,
+    /// Round trip of prepare-upload, including the receiver's accept.
+    pub prepare: Duration,
+    pub upload: Duration,
+}
+
+impl BenchReport {
+    /// Megabytes (10^6 bytes) per second over the upload phase.
+    pub fn throughput_mb_per_s(&self) -> f64 {
+        self.bytes as f64 / 1_000_000.0 / self.upload.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+pub async fn execute(command: BenchCommand) -> anyhow::Result<()> {
+    let target = resolve_target(&command.target).await?;
+    let client = build_client_for_target(default_sender("LocalSend-Bench".into(), None), &target)?;
+    println!(
+        "Benchmarking {} bytes to {} ({:?})",
+        command.size, target.alias, target.ip
+    );
+
+    let report = run_bench(&client, &target, command.size, command.pin.as_deref()).await?;
+    println!(
+        "prepare-upload: {:.1} ms",
+        report.prepare.as_secs_f64() * 1000.0
+    );
+    println!(
+        "upload:         {:.1} ms",
+        report.upload.as_secs_f64() * 1000.0
+    );
+    println!("throughput:     {:.2} MB/s", report.throughput_mb_per_s());
+    Ok(())
+}
+
+/// Offer one file of `size` generated bytes and stream it from memory
+/// through the regular upload path. The receiver saves it like any upload.
+pub(super) async fn run_bench(
+    client: &LocalSendClient,
+    target: &DeviceInfo,
+    size: u64,
+    pin: Option<&str>,
+) -> anyhow::Result<BenchReport> {
+    let payload: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+    let id = FileId::new();
+    let metadata = FileMetadata {
+        id: id.clone(),
+        file_name: "localsend-bench.bin".to_string(),
+        size,
+        file_type: "application/octet-stream".to_string(),
+        sha256: None,
+        preview: None,
+        metadata: None,
+    };
+
+    let started = Instant::now();
+    let response = client
+        .prepare_upload(target, HashMap::from([(id.clone(), metadata)]), pin)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("receiver opened no session"))?;
+    let prepare = started.elapsed();
+    let token = response
+        .files
+        .get(&id)
+        .ok_or_else(|| anyhow::anyhow!("receiver accepted no file"))?;
+
+    let started = Instant::now();
+    client
+        .upload_reader(
+            target,
+            &response.session_id,
+            &id,
+            token,
+            std::io::Cursor::new(payload),
+            size,
+            None,
+        )
+        .await?;
+
+    Ok(BenchReport {
+        bytes: size,
+        prepare,
+        upload: started.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BenchCommand, run_bench};
+    use crate::protocol::{DeviceInfo, Protocol};
+    use crate::server::LocalSendServer;
+    use crate::{LocalSendClient, parse_size};
+    use clap::Parser;
+
+    #[tokio::test]
+    async fn bench_against_a_loopback_receiver_reports_throughput() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut server, _events) = LocalSendServer::builder()
+            .alias("bench-target")
+            .port(0)
+            .save_dir(dir.path())
+            .protocol(Protocol::Http)
+            .auto_accept(true)
+            .build()
+            .await
+            .expect("start receiver");
+        let mut target = DeviceInfo::new("bench-target".into(), server.port(), Protocol::Http);
+        target.ip = Some("127.0.0.1".into());
+        let client = LocalSendClient::new(DeviceInfo::new("bench".into(), 0, Protocol::Http));
+
+        let size = parse_size("256KiB").unwrap();
+        let report = run_bench(&client, &target, size, None)
+            .await
+            .expect("bench");
+
+        assert_eq!(report.bytes, size);
+        let throughput = report.throughput_mb_per_s();
+        assert!(throughput.is_finite() && throughput > 0.0, "{throughput}");
+        assert_eq!(
+            std::fs::metadata(dir.path().join("localsend-bench.bin"))
+                .unwrap()
+                .len(),
+            size
+        );
+
+        server.stop().await;
+    }
+
+    #[test]
+    fn size_accepts_human_units() {
+        let command =
+            BenchCommand::try_parse_from(["bench", "--size", "2MiB", "10.0.0.2"]).unwrap();
+        assert_eq!(command.size, 2 << 20);
+        assert!(BenchCommand::try_parse_from(["bench", "--size", "lots", "10.0.0.2"]).is_err());
+    }
+}
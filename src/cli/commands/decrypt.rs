@@ -0,0 +1,42 @@
+use anyhow::Context;
+use clap::Parser;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "decrypt",
+    about = "Decrypt a file saved by `receive --encrypt-passphrase`"
+)]
+pub struct DecryptCommand {
+    /// The `.lsenc` file; its `.lsenc.json` manifest must sit beside it.
+    file: PathBuf,
+
+    /// Where to write the plaintext (default: the file without `.lsenc`).
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Replace the output file if it already exists.
+    #[arg(long)]
+    force: bool,
+
+    #[arg(long, env = "LOCALSEND_AT_REST_PASSPHRASE", hide_env_values = true)]
+    passphrase: String,
+}
+
+pub async fn execute(command: DecryptCommand) -> anyhow::Result<()> {
+    let output = match command.output {
+        Some(output) => output,
+        None if command.file.extension().is_some_and(|ext| ext == "lsenc") => {
+            command.file.with_extension("")
+        }
+        None => anyhow::bail!(
+            "pass --output: {} has no .lsenc extension",
+            command.file.display()
+        ),
+    };
+    crate::decrypt_file(&command.file, &command.passphrase, &output, command.force)
+        .await
+        .with_context(|| format!("decrypting {}", command.file.display()))?;
+    println!("Decrypted to {}", output.display());
+    Ok(())
+}
@@ -1,8 +1,11 @@
 use crate::DeviceInfo;
-use crate::discovery::Discovery;
-use clap::Parser;
+use crate::core::DeviceInfoBuilder;
+use crate::discovery::{AdaptiveDiscovery, DiscoveryMode};
+use crate::protocol::DEFAULT_HTTP_PORT;
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
 use serde_json;
-use std::sync::{Arc, Mutex};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 #[derive(Parser, Debug)]
@@ -13,45 +16,74 @@ pub struct DiscoverCommand {
 
     #[arg(short, long)]
     json: bool,
+
+    /// Discovery backend: multicast with a subnet-scan fallback, or force one
+    #[arg(long, value_enum, default_value_t = DiscoverMode::Auto)]
+    mode: DiscoverMode,
+
+    /// Also write the discovered devices to this JSON file, for `send --from`.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
 }
 
-pub async fn execute(command: DiscoverCommand) -> anyhow::Result<()> {
-    let mut discovery = crate::discovery::MulticastDiscovery::new(
-        "LocalSend-Rust".to_string(),
-        53317,
-        crate::protocol::Protocol::Https,
-    )?;
-
-    let devices = Arc::new(Mutex::new(Vec::<DeviceInfo>::new()));
-    let devices_clone = devices.clone();
-
-    discovery.on_discovered(move |device: DeviceInfo| {
-        let mut devices = devices_clone.lock().unwrap();
-        // Avoid duplicates
-        if !devices.iter().any(|d| d.fingerprint == device.fingerprint) {
-            devices.push(device);
-        }
-    });
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum DiscoverMode {
+    Auto,
+    Multicast,
+    Scan,
+}
 
-    discovery.start().await?;
-    discovery.announce_presence().await?;
+impl From<DiscoverMode> for DiscoveryMode {
+    fn from(mode: DiscoverMode) -> Self {
+        match mode {
+            DiscoverMode::Auto => DiscoveryMode::Auto,
+            DiscoverMode::Multicast => DiscoveryMode::Multicast,
+            DiscoverMode::Scan => DiscoveryMode::Scan,
+        }
+    }
+}
 
-    let timeout_duration = Duration::from_secs(command.timeout);
-    tokio::time::sleep(timeout_duration).await;
+pub async fn execute(command: DiscoverCommand) -> anyhow::Result<()> {
+    let device = DeviceInfoBuilder::new("LocalSend-Rust", DEFAULT_HTTP_PORT).build();
+    let mut discovery = AdaptiveDiscovery::new_with_device(device)?.with_mode(command.mode.into());
 
-    discovery.stop();
+    let report = discovery
+        .discover(Duration::from_secs(command.timeout))
+        .await?;
 
-    let devices = devices.lock().unwrap();
+    if let Some(path) = &command.output {
+        save_devices(path, &report.devices)?;
+        let saved = format!(
+            "Saved {} device(s) to {}",
+            report.devices.len(),
+            path.display()
+        );
+        // Under --json, stdout carries only the device array.
+        if command.json {
+            eprintln!("{saved}");
+        } else {
+            println!("{saved}");
+        }
+    }
 
     if command.json {
-        println!("{}", serde_json::to_string_pretty(&*devices)?);
+        println!("{}", serde_json::to_string_pretty(&report.devices)?);
     } else {
-        display_devices(&devices);
+        if report.used_fallback {
+            println!("No multicast responses; fell back to an HTTP subnet scan");
+        }
+        display_devices(&report.devices);
     }
 
     Ok(())
 }
 
+/// Persist `devices` as the same JSON array `--json` prints.
+pub(super) fn save_devices(path: &Path, devices: &[DeviceInfo]) -> anyhow::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(devices)?)
+        .with_context(|| format!("writing {}", path.display()))
+}
+
 fn display_devices(devices: &[DeviceInfo]) {
     if devices.is_empty() {
         println!("No devices discovered");
@@ -63,3 +95,19 @@ fn display_devices(devices: &[DeviceInfo]) {
         println!("  - {} (port {})", device.alias, device.port);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{DiscoverCommand, DiscoverMode};
+    use clap::Parser;
+
+    #[test]
+    fn mode_defaults_to_auto_and_can_be_forced() {
+        let command = DiscoverCommand::try_parse_from(["discover"]).expect("parse defaults");
+        assert_eq!(command.mode, DiscoverMode::Auto);
+
+        let command = DiscoverCommand::try_parse_from(["discover", "--mode", "scan"])
+            .expect("parse forced mode");
+        assert_eq!(command.mode, DiscoverMode::Scan);
+    }
+}
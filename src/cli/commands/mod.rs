@@ -1,15 +1,24 @@
+pub mod bench;
+#[cfg(feature = "encryption")]
+pub mod decrypt;
 pub mod discover;
 pub mod receive;
 pub mod send;
 #[cfg(feature = "tui")]
 pub mod tui;
 
+pub use bench::BenchCommand;
+#[cfg(feature = "encryption")]
+pub use decrypt::DecryptCommand;
 pub use discover::DiscoverCommand;
 pub use receive::ReceiveCommand;
 pub use send::SendCommand;
 #[cfg(feature = "tui")]
 pub use tui::TuiCommand;
 
+pub use bench::execute as run_bench;
+#[cfg(feature = "encryption")]
+pub use decrypt::execute as run_decrypt;
 pub use discover::execute as run_discover;
 pub use receive::execute as run_receive;
 pub use send::execute as run_send;
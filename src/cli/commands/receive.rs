@@ -1,6 +1,13 @@
+use crate::crypto::Identity;
 use crate::discovery::traits::Discovery;
-use clap::Parser;
+use crate::server::{DuplicatePolicy, ReceiveSummary, SaveLayout, ServerEvent};
+use anyhow::Context;
+use clap::{Parser, ValueEnum};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 #[derive(Parser, Debug)]
 #[command(name = "receive", about = "Start LocalSend server to receive files")]
@@ -14,6 +21,10 @@ pub struct ReceiveCommand {
     #[arg(long)]
     pin: Option<String>,
 
+    /// Device model shown to peers (defaults to the OS name), e.g. "ThinkPad X1 / Fedora".
+    #[arg(long)]
+    device_model: Option<String>,
+
     #[arg(long)]
     auto_accept: bool,
 
@@ -22,6 +33,93 @@ pub struct ReceiveCommand {
     #[arg(long, default_value_t = 0, requires = "auto_accept")]
     accept_delay_ms: u64,
 
+    /// Print the end-of-run summary as JSON.
+    #[arg(long)]
+    json: bool,
+
+    /// Reject uploads without a `Content-Length` matching the declared size.
+    #[arg(long)]
+    strict_content_length: bool,
+
+    /// Stay discoverable but refuse every incoming transfer (HTTP 403).
+    #[arg(long, conflicts_with = "auto_accept")]
+    receive_disabled: bool,
+
+    /// Auto-accept transfers smaller than this in total (e.g. `10MB`) and
+    /// prompt for larger ones.
+    #[arg(long, value_parser = parse_threshold, conflicts_with = "auto_accept")]
+    auto_accept_below: Option<u64>,
+
+    /// Decline every transfer that is not auto-accepted immediately, instead
+    /// of prompting and waiting (for unattended receivers).
+    #[arg(long, conflicts_with = "auto_accept")]
+    auto_decline: bool,
+
+    /// Refuse transfers larger than this in total (e.g. `20GB`).
+    #[arg(long, value_parser = parse_threshold)]
+    max_size: Option<u64>,
+
+    /// Only take transfers from the sender with this fingerprint (repeatable).
+    #[arg(long = "allow", value_name = "FINGERPRINT")]
+    allow: Vec<String>,
+
+    /// Refuse transfers from the sender with this fingerprint (repeatable).
+    #[arg(long = "block", value_name = "FINGERPRINT")]
+    block: Vec<String>,
+
+    /// Discard uploads identical (by SHA-256) to a same-named file already
+    /// in the output directory instead of saving a renamed copy.
+    #[arg(long)]
+    skip_duplicates: bool,
+
+    /// Keep uploads that are cut off part way so the sender can resume them.
+    #[arg(long)]
+    resumable: bool,
+
+    /// Append every received file to `.localsend-received.jsonl` in the
+    /// output directory.
+    #[arg(long)]
+    index: bool,
+
+    /// Send CORS headers so the LocalSend web client
+    /// (https://web.localsend.org) can connect. Any page on an allowed
+    /// origin can then offer files, and with --auto-accept have them saved
+    /// unasked.
+    #[arg(long)]
+    web_client: bool,
+
+    /// Let browser pages from this origin connect too, such as a self-hosted
+    /// web client; repeat for more. `*` lets in every web page the user
+    /// visits.
+    #[arg(long = "web-client-origin", value_name = "ORIGIN")]
+    web_client_origins: Vec<String>,
+
+    /// Also accept transfers from senders on the old LocalSend v1 API.
+    #[arg(long)]
+    legacy: bool,
+
+    /// Give received files the modified time the sender declared.
+    #[arg(long)]
+    preserve_mtime: bool,
+
+    /// Take transfers from up to this many senders at once.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    max_sessions: u64,
+
+    /// Sort received files into a folder per sender or per day.
+    #[arg(long, value_enum, default_value_t = Layout::Flat)]
+    layout: Layout,
+
+    /// Also copy every received file into this directory (e.g. a NAS mount).
+    #[arg(long)]
+    mirror_dir: Option<PathBuf>,
+
+    /// Encrypt received files at rest with this passphrase (saved as
+    /// `<name>.lsenc`; recover them with `decrypt`).
+    #[cfg(feature = "encryption")]
+    #[arg(long, env = "LOCALSEND_AT_REST_PASSPHRASE", hide_env_values = true)]
+    encrypt_passphrase: Option<String>,
+
     /// Test-only: throttle receiver body consumption to the given KiB/s.
     #[arg(long, hide = true, value_parser = clap::value_parser!(u64).range(1..))]
     receive_rate_limit_kib: Option<u64>,
@@ -31,29 +129,118 @@ pub struct ReceiveCommand {
     #[cfg(feature = "https")]
     #[arg(long)]
     no_https: bool,
+
+    /// Serve this PEM certificate instead of a fresh self-signed one, so the
+    /// fingerprint peers pinned stays the same across restarts.
+    #[cfg(feature = "https")]
+    #[arg(long, requires = "key", conflicts_with = "no_https")]
+    cert: Option<PathBuf>,
+
+    /// The PEM private key for `--cert`.
+    #[cfg(feature = "https")]
+    #[arg(long, requires = "cert")]
+    key: Option<PathBuf>,
+
+    /// Keep this device's fingerprint (and HTTPS certificate) here instead
+    /// of in `receive` under the default config directory. Instances running
+    /// at once need different directories to see each other.
+    #[arg(long, conflicts_with = "ephemeral")]
+    identity_dir: Option<PathBuf>,
+
+    /// Start as a new device, with a fresh fingerprint, and keep nothing.
+    #[arg(long)]
+    ephemeral: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Layout {
+    Flat,
+    BySender,
+    ByDate,
+}
+
+impl From<Layout> for SaveLayout {
+    fn from(layout: Layout) -> Self {
+        match layout {
+            Layout::Flat => SaveLayout::Flat,
+            Layout::BySender => SaveLayout::BySender,
+            Layout::ByDate => SaveLayout::ByDate,
+        }
+    }
+}
+
+/// The identity kept in `dir` (or [`default_identity_dir`] for `instance`),
+/// or `None` when the device should be new this run.
+pub(crate) fn load_identity(
+    dir: Option<PathBuf>,
+    ephemeral: bool,
+    protocol: crate::protocol::Protocol,
+    instance: &str,
+) -> anyhow::Result<Option<Identity>> {
+    if ephemeral {
+        return Ok(None);
+    }
+    let Some(dir) = dir.or_else(|| default_identity_dir(instance)) else {
+        tracing::warn!("No config directory found; this device gets a new fingerprint each run");
+        return Ok(None);
+    };
+    let identity = Identity::load_or_create(&dir, protocol)
+        .with_context(|| format!("loading the device identity in {}", dir.display()))?;
+    Ok(Some(identity))
+}
+
+/// Where the `instance` command (`receive`, `tui`) keeps its identity by
+/// default: a directory of its own under [`Identity::default_dir`]. Sharing
+/// one would give a background receiver and the TUI the same fingerprint,
+/// and discovery drops announcements carrying its own as echoes, so they
+/// could never see each other.
+pub(crate) fn default_identity_dir(instance: &str) -> Option<PathBuf> {
+    Identity::default_dir().map(|dir| dir.join(instance))
+}
+
+/// A human-readable line: on stdout, or on stderr under `--json`, where stdout
+/// carries only the summary object.
+macro_rules! status {
+    ($json:expr, $($arg:tt)*) => {
+        if $json {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+fn parse_threshold(input: &str) -> Result<u64, String> {
+    crate::core::parse_size(input).map_err(|error| error.to_string())
 }
 
 pub async fn execute(command: ReceiveCommand) -> anyhow::Result<()> {
+    let json = command.json;
     if !command.directory.exists() {
-        tokio::fs::create_dir_all(&command.directory).await?;
-        println!(
+        crate::core::ensure_dir(&command.directory).await?;
+        status!(
+            json,
             "Created download directory: {}",
             command.directory.display()
         );
     }
 
-    println!("Starting LocalSend server on port {}", command.port);
-    println!("Save directory: {}", command.directory.display());
+    status!(json, "Starting LocalSend server on port {}", command.port);
+    status!(json, "Save directory: {}", command.directory.display());
 
     if let Some(ref pin) = command.pin {
-        println!("PIN required: {}", pin);
+        status!(json, "PIN required: {}", pin);
     }
 
     if command.auto_accept {
         if command.accept_delay_ms == 0 {
-            println!("Auto-accept mode ENABLED - files will be accepted without confirmation!");
+            status!(
+                json,
+                "Auto-accept mode ENABLED - files will be accepted without confirmation!"
+            );
         } else {
-            println!(
+            status!(
+                json,
                 "Delayed auto-accept mode ENABLED - requests will wait {} ms",
                 command.accept_delay_ms
             );
@@ -65,7 +252,8 @@ pub async fn execute(command: ReceiveCommand) -> anyhow::Result<()> {
     #[cfg(not(feature = "https"))]
     let https_enabled = false;
 
-    println!(
+    status!(
+        json,
         "Transport: {}",
         if https_enabled { "HTTPS" } else { "HTTP" }
     );
@@ -87,31 +275,204 @@ pub async fn execute(command: ReceiveCommand) -> anyhow::Result<()> {
             command.auto_accept,
             command.accept_delay_ms,
         ));
+    // A certificate passed in replaces the identity's, so none is made for it.
+    #[cfg(feature = "https")]
+    let identity_protocol = if command.cert.is_some() {
+        crate::protocol::Protocol::Http
+    } else {
+        protocol_enum
+    };
+    #[cfg(not(feature = "https"))]
+    let identity_protocol = protocol_enum;
+    if let Some(identity) = load_identity(
+        command.identity_dir,
+        command.ephemeral,
+        identity_protocol,
+        "receive",
+    )? {
+        builder = builder.fingerprint(identity.fingerprint);
+        #[cfg(feature = "https")]
+        if let Some(certificate) = identity.certificate {
+            builder = builder.tls_certificate(certificate);
+        }
+    }
+    #[cfg(feature = "https")]
+    if let (Some(cert), Some(key)) = (&command.cert, &command.key) {
+        let read = |path: &PathBuf| {
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))
+        };
+        let certificate = crate::crypto::TlsCertificate::from_pem(read(cert)?, read(key)?)?;
+        status!(json, "Using the certificate in {}", cert.display());
+        builder = builder.tls_certificate(certificate);
+    }
+    if command.strict_content_length {
+        builder = builder.strict_content_length(true);
+    }
+    if command.receive_disabled {
+        status!(
+            json,
+            "Receiving is disabled: incoming transfers will be refused"
+        );
+        builder = builder.receive_disabled(true);
+    }
+    if let Some(bytes) = command.auto_accept_below {
+        status!(
+            json,
+            "Transfers under {bytes} bytes are accepted without confirmation"
+        );
+        builder = builder.auto_accept_below(bytes);
+    }
+    if command.auto_decline {
+        status!(
+            json,
+            "Auto-decline mode ENABLED - incoming transfers are refused without a prompt"
+        );
+        builder = builder.auto_decline(true);
+    }
+    if let Some(bytes) = command.max_size {
+        status!(json, "Transfers over {bytes} bytes are refused");
+        builder = builder.max_total_size(bytes);
+    }
+    if !command.allow.is_empty() {
+        status!(
+            json,
+            "Only taking transfers from {} allowed sender(s)",
+            command.allow.len()
+        );
+        builder = builder.allowed_fingerprints(command.allow.iter().cloned());
+    }
+    if !command.block.is_empty() {
+        builder = builder.blocked_fingerprints(command.block.iter().cloned());
+    }
+    if command.skip_duplicates {
+        builder = builder.duplicate_policy(DuplicatePolicy::SkipIdentical);
+    }
+    builder = builder.save_layout(command.layout.into());
+    if command.resumable {
+        builder = builder.resumable_uploads(true);
+    }
+    if command.index {
+        let index = command.directory.join(crate::server::INDEX_FILE_NAME);
+        status!(json, "Recording received files in {}", index.display());
+        builder = builder.history_file(index);
+    }
+    if command.web_client {
+        builder = builder.web_client(true);
+    }
+    if !command.web_client_origins.is_empty() {
+        builder = builder.web_client_origins(command.web_client_origins.clone());
+    }
+    if command.legacy {
+        status!(json, "Accepting transfers over the legacy v1 API too");
+        builder = builder.enable_v1(true);
+    }
+    if command.preserve_mtime {
+        builder = builder.preserve_modified_time(true);
+    }
+    if command.max_sessions > 1 {
+        status!(
+            json,
+            "Taking transfers from up to {} senders at once",
+            command.max_sessions
+        );
+        builder = builder.max_sessions(command.max_sessions as usize);
+    }
+    if let Some(ref mirror_dir) = command.mirror_dir {
+        status!(json, "Mirroring received files to {}", mirror_dir.display());
+        builder = builder.mirror_dir(mirror_dir);
+    }
+    #[cfg(feature = "encryption")]
+    if let Some(ref passphrase) = command.encrypt_passphrase {
+        status!(json, "Received files will be encrypted at rest");
+        builder = builder.encrypt_at_rest(passphrase.clone());
+    }
     if let Some(kib_per_second) = command.receive_rate_limit_kib {
         let bytes_per_second = kib_per_second.saturating_mul(1_024);
-        println!("Test receiver rate limit: {kib_per_second} KiB/s");
+        status!(json, "Test receiver rate limit: {kib_per_second} KiB/s");
         builder = builder.receive_rate_limit(bytes_per_second);
     }
     if let Some(ref pin) = command.pin {
         builder = builder.pin(pin.clone());
     }
-    let (mut server, mut events) = builder.build().await?;
+    if let Some(ref model) = command.device_model {
+        builder = builder.device_model(model.clone());
+    }
+    let (mut server, events) = builder.build().await?;
+    status!(json, "Device fingerprint: {}", server.device().fingerprint);
 
     // Discovery must announce the SAME device identity the server uses.
     let mut discovery =
         crate::discovery::MulticastDiscovery::new_with_device(server.device().clone());
-    println!("Starting multicast discovery...");
+    status!(json, "Starting multicast discovery...");
     discovery.start().await?;
-    println!("Announcing presence to network...");
+    status!(json, "Announcing presence to network...");
     discovery.announce_presence().await?;
 
-    let auto_accept = command.auto_accept;
-    let accept_delay_ms = command.accept_delay_ms;
-    let event_loop = tokio::spawn(async move {
+    let started = Instant::now();
+    let summary = Arc::new(Mutex::new(ReceiveSummary::default()));
+    let event_loop = spawn_event_loop(
+        events,
+        command.auto_accept,
+        command.accept_delay_ms,
+        summary.clone(),
+        json,
+    );
+
+    tokio::signal::ctrl_c().await?;
+
+    status!(json, "\nShutting down server...");
+    event_loop.abort();
+    server.stop().await;
+    discovery.stop();
+
+    print_summary(&summary, started, command.json)
+}
+
+/// Report what a run received, as one JSON object or a short line.
+pub(super) fn print_summary(
+    summary: &Mutex<ReceiveSummary>,
+    started: Instant,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut summary = summary.lock().unwrap().clone();
+    summary.duration = started.elapsed();
+    if json {
+        println!("{}", serde_json::to_string(&summary)?);
+    } else {
+        println!(
+            "Received {} file(s), {} bytes from {} sender(s) in {:.1}s",
+            summary.files_received,
+            summary.total_bytes,
+            summary.senders.len(),
+            summary.duration.as_secs_f64()
+        );
+        if summary.duplicates_skipped > 0 {
+            println!(
+                "Skipped {} duplicate(s) already on disk",
+                summary.duplicates_skipped
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print server events, fold them into `summary`, and answer incoming transfers
+/// until the channel closes. With `json`, the lines go to stderr.
+/// Shared with `send --keep-serving`, which runs the same receiver after sending.
+pub(super) fn spawn_event_loop(
+    mut events: mpsc::Receiver<ServerEvent>,
+    auto_accept: bool,
+    accept_delay_ms: u64,
+    summary: Arc<Mutex<ReceiveSummary>>,
+    json: bool,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
         while let Some(ev) = events.recv().await {
+            summary.lock().unwrap().record(&ev);
             match ev {
-                crate::server::ServerEvent::TransferRequest(req) => {
-                    println!(
+                ServerEvent::TransferRequest(req) => {
+                    status!(
+                        json,
                         "Incoming transfer from '{}' ({} file(s))",
                         req.sender().alias,
                         req.files().len()
@@ -121,25 +482,42 @@ pub async fn execute(command: ReceiveCommand) -> anyhow::Result<()> {
                             tokio::time::sleep(std::time::Duration::from_millis(accept_delay_ms))
                                 .await;
                         }
-                        req.accept();
+                        if !req.accept() {
+                            status!(json, "Transfer request expired before it was accepted");
+                        }
                     } else {
                         // Headless interactive: y/n on stdin.
                         let accept = inquire::Confirm::new("Accept this transfer?")
                             .with_default(false)
                             .prompt()
                             .unwrap_or(false);
-                        if accept { req.accept() } else { req.decline() }
+                        if !accept {
+                            req.decline();
+                        } else if !req.accept() {
+                            status!(json, "Transfer request expired before it was accepted");
+                        }
                     }
                 }
-                crate::server::ServerEvent::TextReceived {
+                ServerEvent::TextReceived {
                     text, sender_alias, ..
-                } => println!("Message from {}: {}", sender_alias, text),
-                crate::server::ServerEvent::WebShareRequest(request) => {
-                    println!("Browser download request from {}", request.ip());
+                } => status!(json, "Message from {}: {}", sender_alias, text),
+                ServerEvent::WebShareRequest(request) => {
+                    status!(json, "Browser download request from {}", request.ip());
                 }
-                crate::server::ServerEvent::WebShareDownloadProgress { .. }
-                | crate::server::ServerEvent::WebShareSessionDone { .. } => {}
-                crate::server::ServerEvent::FileReceiveProgress {
+                ServerEvent::WebShareDownloadProgress { .. }
+                | ServerEvent::WebShareSessionDone { .. } => {}
+                ServerEvent::SessionStarted {
+                    sender_alias,
+                    file_count,
+                    total_bytes,
+                    ..
+                } => {
+                    status!(
+                        json,
+                        "Receiving {file_count} file(s), {total_bytes} bytes, from {sender_alias}"
+                    );
+                }
+                ServerEvent::FileReceiveProgress {
                     file_name,
                     bytes_received,
                     total_bytes,
@@ -147,7 +525,7 @@ pub async fn execute(command: ReceiveCommand) -> anyhow::Result<()> {
                 } => {
                     eprintln!("Receiving {file_name}: {bytes_received}/{total_bytes} bytes");
                 }
-                crate::server::ServerEvent::FileReceived {
+                ServerEvent::FileReceived {
                     file_name,
                     path,
                     size,
@@ -156,7 +534,8 @@ pub async fn execute(command: ReceiveCommand) -> anyhow::Result<()> {
                     ..
                 } => {
                     let _ = message_text;
-                    println!(
+                    status!(
+                        json,
                         "Received '{}' ({} bytes) from {} -> {}",
                         file_name,
                         size,
@@ -164,21 +543,37 @@ pub async fn execute(command: ReceiveCommand) -> anyhow::Result<()> {
                         path.display()
                     );
                 }
-                crate::server::ServerEvent::SessionDone { session_id } => {
-                    println!("Session {} complete", session_id);
+                ServerEvent::FileSkipped {
+                    file_name,
+                    path,
+                    sender_alias,
+                    ..
+                } => {
+                    status!(
+                        json,
+                        "Skipped '{}' from {}: identical to {}",
+                        file_name,
+                        sender_alias,
+                        path.display()
+                    );
+                }
+                ServerEvent::SessionDone { session_id } => {
+                    status!(json, "Session {} complete", session_id);
                 }
+                ServerEvent::TransferRejected(rejection) => {
+                    status!(
+                        json,
+                        "Rejected transfer from '{}' ({} file(s), {} bytes): {:?}",
+                        rejection.sender_alias,
+                        rejection.file_count,
+                        rejection.total_size,
+                        rejection.reason
+                    );
+                }
+                ServerEvent::DeviceRegistered(_) => {}
             }
         }
-    });
-
-    tokio::signal::ctrl_c().await?;
-
-    println!("\nShutting down server...");
-    event_loop.abort();
-    server.stop().await;
-    discovery.stop();
-
-    Ok(())
+    })
 }
 
 fn server_auto_accept(auto_accept: bool, accept_delay_ms: u64) -> bool {
@@ -187,9 +582,20 @@ fn server_auto_accept(auto_accept: bool, accept_delay_ms: u64) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use super::{ReceiveCommand, server_auto_accept};
+    use super::{Layout, ReceiveCommand, default_identity_dir, server_auto_accept};
     use clap::Parser;
 
+    #[test]
+    fn receive_and_tui_keep_separate_identities() {
+        let (Some(receive), Some(tui)) =
+            (default_identity_dir("receive"), default_identity_dir("tui"))
+        else {
+            return;
+        };
+        assert_ne!(receive, tui);
+        assert_eq!(receive.parent(), tui.parent());
+    }
+
     #[test]
     fn parses_auto_accept_delay() {
         let command = ReceiveCommand::try_parse_from([
@@ -212,6 +618,36 @@ mod tests {
         assert!(error.to_string().contains("--auto-accept"));
     }
 
+    #[test]
+    fn allow_and_block_are_repeatable() {
+        let command = ReceiveCommand::try_parse_from([
+            "receive", "--allow", "aa", "--allow", "bb", "--block", "cc",
+        ])
+        .expect("allow and block should parse");
+
+        assert_eq!(command.allow, ["aa", "bb"]);
+        assert_eq!(command.block, ["cc"]);
+    }
+
+    #[test]
+    fn layout_defaults_to_flat() {
+        let command = ReceiveCommand::try_parse_from(["receive"]).expect("parse defaults");
+        assert_eq!(command.layout, Layout::Flat);
+
+        let command = ReceiveCommand::try_parse_from(["receive", "--layout", "by-sender"])
+            .expect("parse layout");
+        assert_eq!(command.layout, Layout::BySender);
+    }
+
+    #[test]
+    fn max_size_takes_a_human_size() {
+        let command = ReceiveCommand::try_parse_from(["receive", "--max-size", "20GB"])
+            .expect("max size should parse");
+        assert_eq!(command.max_size, Some(20_000_000_000));
+
+        assert!(ReceiveCommand::try_parse_from(["receive", "--max-size", "lots"]).is_err());
+    }
+
     #[test]
     fn delayed_auto_accept_uses_transfer_request_path() {
         assert!(server_auto_accept(true, 0));
@@ -219,6 +655,28 @@ mod tests {
         assert!(!server_auto_accept(false, 0));
     }
 
+    #[cfg(feature = "https")]
+    #[test]
+    fn a_certificate_needs_its_key_and_https() {
+        let command =
+            ReceiveCommand::try_parse_from(["receive", "--cert", "c.pem", "--key", "k.pem"])
+                .expect("parse a cert and key");
+        assert_eq!(command.cert.as_deref(), Some(std::path::Path::new("c.pem")));
+        assert!(ReceiveCommand::try_parse_from(["receive", "--cert", "c.pem"]).is_err());
+        assert!(ReceiveCommand::try_parse_from(["receive", "--key", "k.pem"]).is_err());
+        assert!(
+            ReceiveCommand::try_parse_from([
+                "receive",
+                "--cert",
+                "c.pem",
+                "--key",
+                "k.pem",
+                "--no-https"
+            ])
+            .is_err()
+        );
+    }
+
     #[test]
     fn parses_test_receiver_rate_limit() {
         let command =
@@ -233,4 +691,14 @@ mod tests {
         ReceiveCommand::try_parse_from(["receive", "--receive-rate-limit-kib", "0"])
             .expect_err("zero receiver limit should be rejected");
     }
+
+    #[test]
+    fn an_ephemeral_receiver_keeps_no_identity() {
+        let command = ReceiveCommand::try_parse_from(["receive", "--ephemeral"]).unwrap();
+        assert!(command.ephemeral);
+        assert!(
+            ReceiveCommand::try_parse_from(["receive", "--ephemeral", "--identity-dir", "id"])
+                .is_err()
+        );
+    }
 }
@@ -1,16 +1,28 @@
+use super::receive::{print_summary, spawn_event_loop};
 use crate::DeviceInfo;
-use crate::client::{LocalSendClient, TlsTrustPolicy};
-use crate::core::file::build_file_metadata;
+use crate::client::{ClientConfig, LocalSendClient, ProgressCallback, TlsTrustPolicy};
+use crate::core::device::get_device_model;
+use crate::core::file::{build_file_metadata_with_hash, collect_directory_files};
 use crate::crypto::generate_fingerprint;
 use crate::discovery::{Discovery, MulticastDiscovery};
 use crate::protocol::types::FileMetadataDetails;
-use crate::protocol::{DeviceType, FileMetadata, Protocol};
+use crate::protocol::{
+    DEFAULT_HTTP_PORT, DeviceType, FileMetadata, Protocol, SessionId, advertised_version,
+};
+use crate::server::{LocalSendServer, ReceiveSummary, ServerEvent};
+use anyhow::Context;
 use clap::Parser;
-use reqwest::Client;
+use futures_util::StreamExt;
 use std::collections::HashMap;
 use std::net::IpAddr;
-use std::path::PathBuf;
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Default for `send --concurrency`.
+const DEFAULT_UPLOAD_CONCURRENCY: u16 = 4;
 
 #[derive(Parser, Debug)]
 #[command(name = "send", about = "Send files to a LocalSend device")]
@@ -19,52 +31,422 @@ pub struct SendCommand {
     /// `[ipv6]:port`, or a discovered device alias.
     target: String,
 
+    /// Files, directories, or text. Without `--text`/`--file`, an argument
+    /// naming an existing path is sent as that path and anything else as text.
     #[arg(required = true)]
-    files: Vec<String>, // Changed from PathBuf to String to support text
+    files: Vec<String>,
+
+    /// Send every argument as a literal text message, even if it names a file.
+    #[arg(long, conflicts_with = "file")]
+    text: bool,
+
+    /// Send every argument as a file or directory; a missing path is an error
+    /// instead of turning into a text message.
+    #[arg(long)]
+    file: bool,
 
     #[arg(short, long)]
     pin: Option<String>,
 
+    /// Name shown to the receiver in its accept prompt.
+    #[arg(long, env = "LOCALSEND_ALIAS", default_value = "LocalSend-Rust")]
+    alias: String,
+
+    /// Device model shown to the peer (defaults to the OS name).
+    #[arg(long)]
+    device_model: Option<String>,
+
     /// Test-only: throttle sender stream production to the given KiB/s.
     #[arg(long, hide = true, value_parser = clap::value_parser!(u64).range(1..))]
     send_rate_limit_kib: Option<u64>,
+
+    /// How many files to upload at once.
+    #[arg(long, default_value_t = DEFAULT_UPLOAD_CONCURRENCY, value_parser = clap::value_parser!(u16).range(1..))]
+    concurrency: u16,
+
+    /// Include dotfiles and OS junk (.DS_Store, Thumbs.db, ...) when sending
+    /// a directory; they are skipped by default.
+    #[arg(long)]
+    include_hidden: bool,
+
+    /// Attach a small thumbnail to each image offered, for the receiver's
+    /// accept prompt.
+    #[cfg(feature = "thumbnails")]
+    #[arg(long)]
+    previews: bool,
+
+    /// Keep a receiver running after the send so the peer can send back.
+    #[arg(long)]
+    keep_serving: bool,
+
+    /// Port for the `--keep-serving` receiver.
+    #[arg(long, default_value_t = DEFAULT_HTTP_PORT, requires = "keep_serving")]
+    serve_port: u16,
+
+    /// Resolve the target from a device list saved by `discover --output`
+    /// instead of probing or discovering it live.
+    #[arg(long)]
+    from: Option<PathBuf>,
+
+    /// Save directory for the `--keep-serving` receiver.
+    #[arg(short, long, default_value = "./downloads", requires = "keep_serving")]
+    directory: PathBuf,
+}
+
+impl SendCommand {
+    fn input_mode(&self) -> InputMode {
+        if self.text {
+            InputMode::Text
+        } else if self.file {
+            InputMode::File
+        } else {
+            InputMode::Auto
+        }
+    }
+}
+
+/// How `send` reads its positional arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputMode {
+    /// Guess per argument, by whether the path exists.
+    Auto,
+    Text,
+    File,
+}
+
+/// What one positional argument is sent as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputKind {
+    Directory,
+    File,
+    Text,
+}
+
+/// Classify `input` under `mode`. The filesystem is only consulted to tell
+/// a directory from a file, and in `Auto` mode as the last-resort guess.
+fn classify_input(input: &str, mode: InputMode) -> anyhow::Result<InputKind> {
+    if mode == InputMode::Text {
+        return Ok(InputKind::Text);
+    }
+    let path = Path::new(input);
+    if path.is_dir() {
+        Ok(InputKind::Directory)
+    } else if path.exists() {
+        Ok(InputKind::File)
+    } else if mode == InputMode::File {
+        anyhow::bail!("No such file or directory: {input}")
+    } else {
+        Ok(InputKind::Text)
+    }
 }
 
 pub async fn execute(command: SendCommand) -> anyhow::Result<()> {
-    let target = resolve_target(&command.target).await?;
-    println!("Sending to: {} ({:?})", target.alias, target.ip);
+    let Some((mut server, events)) = send_then_serve(&command).await? else {
+        return Ok(());
+    };
+
+    // Announce the receiver so the peer can find us for the reverse direction.
+    let mut discovery =
+        crate::discovery::MulticastDiscovery::new_with_device(server.device().clone());
+    match discovery.start().await {
+        Ok(()) => {
+            if let Err(error) = discovery.announce_presence().await {
+                eprintln!("Multicast announcement failed: {error}");
+            }
+        }
+        Err(error) => eprintln!("Multicast discovery unavailable: {error}"),
+    }
+
+    println!("Still receiving on port {} (Ctrl+C to stop)", server.port());
+    let started = Instant::now();
+    let summary = Arc::new(Mutex::new(ReceiveSummary::default()));
+    let event_loop = spawn_event_loop(events, false, 0, summary.clone(), false);
+
+    tokio::signal::ctrl_c().await?;
+
+    println!("\nShutting down server...");
+    event_loop.abort();
+    server.stop().await;
+    discovery.stop();
+
+    print_summary(&summary, started, false)
+}
+
+/// Send the files. With `--keep-serving` a receiver is started first, its
+/// identity is what we register with the peer, and it is returned still running.
+async fn send_then_serve(
+    command: &SendCommand,
+) -> anyhow::Result<Option<(LocalSendServer, mpsc::Receiver<ServerEvent>)>> {
+    if !command.keep_serving {
+        send_files(
+            command,
+            default_sender(command.alias.clone(), command.device_model.clone()),
+        )
+        .await?;
+        return Ok(None);
+    }
+
+    crate::core::ensure_dir(&command.directory).await?;
+    #[cfg(feature = "https")]
+    let protocol = Protocol::Https;
+    #[cfg(not(feature = "https"))]
+    let protocol = Protocol::Http;
+    let mut builder = LocalSendServer::builder()
+        .alias(command.alias.clone())
+        .port(command.serve_port)
+        .save_dir(&command.directory)
+        .protocol(protocol);
+    if let Some(ref model) = command.device_model {
+        builder = builder.device_model(model.clone());
+    }
+    let (mut server, events) = builder.build().await?;
+
+    if let Err(error) = send_files(command, server.device().clone()).await {
+        server.stop().await;
+        return Err(error);
+    }
+    Ok(Some((server, events)))
+}
 
-    let sender = DeviceInfo {
-        alias: "LocalSend-Rust".to_string(),
-        version: "2.1".to_string(),
-        device_model: Some(std::env::consts::OS.to_string()),
+/// The identity a plain send (no `--keep-serving` receiver) registers with.
+pub(super) fn default_sender(alias: String, device_model: Option<String>) -> DeviceInfo {
+    DeviceInfo {
+        alias,
+        version: advertised_version(),
+        device_model: Some(device_model.unwrap_or_else(get_device_model)),
         device_type: Some(DeviceType::Desktop),
         fingerprint: generate_fingerprint(),
         port: 53318,
         protocol: Protocol::Https, // Default to HTTPS
         download: false,
         ip: None,
+    }
+}
+
+async fn send_files(command: &SendCommand, sender: DeviceInfo) -> anyhow::Result<()> {
+    let target = match &command.from {
+        Some(path) => resolve_from_file(path, &command.target)?,
+        None => resolve_target(&command.target).await?,
     };
+    println!("Sending to: {} ({:?})", target.alias, target.ip);
+
     let client = build_client_for_target(sender, &target)?;
     let send_rate_limit = command
         .send_rate_limit_kib
         .map(|kib_per_second| kib_per_second.saturating_mul(1_024));
 
-    // Register first to ensure connection
-    let _ = client.register(&target).await;
+    // Register first to ensure connection; a receiver that is still starting
+    // up gets a couple of retries.
+    let _ = client
+        .register_with_retry(&target, 2, Duration::from_millis(250))
+        .await;
 
-    let mut file_metadata_map: HashMap<String, FileSource> = HashMap::new();
-    let mut files_metadata = HashMap::new();
+    let (file_metadata_map, files_metadata) =
+        build_offer(&command.files, command.input_mode(), command.include_hidden).await?;
+    #[cfg(feature = "thumbnails")]
+    let files_metadata = if command.previews {
+        add_previews(&file_metadata_map, files_metadata).await
+    } else {
+        files_metadata
+    };
+
+    let Some(upload_response) = client
+        .prepare_upload(&target, files_metadata, command.pin.as_deref())
+        .await?
+    else {
+        // 204 No Content - likely text message sent successfully
+        println!("Transfer completed successfully (No files needed to be transferred).");
+        return Ok(());
+    };
 
-    enum FileSource {
-        Path(PathBuf),
-        Text(String),
+    println!("Session ID: {}", upload_response.session_id);
+
+    let mut transfer_total = 0;
+    for file_id in upload_response.files.keys() {
+        if let Some(FileSource::Path(path)) = file_metadata_map.get(file_id.as_str()) {
+            transfer_total += tokio::fs::metadata(path).await?.len();
+        }
+    }
+    let progress = Arc::new(TransferProgress::new(transfer_total));
+
+    let uploads = upload_response.files.iter().map(|(file_id, token)| {
+        let (client, target, progress) = (&client, &target, &progress);
+        let session_id = &upload_response.session_id;
+        let source = file_metadata_map.get(file_id.as_str());
+        async move {
+            let source =
+                source.ok_or_else(|| anyhow::anyhow!("File not found for ID: {}", file_id))?;
+            match source {
+                FileSource::Path(path) => {
+                    let file_size = tokio::fs::metadata(path).await?.len();
+                    println!("Uploading: {} ({} bytes)", path.display(), file_size);
+
+                    client
+                        .upload_file_with_rate_limit(
+                            target,
+                            session_id,
+                            file_id,
+                            token,
+                            path,
+                            Some(progress.callback()),
+                            send_rate_limit,
+                        )
+                        .await
+                        .with_context(|| format!("uploading {}", path.display()))?;
+
+                    println!(
+                        "Success: {}",
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    );
+                }
+                FileSource::Text(text) => {
+                    println!("Sending text message: \"{}\"", text);
+                    client
+                        .upload_bytes(target, session_id, file_id, token, text.as_bytes().to_vec())
+                        .await
+                        .context("sending text message")?;
+                    println!("Success: Text message sent");
+                }
+            }
+            anyhow::Ok(())
+        }
+    });
+    // Every upload runs to its end, so one failure does not hide the others.
+    let all_uploads = futures_util::stream::iter(uploads)
+        .buffer_unordered(usize::from(command.concurrency))
+        .filter_map(|result| async move { result.err() })
+        .collect::<Vec<anyhow::Error>>();
+    let failures = tokio::select! {
+        failures = all_uploads => failures,
+        _ = tokio::signal::ctrl_c() => {
+            cancel_session(&client, &target, &upload_response.session_id).await;
+            anyhow::bail!("Transfer interrupted");
+        }
+    };
+
+    if !failures.is_empty() {
+        for failure in &failures {
+            eprintln!("Failed: {failure:#}");
+        }
+        cancel_session(&client, &target, &upload_response.session_id).await;
+        anyhow::bail!(
+            "{} of {} file(s) failed to upload",
+            failures.len(),
+            upload_response.files.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Close the session on the receiver, so it does not hold it open for files
+/// that will not come. Best effort: the send is failing already.
+async fn cancel_session(client: &LocalSendClient, target: &DeviceInfo, session_id: &SessionId) {
+    if let Err(error) = client.cancel(target, session_id).await {
+        eprintln!("Could not cancel the session on the receiver: {error}");
+    }
+}
+
+/// Overall progress of the files one `send` uploads concurrently, drawn as a
+/// single stderr line that is redrawn once per whole percent.
+struct TransferProgress {
+    total: u64,
+    sent: std::sync::atomic::AtomicU64,
+    last_percent: std::sync::atomic::AtomicU64,
+}
+
+impl TransferProgress {
+    fn new(total: u64) -> Self {
+        Self {
+            total,
+            sent: std::sync::atomic::AtomicU64::new(0),
+            last_percent: std::sync::atomic::AtomicU64::new(u64::MAX),
+        }
+    }
+
+    /// A callback for one file; it adds what that file sends to the total.
+    fn callback(self: &Arc<Self>) -> ProgressCallback {
+        let progress = self.clone();
+        let file_sent = std::sync::atomic::AtomicU64::new(0);
+        Box::new(move |sent, _, _| {
+            let delta = sent.saturating_sub(file_sent.swap(sent, Ordering::Relaxed));
+            let sent = progress.sent.fetch_add(delta, Ordering::Relaxed) + delta;
+            progress.report(sent);
+        })
+    }
+
+    fn report(&self, sent: u64) {
+        let ratio = if self.total == 0 {
+            1.0
+        } else {
+            sent as f64 / self.total as f64
+        };
+        let percent = (ratio * 100.0) as u64;
+        if self.last_percent.swap(percent, Ordering::Relaxed) == percent {
+            return;
+        }
+        eprint!("\r  {}", progress_line(sent, self.total, ratio));
+        if sent >= self.total {
+            eprintln!();
+        }
+    }
+}
+
+fn progress_line(sent: u64, total: u64, ratio: f64) -> String {
+    format!("{:>3}% ({sent} / {total} bytes)", (ratio * 100.0) as u64)
+}
+
+enum FileSource {
+    Path(PathBuf),
+    Text(String),
+}
+
+type Offer = (
+    HashMap<String, FileSource>,
+    HashMap<crate::protocol::FileId, FileMetadata>,
+);
+
+/// Give every offered image file its thumbnail; text and other files are
+/// left as they are.
+#[cfg(feature = "thumbnails")]
+async fn add_previews(
+    sources: &HashMap<String, FileSource>,
+    mut files: HashMap<crate::protocol::FileId, FileMetadata>,
+) -> HashMap<crate::protocol::FileId, FileMetadata> {
+    for (id, meta) in files.iter_mut() {
+        if let Some(FileSource::Path(path)) = sources.get(id.as_str()) {
+            meta.preview = crate::core::file::image_preview(path, meta).await;
+        }
     }
+    files
+}
 
-    for input in &command.files {
+/// Turn the command-line inputs into the offered files: a directory expands
+/// to the files below it (named relative to it), an existing file is sent as
+/// is, and anything else is sent as a text message.
+async fn build_offer(
+    inputs: &[String],
+    mode: InputMode,
+    include_hidden: bool,
+) -> anyhow::Result<Offer> {
+    let mut file_metadata_map: HashMap<String, FileSource> = HashMap::new();
+    let mut files_metadata = HashMap::new();
+
+    for input in inputs {
         let path = PathBuf::from(input);
-        if path.exists() {
-            let file_meta = build_file_metadata(&path).await?;
+        let kind = classify_input(input, mode)?;
+        if kind == InputKind::Directory {
+            for file in collect_directory_files(&path, include_hidden).await? {
+                let mut file_meta = build_file_metadata_with_hash(&file.path).await?;
+                file_meta.file_name = file.relative_name;
+                file_metadata_map.insert(
+                    file_meta.id.as_str().to_string(),
+                    FileSource::Path(file.path),
+                );
+                files_metadata.insert(file_meta.id.clone(), file_meta);
+            }
+        } else if kind == InputKind::File {
+            let file_meta = build_file_metadata_with_hash(&path).await?;
             file_metadata_map.insert(file_meta.id.as_str().to_string(), FileSource::Path(path));
             files_metadata.insert(file_meta.id.clone(), file_meta);
         } else {
@@ -88,75 +470,10 @@ pub async fn execute(command: SendCommand) -> anyhow::Result<()> {
         }
     }
 
-    let upload_response = client
-        .prepare_upload(&target, files_metadata, command.pin.as_deref())
-        .await?;
-
-    println!("Session ID: {}", upload_response.session_id);
-
-    if upload_response.session_id.as_str().is_empty() {
-        // 204 No Content - likely text message sent successfully
-        println!("Transfer completed successfully (No files needed to be transferred).");
-        return Ok(());
-    }
-
-    for (file_id, token) in &upload_response.files {
-        let source = file_metadata_map
-            .get(file_id.as_str())
-            .ok_or_else(|| anyhow::anyhow!("File not found for ID: {}", file_id))?;
-
-        match source {
-            FileSource::Path(path) => {
-                let file_size = tokio::fs::metadata(path).await?.len();
-                println!("Uploading: {} ({} bytes)", path.display(), file_size);
-
-                client
-                    .upload_file_with_rate_limit(
-                        &target,
-                        &upload_response.session_id,
-                        file_id,
-                        token,
-                        path,
-                        None,
-                        send_rate_limit,
-                    )
-                    .await?;
-
-                println!(
-                    "Success: {}",
-                    path.file_name().unwrap_or_default().to_string_lossy()
-                );
-            }
-            FileSource::Text(text) => {
-                println!("Sending text message: \"{}\"", text);
-                // We need to write text to a temp file or modify client to accept bytes.
-                // For now, let's just write to a temp file.
-                let temp_dir = std::env::temp_dir();
-                let temp_file = temp_dir.join(format!("localsend_text_{}.txt", file_id));
-                tokio::fs::write(&temp_file, text.as_bytes()).await?;
-
-                client
-                    .upload_file_with_rate_limit(
-                        &target,
-                        &upload_response.session_id,
-                        file_id,
-                        token,
-                        &temp_file,
-                        None,
-                        send_rate_limit,
-                    )
-                    .await?;
-
-                let _ = tokio::fs::remove_file(temp_file).await;
-                println!("Success: Text message sent");
-            }
-        }
-    }
-
-    Ok(())
+    Ok((file_metadata_map, files_metadata))
 }
 
-fn build_client_for_target(
+pub(super) fn build_client_for_target(
     sender: DeviceInfo,
     target: &DeviceInfo,
 ) -> anyhow::Result<LocalSendClient> {
@@ -205,9 +522,9 @@ fn split_host_port(target: &str) -> (String, Option<u16>) {
     }
 }
 
-async fn resolve_target(target: &str) -> anyhow::Result<DeviceInfo> {
+pub(super) async fn resolve_target(target: &str) -> anyhow::Result<DeviceInfo> {
     let (host, explicit_port) = split_host_port(target);
-    let port = explicit_port.unwrap_or(crate::protocol::constants::DEFAULT_HTTP_PORT);
+    let port = explicit_port.unwrap_or(DEFAULT_HTTP_PORT);
 
     // 1. Try if host is an IP address
     if let Ok(ip) = host.parse::<IpAddr>() {
@@ -228,75 +545,78 @@ async fn resolve_target(target: &str) -> anyhow::Result<DeviceInfo> {
         crate::protocol::Protocol::Https,
     )?;
 
-    let found_device = std::sync::Arc::new(std::sync::Mutex::new(None as Option<DeviceInfo>));
-    let found_device_clone = found_device.clone();
+    let (matches_tx, matches_rx) = mpsc::unbounded_channel();
     let host_owned = host.clone();
 
-    discovery.on_discovered(move |device: DeviceInfo| {
-        let matches_alias = device.alias == host_owned;
-        let matches_ip = device.ip.as_deref() == Some(host_owned.as_str());
-        // If the user pinned a port, require it too; otherwise match on alias/ip alone.
-        let matches_port = explicit_port.is_none_or(|p| device.port == p);
-
-        if (matches_alias || matches_ip) && matches_port {
-            let mut found = found_device_clone.lock().unwrap();
-            if found.is_none() {
-                *found = Some(device);
-            }
+    discovery.on_discovered(Box::new(move |device: DeviceInfo| {
+        if matches_target(&device, &host_owned, explicit_port) {
+            let _ = matches_tx.send(device);
         }
-    });
+    }));
 
     discovery.start().await?;
     // Send announcement to trigger responses
     discovery.announce_presence().await?;
 
     println!("Searching for device '{}'...", target);
-    for _ in 0..50 {
-        // Wait up to 5 seconds
-        if found_device.lock().unwrap().is_some() {
-            break;
-        }
-        tokio::time::sleep(Duration::from_millis(100)).await;
-    }
-
+    let found = first_match(matches_rx, RESOLVE_WINDOW).await;
     discovery.stop();
 
-    let found = found_device.lock().unwrap();
-    if let Some(device) = found.clone() {
-        Ok(device)
-    } else {
-        anyhow::bail!("Could not resolve target: {}", target);
-    }
+    found.ok_or_else(|| anyhow::anyhow!("Could not resolve target: {}", target))
+}
+
+/// How long `resolve_target` listens for the named device.
+const RESOLVE_WINDOW: Duration = Duration::from_secs(5);
+
+/// The first matching device, as soon as it is reported, or `None` once
+/// `window` passes without one.
+async fn first_match(
+    mut matches: mpsc::UnboundedReceiver<DeviceInfo>,
+    window: Duration,
+) -> Option<DeviceInfo> {
+    tokio::time::timeout(window, matches.recv())
+        .await
+        .ok()
+        .flatten()
+}
+
+fn matches_target(device: &DeviceInfo, host: &str, explicit_port: Option<u16>) -> bool {
+    let matches_alias = device.alias == host;
+    let matches_ip = device.ip.as_deref() == Some(host);
+    // If the user pinned a port, require it too; otherwise match on alias/ip alone.
+    let matches_port = explicit_port.is_none_or(|p| device.port == p);
+    (matches_alias || matches_ip) && matches_port
+}
+
+/// Look `target` up in a `discover --output` file, matching it the same way a
+/// live discovery would.
+fn resolve_from_file(path: &Path, target: &str) -> anyhow::Result<DeviceInfo> {
+    let contents =
+        std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let devices: Vec<DeviceInfo> = serde_json::from_str(&contents)
+        .with_context(|| format!("{} is not a saved device list", path.display()))?;
+    let (host, explicit_port) = split_host_port(target);
+    devices
+        .into_iter()
+        .find(|device| matches_target(device, &host, explicit_port))
+        .ok_or_else(|| anyhow::anyhow!("No device matching {target} in {}", path.display()))
 }
 
 async fn probe_device(ip: String, port: u16) -> anyhow::Result<DeviceInfo> {
-    let client = Client::builder()
-        .danger_accept_invalid_certs(true)
-        .timeout(Duration::from_secs(2))
-        .build()?;
-
-    // Try HTTPS first
-    let url = format!("https://{}:{}/api/localsend/v2/info", ip, port);
-    if let Ok(resp) = client.get(&url).send().await
-        && resp.status().is_success()
-    {
-        let mut device: DeviceInfo = resp.json().await?;
-        device.ip = Some(ip.clone());
-        device.port = port;
-        device.protocol = crate::protocol::Protocol::Https; // Ensure protocol is set matches what we used
-        return Ok(device);
-    }
-
-    // Try HTTP
-    let url = format!("http://{}:{}/api/localsend/v2/info", ip, port);
-    if let Ok(resp) = client.get(&url).send().await
-        && resp.status().is_success()
-    {
-        let mut device: DeviceInfo = resp.json().await?;
-        device.ip = Some(ip.clone());
-        device.port = port;
-        device.protocol = crate::protocol::Protocol::Http;
-        return Ok(device);
+    // The fingerprint is not known yet, so any certificate is accepted here;
+    // the transfer itself pins whatever this probe reports.
+    let config = ClientConfig::default()
+        .with_accept_invalid_certs(true)
+        .with_request_timeout(Duration::from_secs(2));
+    let client = LocalSendClient::new(DeviceInfo::new("LocalSend-Rust".into(), 0, Protocol::Http))
+        .with_config(config);
+
+    for protocol in [Protocol::Https, Protocol::Http] {
+        let mut target = DeviceInfo::new(String::new(), port, protocol);
+        target.ip = Some(ip.clone());
+        if let Ok(device) = client.fetch_info(&target).await {
+            return Ok(device);
+        }
     }
 
     anyhow::bail!("Failed to probe device at {}:{}", ip, port)
@@ -304,9 +624,54 @@ async fn probe_device(ip: String, port: u16) -> anyhow::Result<DeviceInfo> {
 
 #[cfg(test)]
 mod tests {
-    use super::{SendCommand, build_client_for_target, split_host_port, target_trust_policy};
+    use super::{
+        FileSource, InputKind, InputMode, SendCommand, TransferProgress, build_client_for_target,
+        build_offer, classify_input, default_sender, first_match, progress_line, resolve_from_file,
+        send_then_serve, split_host_port, target_trust_policy,
+    };
+    use crate::client::LocalSendClient;
+    use crate::core::file::build_file_metadata_with_hash;
     use crate::protocol::{DeviceInfo, Protocol};
+    use crate::server::LocalSendServer;
     use clap::Parser;
+    use std::collections::HashMap;
+
+    #[test]
+    fn progress_line_shows_percent_and_bytes() {
+        assert_eq!(progress_line(0, 2_048, 0.0), "  0% (0 / 2048 bytes)");
+        assert_eq!(progress_line(1_024, 2_048, 0.5), " 50% (1024 / 2048 bytes)");
+        assert_eq!(progress_line(2_048, 2_048, 1.0), "100% (2048 / 2048 bytes)");
+    }
+
+    #[test]
+    fn transfer_progress_sums_the_files_uploading_at_once() {
+        let progress = std::sync::Arc::new(TransferProgress::new(300));
+        let (a, b) = (progress.callback(), progress.callback());
+        a(0, 100, 0.0);
+        b(0, 200, 0.0);
+        a(60, 100, 0.6);
+        b(150, 200, 0.75);
+        a(100, 100, 1.0);
+        assert_eq!(
+            progress.sent.load(std::sync::atomic::Ordering::Relaxed),
+            250
+        );
+        b(200, 200, 1.0);
+        assert_eq!(
+            progress.sent.load(std::sync::atomic::Ordering::Relaxed),
+            300
+        );
+    }
+
+    #[test]
+    fn concurrency_defaults_to_four_and_rejects_zero() {
+        let command = SendCommand::try_parse_from(["send", "127.0.0.1", "a.txt"]).unwrap();
+        assert_eq!(command.concurrency, 4);
+        assert!(
+            SendCommand::try_parse_from(["send", "--concurrency", "0", "127.0.0.1", "a.txt"])
+                .is_err()
+        );
+    }
 
     #[test]
     fn bare_ipv4_has_no_port() {
@@ -403,4 +768,363 @@ mod tests {
 
         assert_eq!(target_trust_policy(&target), None);
     }
+
+    #[tokio::test]
+    async fn keep_serving_leaves_the_local_receiver_answering_info() {
+        let target_dir = tempfile::tempdir().expect("target directory");
+        let (mut target, _events) = LocalSendServer::builder()
+            .alias("keep-serving-target")
+            .port(0)
+            .save_dir(target_dir.path())
+            .protocol(Protocol::Http)
+            .auto_accept(true)
+            .build()
+            .await
+            .expect("start target receiver");
+
+        let local_dir = tempfile::tempdir().expect("local directory");
+        let file = local_dir.path().join("hello.txt");
+        std::fs::write(&file, b"hello").expect("write file");
+        let target_addr = format!("127.0.0.1:{}", target.port());
+        let command = SendCommand::try_parse_from([
+            "send".as_ref(),
+            "--keep-serving".as_ref(),
+            "--serve-port".as_ref(),
+            "0".as_ref(),
+            "--directory".as_ref(),
+            local_dir.path().as_os_str(),
+            target_addr.as_ref(),
+            file.as_os_str(),
+        ])
+        .expect("parse keep-serving send");
+
+        let (mut server, _events) = send_then_serve(&command)
+            .await
+            .expect("send succeeds")
+            .expect("--keep-serving returns the running receiver");
+
+        let scheme = match server.device().protocol {
+            Protocol::Https => "https",
+            Protocol::Http => "http",
+        };
+        let info: DeviceInfo = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .build()
+            .expect("build client")
+            .get(format!(
+                "{scheme}://127.0.0.1:{}/api/localsend/v2/info",
+                server.port()
+            ))
+            .send()
+            .await
+            .expect("local receiver answers /info")
+            .json()
+            .await
+            .expect("decode /info");
+        assert_eq!(info.fingerprint, server.device().fingerprint);
+
+        server.stop().await;
+        target.stop().await;
+    }
+
+    #[tokio::test]
+    async fn alias_flag_names_the_sender_in_the_prepare_request() {
+        let target_dir = tempfile::tempdir().expect("target directory");
+        let (mut target, mut events) = LocalSendServer::builder()
+            .alias("alias-target")
+            .port(0)
+            .save_dir(target_dir.path())
+            .protocol(Protocol::Http)
+            .auto_accept(true)
+            .build()
+            .await
+            .expect("start target receiver");
+
+        let local_dir = tempfile::tempdir().expect("local directory");
+        let file = local_dir.path().join("hello.txt");
+        std::fs::write(&file, b"hello").expect("write file");
+        let target_addr = format!("127.0.0.1:{}", target.port());
+        let command = SendCommand::try_parse_from([
+            "send".as_ref(),
+            "--alias".as_ref(),
+            "Kitchen Laptop".as_ref(),
+            target_addr.as_ref(),
+            file.as_os_str(),
+        ])
+        .expect("parse send with alias");
+        assert!(send_then_serve(&command).await.expect("send").is_none());
+
+        let mut sender_alias = None;
+        while let Ok(event) = events.try_recv() {
+            if let crate::server::ServerEvent::FileReceived {
+                sender_alias: alias,
+                ..
+            } = event
+            {
+                sender_alias = Some(alias);
+            }
+        }
+        assert_eq!(sender_alias.as_deref(), Some("Kitchen Laptop"));
+
+        target.stop().await;
+    }
+
+    #[tokio::test]
+    async fn concurrent_uploads_deliver_every_file() {
+        let target_dir = tempfile::tempdir().expect("target directory");
+        let (mut target, _events) = LocalSendServer::builder()
+            .alias("concurrent-target")
+            .port(0)
+            .save_dir(target_dir.path())
+            .protocol(Protocol::Http)
+            .auto_accept(true)
+            .build()
+            .await
+            .expect("start target receiver");
+
+        let local_dir = tempfile::tempdir().expect("local directory");
+        let files: Vec<_> = (0..6)
+            .map(|i| {
+                let file = local_dir.path().join(format!("part-{i}.bin"));
+                std::fs::write(&file, vec![i as u8; 4_096 * (i + 1)]).expect("write file");
+                file
+            })
+            .collect();
+        let target_addr = format!("127.0.0.1:{}", target.port());
+        let mut args = vec![
+            "send".into(),
+            "--concurrency".into(),
+            "3".into(),
+            target_addr.into(),
+        ];
+        args.extend(files.iter().map(|file| file.clone().into_os_string()));
+        let command = SendCommand::try_parse_from::<_, std::ffi::OsString>(args)
+            .expect("parse concurrent send");
+        assert!(send_then_serve(&command).await.expect("send").is_none());
+
+        for (i, file) in files.iter().enumerate() {
+            let received = std::fs::read(target_dir.path().join(file.file_name().unwrap()))
+                .expect("file arrived");
+            assert_eq!(received, vec![i as u8; 4_096 * (i + 1)]);
+        }
+
+        target.stop().await;
+    }
+
+    #[tokio::test]
+    async fn a_failed_upload_cancels_the_session_on_the_receiver() {
+        let target_dir = tempfile::tempdir().expect("target directory");
+        // A file where the sent folder should go makes the upload fail.
+        std::fs::write(target_dir.path().join("album"), b"in the way").expect("blocker");
+        let (mut target, _events) = LocalSendServer::builder()
+            .alias("blocked-target")
+            .port(0)
+            .save_dir(target_dir.path())
+            .protocol(Protocol::Http)
+            .auto_accept(true)
+            .build()
+            .await
+            .expect("start target receiver");
+
+        let local_dir = tempfile::tempdir().expect("local directory");
+        let album = local_dir.path().join("album");
+        std::fs::create_dir(&album).expect("album");
+        std::fs::write(album.join("a.jpg"), b"jpeg").expect("write file");
+        let target_addr = format!("127.0.0.1:{}", target.port());
+        let command = SendCommand::try_parse_from([
+            "send",
+            &target_addr,
+            album.to_str().expect("utf-8 path"),
+        ])
+        .expect("parse send");
+        let Err(error) = send_then_serve(&command).await else {
+            panic!("the upload should fail");
+        };
+        assert!(error.to_string().contains("1 of 1"), "{error:#}");
+
+        // The session is gone, so the receiver takes the next offer at once.
+        let client = LocalSendClient::new(default_sender("again".into(), None));
+        let mut device = DeviceInfo::new("blocked-target".into(), target.port(), Protocol::Http);
+        device.ip = Some("127.0.0.1".into());
+        let meta = build_file_metadata_with_hash(&album.join("a.jpg"))
+            .await
+            .expect("metadata");
+        let next = client
+            .prepare_upload(&device, HashMap::from([(meta.id.clone(), meta)]), None)
+            .await
+            .expect("a new session is accepted");
+        assert!(next.is_some());
+
+        target.stop().await;
+    }
+
+    #[tokio::test]
+    async fn text_argument_arrives_as_a_text_message_and_writes_no_file() {
+        let target_dir = tempfile::tempdir().expect("target directory");
+        let (mut target, mut events) = LocalSendServer::builder()
+            .alias("text-target")
+            .port(0)
+            .save_dir(target_dir.path())
+            .protocol(Protocol::Http)
+            .auto_accept(true)
+            .build()
+            .await
+            .expect("start target receiver");
+
+        let target_addr = format!("127.0.0.1:{}", target.port());
+        let command = SendCommand::try_parse_from([
+            "send",
+            "--alias",
+            "Texter",
+            &target_addr,
+            "pick up milk",
+        ])
+        .expect("parse text send");
+        assert!(send_then_serve(&command).await.expect("send").is_none());
+
+        let mut texts = Vec::new();
+        let mut session_done = false;
+        while let Ok(event) = events.try_recv() {
+            match event {
+                crate::server::ServerEvent::TextReceived {
+                    text, sender_alias, ..
+                } => texts.push((text, sender_alias)),
+                crate::server::ServerEvent::SessionDone { .. } => session_done = true,
+                _ => {}
+            }
+        }
+        assert_eq!(texts, [("pick up milk".to_string(), "Texter".to_string())]);
+        assert!(session_done);
+        // Text is surfaced as an event only; saving it is up to the consumer.
+        assert_eq!(std::fs::read_dir(target_dir.path()).unwrap().count(), 0);
+
+        target.stop().await;
+    }
+
+    #[test]
+    fn serve_options_require_keep_serving() {
+        SendCommand::try_parse_from(["send", "--serve-port", "53320", "127.0.0.1", "a.txt"])
+            .expect_err("--serve-port without --keep-serving should be rejected");
+    }
+
+    #[tokio::test]
+    async fn directory_send_skips_hidden_and_junk_files_by_default() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("album");
+        std::fs::create_dir_all(&dir).unwrap();
+        for file in ["photo.jpg", ".hidden", "Thumbs.db"] {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+        let inputs = [dir.to_string_lossy().to_string()];
+
+        let names = |offer: super::Offer| {
+            let mut names: Vec<_> = offer.1.into_values().map(|file| file.file_name).collect();
+            names.sort();
+            names
+        };
+        assert_eq!(
+            names(build_offer(&inputs, InputMode::Auto, false).await.unwrap()),
+            ["album/photo.jpg"]
+        );
+        assert_eq!(
+            names(build_offer(&inputs, InputMode::Auto, true).await.unwrap()),
+            ["album/.hidden", "album/Thumbs.db", "album/photo.jpg"]
+        );
+    }
+
+    #[tokio::test]
+    async fn text_flag_sends_an_existing_path_as_its_literal_string() {
+        let dir = tempfile::tempdir().unwrap();
+        let readme = dir.path().join("README.md");
+        std::fs::write(&readme, "# not me").unwrap();
+        let input = readme.to_string_lossy().to_string();
+
+        let command = SendCommand::try_parse_from(["send", "--text", "10.0.0.2", &input]).unwrap();
+        let (sources, files) = build_offer(&command.files, command.input_mode(), false)
+            .await
+            .unwrap();
+        let sent = sources.into_values().next().unwrap();
+        assert!(matches!(sent, FileSource::Text(ref text) if text == &input));
+        let meta = files.into_values().next().unwrap();
+        assert_eq!(meta.preview.as_deref(), Some(input.as_str()));
+
+        // Without the flag the same argument is still sent as the file,
+        // with its checksum declared.
+        assert_eq!(
+            classify_input(&input, InputMode::Auto).unwrap(),
+            InputKind::File
+        );
+        let (_, files) = build_offer(&command.files, InputMode::Auto, false)
+            .await
+            .unwrap();
+        let meta = files.into_values().next().unwrap();
+        assert_eq!(
+            meta.sha256,
+            Some(crate::crypto::sha256_from_bytes(b"# not me"))
+        );
+    }
+
+    #[test]
+    fn file_flag_refuses_to_fall_back_to_text() {
+        assert_eq!(
+            classify_input("hello there", InputMode::Auto).unwrap(),
+            InputKind::Text
+        );
+        assert!(classify_input("./missing.txt", InputMode::File).is_err());
+        SendCommand::try_parse_from(["send", "--text", "--file", "10.0.0.2", "x"])
+            .expect_err("--text and --file conflict");
+    }
+
+    #[test]
+    fn default_sender_advertises_the_protocol_version() {
+        assert_eq!(
+            default_sender("LocalSend-Rust".into(), None).version,
+            crate::protocol::PROTOCOL_VERSION
+        );
+    }
+
+    #[test]
+    fn send_resolves_a_target_from_a_saved_discovery() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("devices.json");
+        let mut laptop = DeviceInfo::new("Laptop".into(), 53317, Protocol::Https);
+        laptop.ip = Some("192.168.1.20".into());
+        let mut phone = DeviceInfo::new("Phone".into(), 53320, Protocol::Http);
+        phone.ip = Some("192.168.1.30".into());
+        super::super::discover::save_devices(&file, &[laptop.clone(), phone.clone()]).unwrap();
+
+        assert_eq!(resolve_from_file(&file, "Laptop").unwrap(), laptop);
+        assert_eq!(
+            resolve_from_file(&file, "192.168.1.30:53320").unwrap(),
+            phone
+        );
+        assert!(resolve_from_file(&file, "192.168.1.30:53317").is_err());
+        assert!(resolve_from_file(&file, "Tablet").is_err());
+    }
+
+    #[tokio::test]
+    async fn resolution_returns_as_soon_as_the_target_is_reported() {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let peer = DeviceInfo::new("Laptop".into(), 53317, Protocol::Http);
+        let injected = peer.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            let _ = tx.send(injected);
+        });
+
+        let started = std::time::Instant::now();
+        let found = first_match(rx, std::time::Duration::from_secs(5)).await;
+        assert_eq!(found, Some(peer));
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(1),
+            "resolution waited {:?} instead of returning on the match",
+            started.elapsed()
+        );
+
+        let (_tx, silent) = tokio::sync::mpsc::unbounded_channel::<DeviceInfo>();
+        assert_eq!(
+            first_match(silent, std::time::Duration::from_millis(50)).await,
+            None
+        );
+    }
 }
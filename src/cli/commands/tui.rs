@@ -1,6 +1,7 @@
 //! TUI command for interactive terminal interface.
 
 use clap::Parser;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(name = "tui", about = "Launch interactive TUI mode")]
@@ -21,6 +22,32 @@ pub struct TuiCommand {
     #[arg(long)]
     pub auto_accept: bool,
 
+    /// Move Inbox items past the newest 500 into this JSON-lines file
+    /// instead of keeping them in memory.
+    #[arg(long)]
+    pub history: Option<PathBuf>,
+
+    /// Record received files in `.localsend-received.jsonl` in the save
+    /// directory, and start the Inbox from what it already holds.
+    #[arg(long)]
+    pub index: bool,
+
+    /// Attach a small thumbnail to each image sent, for the receiver's
+    /// accept prompt.
+    #[cfg(feature = "thumbnails")]
+    #[arg(long)]
+    pub previews: bool,
+
+    /// Keep this device's alias, fingerprint and HTTPS certificate here
+    /// instead of in `tui` under the default config directory.
+    #[arg(long, conflicts_with = "ephemeral")]
+    pub identity_dir: Option<PathBuf>,
+
+    /// Start as a new device, with a fresh alias and fingerprint, and keep
+    /// nothing.
+    #[arg(long)]
+    pub ephemeral: bool,
+
     /// Use plain HTTP instead of HTTPS. LocalSend uses HTTPS by default (matching
     /// the official app); pass this for easy interop/testing with HTTP-only peers.
     #[cfg(feature = "https")]
@@ -33,16 +60,36 @@ pub async fn execute(command: TuiCommand) -> anyhow::Result<()> {
     let https = !command.no_https;
     #[cfg(not(feature = "https"))]
     let https = false;
-
-    crate::tui::run_tui(
+    let protocol = if https {
+        crate::protocol::Protocol::Https
+    } else {
+        crate::protocol::Protocol::Http
+    };
+    let identity =
+        super::receive::load_identity(command.identity_dir, command.ephemeral, protocol, "tui")?;
+    let mut app = crate::tui::App::new(
         command.port,
         command.alias,
         https,
         command.pin,
         command.auto_accept,
+        identity,
     )
-    .await
-    .map_err(|e| anyhow::anyhow!("TUI error: {}", e))
+    .map_err(|e| anyhow::anyhow!("TUI error: {}", e))?;
+    if let Some(path) = command.history {
+        app = app.with_history_file(path);
+    }
+    if command.index {
+        app = app.with_index();
+    }
+    #[cfg(feature = "thumbnails")]
+    if command.previews {
+        app = app.with_previews();
+    }
+
+    crate::tui::run_tui(app)
+        .await
+        .map_err(|e| anyhow::anyhow!("TUI error: {}", e))
 }
 
 #[cfg(all(test, feature = "https"))]
@@ -5,6 +5,12 @@ pub mod commands;
 pub mod ui;
 
 pub use cli::{Cli, Commands};
+pub use commands::bench::BenchCommand;
+pub use commands::bench::execute as run_bench;
+#[cfg(feature = "encryption")]
+pub use commands::decrypt::DecryptCommand;
+#[cfg(feature = "encryption")]
+pub use commands::decrypt::execute as run_decrypt;
 pub use commands::discover::DiscoverCommand;
 pub use commands::discover::execute as run_discover;
 pub use commands::receive::ReceiveCommand;
@@ -1,7 +1,9 @@
+use crate::client::config::{ClientConfig, RetryPolicy};
 use crate::client::trust_policy::TlsTrustPolicy;
 use crate::error::{LocalSendError, Result};
 use crate::protocol::{
-    DeviceInfo, FileId, FileMetadata, PrepareUploadRequest, PrepareUploadResponse, SessionId, Token,
+    DeviceInfo, FileId, FileMetadata, PrepareUploadRequest, PrepareUploadResponse, SessionId,
+    Token, UploadQuery, UploadStatus,
 };
 use crosscopy_file_service::{
     AuthorizedLocalSendHttpRequest, FileTransferSource, FileV3HandoffHeaderSink,
@@ -11,69 +13,181 @@ use reqwest::{Body, Client as HttpClient, StatusCode};
 use std::collections::HashMap;
 #[cfg(feature = "https")]
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::fs::File;
+use tokio::io::{AsyncRead, AsyncSeekExt};
 use tokio_util::io::ReaderStream;
 
+/// Upload progress as `(bytes_sent, total_bytes, ratio)`, `ratio` in `0.0..=1.0`.
+/// Called once with `0.0` before the first byte goes out, after every chunk,
+/// and once with `1.0` when the receiver has acknowledged the whole body.
 pub type ProgressCallback = Box<dyn Fn(u64, u64, f64) + Send + Sync>;
 
+/// One sample from [`LocalSendClient::upload_file_with_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressUpdate {
+    /// Cumulative bytes handed to the connection so far.
+    Sent { bytes_sent: u64, total_bytes: u64 },
+    /// The receiver acknowledged the whole file. Always the last update.
+    Completed { total_bytes: u64 },
+}
+
+/// Buffered progress updates per upload. When a slow consumer lets this fill
+/// up, further `Sent` samples are dropped rather than stalling the upload; the
+/// next one delivered is cumulative, so nothing is lost but granularity. The
+/// last slot is kept for `Completed`, so that one is never dropped and never
+/// waits on the consumer either.
+const PROGRESS_CHANNEL_CAPACITY: usize = 64;
+
+/// `target`'s LocalSend API `endpoint`, with an IPv6 address in brackets.
+fn api_url(target: &DeviceInfo, endpoint: &str) -> Result<String> {
+    let ip = target
+        .ip
+        .as_ref()
+        .ok_or_else(|| LocalSendError::network("Target IP not provided"))?;
+    let host = if ip.contains(':') {
+        format!("[{ip}]")
+    } else {
+        ip.clone()
+    };
+    Ok(format!(
+        "{}://{}:{}/api/localsend/v2/{endpoint}",
+        target.protocol, host, target.port
+    ))
+}
+
+/// `request` with the receiver's PIN as a query parameter, when there is one.
+fn with_pin(request: reqwest::RequestBuilder, pin: Option<&str>) -> reqwest::RequestBuilder {
+    match pin {
+        Some(pin) => request.query(&[("pin", pin)]),
+        None => request,
+    }
+}
+
+fn http_client(trust: Option<&TlsTrustPolicy>, config: &ClientConfig) -> Result<HttpClient> {
+    let mut builder = HttpClient::builder();
+    if let Some(timeout) = config.connect_timeout {
+        builder = builder.connect_timeout(timeout);
+    }
+    match trust {
+        // A pinned fingerprint is never loosened by `accept_invalid_certs`.
+        Some(TlsTrustPolicy::PinnedFingerprint(fingerprint)) => {
+            #[cfg(feature = "https")]
+            {
+                let verifier = FingerprintVerifier::new(fingerprint.clone())?;
+                let tls_config = rustls::ClientConfig::builder()
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(verifier))
+                    .with_no_client_auth();
+                builder = builder.tls_backend_preconfigured(tls_config);
+            }
+
+            #[cfg(not(feature = "https"))]
+            {
+                let _ = fingerprint;
+                return Err(LocalSendError::network(
+                    "Pinned LocalSend TLS requires the https feature",
+                ));
+            }
+        }
+        Some(TlsTrustPolicy::InsecureForTests) => {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        None if config.accept_invalid_certs => {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        None => {}
+    }
+    builder.build().map_err(LocalSendError::from)
+}
+
 #[derive(Clone)]
 pub struct LocalSendClient {
     client: HttpClient,
     device: DeviceInfo,
+    config: ClientConfig,
+    source: HttpSource,
+}
+
+/// Where the HTTP client came from, so `with_config` knows whether it may
+/// rebuild it.
+#[derive(Clone)]
+enum HttpSource {
+    Default,
+    Trust(TlsTrustPolicy),
+    /// Supplied by the caller; never rebuilt.
+    Injected,
 }
 
 impl LocalSendClient {
     pub fn new(device: DeviceInfo) -> Self {
+        let config = ClientConfig::default();
         Self {
-            client: HttpClient::new(),
+            // Fails only where `reqwest::Client::new` would panic as well:
+            // the TLS backend cannot be initialised.
+            client: http_client(None, &config).expect("HTTP client builds"),
             device,
+            config,
+            source: HttpSource::Default,
         }
     }
 
-    pub fn with_trust_policy(device: DeviceInfo, policy: TlsTrustPolicy) -> Result<Self> {
-        let client = match policy {
-            TlsTrustPolicy::InsecureForTests => HttpClient::builder()
-                .danger_accept_invalid_certs(true)
-                .build()
-                .map_err(LocalSendError::from)?,
-            TlsTrustPolicy::PinnedFingerprint(fingerprint) => {
-                #[cfg(feature = "https")]
-                {
-                    let verifier = FingerprintVerifier::new(fingerprint)?;
-                    let tls_config = rustls::ClientConfig::builder()
-                        .dangerous()
-                        .with_custom_certificate_verifier(Arc::new(verifier))
-                        .with_no_client_auth();
-                    HttpClient::builder()
-                        .tls_backend_preconfigured(tls_config)
-                        .build()
-                        .map_err(LocalSendError::from)?
-                }
+    /// Use `client` for every request: custom root certificates, a proxy,
+    /// pool settings. It is taken as is; [`Self::with_config`] still applies
+    /// the per-request settings (timeouts, retries) but never replaces it, so
+    /// `connect_timeout` and `accept_invalid_certs` are the caller's to set.
+    pub fn with_http_client(device: DeviceInfo, client: HttpClient) -> Self {
+        Self {
+            client,
+            device,
+            config: ClientConfig::default(),
+            source: HttpSource::Injected,
+        }
+    }
 
-                #[cfg(not(feature = "https"))]
-                {
-                    let _ = fingerprint;
-                    return Err(LocalSendError::network(
-                        "Pinned LocalSend TLS requires the https feature",
-                    ));
-                }
+    /// Apply `config`. The HTTP client is rebuilt with its connect timeout
+    /// and certificate settings, keeping any trust policy in place; one from
+    /// [`Self::with_http_client`] is kept as is.
+    pub fn with_config(mut self, config: ClientConfig) -> Self {
+        let trust = match &self.source {
+            HttpSource::Default => None,
+            HttpSource::Trust(policy) => Some(policy),
+            HttpSource::Injected => {
+                self.config = config;
+                return self;
             }
         };
+        self.client = http_client(trust, &config).expect("HTTP client builds");
+        self.config = config;
+        self
+    }
+
+    pub fn with_trust_policy(device: DeviceInfo, policy: TlsTrustPolicy) -> Result<Self> {
+        let config = ClientConfig::default();
+        Ok(Self {
+            client: http_client(Some(&policy), &config)?,
+            device,
+            config,
+            source: HttpSource::Trust(policy),
+        })
+    }
 
-        Ok(Self { client, device })
+    /// Bound a control request (not an upload body) by the request timeout.
+    fn control(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.config.request_timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
     }
 
     pub async fn register(&self, target: &DeviceInfo) -> Result<DeviceInfo> {
-        let ip = target
-            .ip
-            .as_ref()
-            .ok_or_else(|| LocalSendError::network("Target IP not provided"))?;
-        let url = format!(
-            "{}://{}:{}/api/localsend/v2/register",
-            target.protocol, ip, target.port
-        );
+        let url = api_url(target, "register")?;
 
-        let response = self.client.post(&url).json(&self.device).send().await?;
+        let mut request = self.client.post(&url).json(&self.device);
+        if let Some(timeout) = self.config.register_timeout.or(self.config.request_timeout) {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await?;
         let status = response.status();
 
         if status.is_success() {
@@ -83,7 +197,9 @@ impl LocalSendClient {
             }
 
             match serde_json::from_slice::<DeviceInfo>(&bytes) {
-                Ok(info) => Ok(info),
+                // A port nobody can connect to is no better than none.
+                Ok(info) if crate::protocol::validate_port(info.port).is_ok() => Ok(info),
+                Ok(_) => Ok(target.clone()),
                 Err(_e) => {
                     // If we successfully posted our info (200 OK) but can't parse the response,
                     // we still consider registration successful because the other device received our info.
@@ -103,45 +219,115 @@ impl LocalSendClient {
         }
     }
 
+    /// The peer's `/info`, without registering ourselves. `ip`, `port` and
+    /// `protocol` come from `target`: the official app leaves the latter two
+    /// out of its answer.
+    pub async fn fetch_info(&self, target: &DeviceInfo) -> Result<DeviceInfo> {
+        let url = api_url(target, "info")?;
+        let response = self.control(self.client.get(&url)).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(LocalSendError::http_failed(
+                status.as_u16(),
+                "Info request failed",
+            ));
+        }
+        let mut device: DeviceInfo = response.json().await?;
+        device.ip = target.ip.clone();
+        device.port = target.port;
+        device.protocol = target.protocol;
+        Ok(device)
+    }
+
+    /// [`Self::register`], retried up to `retries` more times while the
+    /// failure is [transient](LocalSendError::is_transient) (a peer still
+    /// booting, a dropped packet). The first retry waits `backoff` and each
+    /// later one twice as long. A refusal is returned at once.
+    pub async fn register_with_retry(
+        &self,
+        target: &DeviceInfo,
+        retries: u32,
+        backoff: std::time::Duration,
+    ) -> Result<DeviceInfo> {
+        let policy = RetryPolicy {
+            max_attempts: retries.saturating_add(1),
+            initial_backoff: backoff,
+            max_backoff: backoff.saturating_mul(2_u32.saturating_pow(retries)),
+            max_elapsed: std::time::Duration::MAX,
+        };
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let result = self.register(target).await;
+            let delay = match &result {
+                Err(error) if error.is_transient() => policy.next_delay(attempt, started.elapsed()),
+                _ => None,
+            };
+            let Some(delay) = delay else {
+                return result;
+            };
+            tracing::debug!(
+                "Registering with {} failed, retrying in {delay:?}",
+                target.alias
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Offer `files` to `target`. `None` means the receiver answered 204: it
+    /// took everything from the offer itself (a text message's `preview`)
+    /// and there is no session to upload into.
     pub async fn prepare_upload(
         &self,
         target: &DeviceInfo,
         files: HashMap<FileId, FileMetadata>,
         pin: Option<&str>,
-    ) -> Result<PrepareUploadResponse> {
-        let ip = target
-            .ip
-            .as_ref()
-            .ok_or_else(|| LocalSendError::network("Target IP not provided"))?;
-        let mut url = format!(
-            "{}://{}:{}/api/localsend/v2/prepare-upload",
-            target.protocol, ip, target.port
-        );
-
-        if let Some(pin_value) = pin {
-            url = format!("{}?pin={}", url, pin_value);
-        }
-
+    ) -> Result<Option<PrepareUploadResponse>> {
+        let url = api_url(target, "prepare-upload")?;
         let request = PrepareUploadRequest {
             info: self.device.clone(),
             files,
         };
 
-        let response = self.client.post(&url).json(&request).send().await?;
+        let started = Instant::now();
+        let mut attempt = 1;
+        loop {
+            let result = self.send_prepare_upload(&url, pin, &request).await;
+            let delay = match (&result, self.config.conflict_retry) {
+                (Err(LocalSendError::SessionBlocked), Some(policy)) => {
+                    policy.next_delay(attempt, started.elapsed())
+                }
+                _ => None,
+            };
+            let Some(delay) = delay else {
+                return result;
+            };
+            tracing::debug!("Receiver busy (409), retrying prepare-upload in {delay:?}");
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_prepare_upload(
+        &self,
+        url: &str,
+        pin: Option<&str>,
+        request: &PrepareUploadRequest,
+    ) -> Result<Option<PrepareUploadResponse>> {
+        let response = self
+            .control(with_pin(self.client.post(url), pin).json(request))
+            .send()
+            .await?;
 
         let status = response.status();
         match status {
             StatusCode::OK => {
                 let upload_response: PrepareUploadResponse = response.json().await?;
-                Ok(upload_response)
-            }
-            StatusCode::NO_CONTENT => {
-                // This happens when sending text messages or if the receiver accepted the metadata but needs no file transfer
-                Ok(PrepareUploadResponse {
-                    session_id: SessionId::from_string(String::new()),
-                    files: HashMap::new(),
-                })
+                Ok(Some(upload_response))
             }
+            // This happens when sending text messages or if the receiver accepted the metadata but needs no file transfer
+            StatusCode::NO_CONTENT => Ok(None),
             StatusCode::UNAUTHORIZED => Err(LocalSendError::InvalidPin),
             StatusCode::FORBIDDEN => Err(LocalSendError::Rejected {
                 status: status.as_u16(),
@@ -156,6 +342,33 @@ impl LocalSendClient {
         }
     }
 
+    /// List the files `target` is sharing in download mode, before calling
+    /// `prepare-download`. `pin` is the target's Web Share PIN, if it has one.
+    pub async fn fetch_shared_files(
+        &self,
+        target: &DeviceInfo,
+        pin: Option<&str>,
+    ) -> Result<Vec<FileMetadata>> {
+        let url = api_url(target, "shared")?;
+        let response = self
+            .control(with_pin(self.client.get(&url), pin))
+            .send()
+            .await?;
+        let status = response.status();
+        match status {
+            StatusCode::OK => Ok(response.json().await?),
+            StatusCode::UNAUTHORIZED => Err(LocalSendError::InvalidPin),
+            StatusCode::FORBIDDEN => Err(LocalSendError::Rejected {
+                status: status.as_u16(),
+            }),
+            StatusCode::TOO_MANY_REQUESTS => Err(LocalSendError::RateLimited),
+            _ => Err(LocalSendError::http_failed(
+                status.as_u16(),
+                "Listing shared files failed",
+            )),
+        }
+    }
+
     /// Send one File-v3-authorized regular file.  The caller transfers the
     /// redacting handoff owner by value; it is consumed precisely while this
     /// method writes the one protected `prepare-upload` header.  The subsequent
@@ -196,15 +409,8 @@ impl LocalSendClient {
         }
         let file_id = metadata.id.clone();
         let files = HashMap::from([(file_id.clone(), metadata)]);
-        let ip = target
-            .ip
-            .as_ref()
-            .ok_or_else(|| LocalSendError::network("Target IP not provided"))?;
-        let url = format!(
-            "{}://{}:{}/api/localsend/v2/prepare-upload",
-            target.protocol, ip, target.port
-        );
-        let mut headers = CrossCopyPrepareHeaders::new(self.client.post(&url));
+        let url = api_url(target, "prepare-upload")?;
+        let mut headers = CrossCopyPrepareHeaders::new(self.control(self.client.post(&url)));
         let _metadata = request.apply_handoff_header(&mut headers);
         let response = tokio::select! {
             biased;
@@ -268,6 +474,120 @@ impl LocalSendClient {
         .await
     }
 
+    /// How many bytes of `file_id` the receiver kept from an interrupted
+    /// upload, to resume from. A receiver that does not keep partials
+    /// (including the official app, which has no such endpoint) reports 0.
+    pub async fn upload_status(
+        &self,
+        target: &DeviceInfo,
+        session_id: &SessionId,
+        file_id: &FileId,
+        token: &Token,
+    ) -> Result<u64> {
+        let url = api_url(target, "upload-status")?;
+        let query = UploadQuery {
+            session_id: session_id.clone(),
+            file_id: file_id.clone(),
+            token: token.clone(),
+            offset: None,
+        };
+        let response = self
+            .control(self.client.get(&url).query(&query))
+            .send()
+            .await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(0),
+            status if status.is_success() => Ok(response.json::<UploadStatus>().await?.received),
+            status => Err(LocalSendError::http_failed(
+                status.as_u16(),
+                "Upload status request failed",
+            )),
+        }
+    }
+
+    /// Like [`Self::upload_file`], but first asks the receiver how much of the
+    /// file it kept from an earlier, interrupted attempt (see
+    /// [`Self::upload_status`]) and sends only the rest. Progress covers the
+    /// bytes sent this time.
+    pub async fn resume_file(
+        &self,
+        target: &DeviceInfo,
+        session_id: &SessionId,
+        file_id: &FileId,
+        token: &Token,
+        file_path: &std::path::Path,
+        progress: Option<ProgressCallback>,
+    ) -> Result<()> {
+        let offset = self
+            .upload_status(target, session_id, file_id, token)
+            .await?;
+        let mut file = File::open(file_path).await?;
+        let total_bytes = file.metadata().await?.len();
+        if offset > total_bytes {
+            return Err(LocalSendError::invalid_state(format!(
+                "Receiver holds {offset} bytes of a {total_bytes}-byte file"
+            )));
+        }
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        self.upload_reader_with_rate_limit(
+            target,
+            session_id,
+            file_id,
+            token,
+            file,
+            total_bytes - offset,
+            progress,
+            None,
+            Some(offset).filter(|offset| *offset > 0),
+        )
+        .await
+    }
+
+    /// Like [`Self::upload_file`], but reports progress on a channel instead of
+    /// a callback. Drive the returned future (e.g. in a `select!` with the
+    /// receiver); the receiver yields [`ProgressUpdate::Sent`] samples followed
+    /// by one [`ProgressUpdate::Completed`] if the upload succeeds. The future
+    /// never waits on the receiver, so it may also be awaited on its own and
+    /// the updates read afterwards.
+    pub fn upload_file_with_progress<'a>(
+        &'a self,
+        target: &'a DeviceInfo,
+        session_id: &'a SessionId,
+        file_id: &'a FileId,
+        token: &'a Token,
+        file_path: &'a std::path::Path,
+    ) -> (
+        impl std::future::Future<Output = Result<()>> + Send + 'a,
+        tokio::sync::mpsc::Receiver<ProgressUpdate>,
+    ) {
+        let (tx, rx) = tokio::sync::mpsc::channel(PROGRESS_CHANNEL_CAPACITY);
+        let upload = async move {
+            let total_bytes = tokio::fs::metadata(file_path).await?.len();
+            let sample_tx = tx.clone();
+            let progress: ProgressCallback = Box::new(move |bytes_sent, total_bytes, _ratio| {
+                if sample_tx.capacity() <= 1 {
+                    return;
+                }
+                let _ = sample_tx.try_send(ProgressUpdate::Sent {
+                    bytes_sent,
+                    total_bytes,
+                });
+            });
+            self.upload_file(
+                target,
+                session_id,
+                file_id,
+                token,
+                file_path,
+                Some(progress),
+            )
+            .await?;
+            let _ = tx.try_send(ProgressUpdate::Completed { total_bytes });
+            Ok(())
+        };
+        (upload, rx)
+    }
+
     /// Uploads a file while optionally pacing the source stream. The rate
     /// limit is intended for deterministic integration tests; normal callers
     /// should use [`Self::upload_file`].
@@ -282,20 +602,97 @@ impl LocalSendClient {
         progress: Option<ProgressCallback>,
         rate_limit_bytes_per_second: Option<u64>,
     ) -> Result<()> {
-        let ip = target
-            .ip
-            .as_ref()
-            .ok_or_else(|| LocalSendError::network("Target IP not provided"))?;
-        let url = format!(
-            "{}://{}:{}/api/localsend/v2/upload?sessionId={}&fileId={}&token={}",
-            target.protocol, ip, target.port, session_id, file_id, token
-        );
-
         // Stream the file instead of loading it all into memory
         let file = File::open(file_path).await?;
         let total_bytes = file.metadata().await?.len();
-        let started = std::time::Instant::now();
+        self.upload_reader_with_rate_limit(
+            target,
+            session_id,
+            file_id,
+            token,
+            file,
+            total_bytes,
+            progress,
+            rate_limit_bytes_per_second,
+            None,
+        )
+        .await
+    }
+
+    /// Upload `size` bytes read from `reader` (a decrypted stream, a network
+    /// source, an in-memory buffer) without staging them in a file. `size` is
+    /// sent as the `Content-Length` and must match the size declared in
+    /// prepare-upload; the receiver rejects a body that ends short of it.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn upload_reader<R>(
+        &self,
+        target: &DeviceInfo,
+        session_id: &SessionId,
+        file_id: &FileId,
+        token: &Token,
+        reader: R,
+        size: u64,
+        progress: Option<ProgressCallback>,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        self.upload_reader_with_rate_limit(
+            target, session_id, file_id, token, reader, size, progress, None, None,
+        )
+        .await
+    }
+
+    /// Upload an in-memory buffer, such as the body of a text message.
+    pub async fn upload_bytes(
+        &self,
+        target: &DeviceInfo,
+        session_id: &SessionId,
+        file_id: &FileId,
+        token: &Token,
+        bytes: Vec<u8>,
+    ) -> Result<()> {
+        let size = bytes.len() as u64;
+        self.upload_reader(
+            target,
+            session_id,
+            file_id,
+            token,
+            std::io::Cursor::new(bytes),
+            size,
+            None,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn upload_reader_with_rate_limit<R>(
+        &self,
+        target: &DeviceInfo,
+        session_id: &SessionId,
+        file_id: &FileId,
+        token: &Token,
+        reader: R,
+        total_bytes: u64,
+        progress: Option<ProgressCallback>,
+        rate_limit_bytes_per_second: Option<u64>,
+        offset: Option<u64>,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Send + 'static,
+    {
+        let url = api_url(target, "upload")?;
+        let query = UploadQuery {
+            session_id: session_id.clone(),
+            file_id: file_id.clone(),
+            token: token.clone(),
+            offset,
+        };
+
         let progress = progress.map(std::sync::Arc::new);
+        if let Some(cb) = progress.as_ref() {
+            cb(0, total_bytes, 0.0);
+        }
 
         // Wrap the file stream so every chunk that goes out over the wire
         // also advances a running byte counter and reports it upstream.
@@ -303,7 +700,7 @@ impl LocalSendClient {
         let throttled_bytes = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
         let throttle_counter = throttled_bytes.clone();
         let rate_limit_bytes_per_second = rate_limit_bytes_per_second.filter(|rate| *rate > 0);
-        let paced = ReaderStream::new(file).then(move |chunk| {
+        let paced = ReaderStream::new(reader).then(move |chunk| {
             let target_elapsed = chunk.as_ref().ok().and_then(|bytes| {
                 let cumulative = throttle_counter
                     .fetch_add(bytes.len() as u64, std::sync::atomic::Ordering::Relaxed)
@@ -327,7 +724,15 @@ impl LocalSendClient {
         let counted = paced.inspect(move |chunk| {
             if let (Ok(c), Some(cb)) = (chunk, counter_progress.as_ref()) {
                 sent += c.len() as u64;
-                cb(sent, total_bytes, started.elapsed().as_secs_f64());
+                // The final 1.0 is reserved for the acknowledged upload.
+                let ratio = if total_bytes == 0 {
+                    0.0
+                } else {
+                    (sent as f64 / total_bytes as f64).min(1.0)
+                };
+                if ratio < 1.0 {
+                    cb(sent, total_bytes, ratio);
+                }
             }
         });
         let body = Body::wrap_stream(counted);
@@ -335,6 +740,7 @@ impl LocalSendClient {
         let response = self
             .client
             .post(&url)
+            .query(&query)
             .header(reqwest::header::CONTENT_LENGTH, total_bytes)
             .body(body)
             .send()
@@ -342,7 +748,12 @@ impl LocalSendClient {
 
         let status = response.status();
         match status {
-            StatusCode::OK | StatusCode::NO_CONTENT => Ok(()),
+            StatusCode::OK | StatusCode::NO_CONTENT => {
+                if let Some(cb) = progress.as_ref() {
+                    cb(total_bytes, total_bytes, 1.0);
+                }
+                Ok(())
+            }
             _ => Err(LocalSendError::http_failed(
                 status.as_u16(),
                 "File upload failed",
@@ -351,15 +762,8 @@ impl LocalSendClient {
     }
 
     pub async fn cancel(&self, target: &DeviceInfo, session_id: &SessionId) -> Result<()> {
-        let ip = target
-            .ip
-            .as_ref()
-            .ok_or_else(|| LocalSendError::network("Target IP not provided"))?;
-        let url = format!(
-            "{}://{}:{}/api/localsend/v2/cancel?sessionId={}",
-            target.protocol, ip, target.port, session_id
-        );
-        let response = self.client.post(&url).send().await?;
+        let url = format!("{}?sessionId={}", api_url(target, "cancel")?, session_id);
+        let response = self.control(self.client.post(&url)).send().await?;
         if response.status().is_success() {
             Ok(())
         } else {
@@ -519,4 +923,422 @@ mod tests {
             Err(error) if error.to_string().contains("https feature")
         ));
     }
+
+    #[tokio::test]
+    async fn request_timeout_ends_a_prepare_upload_the_peer_never_answers() {
+        use crate::client::ClientConfig;
+        use std::time::Duration;
+
+        // Accepts connections and reads nothing, like a wedged peer.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+        let mut target = DeviceInfo::new("silent".to_string(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+
+        let client = LocalSendClient::new(DeviceInfo::new("sender".to_string(), 0, Protocol::Http))
+            .with_config(ClientConfig::default().with_request_timeout(Duration::from_millis(200)));
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.prepare_upload(&target, Default::default(), None),
+        )
+        .await
+        .expect("the request timeout fires long before the test's");
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn an_injected_http_client_is_used_and_survives_with_config() {
+        use crate::client::ClientConfig;
+        use std::sync::{Arc, Mutex};
+
+        let agents = Arc::new(Mutex::new(Vec::new()));
+        let seen = agents.clone();
+        let app = axum::Router::new().route(
+            "/api/localsend/v2/register",
+            axum::routing::post(move |headers: axum::http::HeaderMap| {
+                let seen = seen.clone();
+                async move {
+                    let agent = headers
+                        .get(axum::http::header::USER_AGENT)
+                        .and_then(|value| value.to_str().ok())
+                        .unwrap_or_default()
+                        .to_string();
+                    seen.lock().unwrap().push(agent);
+                    axum::http::StatusCode::OK
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let mut target = DeviceInfo::new("peer".to_string(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+
+        let http = reqwest::Client::builder()
+            .user_agent("embedding-app/1.0")
+            .build()
+            .unwrap();
+        let device = DeviceInfo::new("sender".to_string(), 0, Protocol::Http);
+        let client = LocalSendClient::with_http_client(device, http);
+        client.register(&target).await.unwrap();
+        let client = client.with_config(ClientConfig::default());
+        client.register(&target).await.unwrap();
+
+        assert_eq!(
+            *agents.lock().unwrap(),
+            ["embedding-app/1.0", "embedding-app/1.0"]
+        );
+    }
+
+    #[tokio::test]
+    async fn a_register_reply_with_an_unusable_port_keeps_the_target() {
+        let app = axum::Router::new().route(
+            "/api/localsend/v2/register",
+            axum::routing::post(|| async {
+                let mut peer = DeviceInfo::new("peer".to_string(), 0, Protocol::Http);
+                peer.fingerprint = "peer-fp".to_string();
+                axum::Json(peer)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let mut target = DeviceInfo::new("peer".to_string(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+
+        let client = LocalSendClient::new(DeviceInfo::new("sender".to_string(), 0, Protocol::Http));
+        let registered = client.register(&target).await.unwrap();
+        assert_eq!(registered.port, port);
+    }
+
+    #[test]
+    fn api_urls_bracket_ipv6_hosts() {
+        let mut target = DeviceInfo::new("peer".to_string(), 53317, Protocol::Https);
+        target.ip = Some("192.168.1.20".to_string());
+        assert_eq!(
+            super::api_url(&target, "info").unwrap(),
+            "https://192.168.1.20:53317/api/localsend/v2/info"
+        );
+        target.ip = Some("fd00::2".to_string());
+        assert_eq!(
+            super::api_url(&target, "info").unwrap(),
+            "https://[fd00::2]:53317/api/localsend/v2/info"
+        );
+        target.ip = None;
+        assert!(super::api_url(&target, "info").is_err());
+    }
+
+    #[tokio::test]
+    async fn fetch_info_takes_the_address_from_the_target() {
+        // The official app's answer: no port, no protocol.
+        let app = axum::Router::new().route(
+            "/api/localsend/v2/info",
+            axum::routing::get(|| async {
+                axum::Json(serde_json::json!({
+                    "alias": "Phone",
+                    "version": "2.1",
+                    "deviceType": "mobile",
+                    "fingerprint": "abc",
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let mut target = DeviceInfo::new(String::new(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+
+        let client = LocalSendClient::new(DeviceInfo::new("sender".to_string(), 0, Protocol::Http));
+        let device = client.fetch_info(&target).await.unwrap();
+        assert_eq!(device.alias, "Phone");
+        assert_eq!(device.fingerprint, "abc");
+        assert_eq!(device.ip.as_deref(), Some("127.0.0.1"));
+        assert_eq!(device.port, port);
+        assert_eq!(device.protocol, Protocol::Http);
+
+        // Something listening that is not a LocalSend peer.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        target.port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, axum::Router::new()).await });
+        assert!(matches!(
+            client.fetch_info(&target).await,
+            Err(crate::LocalSendError::HttpFailed { status: 404, .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn register_retries_transient_failures_but_not_refusals() {
+        use axum::http::StatusCode;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        // A register endpoint answering `failures` times with `failure`, then 200.
+        async fn peer(failure: StatusCode, failures: usize) -> (DeviceInfo, Arc<AtomicUsize>) {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let handler_calls = calls.clone();
+            let app = axum::Router::new().route(
+                "/api/localsend/v2/register",
+                axum::routing::post(move || {
+                    let calls = handler_calls.clone();
+                    async move {
+                        if calls.fetch_add(1, Ordering::SeqCst) < failures {
+                            failure
+                        } else {
+                            StatusCode::OK
+                        }
+                    }
+                }),
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            let mut target = DeviceInfo::new("peer".to_string(), port, Protocol::Http);
+            target.ip = Some("127.0.0.1".to_string());
+            (target, calls)
+        }
+        let client = LocalSendClient::new(DeviceInfo::new("sender".to_string(), 0, Protocol::Http));
+        let backoff = Duration::from_millis(10);
+
+        let (booting, calls) = peer(StatusCode::SERVICE_UNAVAILABLE, 2).await;
+        client
+            .register_with_retry(&booting, 3, backoff)
+            .await
+            .expect("third attempt registers");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let (down, calls) = peer(StatusCode::SERVICE_UNAVAILABLE, usize::MAX).await;
+        assert!(client.register_with_retry(&down, 1, backoff).await.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 2, "one try plus one retry");
+
+        let (refusing, calls) = peer(StatusCode::FORBIDDEN, usize::MAX).await;
+        assert!(matches!(
+            client.register_with_retry(&refusing, 3, backoff).await,
+            Err(crate::LocalSendError::Rejected { status: 403 })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "a refusal is final");
+    }
+
+    #[tokio::test]
+    async fn prepare_upload_retries_while_the_receiver_is_busy() {
+        use crate::client::{ClientConfig, RetryPolicy};
+        use axum::http::StatusCode;
+        use axum::response::IntoResponse;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::time::Duration;
+
+        // Busy for the first request, then accepts.
+        let calls = Arc::new(AtomicUsize::new(0));
+        let handler_calls = calls.clone();
+        let app = axum::Router::new().route(
+            "/api/localsend/v2/prepare-upload",
+            axum::routing::post(move || {
+                let calls = handler_calls.clone();
+                async move {
+                    if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+                        StatusCode::CONFLICT.into_response()
+                    } else {
+                        axum::Json(serde_json::json!({ "sessionId": "s1", "files": {} }))
+                            .into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut target = DeviceInfo::new("busy".to_string(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+        let sender = DeviceInfo::new("sender".to_string(), 0, Protocol::Http);
+
+        let no_retry = LocalSendClient::new(sender.clone());
+        assert!(matches!(
+            no_retry
+                .prepare_upload(&target, Default::default(), None)
+                .await,
+            Err(crate::LocalSendError::SessionBlocked)
+        ));
+
+        calls.store(0, Ordering::SeqCst);
+        let client = LocalSendClient::new(sender).with_config(
+            ClientConfig::default().with_conflict_retry(RetryPolicy {
+                initial_backoff: Duration::from_millis(10),
+                ..RetryPolicy::default()
+            }),
+        );
+        let response = client
+            .prepare_upload(&target, Default::default(), None)
+            .await
+            .expect("retry after 409 succeeds")
+            .expect("a session");
+        assert_eq!(response.session_id.as_str(), "s1");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn upload_query_percent_encodes_reserved_characters() {
+        use crate::protocol::{FileId, SessionId, Token, UploadQuery};
+        use std::sync::{Arc, Mutex};
+
+        let received = Arc::new(Mutex::new(None::<(UploadQuery, Option<String>)>));
+        let sink = received.clone();
+        let app = axum::Router::new().route(
+            "/api/localsend/v2/upload",
+            axum::routing::post(
+                move |uri: axum::http::Uri, axum::extract::Query(query): axum::extract::Query<UploadQuery>| {
+                    let sink = sink.clone();
+                    async move {
+                        *sink.lock().unwrap() = Some((query, uri.query().map(str::to_string)));
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut target = DeviceInfo::new("mock".to_string(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+        let client = LocalSendClient::new(DeviceInfo::new("sender".to_string(), 0, Protocol::Http));
+        let sent = UploadQuery {
+            session_id: SessionId::from_string("a b".to_string()),
+            file_id: FileId("x/y?z".to_string()),
+            token: Token::from_string("t&fileId=forged#frag+%".to_string()),
+            offset: None,
+        };
+
+        client
+            .upload_reader(
+                &target,
+                &sent.session_id,
+                &sent.file_id,
+                &sent.token,
+                std::io::Cursor::new(b"hi".to_vec()),
+                2,
+                None,
+            )
+            .await
+            .expect("upload with reserved characters in the query");
+
+        let (parsed, raw) = received
+            .lock()
+            .unwrap()
+            .take()
+            .expect("request reached the server");
+        assert_eq!(parsed, sent);
+        let raw = raw.expect("query string");
+        assert!(!raw.contains('#') && !raw.contains(' '), "{raw}");
+        assert_eq!(raw.matches("fileId=").count(), 1, "{raw}");
+    }
+
+    #[tokio::test]
+    async fn pins_with_reserved_characters_reach_the_receiver_intact() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(serde::Deserialize)]
+        struct PinQuery {
+            pin: String,
+        }
+
+        let received = Arc::new(Mutex::new(Vec::<String>::new()));
+        let (upload_sink, shared_sink) = (received.clone(), received.clone());
+        let app = axum::Router::new()
+            .route(
+                "/api/localsend/v2/prepare-upload",
+                axum::routing::post(
+                    move |axum::extract::Query(query): axum::extract::Query<PinQuery>| async move {
+                        upload_sink.lock().unwrap().push(query.pin);
+                        axum::http::StatusCode::NO_CONTENT
+                    },
+                ),
+            )
+            .route(
+                "/api/localsend/v2/shared",
+                axum::routing::get(
+                    move |axum::extract::Query(query): axum::extract::Query<PinQuery>| async move {
+                        shared_sink.lock().unwrap().push(query.pin);
+                        axum::Json(Vec::<crate::protocol::FileMetadata>::new())
+                    },
+                ),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut target = DeviceInfo::new("mock".to_string(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+        let client = LocalSendClient::new(DeviceInfo::new("sender".to_string(), 0, Protocol::Http));
+        let pin = "a&b#c+d%e f";
+
+        client
+            .prepare_upload(&target, Default::default(), Some(pin))
+            .await
+            .expect("prepare-upload with a reserved-character PIN");
+        client
+            .fetch_shared_files(&target, Some(pin))
+            .await
+            .expect("shared listing with a reserved-character PIN");
+        assert_eq!(*received.lock().unwrap(), [pin, pin]);
+    }
+
+    #[tokio::test]
+    async fn upload_reader_streams_an_in_memory_reader() {
+        use crate::protocol::{FileId, SessionId, Token};
+        use std::sync::{Arc, Mutex};
+
+        let received = Arc::new(Mutex::new(None::<(Vec<u8>, Option<String>)>));
+        let sink = received.clone();
+        let app = axum::Router::new().route(
+            "/api/localsend/v2/upload",
+            axum::routing::post(
+                move |headers: axum::http::HeaderMap, body: axum::body::Bytes| {
+                    let sink = sink.clone();
+                    async move {
+                        let length = headers
+                            .get(axum::http::header::CONTENT_LENGTH)
+                            .and_then(|value| value.to_str().ok())
+                            .map(str::to_string);
+                        *sink.lock().unwrap() = Some((body.to_vec(), length));
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let mut target = DeviceInfo::new("mock".to_string(), port, Protocol::Http);
+        target.ip = Some("127.0.0.1".to_string());
+        let client = LocalSendClient::new(DeviceInfo::new("sender".to_string(), 0, Protocol::Http));
+        let payload: Vec<u8> = (0..100_000_u32).map(|i| (i % 251) as u8).collect();
+
+        client
+            .upload_reader(
+                &target,
+                &SessionId::new(),
+                &FileId::new(),
+                &Token::random(),
+                std::io::Cursor::new(payload.clone()),
+                payload.len() as u64,
+                None,
+            )
+            .await
+            .expect("reader upload");
+
+        let (body, length) = received
+            .lock()
+            .unwrap()
+            .take()
+            .expect("mock saw the upload");
+        assert_eq!(body, payload);
+        assert_eq!(length.as_deref(), Some("100000"));
+    }
 }
@@ -0,0 +1,136 @@
+use std::time::Duration;
+
+/// How long connecting to a peer may take by default: long enough for a
+/// slow Wi-Fi hop, short enough that an unreachable host fails visibly.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tunables for a [`crate::client::LocalSendClient`], applied with
+/// [`crate::client::LocalSendClient::with_config`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// Retry `prepare-upload` while the receiver answers 409 (busy with
+    /// another session). `None` fails straight away with `SessionBlocked`.
+    pub conflict_retry: Option<RetryPolicy>,
+    /// Upper bound on a whole `register` round trip. `None` falls back to
+    /// `request_timeout`.
+    pub register_timeout: Option<Duration>,
+    /// Upper bound on establishing any connection, uploads included.
+    /// Defaults to [`DEFAULT_CONNECT_TIMEOUT`]; `None` leaves it to the OS.
+    pub connect_timeout: Option<Duration>,
+    /// Upper bound on a whole control request (`register`, `prepare-upload`,
+    /// `cancel`, listing shared files). Uploads are never cut off by it, so a
+    /// big file can take as long as it needs. `None` waits as long as the
+    /// peer keeps the connection open; note that `prepare-upload` stays open
+    /// while the receiver's user decides.
+    pub request_timeout: Option<Duration>,
+    /// Accept any TLS certificate, as [`crate::client::TlsTrustPolicy::InsecureForTests`]
+    /// does. For tests only.
+    pub accept_invalid_certs: bool,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            conflict_retry: None,
+            register_timeout: None,
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            request_timeout: None,
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn with_conflict_retry(mut self, policy: RetryPolicy) -> Self {
+        self.conflict_retry = Some(policy);
+        self
+    }
+
+    pub fn with_register_timeout(mut self, timeout: Duration) -> Self {
+        self.register_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_accept_invalid_certs(mut self, accept: bool) -> Self {
+        self.accept_invalid_certs = accept;
+        self
+    }
+}
+
+/// Bounded exponential backoff: the delay doubles from `initial_backoff` up to
+/// `max_backoff`, and retrying stops after `max_attempts` tries in total or
+/// once the next wait would run past `max_elapsed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub max_elapsed: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(5),
+            max_elapsed: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The wait before try number `attempt + 1`, given `elapsed` time spent so
+    /// far, or `None` when the budget is used up. `attempt` starts at 1.
+    pub fn next_delay(&self, attempt: u32, elapsed: Duration) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        let delay = self
+            .initial_backoff
+            .saturating_mul(2_u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+        (elapsed + delay <= self.max_elapsed).then_some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RetryPolicy;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap_and_stops_at_the_budget() {
+        let policy = RetryPolicy {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(300),
+            max_elapsed: Duration::from_secs(1),
+        };
+
+        assert_eq!(
+            policy.next_delay(1, Duration::ZERO),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(
+            policy.next_delay(2, Duration::ZERO),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(
+            policy.next_delay(3, Duration::ZERO),
+            Some(Duration::from_millis(300))
+        );
+        assert_eq!(policy.next_delay(5, Duration::ZERO), None);
+        assert_eq!(policy.next_delay(2, Duration::from_millis(900)), None);
+    }
+}
@@ -1,7 +1,9 @@
 #![allow(clippy::module_inception)]
 
 pub mod client;
+pub mod config;
 pub mod trust_policy;
 
-pub use client::LocalSendClient;
+pub use client::{LocalSendClient, ProgressCallback, ProgressUpdate};
+pub use config::{ClientConfig, RetryPolicy};
 pub use trust_policy::TlsTrustPolicy;
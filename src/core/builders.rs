@@ -1,6 +1,6 @@
 use crate::core::device::{get_device_model, get_device_type};
 use crate::crypto::generate_fingerprint;
-use crate::protocol::{DeviceInfo, DeviceType, PROTOCOL_VERSION, Protocol};
+use crate::protocol::{DeviceInfo, DeviceType, Protocol, advertised_version};
 
 /// Builder for DeviceInfo with sensible defaults
 #[derive(Clone, Debug)]
@@ -82,7 +82,7 @@ impl DeviceInfoBuilder {
     pub fn build(self) -> DeviceInfo {
         DeviceInfo {
             alias: self.alias,
-            version: PROTOCOL_VERSION.to_string(),
+            version: advertised_version(),
             device_model: self.device_model.or_else(|| Some(get_device_model())),
             device_type: self.device_type.or_else(|| Some(get_device_type())),
             fingerprint: self.fingerprint.unwrap_or_else(generate_fingerprint),
@@ -0,0 +1,92 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Source of "now" for session timeouts, so expiry can be tested without
+/// sleeping.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// The wall-clock time, for what is named after the date (such as
+    /// [`crate::server::SaveLayout::ByDate`] folders).
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// The real monotonic clock.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A manually advanced clock for tests. Clones share the same time.
+#[derive(Clone, Debug)]
+pub struct MockClock {
+    base: Instant,
+    wall_base: SystemTime,
+    offset: Arc<Mutex<Duration>>,
+}
+
+impl MockClock {
+    pub fn new() -> Self {
+        Self {
+            base: Instant::now(),
+            wall_base: SystemTime::now(),
+            offset: Arc::new(Mutex::new(Duration::ZERO)),
+        }
+    }
+
+    /// Start the wall clock at `time` instead of the real time.
+    pub fn with_wall_time(mut self, time: SystemTime) -> Self {
+        self.wall_base = time;
+        self
+    }
+
+    /// Move time forward by `by` for every clone of this clock.
+    pub fn advance(&self, by: Duration) {
+        let mut offset = self.offset.lock().unwrap_or_else(|e| e.into_inner());
+        *offset += by;
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.base + *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.wall_base + *self.offset.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Clock, MockClock};
+    use std::time::{Duration, SystemTime};
+
+    #[test]
+    fn mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new().with_wall_time(SystemTime::UNIX_EPOCH);
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+        assert_eq!(clock.wall_time(), SystemTime::UNIX_EPOCH);
+
+        clock.clone().advance(Duration::from_secs(5));
+        assert_eq!(clock.now() - start, Duration::from_secs(5));
+        assert_eq!(
+            clock.wall_time(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(5)
+        );
+    }
+}
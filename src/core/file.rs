@@ -1,17 +1,92 @@
 use crate::error::Result;
+use crate::protocol::types::FileMetadataDetails;
 use crate::protocol::{FileId, FileMetadata};
 use mime_guess::from_path;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, RwLock};
+use std::time::SystemTime;
 use tokio::fs;
+use tokio::io::AsyncReadExt;
 
 pub fn generate_file_id() -> FileId {
     FileId::new()
 }
 
+/// Extensions `mime_guess` has no (or only a generic) type for, which phones
+/// and modern tools produce routinely. Keys are lowercase.
+const MIME_OVERRIDES: &[(&str, &str)] = &[
+    ("heic", "image/heic"),
+    ("heics", "image/heic-sequence"),
+    ("heif", "image/heif"),
+    ("avif", "image/avif"),
+    ("jxl", "image/jxl"),
+    ("webp", "image/webp"),
+    ("zst", "application/zstd"),
+    ("opus", "audio/opus"),
+];
+
+static CUSTOM_MIME_TYPES: LazyLock<RwLock<HashMap<String, String>>> =
+    LazyLock::new(Default::default);
+
+/// Map `extension` (without the dot, any case) to `mime` for every later
+/// [`get_mime_type`] call, taking precedence over the built-in table.
+pub fn register_mime_type(extension: &str, mime: impl Into<String>) {
+    if let Ok(mut custom) = CUSTOM_MIME_TYPES.write() {
+        custom.insert(extension.to_ascii_lowercase(), mime.into());
+    }
+}
+
+/// The MIME type LocalSend offers for `path`: a registered type, then the
+/// built-in overrides, then `mime_guess`, then `application/octet-stream`.
 pub fn get_mime_type(path: &Path) -> String {
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase());
+    if let Some(extension) = &extension {
+        if let Some(mime) = CUSTOM_MIME_TYPES
+            .read()
+            .ok()
+            .and_then(|custom| custom.get(extension).cloned())
+        {
+            return mime;
+        }
+        if let Some((_, mime)) = MIME_OVERRIDES.iter().find(|(ext, _)| ext == extension) {
+            return (*mime).to_string();
+        }
+    }
     from_path(path).first_or_octet_stream().to_string()
 }
 
+/// How much of a file [`get_mime_type_sniffed`] reads to recognise it.
+const SNIFF_BYTES: u64 = 8 * 1024;
+
+/// Like [`get_mime_type`], but when the name gives nothing better than
+/// `application/octet-stream` (an extensionless export, say) the first few
+/// KB are checked for a known format signature. A file that cannot be read
+/// keeps the name-based type.
+pub async fn get_mime_type_sniffed(path: &Path) -> String {
+    let guessed = get_mime_type(path);
+    if guessed != mime_guess::mime::APPLICATION_OCTET_STREAM.essence_str() {
+        return guessed;
+    }
+    let mut head = Vec::new();
+    let read = async {
+        fs::File::open(path)
+            .await?
+            .take(SNIFF_BYTES)
+            .read_to_end(&mut head)
+            .await
+    };
+    if let Err(e) = read.await {
+        tracing::debug!("Could not sniff {:?}: {}", path, e);
+        return guessed;
+    }
+    infer::get(&head)
+        .map(|kind| kind.mime_type().to_string())
+        .unwrap_or(guessed)
+}
+
 pub async fn build_file_metadata(path: &Path) -> Result<FileMetadata> {
     let metadata = fs::metadata(path).await?;
 
@@ -23,13 +98,107 @@ pub async fn build_file_metadata(path: &Path) -> Result<FileMetadata> {
             .to_string_lossy()
             .to_string(),
         size: metadata.len(),
-        file_type: get_mime_type(path),
+        file_type: get_mime_type_sniffed(path).await,
         sha256: None,
         preview: None,
-        metadata: None,
+        metadata: timestamps(&metadata),
     })
 }
 
+/// The file's modified and accessed times as LocalSend sends them; a time
+/// the platform does not keep is left out.
+fn timestamps(metadata: &std::fs::Metadata) -> Option<FileMetadataDetails> {
+    let modified = metadata.modified().ok().map(format_timestamp);
+    let accessed = metadata.accessed().ok().map(format_timestamp);
+    (modified.is_some() || accessed.is_some()).then_some(FileMetadataDetails { modified, accessed })
+}
+
+/// RFC 3339 in UTC with milliseconds (`2024-05-01T09:30:00.000Z`), the form
+/// LocalSend peers write.
+pub(crate) fn format_timestamp(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// Reads a timestamp written by [`format_timestamp`] or any other RFC 3339
+/// form; `None` if it does not parse.
+pub(crate) fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(SystemTime::from)
+}
+
+/// Images over this size are offered without a preview; decoding them costs
+/// more than a thumbnail is worth.
+#[cfg(feature = "thumbnails")]
+pub const PREVIEW_MAX_SOURCE_BYTES: u64 = 20 * 1024 * 1024;
+
+/// Longest side of a preview thumbnail, in pixels.
+#[cfg(feature = "thumbnails")]
+const PREVIEW_MAX_DIMENSION: u32 = 256;
+
+#[cfg(feature = "thumbnails")]
+const PREVIEW_MIME_TYPES: &[&str] = &[
+    "image/jpeg",
+    "image/png",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+];
+
+/// Like [`build_file_metadata`], plus a thumbnail in `preview` for images
+/// (see [`image_preview`]).
+#[cfg(feature = "thumbnails")]
+pub async fn build_file_metadata_with_preview(path: &Path) -> Result<FileMetadata> {
+    let mut metadata = build_file_metadata(path).await?;
+    metadata.preview = image_preview(path, &metadata).await;
+    Ok(metadata)
+}
+
+/// A base64 JPEG thumbnail of `path`, at most 256 pixels on its longer
+/// side, for receivers to show in their accept prompt. `None` unless
+/// `metadata` names a common image type no larger than
+/// [`PREVIEW_MAX_SOURCE_BYTES`], or if the image does not decode.
+#[cfg(feature = "thumbnails")]
+pub async fn image_preview(path: &Path, metadata: &FileMetadata) -> Option<String> {
+    if metadata.size > PREVIEW_MAX_SOURCE_BYTES
+        || !PREVIEW_MIME_TYPES.contains(&metadata.file_type.as_str())
+    {
+        return None;
+    }
+    let source = path.to_path_buf();
+    match tokio::task::spawn_blocking(move || encode_thumbnail(&source)).await {
+        Ok(Ok(preview)) => Some(preview),
+        Ok(Err(e)) => {
+            tracing::debug!("No preview for {:?}: {}", path, e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+#[cfg(feature = "thumbnails")]
+fn encode_thumbnail(path: &Path) -> image::ImageResult<String> {
+    use base64::Engine;
+
+    // The content decides the format; the extension may be wrong.
+    let thumbnail = image::ImageReader::open(path)?
+        .with_guessed_format()?
+        .decode()?
+        .thumbnail(PREVIEW_MAX_DIMENSION, PREVIEW_MAX_DIMENSION)
+        .into_rgb8();
+    let mut jpeg = Vec::new();
+    image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, 75).encode_image(&thumbnail)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(jpeg))
+}
+
+/// Like [`build_file_metadata`], but also fills in the file's sha256 so the
+/// receiver can verify what it got. Reads the whole file once.
+pub async fn build_file_metadata_with_hash(path: &Path) -> Result<FileMetadata> {
+    let mut metadata = build_file_metadata(path).await?;
+    metadata.sha256 = Some(crate::crypto::sha256_from_file(path).await?);
+    Ok(metadata)
+}
+
 pub fn build_file_metadata_from_bytes(
     id: FileId,
     file_name: String,
@@ -48,6 +217,88 @@ pub fn build_file_metadata_from_bytes(
     }
 }
 
+/// Names skipped in directory sends unless hidden files are included: OS
+/// thumbnail caches, folder settings, and trash/archive metadata.
+const JUNK_FILE_NAMES: &[&str] = &[
+    ".DS_Store",
+    "Thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+    "$RECYCLE.BIN",
+    "System Volume Information",
+    "__MACOSX",
+];
+
+/// `true` for dotfiles (`.git`, `.hidden`) and the known OS junk above.
+pub fn is_hidden_or_junk(name: &std::ffi::OsStr) -> bool {
+    let name = name.to_string_lossy();
+    name.starts_with('.')
+        || JUNK_FILE_NAMES
+            .iter()
+            .any(|junk| junk.eq_ignore_ascii_case(&name))
+}
+
+/// A regular file found while walking a directory to send.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirectoryFile {
+    pub path: PathBuf,
+    /// `/`-separated name starting with the directory's own name
+    /// (`photos/2024/a.jpg`), so the receiver recreates the folder.
+    pub relative_name: String,
+}
+
+/// Every regular file under `root`, sorted by relative name. Hidden and junk
+/// entries (and everything below a hidden directory) are skipped unless
+/// `include_hidden`; symlinks are never followed.
+pub async fn collect_directory_files(
+    root: &Path,
+    include_hidden: bool,
+) -> Result<Vec<DirectoryFile>> {
+    let root_name = root
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "folder".to_string());
+    let mut pending = vec![(root.to_path_buf(), root_name)];
+    let mut files = Vec::new();
+
+    while let Some((dir, prefix)) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            if !include_hidden && is_hidden_or_junk(&name) {
+                continue;
+            }
+            let relative_name = format!("{prefix}/{}", name.to_string_lossy());
+            let file_type = entry.file_type().await?;
+            if file_type.is_dir() {
+                pending.push((entry.path(), relative_name));
+            } else if file_type.is_file() {
+                files.push(DirectoryFile {
+                    path: entry.path(),
+                    relative_name,
+                });
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.relative_name.cmp(&b.relative_name));
+    Ok(files)
+}
+
+/// Create `dir` and any missing parents. An existing directory, including one
+/// a concurrent caller created a moment earlier, is success; anything else
+/// (permissions, a file in the way) is reported with the offending path.
+pub async fn ensure_dir(dir: &Path) -> Result<()> {
+    match fs::create_dir_all(dir).await {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists && dir.is_dir() => Ok(()),
+        Err(source) => Err(crate::LocalSendError::CreateDir {
+            path: dir.to_path_buf(),
+            source,
+        }),
+    }
+}
+
 /// Resolve a collision-free, traversal-safe save path inside `save_dir`.
 /// Existing files are never overwritten: "a.txt" -> "a (1).txt" -> "a (2).txt".
 pub fn unique_save_path(save_dir: &Path, file_name: &str) -> crate::Result<PathBuf> {
@@ -55,17 +306,8 @@ pub fn unique_save_path(save_dir: &Path, file_name: &str) -> crate::Result<PathB
     if !candidate.exists() {
         return Ok(candidate);
     }
-    let stem = candidate
-        .file_stem()
-        .map(|s| s.to_string_lossy().to_string())
-        .unwrap_or_default();
-    let ext = candidate
-        .extension()
-        .map(|e| format!(".{}", e.to_string_lossy()))
-        .unwrap_or_default();
-    let parent = candidate.parent().unwrap_or(save_dir).to_path_buf();
     for i in 1u32.. {
-        let next = parent.join(format!("{stem} ({i}){ext}"));
+        let next = numbered_path(&candidate, i);
         if !next.exists() {
             return Ok(next);
         }
@@ -73,9 +315,247 @@ pub fn unique_save_path(save_dir: &Path, file_name: &str) -> crate::Result<PathB
     unreachable!()
 }
 
+/// Create the file [`unique_save_path`] picks, atomically: should the name be
+/// taken between the check and the create, the next free one is used rather
+/// than truncating whatever appeared there.
+pub(crate) async fn create_unique_file(
+    save_dir: &Path,
+    file_name: &str,
+) -> crate::Result<(PathBuf, fs::File)> {
+    loop {
+        let path = unique_save_path(save_dir, file_name)?;
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .await
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// `dir/photo.jpg` -> `dir/photo (i).jpg`, the collision-rename scheme.
+pub(crate) fn numbered_path(path: &Path, i: u32) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    path.with_file_name(format!("{stem} ({i}){ext}"))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::unique_save_path;
+    use super::{
+        build_file_metadata, build_file_metadata_with_hash, collect_directory_files,
+        create_unique_file, ensure_dir, format_timestamp, get_mime_type, get_mime_type_sniffed,
+        parse_timestamp, register_mime_type, unique_save_path,
+    };
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn hashed_metadata_carries_the_file_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("abc.txt");
+        std::fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(build_file_metadata(&path).await.unwrap().sha256, None);
+        let hashed = build_file_metadata_with_hash(&path).await.unwrap();
+        assert_eq!(hashed.size, 3);
+        assert_eq!(
+            hashed.sha256.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+    }
+
+    #[tokio::test]
+    async fn metadata_carries_the_modified_time_in_rfc_3339() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dated.txt");
+        std::fs::write(&path, b"x").unwrap();
+        let modified = std::time::UNIX_EPOCH + std::time::Duration::from_millis(1_714_555_800_250);
+        std::fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let details = build_file_metadata(&path).await.unwrap().metadata.unwrap();
+        assert_eq!(
+            details.modified.as_deref(),
+            Some("2024-05-01T09:30:00.250Z")
+        );
+        assert_eq!(parse_timestamp(&format_timestamp(modified)), Some(modified));
+        assert_eq!(parse_timestamp("yesterday"), None);
+    }
+
+    #[cfg(feature = "thumbnails")]
+    #[tokio::test]
+    async fn images_get_a_scaled_down_preview_and_other_files_none() {
+        use base64::Engine;
+
+        let dir = tempfile::tempdir().unwrap();
+        let photo = dir.path().join("wide.png");
+        image::RgbImage::from_pixel(800, 400, image::Rgb([200, 40, 40]))
+            .save(&photo)
+            .unwrap();
+        let preview = super::build_file_metadata_with_preview(&photo)
+            .await
+            .unwrap()
+            .preview
+            .expect("a preview");
+        let jpeg = base64::engine::general_purpose::STANDARD
+            .decode(preview)
+            .unwrap();
+        let thumbnail = image::load_from_memory(&jpeg).unwrap();
+        assert_eq!((thumbnail.width(), thumbnail.height()), (256, 128));
+
+        let notes = dir.path().join("notes.txt");
+        std::fs::write(&notes, b"not an image").unwrap();
+        let broken = dir.path().join("broken.png");
+        std::fs::write(&broken, b"not an image either").unwrap();
+        for path in [notes, broken] {
+            let meta = super::build_file_metadata_with_preview(&path)
+                .await
+                .unwrap();
+            assert_eq!(meta.preview, None, "{path:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn extensionless_files_are_typed_by_their_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = dir.path().join("IMG_EXPORT");
+        std::fs::write(&png, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        assert_eq!(get_mime_type(&png), "application/octet-stream");
+        assert_eq!(get_mime_type_sniffed(&png).await, "image/png");
+        assert_eq!(
+            build_file_metadata(&png).await.unwrap().file_type,
+            "image/png"
+        );
+
+        // The extension still wins when it says something, and unknown bytes
+        // stay octet-stream.
+        let named = dir.path().join("report.txt");
+        std::fs::write(&named, b"\x89PNG\r\n\x1a\n").unwrap();
+        assert_eq!(get_mime_type_sniffed(&named).await, "text/plain");
+        let opaque = dir.path().join("blob");
+        std::fs::write(&opaque, b"nothing recognisable").unwrap();
+        assert_eq!(
+            get_mime_type_sniffed(&opaque).await,
+            "application/octet-stream"
+        );
+        assert_eq!(
+            get_mime_type_sniffed(&dir.path().join("missing")).await,
+            "application/octet-stream"
+        );
+    }
+
+    #[test]
+    fn mime_overrides_cover_formats_mime_guess_misses() {
+        assert_eq!(get_mime_type(Path::new("IMG_0001.HEIC")), "image/heic");
+        assert_eq!(
+            get_mime_type(Path::new("backup.tar.zst")),
+            "application/zstd"
+        );
+        assert_eq!(get_mime_type(Path::new("notes.txt")), "text/plain");
+        assert_eq!(
+            get_mime_type(Path::new("blob.nosuchext")),
+            "application/octet-stream"
+        );
+
+        register_mime_type("LSTEST", "application/x-localsend-test");
+        assert_eq!(
+            get_mime_type(Path::new("payload.lstest")),
+            "application/x-localsend-test"
+        );
+    }
+
+    #[tokio::test]
+    async fn ensure_dir_accepts_existing_dirs_and_names_the_failing_path() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("a/b");
+        ensure_dir(&dir).await.unwrap();
+        ensure_dir(&dir)
+            .await
+            .expect("an existing directory is fine");
+        let nested = dir.join("c");
+        let (first, second) = tokio::join!(ensure_dir(&nested), ensure_dir(&nested));
+        first.and(second).expect("concurrent creation is fine");
+
+        let blocker = root.path().join("file");
+        std::fs::write(&blocker, b"").unwrap();
+        let error = ensure_dir(&blocker.join("sub")).await.unwrap_err();
+        assert!(matches!(error, crate::LocalSendError::CreateDir { .. }));
+        assert!(error.to_string().contains("file/sub"), "{error}");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn ensure_dir_under_a_read_only_parent_fails() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = tempfile::tempdir().unwrap();
+        let parent = root.path().join("locked");
+        std::fs::create_dir(&parent).unwrap();
+        std::fs::set_permissions(&parent, std::fs::Permissions::from_mode(0o555)).unwrap();
+        // Root ignores directory permissions, so there is nothing to observe.
+        let writable = std::fs::create_dir(parent.join("probe")).is_ok();
+        let result = ensure_dir(&parent.join("downloads")).await;
+        std::fs::set_permissions(&parent, std::fs::Permissions::from_mode(0o755)).unwrap();
+        if writable {
+            return;
+        }
+
+        let error = result.unwrap_err();
+        assert!(
+            matches!(&error, crate::LocalSendError::CreateDir { source, .. }
+                if source.kind() == std::io::ErrorKind::PermissionDenied),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn directory_walk_skips_hidden_and_junk_by_default() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("share");
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::create_dir_all(dir.join(".git")).unwrap();
+        for file in [
+            "a.txt",
+            "nested/b.txt",
+            ".hidden",
+            "Thumbs.db",
+            ".git/config",
+        ] {
+            std::fs::write(dir.join(file), file).unwrap();
+        }
+
+        let names = |files: Vec<super::DirectoryFile>| -> Vec<String> {
+            files.into_iter().map(|file| file.relative_name).collect()
+        };
+        assert_eq!(
+            names(collect_directory_files(&dir, false).await.unwrap()),
+            ["share/a.txt", "share/nested/b.txt"]
+        );
+        assert_eq!(
+            names(collect_directory_files(&dir, true).await.unwrap()),
+            [
+                "share/.git/config",
+                "share/.hidden",
+                "share/Thumbs.db",
+                "share/a.txt",
+                "share/nested/b.txt"
+            ]
+        );
+    }
 
     #[test]
     fn unique_save_path_appends_counter_on_collision() {
@@ -92,6 +572,22 @@ mod tests {
         std::fs::remove_dir_all(&dir).unwrap();
     }
 
+    #[tokio::test]
+    async fn racing_creates_of_one_name_get_distinct_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let creates = (0..8).map(|_| create_unique_file(dir.path(), "report.pdf"));
+        let mut paths: Vec<_> = futures_util::future::join_all(creates)
+            .await
+            .into_iter()
+            .map(|created| created.unwrap().0)
+            .collect();
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), 8);
+        assert!(paths.contains(&dir.path().join("report.pdf")));
+        assert!(paths.contains(&dir.path().join("report (1).pdf")));
+    }
+
     #[test]
     fn unique_save_path_still_rejects_traversal() {
         let dir = std::env::temp_dir();
@@ -1,12 +1,19 @@
 pub mod builders;
+pub mod clock;
 pub mod device;
 pub mod file;
 pub mod session;
+pub mod size;
 
 pub use builders::DeviceInfoBuilder;
+pub use clock::{Clock, MockClock, SystemClock};
 pub use device::{get_device_model, get_device_type, get_local_ip};
 pub use file::{
-    build_file_metadata, build_file_metadata_from_bytes, generate_file_id, get_mime_type,
-    unique_save_path,
+    DirectoryFile, build_file_metadata, build_file_metadata_from_bytes,
+    build_file_metadata_with_hash, collect_directory_files, ensure_dir, generate_file_id,
+    get_mime_type, get_mime_type_sniffed, is_hidden_or_junk, register_mime_type, unique_save_path,
 };
+#[cfg(feature = "thumbnails")]
+pub use file::{PREVIEW_MAX_SOURCE_BYTES, build_file_metadata_with_preview, image_preview};
 pub use session::Session;
+pub use size::parse_size;
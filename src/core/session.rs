@@ -1,8 +1,10 @@
+use crate::core::clock::{Clock, SystemClock};
 use crate::protocol::{FileId, FileMetadata, SessionId, Token};
 use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::AtomicU64;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 /// Active file transfer session
 #[derive(Clone, Debug)]
@@ -12,16 +14,34 @@ pub struct Session {
     pub tokens: HashMap<FileId, Token>,
     pub received: HashSet<FileId>,
     pub received_bytes: Arc<AtomicU64>,
+    /// The name each file is saved under, relative to the save directory.
+    /// It is the declared name, except that files declaring the same name get
+    /// distinct ones (see [`Session::save_name`]).
+    pub save_names: HashMap<FileId, String>,
     pub sender_alias: String,
+    /// Overrides the server's save directory for this session's files.
+    pub save_dir: Option<PathBuf>,
+    /// Interrupted uploads kept for the sender to resume, by file.
+    pub partials: HashMap<FileId, PathBuf>,
     pub created_at: Instant,
     pub last_activity: Instant,
+    clock: Arc<dyn Clock>,
 }
 
 impl Session {
     /// Create a new session
     pub fn new(sender_alias: String, files: HashMap<FileId, FileMetadata>) -> Self {
+        Self::with_clock(sender_alias, files, Arc::new(SystemClock))
+    }
+
+    /// Create a session whose activity and timeout are measured on `clock`.
+    pub fn with_clock(
+        sender_alias: String,
+        files: HashMap<FileId, FileMetadata>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
         let id = SessionId::new();
-        let now = Instant::now();
+        let now = clock.now();
 
         // Generate a random, per-file token -- must not be derivable from the
         // session/file ids (guessable tokens would let anyone upload).
@@ -30,26 +50,45 @@ impl Session {
             .map(|file_id| (file_id.clone(), Token::random()))
             .collect();
 
+        let save_names = assign_save_names(&files);
+
         Self {
             id,
             files,
             tokens,
+            save_names,
             received: HashSet::new(),
             received_bytes: Arc::new(AtomicU64::new(0)),
             sender_alias,
+            save_dir: None,
+            partials: HashMap::new(),
             created_at: now,
             last_activity: now,
+            clock,
         }
     }
 
     /// Update the last activity timestamp
     pub fn touch(&mut self) {
-        self.last_activity = Instant::now();
+        self.last_activity = self.clock.now();
     }
 
     /// Check if the session has timed out (default: 5 minutes)
     pub fn is_timed_out(&self, timeout_secs: u64) -> bool {
-        self.last_activity.elapsed().as_secs() > timeout_secs
+        self.clock
+            .now()
+            .saturating_duration_since(self.last_activity)
+            .as_secs()
+            > timeout_secs
+    }
+
+    /// Whether nothing has happened on the session for longer than
+    /// `timeout`, to the nanosecond rather than in whole seconds.
+    pub fn is_idle_for(&self, timeout: Duration) -> bool {
+        self.clock
+            .now()
+            .saturating_duration_since(self.last_activity)
+            > timeout
     }
 
     /// Verify that a token is valid for a given file
@@ -60,6 +99,13 @@ impl Session {
             .unwrap_or(false)
     }
 
+    /// The name `file_id` is saved under. When several files declare the same
+    /// name, the lowest file id keeps it and the rest become `name (1).ext`,
+    /// `name (2).ext`, ... in file-id order, whatever order they upload in.
+    pub fn save_name(&self, file_id: &FileId) -> Option<&str> {
+        self.save_names.get(file_id).map(String::as_str)
+    }
+
     /// Get the token for a file
     pub fn get_token(&self, file_id: &FileId) -> Option<&Token> {
         self.tokens.get(file_id)
@@ -76,16 +122,43 @@ impl Session {
             return false;
         }
         self.received.insert(file_id.clone());
-        self.last_activity = Instant::now();
+        self.last_activity = self.clock.now();
         self.received.len() == self.files.len()
     }
 }
 
+fn assign_save_names(files: &HashMap<FileId, FileMetadata>) -> HashMap<FileId, String> {
+    let mut ordered: Vec<(&FileId, &FileMetadata)> = files.iter().collect();
+    ordered.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
+    // Every declared name is reserved up front, so a numbered name never
+    // takes one that another file in the session declared outright.
+    let mut taken: HashSet<String> = files.values().map(|f| f.file_name.clone()).collect();
+    let mut kept = HashSet::new();
+    let mut names = HashMap::new();
+    for (id, file) in ordered {
+        let name = if kept.insert(file.file_name.as_str()) {
+            file.file_name.clone()
+        } else {
+            let declared = std::path::Path::new(&file.file_name);
+            let name = (1u32..)
+                .map(|i| crate::core::file::numbered_path(declared, i))
+                .map(|path| path.to_string_lossy().into_owned())
+                .find(|candidate| !taken.contains(candidate))
+                .expect("an unused numbered name");
+            taken.insert(name.clone());
+            name
+        };
+        names.insert(id.clone(), name);
+    }
+    names
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::clock::MockClock;
     use crate::protocol::FileMetadata;
-    use std::thread;
     use std::time::Duration;
 
     fn create_test_files() -> HashMap<FileId, FileMetadata> {
@@ -132,16 +205,32 @@ mod tests {
         assert!(!session.verify_token(file_id, &invalid_token));
     }
 
+    #[test]
+    fn guessed_session_file_token_is_rejected() {
+        let files = create_test_files();
+        let session = Session::new("Test".to_string(), files.clone());
+        let file_id = files.keys().next().unwrap();
+
+        // The old derivable format, built from ids a LAN observer can see.
+        let guessed = Token::from_string(format!("{}_{}", session.id, file_id));
+        assert!(!session.verify_token(file_id, &guessed));
+
+        let issued = session.get_token(file_id).unwrap();
+        assert_ne!(issued, &guessed);
+        assert!(session.verify_token(file_id, issued));
+    }
+
     #[test]
     fn test_timeout() {
         let files = create_test_files();
-        let mut session = Session::new("Test".to_string(), files);
+        let clock = MockClock::new();
+        let mut session = Session::with_clock("Test".to_string(), files, Arc::new(clock.clone()));
 
         // Should not timeout immediately
         assert!(!session.is_timed_out(1));
 
-        // Sleep for 2 seconds
-        thread::sleep(Duration::from_secs(2));
+        // Advance past the threshold without sleeping
+        clock.advance(Duration::from_secs(2));
 
         // Should timeout after 1 second threshold
         assert!(session.is_timed_out(1));
@@ -151,6 +240,25 @@ mod tests {
         assert!(!session.is_timed_out(1));
     }
 
+    #[test]
+    fn a_sub_second_idle_timeout_is_not_rounded_to_zero() {
+        let clock = MockClock::new();
+        let mut session = Session::with_clock(
+            "Test".to_string(),
+            create_test_files(),
+            Arc::new(clock.clone()),
+        );
+        let timeout = Duration::from_millis(500);
+
+        clock.advance(Duration::from_millis(400));
+        assert!(!session.is_idle_for(timeout));
+        clock.advance(Duration::from_millis(200));
+        assert!(session.is_idle_for(timeout));
+
+        session.touch();
+        assert!(!session.is_idle_for(timeout));
+    }
+
     #[test]
     fn tokens_are_random_not_derived() {
         let files = create_test_files();
@@ -176,6 +284,47 @@ mod tests {
         assert!(s.mark_received(&ids[1]));
     }
 
+    #[test]
+    fn files_declaring_the_same_name_get_distinct_save_names() {
+        let template = create_test_files().into_values().next().unwrap();
+        let mut files = HashMap::new();
+        for name in ["photo.jpg", "photo.jpg", "photo (1).jpg", "album/photo.jpg"] {
+            let id = FileId::new();
+            files.insert(
+                id.clone(),
+                FileMetadata {
+                    id,
+                    file_name: name.to_string(),
+                    ..template.clone()
+                },
+            );
+        }
+        let session = Session::new("A".to_string(), files.clone());
+
+        let mut ids: Vec<&FileId> = files
+            .iter()
+            .filter(|(_, f)| f.file_name == "photo.jpg")
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        // The lower id keeps the name; "photo (1).jpg" is declared by another
+        // file, so the duplicate skips to "(2)".
+        assert_eq!(session.save_name(ids[0]), Some("photo.jpg"));
+        assert_eq!(session.save_name(ids[1]), Some("photo (2).jpg"));
+
+        let mut saved: Vec<&str> = session.save_names.values().map(String::as_str).collect();
+        saved.sort();
+        assert_eq!(
+            saved,
+            [
+                "album/photo.jpg",
+                "photo (1).jpg",
+                "photo (2).jpg",
+                "photo.jpg"
+            ]
+        );
+    }
+
     #[test]
     fn mark_received_ignores_foreign_file_id() {
         let files = create_test_files();
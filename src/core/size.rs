@@ -0,0 +1,77 @@
+use crate::error::{LocalSendError, Result};
+
+/// Parse a human-friendly byte size such as `1024`, `500KB`, `1KiB` or `2.5MB`.
+///
+/// Suffixes are case-insensitive. `KB`/`MB`/`GB`/`TB` are decimal (powers of
+/// 1000); `KiB`/`MiB`/`GiB`/`TiB` and the bare `K`/`M`/`G`/`T` shorthands are
+/// binary (powers of 1024). A trailing `B` alone means bytes. Fractions are
+/// allowed with a unit and rounded down to whole bytes.
+pub fn parse_size(input: &str) -> Result<u64> {
+    let invalid = || LocalSendError::InvalidSize(input.to_string());
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(trimmed.len());
+    let (number, unit) = trimmed.split_at(split);
+    if number.is_empty() {
+        return Err(invalid());
+    }
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000u64.pow(2),
+        "gb" => 1000u64.pow(3),
+        "tb" => 1000u64.pow(4),
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+
+    if let Ok(whole) = number.parse::<u64>() {
+        return whole.checked_mul(multiplier).ok_or_else(invalid);
+    }
+    let value = number.parse::<f64>().map_err(|_| invalid())?;
+    let bytes = value * multiplier as f64;
+    if multiplier == 1 || !bytes.is_finite() || bytes >= u64::MAX as f64 {
+        return Err(invalid());
+    }
+    Ok(bytes as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_size;
+
+    #[test]
+    fn parses_plain_bytes() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("0").unwrap(), 0);
+        assert_eq!(parse_size("64B").unwrap(), 64);
+    }
+
+    #[test]
+    fn decimal_and_binary_suffixes_differ() {
+        assert_eq!(parse_size("1KB").unwrap(), 1000);
+        assert_eq!(parse_size("1KiB").unwrap(), 1024);
+        assert_eq!(parse_size("1k").unwrap(), 1024);
+        assert_eq!(parse_size("500kb").unwrap(), 500_000);
+        assert_eq!(parse_size("3 GiB").unwrap(), 3 << 30);
+    }
+
+    #[test]
+    fn fractions_need_a_unit() {
+        assert_eq!(parse_size("2.5MB").unwrap(), 2_500_000);
+        assert_eq!(parse_size("1.5KiB").unwrap(), 1536);
+        assert!(parse_size("1.5").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for input in ["", "MB", "abc", "-1", "1XB", "1..2MB", "99999999999TB"] {
+            assert!(parse_size(input).is_err(), "{input:?} must be rejected");
+        }
+    }
+}
@@ -0,0 +1,456 @@
+//! Opt-in encryption of received files on disk (`encryption` feature).
+//!
+//! Each file gets a fresh random salt, and the passphrase is stretched with
+//! PBKDF2-HMAC-SHA256 into a per-file AES-256-GCM key. The body is sealed in
+//! fixed-size chunks as it streams in, so nothing is buffered beyond one
+//! chunk; the last chunk carries a flag in its nonce, which makes a truncated
+//! file fail to decrypt. The parameters needed to decrypt live in a JSON
+//! manifest next to the ciphertext (see [`manifest_path`]).
+
+use crate::error::{LocalSendError, Result};
+use base64::Engine;
+use ring::aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
+use sha2::Digest;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::task::{Context, Poll, ready};
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Extension given to encrypted files; the manifest adds `.json` on top.
+pub const ENCRYPTED_EXTENSION: &str = "lsenc";
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_LEN: usize = 16;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: u32 = 100_000;
+const CIPHER: &str = "AES-256-GCM";
+const KDF: &str = "PBKDF2-HMAC-SHA256";
+
+/// The receiver's at-rest passphrase. The value never appears in `Debug`.
+#[derive(Clone)]
+pub struct AtRestEncryption {
+    passphrase: String,
+}
+
+impl AtRestEncryption {
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        Self {
+            passphrase: passphrase.into(),
+        }
+    }
+
+    /// Wrap `inner` so everything written to it is encrypted under a new salt.
+    pub fn writer<W: AsyncWrite + Unpin>(&self, inner: W) -> Result<EncryptingWriter<W>> {
+        let mut salt = [0u8; SALT_LEN];
+        SystemRandom::new()
+            .fill(&mut salt)
+            .map_err(|_| encryption_error("no system randomness for the salt"))?;
+        Ok(EncryptingWriter {
+            inner,
+            key: derive_key(&self.passphrase, &salt, PBKDF2_ITERATIONS)?,
+            salt,
+            plain: Vec::with_capacity(CHUNK_SIZE),
+            out: Vec::new(),
+            out_pos: 0,
+            counter: 0,
+            finished: false,
+            hasher: sha2::Sha256::new(),
+            size: 0,
+        })
+    }
+}
+
+impl std::fmt::Debug for AtRestEncryption {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("AtRestEncryption([REDACTED])")
+    }
+}
+
+/// Everything besides the passphrase needed to decrypt one file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AtRestManifest {
+    pub version: u32,
+    pub cipher: String,
+    pub kdf: String,
+    pub iterations: u32,
+    /// Base64 (standard alphabet) of the per-file salt.
+    pub salt: String,
+    pub chunk_size: usize,
+    /// The name the sender offered, before `.lsenc` was appended.
+    pub file_name: String,
+    /// Plaintext size and SHA-256, checked after decryption.
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// The manifest written beside `encrypted`: `photo.jpg.lsenc.json`.
+pub fn manifest_path(encrypted: &Path) -> PathBuf {
+    let mut name = encrypted.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// Streams plaintext in, ciphertext out. Call `shutdown` to seal the final
+/// chunk; without it the file cannot be decrypted.
+pub struct EncryptingWriter<W> {
+    inner: W,
+    key: LessSafeKey,
+    salt: [u8; SALT_LEN],
+    plain: Vec<u8>,
+    out: Vec<u8>,
+    out_pos: usize,
+    counter: u64,
+    finished: bool,
+    hasher: sha2::Sha256,
+    size: u64,
+}
+
+impl<W: AsyncWrite + Unpin> EncryptingWriter<W> {
+    /// SHA-256 (lowercase hex) of the plaintext written so far.
+    pub fn plaintext_sha256(&self) -> String {
+        format!("{:x}", self.hasher.clone().finalize())
+    }
+
+    pub fn manifest(&self, file_name: &str) -> AtRestManifest {
+        AtRestManifest {
+            version: 1,
+            cipher: CIPHER.to_string(),
+            kdf: KDF.to_string(),
+            iterations: PBKDF2_ITERATIONS,
+            salt: base64::engine::general_purpose::STANDARD.encode(self.salt),
+            chunk_size: CHUNK_SIZE,
+            file_name: file_name.to_string(),
+            size: self.size,
+            sha256: self.plaintext_sha256(),
+        }
+    }
+
+    /// Write the manifest for the ciphertext at `encrypted`.
+    pub async fn write_manifest(&self, encrypted: &Path, file_name: &str) -> Result<()> {
+        let json = serde_json::to_vec_pretty(&self.manifest(file_name))?;
+        tokio::fs::write(manifest_path(encrypted), json).await?;
+        Ok(())
+    }
+
+    fn seal(&mut self, last: bool) -> std::io::Result<()> {
+        let mut chunk = std::mem::replace(&mut self.plain, Vec::with_capacity(CHUNK_SIZE));
+        self.key
+            .seal_in_place_append_tag(chunk_nonce(self.counter, last), Aad::empty(), &mut chunk)
+            .map_err(|_| std::io::Error::other("failed to seal an encrypted chunk"))?;
+        self.counter += 1;
+        self.out = chunk;
+        self.out_pos = 0;
+        Ok(())
+    }
+
+    fn poll_drain(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        while self.out_pos < self.out.len() {
+            let written =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.out[self.out_pos..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(std::io::ErrorKind::WriteZero.into()));
+            }
+            self.out_pos += written;
+        }
+        self.out.clear();
+        self.out_pos = 0;
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for EncryptingWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        if this.finished {
+            return Poll::Ready(Err(std::io::Error::other(
+                "write after the encrypted file was sealed",
+            )));
+        }
+        let taken = buf.len().min(CHUNK_SIZE - this.plain.len());
+        this.plain.extend_from_slice(&buf[..taken]);
+        this.hasher.update(&buf[..taken]);
+        this.size += taken as u64;
+        if this.plain.len() == CHUNK_SIZE {
+            this.seal(false)?;
+        }
+        Poll::Ready(Ok(taken))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_drain(cx))?;
+        if !this.finished {
+            this.seal(true)?;
+            this.finished = true;
+            ready!(this.poll_drain(cx))?;
+        }
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Decrypt a file written by [`EncryptingWriter`] into `output`, using the
+/// manifest beside it. A wrong passphrase, a truncated or altered file, and a
+/// plaintext that does not match the manifest's size or digest all fail.
+///
+/// The plaintext is decrypted a chunk at a time into a temporary file beside
+/// `output` and only moved into place once it checks out, so a failure
+/// leaves nothing behind. An existing `output` is an error unless
+/// `overwrite` is set. Returns the plaintext size.
+pub async fn decrypt_file(
+    encrypted: &Path,
+    passphrase: &str,
+    output: &Path,
+    overwrite: bool,
+) -> Result<u64> {
+    let manifest: AtRestManifest =
+        serde_json::from_slice(&tokio::fs::read(manifest_path(encrypted)).await?)?;
+    if manifest.version != 1
+        || manifest.cipher != CIPHER
+        || manifest.kdf != KDF
+        || manifest.chunk_size != CHUNK_SIZE
+    {
+        return Err(encryption_error("unsupported manifest"));
+    }
+    let salt = base64::engine::general_purpose::STANDARD
+        .decode(&manifest.salt)
+        .map_err(|_| encryption_error("manifest salt is not base64"))?;
+    let key = derive_key(passphrase, &salt, manifest.iterations)?;
+    if !overwrite && tokio::fs::try_exists(output).await? {
+        return Err(output_exists(output));
+    }
+
+    let name = output.file_name().unwrap_or_default().to_string_lossy();
+    let temp = output.with_file_name(format!(".{name}.{}.tmp", uuid::Uuid::new_v4()));
+    let decrypted = decrypt_into(&key, encrypted, &temp, &manifest).await;
+    let placed = match decrypted {
+        Ok(()) if overwrite => tokio::fs::rename(&temp, output).await.map_err(Into::into),
+        // A link, unlike a rename, never replaces a file that appeared since.
+        Ok(()) => match tokio::fs::hard_link(&temp, output).await {
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(output_exists(output))
+            }
+            linked => linked.map_err(Into::into),
+        },
+        Err(error) => Err(error),
+    };
+    if placed.is_err() || !overwrite {
+        let _ = tokio::fs::remove_file(&temp).await;
+    }
+    placed.map(|()| manifest.size)
+}
+
+async fn decrypt_into(
+    key: &LessSafeKey,
+    encrypted: &Path,
+    temp: &Path,
+    manifest: &AtRestManifest,
+) -> Result<()> {
+    let mut source = tokio::fs::File::open(encrypted).await?;
+    let mut sink = tokio::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temp)
+        .await?;
+    let mut hasher = sha2::Sha256::new();
+    let mut size = 0u64;
+
+    // One chunk is read ahead, to know whether the current one is the last.
+    let mut sealed = vec![0u8; CHUNK_SIZE + TAG_LEN];
+    let mut next = vec![0u8; CHUNK_SIZE + TAG_LEN];
+    let mut len = read_chunk(&mut source, &mut sealed).await?;
+    if len == 0 {
+        return Err(encryption_error("encrypted file is empty"));
+    }
+    for counter in 0u64.. {
+        let next_len = read_chunk(&mut source, &mut next).await?;
+        let last = next_len == 0;
+        let opened = key
+            .open_in_place(chunk_nonce(counter, last), Aad::empty(), &mut sealed[..len])
+            .map_err(|_| encryption_error("wrong passphrase or corrupted file"))?;
+        hasher.update(&*opened);
+        size += opened.len() as u64;
+        sink.write_all(opened).await?;
+        if last {
+            break;
+        }
+        std::mem::swap(&mut sealed, &mut next);
+        len = next_len;
+    }
+
+    if size != manifest.size
+        || !format!("{:x}", hasher.finalize()).eq_ignore_ascii_case(&manifest.sha256)
+    {
+        return Err(encryption_error(
+            "decrypted contents do not match the manifest",
+        ));
+    }
+    sink.sync_all().await?;
+    Ok(())
+}
+
+/// Fill `buf` from `source`, short only at the end of the file.
+async fn read_chunk(source: &mut tokio::fs::File, buf: &mut [u8]) -> Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match source.read(&mut buf[filled..]).await? {
+            0 => break,
+            read => filled += read,
+        }
+    }
+    Ok(filled)
+}
+
+fn output_exists(output: &Path) -> LocalSendError {
+    std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("{} already exists", output.display()),
+    )
+    .into()
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey> {
+    let iterations =
+        NonZeroU32::new(iterations).ok_or_else(|| encryption_error("zero KDF iterations"))?;
+    let mut key = [0u8; 32];
+    ring::pbkdf2::derive(
+        ring::pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key)
+        .map_err(|_| encryption_error("failed to build the AES key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// A 96-bit nonce: the chunk counter in the low 8 bytes, and a final-chunk
+/// flag above it. Keys are per file, so a counter never repeats under one key.
+fn chunk_nonce(counter: u64, last: bool) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[3] = u8::from(last);
+    nonce[4..].copy_from_slice(&counter.to_be_bytes());
+    Nonce::assume_unique_for_key(nonce)
+}
+
+fn encryption_error(message: &str) -> LocalSendError {
+    LocalSendError::Encryption {
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AtRestEncryption, CHUNK_SIZE, decrypt_file, manifest_path};
+    use tokio::io::AsyncWriteExt;
+
+    async fn decrypt(path: &std::path::Path, passphrase: &str) -> crate::Result<Vec<u8>> {
+        let output = path.with_extension("out");
+        decrypt_file(path, passphrase, &output, true).await?;
+        Ok(std::fs::read(output).unwrap())
+    }
+
+    async fn encrypt(dir: &std::path::Path, plaintext: &[u8]) -> std::path::PathBuf {
+        let path = dir.join("secret.bin.lsenc");
+        let file = tokio::fs::File::create(&path).await.unwrap();
+        let mut writer = AtRestEncryption::new("correct horse").writer(file).unwrap();
+        // Odd write sizes so chunk boundaries fall mid-write.
+        for piece in plaintext.chunks(7_777) {
+            writer.write_all(piece).await.unwrap();
+        }
+        writer.shutdown().await.unwrap();
+        writer.write_manifest(&path, "secret.bin").await.unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn round_trips_across_chunk_boundaries() {
+        let dir = tempfile::tempdir().unwrap();
+        for size in [0, 1, CHUNK_SIZE, CHUNK_SIZE * 2 + 5] {
+            let plaintext: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+            let path = encrypt(dir.path(), &plaintext).await;
+            let on_disk = std::fs::read(&path).unwrap();
+            assert_eq!(on_disk.len(), size + 16 * (size / CHUNK_SIZE + 1));
+            if size >= 64 {
+                assert!(!on_disk.windows(64).any(|w| w == &plaintext[..64]));
+            }
+            assert_eq!(decrypt(&path, "correct horse").await.unwrap(), plaintext);
+        }
+    }
+
+    #[tokio::test]
+    async fn wrong_passphrase_and_truncation_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let plaintext = vec![42u8; CHUNK_SIZE + 10];
+        let path = encrypt(dir.path(), &plaintext).await;
+        assert!(decrypt(&path, "wrong").await.is_err());
+
+        // Dropping the final chunk must not pass for a shorter file.
+        let on_disk = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &on_disk[..CHUNK_SIZE + 16]).unwrap();
+        assert!(decrypt(&path, "correct horse").await.is_err());
+
+        assert!(manifest_path(&path).ends_with("secret.bin.lsenc.json"));
+        let leftovers = std::fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().ends_with(".tmp") || name == "secret.bin.out"
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[tokio::test]
+    async fn an_existing_output_is_kept_unless_overwriting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = encrypt(dir.path(), b"new contents").await;
+        let output = dir.path().join("secret.bin");
+        std::fs::write(&output, "old contents").unwrap();
+
+        let error = decrypt_file(&path, "correct horse", &output, false)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("already exists"), "{error}");
+        assert_eq!(std::fs::read(&output).unwrap(), b"old contents");
+
+        let size = decrypt_file(&path, "correct horse", &output, true)
+            .await
+            .unwrap();
+        assert_eq!(size, 12);
+        assert_eq!(std::fs::read(&output).unwrap(), b"new contents");
+        std::fs::remove_file(&output).unwrap();
+        decrypt_file(&path, "correct horse", &output, false)
+            .await
+            .unwrap();
+        assert_eq!(std::fs::read(&output).unwrap(), b"new contents");
+    }
+
+    #[tokio::test]
+    async fn a_manifest_claiming_a_huge_size_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = encrypt(dir.path(), b"small").await;
+        let manifest = manifest_path(&path);
+        let mut json: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&manifest).unwrap()).unwrap();
+        json["size"] = serde_json::json!(u64::MAX);
+        std::fs::write(&manifest, serde_json::to_vec(&json).unwrap()).unwrap();
+
+        assert!(decrypt(&path, "correct horse").await.is_err());
+    }
+}
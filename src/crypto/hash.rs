@@ -1,5 +1,8 @@
 use crate::error::Result;
 use sha2::Digest;
+use tokio::io::AsyncReadExt;
+
+const HASH_BUFFER_SIZE: usize = 64 * 1024;
 
 /// Compute SHA-256 hash of bytes
 pub fn sha256_from_bytes(data: &[u8]) -> String {
@@ -7,8 +10,17 @@ pub fn sha256_from_bytes(data: &[u8]) -> String {
     format!("{:x}", hash)
 }
 
-/// Compute SHA-256 hash of a file
+/// Compute SHA-256 hash of a file, streaming it through a fixed buffer
 pub async fn sha256_from_file(path: &std::path::Path) -> Result<String> {
-    let contents = tokio::fs::read(path).await?;
-    Ok(sha256_from_bytes(&contents))
+    let mut file = tokio::fs::File::open(path).await?;
+    let mut hasher = sha2::Sha256::new();
+    let mut buffer = vec![0; HASH_BUFFER_SIZE];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
 }
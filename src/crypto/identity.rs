@@ -0,0 +1,307 @@
+//! A device identity kept on disk, so peers see the same device on every
+//! run instead of a new one each start.
+
+use crate::error::{LocalSendError, Result};
+use crate::protocol::Protocol;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// The fingerprint and default alias, as JSON.
+pub const IDENTITY_FILE_NAME: &str = "identity.json";
+/// The HTTPS certificate, as PEM.
+#[cfg(feature = "https")]
+pub const CERT_FILE_NAME: &str = "cert.pem";
+/// The HTTPS certificate's private key, as PEM.
+#[cfg(feature = "https")]
+pub const KEY_FILE_NAME: &str = "key.pem";
+/// Held while the identity is read or created.
+const LOCK_FILE_NAME: &str = ".lock";
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredIdentity {
+    fingerprint: String,
+    alias: String,
+}
+
+/// Who this device is to its peers.
+pub struct Identity {
+    /// The fingerprint to advertise: the stored random one over HTTP, the
+    /// certificate's SHA-256 over HTTPS.
+    pub fingerprint: String,
+    /// The alias picked on first run, for when none is given.
+    pub alias: String,
+    /// The certificate to serve, when loaded for HTTPS.
+    #[cfg(feature = "https")]
+    pub certificate: Option<crate::crypto::TlsCertificate>,
+}
+
+impl Identity {
+    /// Read the identity kept in `dir`, creating the directory and whatever
+    /// is missing from it on first use. For [`Protocol::Https`] this
+    /// includes a self-signed certificate and key, so the fingerprint (which
+    /// the spec derives from the certificate) is stable too. That holds after
+    /// the LAN address or host name changes: the certificate is kept even
+    /// once the names in it are out of date, since peers pin the fingerprint,
+    /// not the names. A file that exists but does not parse is an error,
+    /// never silently replaced: that would change the identity. Only a certificate without its key, or
+    /// a key without its certificate, counts as missing, since that is what
+    /// an interrupted first run leaves behind.
+    ///
+    /// Files are written under a temporary name and moved into place, so a
+    /// crash never leaves one half written, and instances starting at once
+    /// on the same directory take turns, so the later ones load what the
+    /// first created.
+    pub fn load_or_create(dir: impl AsRef<Path>, protocol: Protocol) -> Result<Self> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir).map_err(|source| LocalSendError::CreateDir {
+            path: dir.to_path_buf(),
+            source,
+        })?;
+        let _lock = lock(dir)?;
+
+        let path = dir.join(IDENTITY_FILE_NAME);
+        let stored = match read_if_exists(&path)? {
+            Some(contents) => serde_json::from_slice(&contents)?,
+            None => {
+                let stored = StoredIdentity {
+                    fingerprint: crate::crypto::generate_fingerprint(),
+                    alias: format!("LocalSend-Rust-{}", &uuid::Uuid::new_v4().to_string()[..4]),
+                };
+                write_private(&path, &serde_json::to_vec_pretty(&stored)?)?;
+                stored
+            }
+        };
+
+        match protocol {
+            #[cfg(feature = "https")]
+            Protocol::Https => {
+                let certificate = load_or_create_certificate(dir)?;
+                Ok(Identity {
+                    fingerprint: certificate.fingerprint.clone(),
+                    alias: stored.alias,
+                    certificate: Some(certificate),
+                })
+            }
+            #[cfg(not(feature = "https"))]
+            Protocol::Https => Err(LocalSendError::network(
+                "HTTPS support not enabled; build with --features https",
+            )),
+            Protocol::Http => Ok(Identity {
+                fingerprint: stored.fingerprint,
+                alias: stored.alias,
+                #[cfg(feature = "https")]
+                certificate: None,
+            }),
+        }
+    }
+
+    /// Where identities are kept unless told otherwise: `localsend-rs` in
+    /// `$XDG_CONFIG_HOME`, `~/.config` or `%APPDATA%`. `None` when none of
+    /// those is set. Instances running at once need a directory each (the
+    /// CLI uses `receive` and `tui` under it): one fingerprint is one device.
+    pub fn default_dir() -> Option<PathBuf> {
+        let non_empty = |key| std::env::var_os(key).filter(|value| !value.is_empty());
+        non_empty("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .or_else(|| non_empty("APPDATA").map(PathBuf::from))
+            .map(|config| config.join("localsend-rs"))
+    }
+}
+
+#[cfg(feature = "https")]
+fn load_or_create_certificate(dir: &Path) -> Result<crate::crypto::TlsCertificate> {
+    let cert_path = dir.join(CERT_FILE_NAME);
+    let key_path = dir.join(KEY_FILE_NAME);
+    match (read_if_exists(&cert_path)?, read_if_exists(&key_path)?) {
+        (Some(cert), Some(key)) => {
+            let certificate = crate::crypto::TlsCertificate::from_pem(
+                String::from_utf8_lossy(&cert),
+                String::from_utf8_lossy(&key),
+            )?;
+            if !certificate.names_this_device() {
+                tracing::info!(
+                    "The kept TLS certificate names an earlier address or host name; keeping it"
+                );
+            }
+            return Ok(certificate);
+        }
+        (None, None) => {}
+        (cert, _) => {
+            let (kept, missing) = match cert {
+                Some(_) => (&cert_path, &key_path),
+                None => (&key_path, &cert_path),
+            };
+            tracing::warn!(
+                "{} is missing beside {}; replacing both",
+                missing.display(),
+                kept.display()
+            );
+        }
+    }
+
+    let certificate = crate::crypto::generate_tls_certificate()?;
+    // The certificate goes first and comes back last, so that however this
+    // is interrupted, what is left is either a whole pair or a key on its
+    // own, never a key beside a certificate it did not sign.
+    match std::fs::remove_file(&cert_path) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => return Err(error.into()),
+        _ => {}
+    }
+    write_private(&key_path, certificate.key_pem.as_bytes())?;
+    write_private(&cert_path, certificate.cert_pem.as_bytes())?;
+    Ok(certificate)
+}
+
+/// Hold `dir`'s lock file until the returned handle is dropped, so that
+/// instances starting at once take turns to create what is missing. Where
+/// the file system cannot lock, go on without.
+fn lock(dir: &Path) -> Result<std::fs::File> {
+    let file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(dir.join(LOCK_FILE_NAME))?;
+    match file.lock() {
+        Err(error) if error.kind() != std::io::ErrorKind::Unsupported => Err(error.into()),
+        _ => Ok(file),
+    }
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Vec<u8>>> {
+    match std::fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Replace `path` with a file only the current user can read, where that
+/// can be said, in one step: it is written and flushed under a temporary
+/// name beside `path` first, then moved into place.
+fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.{}.tmp", uuid::Uuid::new_v4()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let written = options
+        .open(&temp)
+        .and_then(|mut file| {
+            std::io::Write::write_all(&mut file, contents)?;
+            file.sync_all()
+        })
+        .and_then(|()| std::fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&temp);
+    }
+    Ok(written?)
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "https")]
+    use super::{CERT_FILE_NAME, KEY_FILE_NAME};
+    use super::{IDENTITY_FILE_NAME, Identity};
+    use crate::protocol::Protocol;
+
+    #[test]
+    fn the_identity_survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = Identity::load_or_create(dir.path().join("config"), Protocol::Http).unwrap();
+        let again = Identity::load_or_create(dir.path().join("config"), Protocol::Http).unwrap();
+        assert_eq!(again.fingerprint, first.fingerprint);
+        assert_eq!(again.alias, first.alias);
+        assert!(first.alias.starts_with("LocalSend-Rust-"));
+
+        std::fs::write(dir.path().join("config").join(IDENTITY_FILE_NAME), "{").unwrap();
+        assert!(Identity::load_or_create(dir.path().join("config"), Protocol::Http).is_err());
+    }
+
+    #[cfg(feature = "https")]
+    #[test]
+    fn over_https_the_fingerprint_is_the_kept_certificates() {
+        let dir = tempfile::tempdir().unwrap();
+        let http = Identity::load_or_create(dir.path(), Protocol::Http).unwrap();
+        assert!(http.certificate.is_none());
+
+        let first = Identity::load_or_create(dir.path(), Protocol::Https).unwrap();
+        let certificate = first.certificate.as_ref().unwrap();
+        assert_eq!(first.fingerprint, certificate.fingerprint);
+        assert_ne!(first.fingerprint, http.fingerprint);
+        assert_eq!(first.alias, http.alias);
+
+        let again = Identity::load_or_create(dir.path(), Protocol::Https).unwrap();
+        assert_eq!(again.fingerprint, first.fingerprint);
+        assert_eq!(again.certificate.unwrap().cert_der, certificate.cert_der);
+    }
+
+    #[cfg(feature = "https")]
+    #[test]
+    fn a_key_left_without_its_certificate_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = Identity::load_or_create(dir.path(), Protocol::Https).unwrap();
+        std::fs::remove_file(dir.path().join(CERT_FILE_NAME)).unwrap();
+
+        let again = Identity::load_or_create(dir.path(), Protocol::Https).unwrap();
+        assert_ne!(again.fingerprint, first.fingerprint);
+        let kept = Identity::load_or_create(dir.path(), Protocol::Https).unwrap();
+        assert_eq!(kept.fingerprint, again.fingerprint);
+
+        std::fs::write(dir.path().join(KEY_FILE_NAME), "garbage").unwrap();
+        assert!(Identity::load_or_create(dir.path(), Protocol::Https).is_err());
+    }
+
+    #[cfg(feature = "https")]
+    #[test]
+    fn a_certificate_naming_another_address_is_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let elsewhere = ["10.253.254.1".to_string(), "elsewhere.invalid".to_string()];
+        let stale = crate::crypto::tls::generate_tls_certificate_for(elsewhere.to_vec()).unwrap();
+        std::fs::write(dir.path().join(CERT_FILE_NAME), &stale.cert_pem).unwrap();
+        std::fs::write(dir.path().join(KEY_FILE_NAME), &stale.key_pem).unwrap();
+
+        let identity = Identity::load_or_create(dir.path(), Protocol::Https).unwrap();
+        assert_eq!(identity.fingerprint, stale.fingerprint);
+        assert_eq!(identity.certificate.unwrap().cert_der, stale.cert_der);
+        let kept = Identity::load_or_create(dir.path(), Protocol::Https).unwrap();
+        assert_eq!(kept.fingerprint, stale.fingerprint);
+    }
+
+    #[cfg(feature = "https")]
+    #[test]
+    fn instances_starting_at_once_share_one_identity() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("config");
+        let identities: Vec<_> = std::thread::scope(|scope| {
+            let starts: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| Identity::load_or_create(&config, Protocol::Https)))
+                .collect();
+            starts
+                .into_iter()
+                .map(|start| start.join().unwrap().unwrap())
+                .collect()
+        });
+
+        let kept = Identity::load_or_create(&config, Protocol::Https).unwrap();
+        let http = Identity::load_or_create(&config, Protocol::Http).unwrap();
+        for identity in &identities {
+            assert_eq!(identity.alias, http.alias);
+            assert_eq!(identity.fingerprint, kept.fingerprint);
+        }
+        let leftovers = std::fs::read_dir(&config)
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .ends_with(".tmp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
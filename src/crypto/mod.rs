@@ -1,9 +1,15 @@
+#[cfg(feature = "encryption")]
+pub mod at_rest;
 pub mod fingerprint;
 pub mod hash;
+pub mod identity;
 pub mod tls;
 
 pub use fingerprint::generate_fingerprint;
 pub use hash::{sha256_from_bytes, sha256_from_file};
+pub use identity::{IDENTITY_FILE_NAME, Identity};
 
+#[cfg(feature = "encryption")]
+pub use at_rest::{AtRestEncryption, AtRestManifest, decrypt_file};
 #[cfg(feature = "https")]
 pub use tls::{TlsCertificate, generate_tls_certificate};
@@ -2,6 +2,7 @@
 use crate::error::Result;
 
 #[cfg(feature = "https")]
+#[derive(Clone)]
 pub struct TlsCertificate {
     pub cert_pem: String,
     pub key_pem: String,
@@ -11,9 +12,13 @@ pub struct TlsCertificate {
 
 #[cfg(feature = "https")]
 pub fn generate_tls_certificate() -> Result<TlsCertificate> {
-    use rcgen::generate_simple_self_signed;
+    generate_tls_certificate_for(current_subject_alt_names())
+}
 
-    let cert = generate_simple_self_signed(vec!["localhost".to_string()]).map_err(|e| {
+/// [`generate_tls_certificate`] for the given subject alternative names.
+#[cfg(feature = "https")]
+pub(crate) fn generate_tls_certificate_for(names: Vec<String>) -> Result<TlsCertificate> {
+    let cert = rcgen::generate_simple_self_signed(names).map_err(|e| {
         crate::error::LocalSendError::network(format!("Failed to generate TLS certificate: {}", e))
     })?;
 
@@ -27,3 +32,150 @@ pub fn generate_tls_certificate() -> Result<TlsCertificate> {
         fingerprint,
     })
 }
+
+/// The names the generated certificate is valid for: `localhost` and
+/// `127.0.0.1` always, plus the LAN address peers reach this device on and
+/// the machine's host name when known, so a client that checks the name
+/// against the certificate accepts the connection.
+#[cfg(feature = "https")]
+fn subject_alt_names(lan_ip: Option<std::net::Ipv4Addr>, host_name: Option<String>) -> Vec<String> {
+    let mut names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+    names.extend(
+        lan_ip
+            .filter(|ip| !ip.is_loopback())
+            .map(|ip| ip.to_string()),
+    );
+    names.extend(host_name);
+    names.dedup();
+    names
+}
+
+/// [`subject_alt_names`] for this device as it is now.
+#[cfg(feature = "https")]
+fn current_subject_alt_names() -> Vec<String> {
+    subject_alt_names(crate::core::get_local_ip().ok(), host_name())
+}
+
+/// The host name from the environment, when it is usable as a DNS name.
+#[cfg(feature = "https")]
+fn host_name() -> Option<String> {
+    ["HOSTNAME", "COMPUTERNAME"]
+        .into_iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .map(|name| name.trim().to_ascii_lowercase())
+        .find(|name| {
+            !name.is_empty()
+                && name != "localhost"
+                && name.len() <= 253
+                && name.split('.').all(|label| {
+                    !label.is_empty()
+                        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+                })
+        })
+}
+
+#[cfg(feature = "https")]
+impl TlsCertificate {
+    /// A certificate kept from an earlier run (or issued elsewhere), so the
+    /// device keeps the same fingerprint across restarts. `cert_pem` may
+    /// hold a chain; the first certificate is the device's own, and its DER
+    /// is what the fingerprint is taken from. Fails unless `key_pem` holds a
+    /// private key that goes with that certificate.
+    pub fn from_pem(cert_pem: impl Into<String>, key_pem: impl Into<String>) -> Result<Self> {
+        let (cert_pem, key_pem) = (cert_pem.into(), key_pem.into());
+        let invalid =
+            |what: String| crate::error::LocalSendError::network(format!("Invalid TLS {what}"));
+
+        let chain = rustls_pemfile::certs(&mut cert_pem.as_bytes())
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| invalid(format!("certificate: {e}")))?;
+        let Some(cert_der) = chain.first().map(|der| der.as_ref().to_vec()) else {
+            return Err(invalid("certificate: no CERTIFICATE block found".into()));
+        };
+        let key = rustls_pemfile::private_key(&mut key_pem.as_bytes())
+            .map_err(|e| invalid(format!("key: {e}")))?
+            .ok_or_else(|| invalid("key: no PRIVATE KEY block found".into()))?;
+
+        // The same check serving would make, but now rather than at start.
+        let provider = std::sync::Arc::new(rustls::crypto::ring::default_provider());
+        rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .and_then(|builder| builder.with_no_client_auth().with_single_cert(chain, key))
+            .map_err(|e| invalid(format!("certificate and key: {e}")))?;
+
+        let fingerprint = super::hash::sha256_from_bytes(&cert_der);
+        Ok(TlsCertificate {
+            cert_pem,
+            key_pem,
+            cert_der,
+            fingerprint,
+        })
+    }
+
+    /// Whether this certificate is valid for every name
+    /// [`generate_tls_certificate`] would issue one for now. A kept
+    /// certificate stops being so when the LAN address or host name changes;
+    /// it is still served, since its fingerprint is the device's identity.
+    pub(crate) fn names_this_device(&self) -> bool {
+        self.is_valid_for(&current_subject_alt_names())
+    }
+
+    fn is_valid_for(&self, names: &[String]) -> bool {
+        let der = rustls::pki_types::CertificateDer::from(self.cert_der.as_slice());
+        let Ok(parsed) = rustls::server::ParsedCertificate::try_from(&der) else {
+            return false;
+        };
+        names.iter().all(|name| {
+            rustls::pki_types::ServerName::try_from(name.as_str())
+                .is_ok_and(|name| rustls::client::verify_server_name(&parsed, &name).is_ok())
+        })
+    }
+}
+
+#[cfg(all(test, feature = "https"))]
+mod tests {
+    use super::{
+        TlsCertificate, generate_tls_certificate, generate_tls_certificate_for, subject_alt_names,
+    };
+
+    #[test]
+    fn a_saved_certificate_keeps_its_fingerprint() {
+        let original = generate_tls_certificate().unwrap();
+        let loaded = TlsCertificate::from_pem(&original.cert_pem, &original.key_pem).unwrap();
+        assert_eq!(loaded.fingerprint, original.fingerprint);
+        assert_eq!(loaded.cert_der, original.cert_der);
+
+        let other = generate_tls_certificate().unwrap();
+        assert!(TlsCertificate::from_pem(&original.cert_pem, &other.key_pem).is_err());
+        assert!(TlsCertificate::from_pem("not a pem", &original.key_pem).is_err());
+        assert!(TlsCertificate::from_pem(&original.cert_pem, "").is_err());
+    }
+
+    #[test]
+    fn the_certificate_names_the_lan_address_and_host() {
+        let lan = "192.168.1.20".parse().ok();
+        assert_eq!(
+            subject_alt_names(lan, Some("desk-pc".into())),
+            ["localhost", "127.0.0.1", "192.168.1.20", "desk-pc"]
+        );
+        assert_eq!(subject_alt_names(None, None), ["localhost", "127.0.0.1"]);
+        assert_eq!(
+            subject_alt_names("127.0.0.1".parse().ok(), None),
+            ["localhost", "127.0.0.1"]
+        );
+    }
+
+    #[test]
+    fn a_certificate_for_an_old_address_no_longer_names_the_device() {
+        let names = subject_alt_names("192.168.1.20".parse().ok(), Some("desk-pc".into()));
+        let certificate = generate_tls_certificate_for(names.clone()).unwrap();
+        assert!(certificate.is_valid_for(&names));
+
+        let moved = subject_alt_names("10.0.0.7".parse().ok(), Some("desk-pc".into()));
+        assert!(!certificate.is_valid_for(&moved));
+        let renamed = subject_alt_names("192.168.1.20".parse().ok(), Some("laptop".into()));
+        assert!(!certificate.is_valid_for(&renamed));
+
+        assert!(generate_tls_certificate().unwrap().names_this_device());
+    }
+}
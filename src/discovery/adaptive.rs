@@ -0,0 +1,250 @@
+use crate::core::device::get_local_ip;
+use crate::discovery::{Discovery, HttpDiscovery, MulticastDiscovery};
+use crate::error::LocalSendError;
+use crate::protocol::DeviceInfo;
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+pub type Result<T> = std::result::Result<T, LocalSendError>;
+
+/// How long multicast gets to produce a first device before the subnet scan
+/// starts. Peers answer an announcement within a few hundred milliseconds on a
+/// healthy LAN, so a silent window this long usually means multicast is blocked.
+pub const DEFAULT_FALLBACK_WINDOW: Duration = Duration::from_secs(3);
+
+/// Which backend(s) an [`AdaptiveDiscovery`] uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiscoveryMode {
+    /// Multicast first; an HTTP subnet scan if nothing answers within the
+    /// fallback window.
+    #[default]
+    Auto,
+    /// Multicast announcements only.
+    Multicast,
+    /// HTTP `/info` subnet scan only.
+    Scan,
+}
+
+/// Devices found by one [`AdaptiveDiscovery::discover`] run.
+#[derive(Debug, Clone, Default)]
+pub struct DiscoveryReport {
+    /// De-duplicated by fingerprint; multicast results come first.
+    pub devices: Vec<DeviceInfo>,
+    /// `true` when multicast stayed silent and the subnet scan was run.
+    pub used_fallback: bool,
+}
+
+/// Multicast discovery that falls back to the protocol's HTTP subnet scan on
+/// networks that drop multicast traffic, merging both result sets.
+pub struct AdaptiveDiscovery {
+    multicast: MulticastDiscovery,
+    http: HttpDiscovery,
+    mode: DiscoveryMode,
+    fallback_window: Duration,
+}
+
+impl AdaptiveDiscovery {
+    pub fn new_with_device(device: DeviceInfo) -> Result<Self> {
+        Ok(Self {
+            http: HttpDiscovery::new_with_device(device.clone())?,
+            multicast: MulticastDiscovery::new_with_device(device),
+            mode: DiscoveryMode::default(),
+            fallback_window: DEFAULT_FALLBACK_WINDOW,
+        })
+    }
+
+    pub fn with_mode(mut self, mode: DiscoveryMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_fallback_window(mut self, window: Duration) -> Self {
+        self.fallback_window = window;
+        self
+    }
+
+    /// Discover peers for up to `timeout`. In [`DiscoveryMode::Auto`] the
+    /// multicast listener keeps running while the scan is in flight, so late
+    /// announcements still make it into the report.
+    pub async fn discover(&mut self, timeout: Duration) -> Result<DiscoveryReport> {
+        let found = Arc::new(Mutex::new(Vec::<DeviceInfo>::new()));
+        if self.mode != DiscoveryMode::Scan {
+            let sink = found.clone();
+            self.multicast.start().await?;
+            self.multicast.on_discovered(Box::new(move |device| {
+                if let Ok(mut devices) = sink.lock() {
+                    devices.push(device);
+                }
+            }));
+            if let Err(error) = self.multicast.announce_presence().await {
+                tracing::debug!("Multicast announcement failed: {error}");
+            }
+        }
+
+        let http = &self.http;
+        let mode = self.mode;
+        let report = collect_adaptive(mode, self.fallback_window, timeout, found, || async move {
+            match get_local_ip() {
+                Ok(ip) => http.scan_subnet(&ip.to_string()).await,
+                Err(error) if mode == DiscoveryMode::Auto => {
+                    tracing::debug!("Skipping subnet scan, no local IPv4 address: {error}");
+                    Ok(Vec::new())
+                }
+                Err(error) => Err(error),
+            }
+        })
+        .await;
+
+        if self.mode != DiscoveryMode::Scan {
+            self.multicast.stop();
+        }
+        report
+    }
+}
+
+/// The mode/window state machine, with both backends injected: `multicast` is
+/// filled by a running listener and `scan` performs a one-shot sweep.
+async fn collect_adaptive<F, Fut>(
+    mode: DiscoveryMode,
+    window: Duration,
+    timeout: Duration,
+    multicast: Arc<Mutex<Vec<DeviceInfo>>>,
+    scan: F,
+) -> Result<DiscoveryReport>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Vec<DeviceInfo>>>,
+{
+    let snapshot = |devices: &Arc<Mutex<Vec<DeviceInfo>>>| {
+        devices
+            .lock()
+            .map(|devices| devices.clone())
+            .unwrap_or_default()
+    };
+
+    match mode {
+        DiscoveryMode::Multicast => {
+            tokio::time::sleep(timeout).await;
+            Ok(DiscoveryReport {
+                devices: merge_devices(snapshot(&multicast), Vec::new()),
+                used_fallback: false,
+            })
+        }
+        DiscoveryMode::Scan => Ok(DiscoveryReport {
+            devices: merge_devices(Vec::new(), scan().await?),
+            used_fallback: false,
+        }),
+        DiscoveryMode::Auto => {
+            let window = window.min(timeout);
+            tokio::time::sleep(window).await;
+            if !snapshot(&multicast).is_empty() {
+                tokio::time::sleep(timeout - window).await;
+                return Ok(DiscoveryReport {
+                    devices: merge_devices(snapshot(&multicast), Vec::new()),
+                    used_fallback: false,
+                });
+            }
+
+            tracing::info!("No multicast responses within {window:?}; scanning the subnet");
+            let (scanned, _) = tokio::join!(scan(), tokio::time::sleep(timeout - window));
+            Ok(DiscoveryReport {
+                devices: merge_devices(snapshot(&multicast), scanned?),
+                used_fallback: true,
+            })
+        }
+    }
+}
+
+/// Concatenate the two result sets, keeping the first device per fingerprint.
+fn merge_devices(multicast: Vec<DeviceInfo>, scanned: Vec<DeviceInfo>) -> Vec<DeviceInfo> {
+    let mut seen = HashSet::new();
+    multicast
+        .into_iter()
+        .chain(scanned)
+        .filter(|device| seen.insert(device.fingerprint.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DiscoveryMode, collect_adaptive, merge_devices};
+    use crate::discovery::HttpDiscovery;
+    use crate::{DEFAULT_HTTP_PORT, DeviceInfo, LocalSendError, LocalSendServer, Protocol};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    fn device(fingerprint: &str) -> DeviceInfo {
+        DeviceInfo {
+            fingerprint: fingerprint.into(),
+            ..DeviceInfo::new(fingerprint.into(), DEFAULT_HTTP_PORT, Protocol::Http)
+        }
+    }
+
+    #[test]
+    fn merge_keeps_the_first_device_per_fingerprint() {
+        let merged = merge_devices(
+            vec![device("a"), device("b")],
+            vec![device("b"), device("c")],
+        );
+        let fingerprints: Vec<_> = merged.iter().map(|d| d.fingerprint.as_str()).collect();
+        assert_eq!(fingerprints, ["a", "b", "c"]);
+    }
+
+    #[tokio::test]
+    async fn auto_mode_falls_back_to_a_scan_when_multicast_is_silent() {
+        let output = tempfile::tempdir().expect("output directory");
+        let (mut server, _events) = LocalSendServer::builder()
+            .alias("fallback-target")
+            .port(0)
+            .save_dir(output.path())
+            .protocol(Protocol::Http)
+            .build()
+            .await
+            .expect("start HTTP receiver");
+        let expected_fingerprint = server.device().fingerprint.clone();
+
+        // Multicast is stubbed by a listener that never reports anything.
+        let silent_multicast = Arc::new(Mutex::new(Vec::new()));
+        let http = HttpDiscovery::new("scanner".into(), server.port(), Protocol::Http)
+            .expect("build discovery");
+        let report = collect_adaptive(
+            DiscoveryMode::Auto,
+            Duration::from_millis(50),
+            Duration::from_millis(200),
+            silent_multicast,
+            || http.scan_ips(vec!["127.0.0.1".to_string()]),
+        )
+        .await
+        .expect("adaptive discovery");
+
+        assert!(report.used_fallback);
+        assert!(
+            report
+                .devices
+                .iter()
+                .any(|d| d.fingerprint == expected_fingerprint),
+            "the subnet-scan fallback must find the reachable HTTP host"
+        );
+
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn auto_mode_skips_the_scan_when_multicast_answers() {
+        let multicast = Arc::new(Mutex::new(vec![device("peer")]));
+        let report = collect_adaptive(
+            DiscoveryMode::Auto,
+            Duration::from_millis(10),
+            Duration::from_millis(20),
+            multicast,
+            || std::future::ready(Err(LocalSendError::network("scan must not run"))),
+        )
+        .await
+        .expect("adaptive discovery");
+
+        assert!(!report.used_fallback);
+        assert_eq!(report.devices.len(), 1);
+    }
+}
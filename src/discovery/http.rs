@@ -1,13 +1,14 @@
 use crate::core::device::{get_device_model, get_device_type};
 use crate::crypto::generate_fingerprint;
 use crate::discovery::Discovery;
+use crate::discovery::traits::{DiscoveredCallback, SeenDevice, known_devices, remember_device};
 use crate::error::LocalSendError;
-use crate::protocol::{DeviceInfo, PROTOCOL_VERSION, Protocol};
+use crate::protocol::{DeviceInfo, Protocol, advertised_version, validate_fingerprint};
 use futures_util::{StreamExt, stream};
 use reqwest::Client;
 use std::collections::HashSet;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::sync::broadcast;
 
@@ -30,13 +31,14 @@ pub struct HttpDiscovery {
     client: Client,
     running: Arc<AtomicBool>,
     tx: Option<broadcast::Sender<DeviceInfo>>,
+    known: Arc<RwLock<Vec<SeenDevice>>>,
 }
 
 impl HttpDiscovery {
     pub fn new(alias: String, port: u16, protocol: Protocol) -> Result<Self> {
         let device = DeviceInfo {
             alias,
-            version: PROTOCOL_VERSION.to_string(),
+            version: advertised_version(),
             device_model: Some(get_device_model()),
             device_type: Some(get_device_type()),
             fingerprint: generate_fingerprint(),
@@ -46,11 +48,22 @@ impl HttpDiscovery {
             ip: None,
         };
 
+        Self::new_with_device(device)
+    }
+
+    pub fn local_device(&self) -> &DeviceInfo {
+        &self.local_device
+    }
+
+    /// Probe with an existing identity, so a combined discovery can skip the
+    /// same local fingerprint on both backends.
+    pub fn new_with_device(device: DeviceInfo) -> Result<Self> {
         Ok(Self {
             local_device: device,
             client: build_discovery_client()?,
             running: Arc::new(AtomicBool::new(false)),
             tx: None,
+            known: Arc::new(RwLock::new(Vec::new())),
         })
     }
 
@@ -92,11 +105,18 @@ impl HttpDiscovery {
         let mut seen = HashSet::new();
         let mut result = Vec::new();
         for device in discovered {
-            if device.fingerprint.is_empty() || device.fingerprint == self.local_device.fingerprint
-            {
+            if device.fingerprint == self.local_device.fingerprint {
+                continue;
+            }
+            if let Err(error) = validate_fingerprint(&device.fingerprint) {
+                tracing::debug!("Dropping {:?} ({:?}): {error}", device.alias, device.ip);
                 continue;
             }
             if seen.insert(device.fingerprint.clone()) {
+                remember_device(&self.known, device.clone());
+                if let Some(tx) = &self.tx {
+                    let _ = tx.send(device.clone());
+                }
                 result.push(device);
             }
         }
@@ -227,10 +247,7 @@ impl Discovery for HttpDiscovery {
         ))
     }
 
-    fn on_discovered<F>(&mut self, callback: F)
-    where
-        F: Fn(DeviceInfo) + Send + Sync + 'static,
-    {
+    fn on_discovered(&mut self, callback: DiscoveredCallback) {
         let tx = if let Some(ref t) = self.tx {
             t.clone()
         } else {
@@ -246,7 +263,7 @@ impl Discovery for HttpDiscovery {
     }
 
     fn get_known_devices(&self) -> Vec<DeviceInfo> {
-        vec![]
+        known_devices(&self.known, None)
     }
 }
 
@@ -347,6 +364,38 @@ mod tests {
         server.stop().await;
     }
 
+    #[tokio::test]
+    async fn scanned_devices_are_known_through_the_trait_object() {
+        use crate::discovery::Discovery;
+        use crate::{LocalSendServer, Protocol};
+
+        let output = tempfile::tempdir().expect("output directory");
+        let (mut server, _events) = LocalSendServer::builder()
+            .alias("known-target")
+            .port(0)
+            .save_dir(output.path())
+            .protocol(Protocol::Http)
+            .build()
+            .await
+            .expect("start HTTP receiver");
+        let expected_fingerprint = server.device().fingerprint.clone();
+
+        let discovery = HttpDiscovery::new("scanner".into(), server.port(), Protocol::Http)
+            .expect("build discovery");
+        // Scanning twice must not list the peer twice.
+        for _ in 0..2 {
+            discovery.scan_hosts(vec!["127.0.0.1".to_string()]).await;
+        }
+
+        let discovery: Box<dyn Discovery> = Box::new(discovery);
+        let known = discovery.get_known_devices();
+        assert_eq!(known.len(), 1);
+        assert_eq!(known[0].fingerprint, expected_fingerprint);
+        assert_eq!(known[0].alias, "known-target");
+
+        server.stop().await;
+    }
+
     #[tokio::test]
     #[ignore = "requires CROSSCOPY_E2E_LOCALSEND_TARGET to name a reachable LocalSend peer"]
     async fn scan_ips_finds_an_explicit_e2e_peer() {
@@ -1,7 +1,12 @@
+pub mod adaptive;
 pub mod http;
 pub mod multicast;
 pub mod traits;
 
+pub use adaptive::{AdaptiveDiscovery, DiscoveryMode, DiscoveryReport};
 pub use http::HttpDiscovery;
-pub use multicast::{MulticastConfig, MulticastDiscovery};
-pub use traits::Discovery;
+pub use multicast::{
+    DEFAULT_ANNOUNCE_INTERVAL, DEFAULT_DEVICE_TTL, DEFAULT_INTERFACE_POLL_INTERVAL,
+    DEFAULT_MULTICAST_TTL, InterfaceSource, MulticastConfig, MulticastDiscovery,
+};
+pub use traits::{DiscoveredCallback, Discovery};
@@ -1,21 +1,26 @@
-use crate::client::{LocalSendClient, TlsTrustPolicy};
+use crate::client::{ClientConfig, LocalSendClient, TlsTrustPolicy};
 use crate::core::device::{get_device_model, get_device_type, get_local_ip};
 use crate::crypto::generate_fingerprint;
 use crate::discovery::Discovery;
+use crate::discovery::traits::{
+    DiscoveredCallback, SeenDevice, forget_stale, known_devices, remember_device,
+};
 use crate::error::LocalSendError;
 use crate::protocol::{
-    AnnouncementMessage, DEFAULT_MULTICAST_ADDRESS, DEFAULT_MULTICAST_PORT, DeviceInfo,
-    PROTOCOL_VERSION, Protocol,
+    AnnouncementMessage, DEFAULT_MULTICAST_ADDRESS, DEFAULT_MULTICAST_PORT, DeviceInfo, Protocol,
+    advertised_version, validate_fingerprint, validate_port,
 };
 use if_addrs::{IfAddr, get_if_addrs};
 use socket2::{Domain, Protocol as SocketProtocol, Socket, Type};
 use std::collections::BTreeSet;
-use std::net::{Ipv4Addr, SocketAddr};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::future::Future;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::net::UdpSocket;
 use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
 
 pub type Result<T> = std::result::Result<T, LocalSendError>;
 
@@ -24,6 +29,15 @@ pub struct MulticastConfig {
     pub address: Ipv4Addr,
     pub port: u16,
     pub interface_names: Option<BTreeSet<String>>,
+    /// Sent in every announcement and reply, so peers on the same network
+    /// connect here instead of to the datagram's source address. See
+    /// [`Self::with_advertised_ip`].
+    pub advertised_ip: Option<Ipv4Addr>,
+    /// An IPv6 group joined next to the IPv4 one, on the same port. See
+    /// [`Self::with_ipv6_address`].
+    pub ipv6_address: Option<Ipv6Addr>,
+    /// Routers an announcement may cross. See [`Self::with_multicast_ttl`].
+    pub multicast_ttl: u8,
 }
 
 impl MulticastConfig {
@@ -42,8 +56,52 @@ impl MulticastConfig {
             address,
             port,
             interface_names,
+            advertised_ip: None,
+            ipv6_address: None,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
         })
     }
+
+    /// Tell peers to reach us on `ip`. On a multi-homed host the multicast
+    /// source address can belong to an interface peers cannot route to (a
+    /// container bridge, a VPN). Only peers running this crate honour it, and
+    /// only when the datagram's source and `ip` share one of their networks.
+    pub fn with_advertised_ip(mut self, ip: Ipv4Addr) -> Self {
+        self.advertised_ip = Some(ip);
+        self
+    }
+
+    /// Also discover over IPv6, on the group `address` (on the interface the
+    /// system picks for it). The official app speaks IPv4 only; this reaches
+    /// peers running this crate on dual-stack or IPv6-only networks. Use a
+    /// group wider than link-local (`ff05::/16`, say): replies to an
+    /// `ff02::` group come from link-local addresses, which HTTP cannot dial.
+    pub fn with_ipv6_address(mut self, address: Ipv6Addr) -> Result<Self> {
+        if !address.is_multicast() {
+            return Err(LocalSendError::InvalidMulticastAddress(address.to_string()));
+        }
+        self.ipv6_address = Some(address);
+        Ok(self)
+    }
+
+    /// Let announcements cross up to `ttl - 1` multicast routers (the IPv6
+    /// hop limit too). The default of 1 keeps them on the local link, like
+    /// the official app's. More is for routed networks that forward the
+    /// group; it also announces this device's alias, fingerprint and port
+    /// to every subnet those routers reach, and lets peers there start
+    /// transfers to it, so raise it only on networks you trust end to end.
+    pub fn with_multicast_ttl(mut self, ttl: u8) -> Self {
+        self.multicast_ttl = ttl;
+        self
+    }
+
+    /// Where `socket` sends to the group: the IPv6 group from an IPv6 socket.
+    fn group_for(&self, socket: &UdpSocket) -> SocketAddr {
+        match (socket.local_addr(), self.ipv6_address) {
+            (Ok(SocketAddr::V6(_)), Some(group)) => SocketAddr::from((group, self.port)),
+            _ => SocketAddr::from((self.address, self.port)),
+        }
+    }
 }
 
 impl Default for MulticastConfig {
@@ -54,24 +112,62 @@ impl Default for MulticastConfig {
                 .expect("LocalSend's multicast constant must be a valid IPv4 address"),
             port: DEFAULT_MULTICAST_PORT,
             interface_names: None,
+            advertised_ip: None,
+            ipv6_address: None,
+            multicast_ttl: DEFAULT_MULTICAST_TTL,
         }
     }
 }
 
+/// [`MulticastConfig::multicast_ttl`] unless set: announcements stay on the
+/// local link.
+pub const DEFAULT_MULTICAST_TTL: u8 = 1;
+
+/// How often [`MulticastDiscovery`] re-reads the local interfaces to notice a
+/// network switch (Wi-Fi to Ethernet, roaming) and rejoin the group.
+pub const DEFAULT_INTERFACE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often [`MulticastDiscovery`] re-broadcasts its announcement while it
+/// runs, so a peer that joins after the start-up bursts still finds us.
+pub const DEFAULT_ANNOUNCE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long [`MulticastDiscovery`] keeps a peer it has stopped hearing from.
+/// Several [`DEFAULT_ANNOUNCE_INTERVAL`]s, so one lost datagram does not
+/// drop a peer that is still there.
+pub const DEFAULT_DEVICE_TTL: Duration = Duration::from_secs(30);
+
+/// How long answering an announcement waits on the peer's `/register` before
+/// falling back to a UDP reply; a half-open peer must not pin the task.
+const ANNOUNCEMENT_REGISTER_TIMEOUT: Duration = Duration::from_secs(2);
+/// Extra registration attempts for a peer that is still starting its server
+/// when its announcement arrives, before falling back to a UDP reply.
+const ANNOUNCEMENT_REGISTER_RETRIES: u32 = 2;
+const ANNOUNCEMENT_REGISTER_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Lists the IPv4 interface addresses the multicast group should be joined on.
+pub type InterfaceSource = Arc<dyn Fn() -> Result<Vec<Ipv4Addr>> + Send + Sync>;
+
 #[derive(Clone)]
 pub struct MulticastDiscovery {
     local_device: DeviceInfo,
     config: MulticastConfig,
-    sockets: Vec<Arc<UdpSocket>>,
+    sockets: Arc<RwLock<Vec<Arc<UdpSocket>>>>,
+    generation: Arc<AtomicU64>,
     running: Arc<AtomicBool>,
     tx: Option<broadcast::Sender<DeviceInfo>>,
+    known: Arc<RwLock<Vec<SeenDevice>>>,
+    interface_source: InterfaceSource,
+    interface_poll_interval: Duration,
+    announce_interval: Option<Duration>,
+    device_ttl: Duration,
+    watcher: Option<CancellationToken>,
 }
 
 impl MulticastDiscovery {
     pub fn new(alias: String, port: u16, protocol: Protocol) -> Result<Self> {
         let device = DeviceInfo {
             alias,
-            version: PROTOCOL_VERSION.to_string(),
+            version: advertised_version(),
             device_model: Some(get_device_model()),
             device_type: Some(get_device_type()),
             fingerprint: generate_fingerprint(),
@@ -90,22 +186,310 @@ impl MulticastDiscovery {
     }
 
     pub fn new_with_device_and_config(device: DeviceInfo, config: MulticastConfig) -> Result<Self> {
-        let config = MulticastConfig::new(config.address, config.port, config.interface_names)?;
+        // A config built as a struct literal is checked like one from `new`.
+        MulticastConfig::new(config.address, config.port, None)?;
         let (tx, _rx) = broadcast::channel(100);
+        let interface_names = config.interface_names.clone();
         Ok(Self {
             local_device: device,
             config,
-            sockets: Vec::new(),
+            sockets: Arc::new(RwLock::new(Vec::new())),
+            generation: Arc::new(AtomicU64::new(0)),
             running: Arc::new(AtomicBool::new(false)),
             tx: Some(tx),
+            known: Arc::new(RwLock::new(Vec::new())),
+            interface_source: Arc::new(move || {
+                Self::multicast_interfaces(interface_names.as_ref())
+            }),
+            interface_poll_interval: DEFAULT_INTERFACE_POLL_INTERVAL,
+            announce_interval: Some(DEFAULT_ANNOUNCE_INTERVAL),
+            device_ttl: DEFAULT_DEVICE_TTL,
+            watcher: None,
         })
     }
 
+    pub fn local_device(&self) -> &DeviceInfo {
+        &self.local_device
+    }
+
+    /// Record a peer heard from outside multicast, such as a register request
+    /// to our server ([`crate::server::ServerEvent::DeviceRegistered`]): it
+    /// joins or refreshes the known list and reaches the
+    /// [`Discovery::on_discovered`] callbacks like an announcement would.
+    pub fn remember(&self, device: DeviceInfo) {
+        if device.fingerprint == self.local_device.fingerprint {
+            return;
+        }
+        remember_device(&self.known, device.clone());
+        if let Some(tx) = &self.tx {
+            let _ = tx.send(device);
+        }
+    }
+
     /// Replace the identity used by future announcements without rebuilding
     /// sockets or losing the current discovery cache.
     pub fn set_local_device(&mut self, device: DeviceInfo) {
         self.local_device = device;
     }
+
+    /// Override where interface addresses come from; the listener rejoins the
+    /// group whenever the returned set changes between polls.
+    pub fn with_interface_source<F>(mut self, source: F) -> Self
+    where
+        F: Fn() -> Result<Vec<Ipv4Addr>> + Send + Sync + 'static,
+    {
+        self.interface_source = Arc::new(source);
+        self
+    }
+
+    /// Join the group, and send announcements, on the interface with address
+    /// `interface` only: on a host with a VPN or a container bridge up, the
+    /// automatic choice can land on a network the peers are not on. `None`
+    /// goes back to that automatic choice (the interfaces on the primary
+    /// LAN, narrowed by [`MulticastConfig::interface_names`]).
+    pub fn with_interface(self, interface: Option<Ipv4Addr>) -> Self {
+        match interface {
+            Some(interface) => self.with_interface_source(move || Ok(vec![interface])),
+            None => {
+                let interface_names = self.config.interface_names.clone();
+                self.with_interface_source(move || {
+                    Self::multicast_interfaces(interface_names.as_ref())
+                })
+            }
+        }
+    }
+
+    pub fn with_interface_poll_interval(mut self, interval: Duration) -> Self {
+        self.interface_poll_interval = interval;
+        self
+    }
+
+    /// Re-announce every `interval` from [`Discovery::start`] until
+    /// [`Discovery::stop`]; `None` leaves only the bursts of
+    /// [`Discovery::announce_presence`].
+    pub fn with_announce_interval(mut self, interval: Option<Duration>) -> Self {
+        self.announce_interval = interval;
+        self
+    }
+
+    /// How long a peer stays known without being heard from again; the
+    /// default is [`DEFAULT_DEVICE_TTL`].
+    pub fn with_device_ttl(mut self, ttl: Duration) -> Self {
+        self.device_ttl = ttl;
+        self
+    }
+
+    /// What [`Discovery::announce_presence`] sends.
+    fn announcement(&self) -> AnnouncementMessage {
+        announcement(&self.local_device, &self.config)
+    }
+
+    fn membership(&self) -> Membership {
+        Membership {
+            config: self.config.clone(),
+            local_device: self.local_device.clone(),
+            sockets: self.sockets.clone(),
+            generation: self.generation.clone(),
+            tx: self.tx.as_ref().unwrap().clone(),
+            known: self.known.clone(),
+        }
+    }
+}
+
+/// The sockets joined to the multicast group and their receive loops. Each
+/// join bumps `generation`, which retires the loops of the previous sockets.
+#[derive(Clone)]
+struct Membership {
+    config: MulticastConfig,
+    local_device: DeviceInfo,
+    sockets: Arc<RwLock<Vec<Arc<UdpSocket>>>>,
+    generation: Arc<AtomicU64>,
+    tx: broadcast::Sender<DeviceInfo>,
+    known: Arc<RwLock<Vec<SeenDevice>>>,
+}
+
+impl Membership {
+    fn join(&self, interfaces: &[Ipv4Addr]) -> Result<()> {
+        let group = self.config.address;
+        let mut sockets = interfaces
+            .iter()
+            .map(|&interface| {
+                create_reusable_udp_socket(
+                    self.config.port,
+                    GroupJoin::V4 { group, interface },
+                    self.config.multicast_ttl,
+                )
+                .map(Arc::new)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(group) = self.config.ipv6_address {
+            // IPv6 is an addition: a host without it still discovers over IPv4.
+            let join = GroupJoin::V6 {
+                group,
+                interface: 0,
+            };
+            match create_reusable_udp_socket(self.config.port, join, self.config.multicast_ttl) {
+                Ok(socket) => sockets.push(Arc::new(socket)),
+                Err(error) => tracing::warn!("Not discovering over IPv6: {error}"),
+            }
+        }
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        for socket in &sockets {
+            self.spawn_listener(socket.clone(), generation);
+        }
+        *self.sockets.write().unwrap() = sockets;
+        Ok(())
+    }
+
+    fn spawn_listener(&self, socket: Arc<UdpSocket>, generation: u64) {
+        let tx = self.tx.clone();
+        let known = self.known.clone();
+        let current = self.generation.clone();
+        let local_device = self.local_device.clone();
+        let multicast_addr = self.config.group_for(&socket);
+        let advertised_ip = self.config.advertised_ip;
+
+        tokio::spawn(async move {
+            let mut buf = vec![0u8; 65536];
+
+            while current.load(Ordering::SeqCst) == generation {
+                match tokio::time::timeout(Duration::from_secs(1), socket.recv_from(&mut buf)).await
+                {
+                    Ok(Ok((len, src))) => {
+                        let Some((device, is_announcement)) =
+                            parse_announcement(&buf[..len], src, &local_device.fingerprint)
+                        else {
+                            continue;
+                        };
+                        remember_device(&known, device.clone());
+                        let _ = tx.send(device.clone());
+
+                        if is_announcement {
+                            let local_device = local_device.clone();
+                            let socket = socket.clone();
+
+                            tokio::spawn(async move {
+                                MulticastDiscovery::respond_to_announcement(
+                                    &device,
+                                    &local_device,
+                                    &socket,
+                                    multicast_addr,
+                                    advertised_ip,
+                                    ANNOUNCEMENT_REGISTER_TIMEOUT,
+                                )
+                                .await;
+                            });
+                        }
+                    }
+                    Ok(Err(_)) | Err(_) => continue,
+                }
+            }
+        });
+    }
+
+    /// One announcement on the current sockets, so peers learn our new address.
+    async fn announce_once(&self) -> Result<()> {
+        let msg = serde_json::to_vec(&announcement(&self.local_device, &self.config))?;
+        let sockets = self.sockets.read().unwrap().clone();
+        for socket in sockets {
+            socket.send_to(&msg, self.config.group_for(&socket)).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Poll `source` every `interval` and call `rejoin` with the new address set
+/// whenever it differs from the last one joined. A failed rejoin is retried on
+/// the next poll.
+async fn watch_interfaces<F, Fut>(
+    source: InterfaceSource,
+    mut current: Vec<Ipv4Addr>,
+    interval: Duration,
+    cancel: CancellationToken,
+    mut rejoin: F,
+) where
+    F: FnMut(Vec<Ipv4Addr>) -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    current.sort();
+    current.dedup();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = ticker.tick() => {}
+        }
+
+        let mut interfaces = match source() {
+            Ok(interfaces) => interfaces,
+            Err(error) => {
+                tracing::debug!("Failed to re-read network interfaces: {error}");
+                continue;
+            }
+        };
+        interfaces.sort();
+        interfaces.dedup();
+        if interfaces == current {
+            continue;
+        }
+
+        tracing::info!(
+            "Network interfaces changed from {current:?} to {interfaces:?}; rejoining multicast"
+        );
+        match rejoin(interfaces.clone()).await {
+            Ok(()) => current = interfaces,
+            Err(error) => tracing::warn!("Failed to rejoin multicast group: {error}"),
+        }
+    }
+}
+
+/// Call `announce` every `interval` until `cancel` fires. The first call
+/// comes one interval in: start-up is what `announce_presence` is for.
+async fn announce_periodically<F, Fut>(
+    interval: Duration,
+    cancel: CancellationToken,
+    mut announce: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = ticker.tick() => {}
+        }
+        if let Err(error) = announce().await {
+            tracing::debug!("Periodic multicast announcement failed: {error}");
+        }
+    }
+}
+
+/// Every half `ttl`, drop the peers not heard from within it, until `cancel`
+/// fires. [`Discovery::get_known_devices`] filters by age on its own; this
+/// keeps departed peers from piling up in a long-running listener.
+async fn expire_devices(
+    known: Arc<RwLock<Vec<SeenDevice>>>,
+    ttl: Duration,
+    cancel: CancellationToken,
+) {
+    let mut ticker = tokio::time::interval((ttl / 2).max(Duration::from_millis(1)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    ticker.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = ticker.tick() => forget_stale(&known, ttl),
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -115,132 +499,78 @@ impl Discovery for MulticastDiscovery {
             return Err(LocalSendError::network("Discovery already running"));
         }
 
-        let bind_addr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, self.config.port));
-        let sockets = Self::multicast_interfaces(self.config.interface_names.as_ref())?
-            .into_iter()
-            .map(|interface| create_reusable_udp_socket(&bind_addr, interface, self.config.address))
-            .collect::<Result<Vec<_>>>()?
-            .into_iter()
-            .map(Arc::new)
-            .collect::<Vec<_>>();
-
-        self.sockets = sockets.clone();
+        let interfaces = (self.interface_source)()?;
+        let membership = self.membership();
+        membership.join(&interfaces)?;
         self.running.store(true, Ordering::Relaxed);
 
-        for socket in sockets {
-            let tx = self.tx.as_ref().unwrap().clone();
-            let local_fingerprint = self.local_device.fingerprint.clone();
-            let running = self.running.clone();
-            let local_device = self.local_device.clone();
-            let multicast_addr = SocketAddr::from((self.config.address, self.config.port));
-
-            tokio::spawn(async move {
-                let mut buf = vec![0u8; 65536];
-
-                while running.load(Ordering::Relaxed) {
-                    match tokio::time::timeout(Duration::from_secs(1), socket.recv_from(&mut buf))
-                        .await
-                    {
-                        Ok(Ok((len, src))) => {
-                            if len > 0 {
-                                let msg = match String::from_utf8(buf[..len].to_vec()) {
-                                    Ok(s) => s,
-                                    Err(_) => continue,
-                                };
-
-                                if let Ok(announcement) =
-                                    serde_json::from_str::<AnnouncementMessage>(&msg)
-                                {
-                                    if announcement.fingerprint == local_fingerprint {
-                                        continue;
-                                    }
-
-                                    let device = DeviceInfo {
-                                        alias: announcement.alias.clone(),
-                                        version: announcement.version.clone(),
-                                        device_model: announcement.device_model.clone(),
-                                        device_type: announcement.device_type,
-                                        fingerprint: announcement.fingerprint.clone(),
-                                        port: announcement.port,
-                                        protocol: announcement.protocol,
-                                        download: announcement.download,
-                                        ip: Some(src.ip().to_string()),
-                                    };
-
-                                    let is_announcement = announcement.announce
-                                        || announcement.announcement.unwrap_or(false);
-                                    let _ = tx.send(device.clone());
-
-                                    if is_announcement {
-                                        let local_device = local_device.clone();
-                                        let socket = socket.clone();
-
-                                        tokio::spawn(async move {
-                                            Self::respond_to_announcement(
-                                                &device,
-                                                &local_device,
-                                                &socket,
-                                                multicast_addr,
-                                            )
-                                            .await;
-                                        });
-                                    }
-                                }
-                            }
-                        }
-                        Ok(Err(_)) | Err(_) => continue,
-                    }
-                }
-            });
+        let cancel = CancellationToken::new();
+        self.watcher = Some(cancel.clone());
+        if let Some(interval) = self.announce_interval {
+            let membership = membership.clone();
+            tokio::spawn(announce_periodically(interval, cancel.clone(), move || {
+                let membership = membership.clone();
+                async move { membership.announce_once().await }
+            }));
         }
+        tokio::spawn(expire_devices(
+            self.known.clone(),
+            self.device_ttl,
+            cancel.clone(),
+        ));
+        tokio::spawn(watch_interfaces(
+            self.interface_source.clone(),
+            interfaces,
+            self.interface_poll_interval,
+            cancel,
+            move |interfaces| {
+                let membership = membership.clone();
+                async move {
+                    membership.join(&interfaces)?;
+                    membership.announce_once().await
+                }
+            },
+        ));
 
         Ok(())
     }
 
     fn stop(&mut self) {
         self.running.store(false, Ordering::Relaxed);
-        self.sockets.clear();
+        if let Some(watcher) = self.watcher.take() {
+            watcher.cancel();
+        }
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.sockets.write().unwrap().clear();
         self.tx = None;
     }
 
     async fn announce_presence(&self) -> std::result::Result<(), LocalSendError> {
-        if self.sockets.is_empty() {
+        let sockets = self.sockets.read().unwrap().clone();
+        if sockets.is_empty() {
             return Err(LocalSendError::network("Discovery not started"));
         }
 
-        let announcement = AnnouncementMessage {
-            alias: self.local_device.alias.clone(),
-            version: self.local_device.version.clone(),
-            device_model: self.local_device.device_model.clone(),
-            device_type: self.local_device.device_type,
-            fingerprint: self.local_device.fingerprint.clone(),
-            port: self.local_device.port,
-            protocol: self.local_device.protocol,
-            download: self.local_device.download,
-            announce: true,
-            announcement: Some(true),
-        };
+        let announcement = self.announcement();
 
         let msg = serde_json::to_string(&announcement)?;
         let buf = msg.as_bytes();
-        let multicast_addr = SocketAddr::from((self.config.address, self.config.port));
 
-        // Send announcement multiple times with delays to improve reliability
+        // Send announcement multiple times with delays to improve reliability.
+        // Re-read the sockets each round so a rejoin in between is honoured.
         let delays = [100, 500, 2000];
         for delay in delays {
             tokio::time::sleep(Duration::from_millis(delay)).await;
-            for socket in &self.sockets {
-                socket.send_to(buf, &multicast_addr).await?;
+            let sockets = self.sockets.read().unwrap().clone();
+            for socket in &sockets {
+                socket.send_to(buf, self.config.group_for(socket)).await?;
             }
         }
 
         Ok(())
     }
 
-    fn on_discovered<F>(&mut self, callback: F)
-    where
-        F: Fn(DeviceInfo) + Send + Sync + 'static,
-    {
+    fn on_discovered(&mut self, callback: DiscoveredCallback) {
         let tx = if let Some(ref t) = self.tx {
             t.clone()
         } else {
@@ -256,7 +586,7 @@ impl Discovery for MulticastDiscovery {
     }
 
     fn get_known_devices(&self) -> Vec<DeviceInfo> {
-        vec![]
+        known_devices(&self.known, Some(self.device_ttl))
     }
 }
 
@@ -300,14 +630,16 @@ impl MulticastDiscovery {
     fn client_for_announcement(
         local_device: DeviceInfo,
         target_device: &DeviceInfo,
+        register_timeout: Duration,
     ) -> Result<LocalSendClient> {
-        match target_device.protocol {
-            Protocol::Http => Ok(LocalSendClient::new(local_device)),
+        let client = match target_device.protocol {
+            Protocol::Http => LocalSendClient::new(local_device),
             Protocol::Https => LocalSendClient::with_trust_policy(
                 local_device,
                 TlsTrustPolicy::PinnedFingerprint(target_device.fingerprint.clone()),
-            ),
-        }
+            )?,
+        };
+        Ok(client.with_config(ClientConfig::default().with_register_timeout(register_timeout)))
     }
 
     pub async fn scan(
@@ -321,12 +653,12 @@ impl MulticastDiscovery {
 
         // Register a callback to update the devices list during the scan
         let devices_clone = devices.clone();
-        self.on_discovered(move |device| {
+        self.on_discovered(Box::new(move |device| {
             let mut guard = devices_clone.write().unwrap();
             if !guard.iter().any(|d| d.fingerprint == device.fingerprint) {
                 guard.push(device);
             }
-        });
+        }));
 
         // Clear devices
         devices.write().unwrap().clear();
@@ -345,6 +677,8 @@ impl MulticastDiscovery {
         local_device: &DeviceInfo,
         socket: &UdpSocket,
         multicast_addr: SocketAddr,
+        advertised_ip: Option<Ipv4Addr>,
+        register_timeout: Duration,
     ) {
         tracing::debug!(
             "Responding to announcement from {} ({:?})",
@@ -354,8 +688,15 @@ impl MulticastDiscovery {
 
         // The discovery announcement contains the peer's certificate fingerprint.
         // Use it for HTTPS registration instead of system CA verification.
-        match Self::client_for_announcement(local_device.clone(), target_device) {
-            Ok(client) => match client.register(target_device).await {
+        match Self::client_for_announcement(local_device.clone(), target_device, register_timeout) {
+            Ok(client) => match client
+                .register_with_retry(
+                    target_device,
+                    ANNOUNCEMENT_REGISTER_RETRIES,
+                    ANNOUNCEMENT_REGISTER_BACKOFF,
+                )
+                .await
+            {
                 Ok(_) => {
                     tracing::debug!(
                         "Successfully registered with {} via HTTP",
@@ -382,18 +723,8 @@ impl MulticastDiscovery {
         }
 
         // Fallback: Send UDP response
-        let announcement = AnnouncementMessage {
-            alias: local_device.alias.clone(),
-            version: local_device.version.clone(),
-            device_model: local_device.device_model.clone(),
-            device_type: local_device.device_type,
-            fingerprint: local_device.fingerprint.clone(),
-            port: local_device.port,
-            protocol: local_device.protocol,
-            download: local_device.download,
-            announce: false,
-            announcement: Some(false),
-        };
+        let announcement =
+            AnnouncementMessage::from_device(local_device, false).with_ip(advertised_ip);
 
         if let Ok(msg) = serde_json::to_string(&announcement) {
             let buf = msg.as_bytes();
@@ -406,6 +737,40 @@ impl MulticastDiscovery {
     }
 }
 
+/// An announcement from `local_device`, asking for replies.
+fn announcement(local_device: &DeviceInfo, config: &MulticastConfig) -> AnnouncementMessage {
+    AnnouncementMessage::from_device(local_device, true).with_ip(config.advertised_ip)
+}
+
+/// Decode one datagram into the announcing peer (addressed by the packet's
+/// source) and whether it asked for a reply. Our own echoes, non-LocalSend
+/// payloads and peers without a usable fingerprint or port are dropped.
+fn parse_announcement(
+    datagram: &[u8],
+    src: SocketAddr,
+    local_fingerprint: &str,
+) -> Option<(DeviceInfo, bool)> {
+    let announcement = serde_json::from_slice::<AnnouncementMessage>(datagram).ok()?;
+    if announcement.fingerprint == local_fingerprint {
+        return None;
+    }
+    if let Err(error) = validate_fingerprint(&announcement.fingerprint)
+        .and_then(|()| validate_port(announcement.port))
+    {
+        tracing::debug!(
+            "Dropping announcement from {} ({src}): {error}",
+            announcement.alias
+        );
+        return None;
+    }
+
+    let is_announcement = announcement.announce;
+    Some((
+        DeviceInfo::from_announcement(announcement, src),
+        is_announcement,
+    ))
+}
+
 fn select_interface_addresses(
     addresses: impl IntoIterator<Item = (String, Ipv4Addr, Ipv4Addr)>,
     interface_names: Option<&BTreeSet<String>>,
@@ -426,6 +791,17 @@ fn select_interface_addresses(
         .collect()
 }
 
+/// The group a socket joins, and the interface it joins and sends on.
+#[derive(Debug, Clone, Copy)]
+enum GroupJoin {
+    V4 {
+        group: Ipv4Addr,
+        interface: Ipv4Addr,
+    },
+    /// `interface` is an interface index; 0 lets the system choose.
+    V6 { group: Ipv6Addr, interface: u32 },
+}
+
 /// Creates a UDP socket with port reuse enabled.
 ///
 /// This is critical for LocalSend discovery because:
@@ -436,20 +812,29 @@ fn select_interface_addresses(
 /// By enabling SO_REUSEADDR (and SO_REUSEPORT on Unix), the OS allows multiple
 /// processes to bind to the same UDP port. For multicast traffic, the OS will
 /// clone incoming packets and deliver them to all participating sockets.
-fn create_reusable_udp_socket(
-    bind_addr: &SocketAddr,
-    interface: Ipv4Addr,
-    multicast_addr: Ipv4Addr,
-) -> Result<UdpSocket> {
-    let domain = if bind_addr.is_ipv4() {
-        Domain::IPV4
-    } else {
-        Domain::IPV6
+fn create_reusable_udp_socket(port: u16, join: GroupJoin, ttl: u8) -> Result<UdpSocket> {
+    let (domain, bind_addr) = match join {
+        GroupJoin::V4 { .. } => (
+            Domain::IPV4,
+            SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)),
+        ),
+        GroupJoin::V6 { .. } => (
+            Domain::IPV6,
+            SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)),
+        ),
     };
 
     let socket = Socket::new(domain, Type::DGRAM, Some(SocketProtocol::UDP))
         .map_err(|e| LocalSendError::network(format!("Failed to create socket: {}", e)))?;
 
+    // IPv6 only, so it shares the port with the IPv4 socket instead of
+    // taking its traffic too.
+    if domain == Domain::IPV6 {
+        socket
+            .set_only_v6(true)
+            .map_err(|e| LocalSendError::network(format!("Failed to set only_v6: {}", e)))?;
+    }
+
     // Enable address reuse (supported on most platforms including Windows)
     socket
         .set_reuse_address(true)
@@ -462,19 +847,45 @@ fn create_reusable_udp_socket(
         .map_err(|e| LocalSendError::network(format!("Failed to set reuse_port: {}", e)))?;
 
     socket
-        .bind(&(*bind_addr).into())
+        .bind(&bind_addr.into())
         .map_err(|e| LocalSendError::network(format!("Failed to bind to {}: {}", bind_addr, e)))?;
 
-    socket
-        .join_multicast_v4(&multicast_addr, &interface)
-        .map_err(|error| {
-            LocalSendError::network(format!("Failed to join multicast on {interface}: {error}"))
-        })?;
-    socket.set_multicast_if_v4(&interface).map_err(|error| {
-        LocalSendError::network(format!(
-            "Failed to select multicast interface {interface}: {error}"
-        ))
-    })?;
+    match join {
+        GroupJoin::V4 { group, interface } => {
+            socket
+                .join_multicast_v4(&group, &interface)
+                .map_err(|error| {
+                    LocalSendError::network(format!(
+                        "Failed to join multicast on {interface}: {error}"
+                    ))
+                })?;
+            socket.set_multicast_if_v4(&interface).map_err(|error| {
+                LocalSendError::network(format!(
+                    "Failed to select multicast interface {interface}: {error}"
+                ))
+            })?;
+            socket.set_multicast_ttl_v4(u32::from(ttl)).map_err(|e| {
+                LocalSendError::network(format!("Failed to set multicast TTL: {e}"))
+            })?;
+        }
+        GroupJoin::V6 { group, interface } => {
+            socket
+                .join_multicast_v6(&group, interface)
+                .map_err(|error| {
+                    LocalSendError::network(format!(
+                        "Failed to join multicast group {group}: {error}"
+                    ))
+                })?;
+            socket.set_multicast_if_v6(interface).map_err(|error| {
+                LocalSendError::network(format!(
+                    "Failed to select IPv6 multicast interface {interface}: {error}"
+                ))
+            })?;
+            socket.set_multicast_hops_v6(u32::from(ttl)).map_err(|e| {
+                LocalSendError::network(format!("Failed to set multicast hops: {e}"))
+            })?;
+        }
+    }
 
     // Convert to tokio UdpSocket after configuring the multicast interface.
     let std_socket: std::net::UdpSocket = socket.into();
@@ -488,10 +899,17 @@ fn create_reusable_udp_socket(
 
 #[cfg(test)]
 mod tests {
-    use super::{MulticastConfig, MulticastDiscovery, select_interface_addresses};
-    use crate::LocalSendError;
+    use super::{
+        MulticastConfig, MulticastDiscovery, announce_periodically, expire_devices,
+        parse_announcement, select_interface_addresses, watch_interfaces,
+    };
+    use crate::protocol::AnnouncementMessage;
+    use crate::{DeviceInfo, LocalSendError, Protocol};
     use std::collections::BTreeSet;
-    use std::net::Ipv4Addr;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
 
     #[derive(Clone)]
     struct TestInterface {
@@ -510,6 +928,354 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn interface_change_triggers_a_rejoin() {
+        let wifi = Ipv4Addr::new(192, 168, 1, 20);
+        let ethernet = Ipv4Addr::new(10, 0, 0, 5);
+        let addresses = Arc::new(Mutex::new(vec![wifi]));
+        let rejoins = Arc::new(Mutex::new(Vec::new()));
+
+        let source = addresses.clone();
+        let joined = rejoins.clone();
+        let cancel = CancellationToken::new();
+        let watcher = tokio::spawn(watch_interfaces(
+            Arc::new(move || Ok(source.lock().unwrap().clone())),
+            vec![wifi],
+            Duration::from_millis(10),
+            cancel.clone(),
+            move |interfaces| {
+                joined.lock().unwrap().push(interfaces);
+                std::future::ready(Ok(()))
+            },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(
+            rejoins.lock().unwrap().is_empty(),
+            "unchanged IP must not rejoin"
+        );
+
+        *addresses.lock().unwrap() = vec![ethernet];
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+        watcher.await.unwrap();
+
+        assert_eq!(*rejoins.lock().unwrap(), [vec![ethernet]]);
+    }
+
+    #[tokio::test]
+    async fn announcements_repeat_until_cancelled() {
+        let announced = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let count = announced.clone();
+        let cancel = CancellationToken::new();
+        let announcer = tokio::spawn(announce_periodically(
+            Duration::from_millis(10),
+            cancel.clone(),
+            move || {
+                count.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                std::future::ready(Ok(()))
+            },
+        ));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        cancel.cancel();
+        announcer.await.unwrap();
+        let after_stop = announced.load(std::sync::atomic::Ordering::SeqCst);
+        assert!(after_stop >= 3, "only {after_stop} announcements in 100ms");
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            announced.load(std::sync::atomic::Ordering::SeqCst),
+            after_stop
+        );
+    }
+
+    #[tokio::test]
+    async fn peers_not_heard_from_within_the_ttl_are_dropped() {
+        use crate::discovery::Discovery;
+        use crate::discovery::traits::remember_device;
+
+        let ttl = Duration::from_millis(100);
+        let discovery = MulticastDiscovery::new_with_device(DeviceInfo::new(
+            "local".into(),
+            53317,
+            Protocol::Http,
+        ))
+        .with_device_ttl(ttl);
+        let peer = |fingerprint: &str| DeviceInfo {
+            fingerprint: fingerprint.into(),
+            ..DeviceInfo::new(fingerprint.into(), 53317, Protocol::Http)
+        };
+        let cancel = CancellationToken::new();
+        let sweeper = tokio::spawn(expire_devices(discovery.known.clone(), ttl, cancel.clone()));
+
+        remember_device(&discovery.known, peer("leaving"));
+        remember_device(&discovery.known, peer("staying"));
+        assert_eq!(discovery.get_known_devices().len(), 2);
+
+        for _ in 0..3 {
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            remember_device(&discovery.known, peer("staying"));
+        }
+        let known: Vec<_> = discovery
+            .get_known_devices()
+            .into_iter()
+            .map(|device| device.fingerprint)
+            .collect();
+        assert_eq!(known, ["staying"]);
+        assert_eq!(
+            discovery.known.read().unwrap().len(),
+            1,
+            "swept, not just hidden"
+        );
+
+        cancel.cancel();
+        sweeper.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_peer_registering_with_us_stays_known_without_announcing() {
+        use crate::discovery::Discovery;
+
+        let ttl = Duration::from_millis(100);
+        let mut discovery = MulticastDiscovery::new_with_device(DeviceInfo::new(
+            "local".into(),
+            53317,
+            Protocol::Http,
+        ))
+        .with_device_ttl(ttl);
+        let (found_tx, mut found) = tokio::sync::mpsc::unbounded_channel();
+        discovery.on_discovered(Box::new(move |device| {
+            let _ = found_tx.send(device.fingerprint);
+        }));
+        let peer = DeviceInfo {
+            fingerprint: "official".into(),
+            ..DeviceInfo::new("phone".into(), 53317, Protocol::Http)
+        };
+
+        discovery.remember(discovery.local_device().clone());
+        for _ in 0..3 {
+            discovery.remember(peer.clone());
+            tokio::time::sleep(Duration::from_millis(60)).await;
+        }
+        let known: Vec<_> = discovery
+            .get_known_devices()
+            .into_iter()
+            .map(|device| device.fingerprint)
+            .collect();
+        assert_eq!(known, ["official"]);
+        assert_eq!(found.recv().await.as_deref(), Some("official"));
+    }
+
+    #[test]
+    fn a_chosen_interface_replaces_the_automatic_choice() {
+        let device = DeviceInfo::new("local".into(), 53317, Protocol::Http);
+        let lan = Ipv4Addr::new(192, 168, 1, 20);
+        let discovery = MulticastDiscovery::new_with_device(device).with_interface(Some(lan));
+        assert_eq!((discovery.interface_source)().unwrap(), [lan]);
+
+        let discovery = discovery.with_interface(None);
+        assert_eq!(
+            (discovery.interface_source)().unwrap(),
+            MulticastDiscovery::multicast_interfaces(None).unwrap()
+        );
+    }
+
+    #[test]
+    fn a_peer_re_announcing_from_a_new_address_is_updated_in_place() {
+        use crate::discovery::Discovery;
+        use crate::discovery::traits::remember_device;
+
+        let discovery = MulticastDiscovery::new_with_device(DeviceInfo::new(
+            "local".into(),
+            53317,
+            Protocol::Http,
+        ));
+        let mut peer = DeviceInfo {
+            fingerprint: "roaming".into(),
+            ip: Some("192.168.1.20".into()),
+            ..DeviceInfo::new("laptop".into(), 53317, Protocol::Http)
+        };
+        remember_device(&discovery.known, peer.clone());
+        peer.ip = Some("10.0.0.7".into());
+        peer.port = 53318;
+        remember_device(&discovery.known, peer);
+
+        let known = discovery.get_known_devices();
+        assert_eq!(known.len(), 1);
+        assert_eq!(known[0].ip.as_deref(), Some("10.0.0.7"));
+        assert_eq!(known[0].port, 53318);
+    }
+
+    #[test]
+    fn announcement_without_a_fingerprint_is_not_added() {
+        let peer = DeviceInfo {
+            fingerprint: "peer-fingerprint".into(),
+            ..DeviceInfo::new("peer".into(), 53317, Protocol::Https)
+        };
+        let src: SocketAddr = "192.168.1.20:53317".parse().unwrap();
+
+        let valid = serde_json::to_vec(&AnnouncementMessage::from_device(&peer, true)).unwrap();
+        let (device, is_announcement) =
+            parse_announcement(&valid, src, "local").expect("valid peer is added");
+        assert_eq!(device.ip.as_deref(), Some("192.168.1.20"));
+        assert!(is_announcement);
+
+        for fingerprint in ["", "   "] {
+            let mut announcement = AnnouncementMessage::from_device(&peer, true);
+            announcement.fingerprint = fingerprint.into();
+            let datagram = serde_json::to_vec(&announcement).unwrap();
+            assert!(parse_announcement(&datagram, src, "local").is_none());
+        }
+
+        let mut announcement = AnnouncementMessage::from_device(&peer, true);
+        announcement.port = 0;
+        let datagram = serde_json::to_vec(&announcement).unwrap();
+        assert!(parse_announcement(&datagram, src, "local").is_none());
+    }
+
+    #[tokio::test]
+    async fn unresponsive_register_target_falls_back_to_udp_within_the_timeout() {
+        // Accepts the connection, then never answers.
+        let silent = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let silent_port = silent.local_addr().unwrap().port();
+        let _hold = tokio::spawn(async move {
+            let mut open = Vec::new();
+            while let Ok((stream, _)) = silent.accept().await {
+                open.push(stream);
+            }
+        });
+
+        let group = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut peer = crate::DeviceInfo::new("silent".into(), silent_port, crate::Protocol::Http);
+        peer.ip = Some("127.0.0.1".into());
+        let local = crate::DeviceInfo::new("local".into(), 53317, crate::Protocol::Http);
+
+        let timeout = Duration::from_millis(200);
+        let started = std::time::Instant::now();
+        tokio::time::timeout(
+            Duration::from_secs(2),
+            MulticastDiscovery::respond_to_announcement(
+                &peer,
+                &local,
+                &socket,
+                group.local_addr().unwrap(),
+                None,
+                timeout,
+            ),
+        )
+        .await
+        .expect("register must give up instead of hanging");
+        assert!(started.elapsed() >= timeout);
+
+        let mut buf = vec![0u8; 4096];
+        let (len, _) = tokio::time::timeout(Duration::from_secs(1), group.recv_from(&mut buf))
+            .await
+            .expect("UDP fallback reply")
+            .unwrap();
+        let reply: AnnouncementMessage = serde_json::from_slice(&buf[..len]).unwrap();
+        assert_eq!(reply.alias, "local");
+        assert!(!reply.announce);
+    }
+
+    #[tokio::test]
+    async fn received_announcements_are_known_through_the_trait_object() {
+        use crate::discovery::Discovery;
+
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = MulticastConfig::new("224.0.0.167".parse().unwrap(), port, None).unwrap();
+        let local = DeviceInfo {
+            fingerprint: "local-fingerprint".into(),
+            ..DeviceInfo::new("local".into(), 53317, Protocol::Http)
+        };
+        let listener = MulticastDiscovery::new_with_device_and_config(local, config)
+            .unwrap()
+            .with_interface_source(|| Ok(vec![Ipv4Addr::LOCALHOST]));
+        let mut discovery: Box<dyn Discovery> = Box::new(listener);
+        discovery.start().await.expect("join on loopback");
+        assert!(discovery.get_known_devices().is_empty());
+
+        // A response (not an announcement), so no register round-trip starts.
+        // Sent straight to the bound port, so multicast routing is not needed.
+        let peer = DeviceInfo {
+            fingerprint: "peer-fingerprint".into(),
+            ..DeviceInfo::new("peer".into(), 53317, Protocol::Http)
+        };
+        let datagram = serde_json::to_vec(&AnnouncementMessage::from_device(&peer, false)).unwrap();
+        let sender = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut known = Vec::new();
+        for _ in 0..100 {
+            sender
+                .send_to(&datagram, ("127.0.0.1", port))
+                .await
+                .unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            known = discovery.get_known_devices();
+            if !known.is_empty() {
+                break;
+            }
+        }
+        discovery.stop();
+
+        assert_eq!(known.len(), 1, "the same peer is listed once");
+        assert_eq!(known[0].fingerprint, "peer-fingerprint");
+        assert_eq!(known[0].ip.as_deref(), Some("127.0.0.1"));
+        assert_eq!(discovery.get_known_devices().len(), 1, "kept across stop");
+    }
+
+    #[test]
+    fn an_advertised_ip_is_used_only_on_the_sources_network() {
+        let lan = Ipv4Addr::new(192, 168, 1, 50);
+        let config = MulticastConfig::default().with_advertised_ip(lan);
+        let discovery = MulticastDiscovery::new_with_device_and_config(
+            DeviceInfo {
+                fingerprint: "multi-homed".into(),
+                ..DeviceInfo::new("multi-homed".into(), 53317, Protocol::Http)
+            },
+            config,
+        )
+        .unwrap();
+        let announcement = discovery.announcement();
+        assert_eq!(announcement.ip.as_deref(), Some("192.168.1.50"));
+        let networks = [(
+            Ipv4Addr::new(192, 168, 1, 7),
+            Ipv4Addr::new(255, 255, 255, 0),
+        )];
+
+        // The datagram left from a second address on the same LAN.
+        let sibling = Some("192.168.1.20".parse().unwrap());
+        let device = DeviceInfo::from_announcement_on(announcement.clone(), sibling, &networks);
+        assert_eq!(device.ip.as_deref(), Some("192.168.1.50"));
+
+        // From any other network the advertised address could point anywhere,
+        // so the source address is kept.
+        let bridge = Some("172.17.0.1".parse().unwrap());
+        let device = DeviceInfo::from_announcement_on(announcement.clone(), bridge, &networks);
+        assert_eq!(device.ip.as_deref(), Some("172.17.0.1"));
+        let elsewhere = AnnouncementMessage {
+            ip: Some("10.0.0.1".into()),
+            ..announcement
+        };
+        let device = DeviceInfo::from_announcement_on(elsewhere, sibling, &networks);
+        assert_eq!(device.ip.as_deref(), Some("192.168.1.20"));
+
+        // Without one, the source address is all there is.
+        let plain = serde_json::to_vec(&AnnouncementMessage::from_device(
+            discovery.local_device(),
+            true,
+        ))
+        .unwrap();
+        assert!(!String::from_utf8_lossy(&plain).contains("\"ip\""));
+        let src: SocketAddr = "172.17.0.1:53317".parse().unwrap();
+        let (device, _) = parse_announcement(&plain, src, "local").unwrap();
+        assert_eq!(device.ip.as_deref(), Some("172.17.0.1"));
+    }
+
     #[test]
     fn multicast_config_rejects_non_multicast_address() {
         let result = MulticastConfig::new("192.168.1.1".parse().unwrap(), 53317, None);
@@ -517,6 +1283,95 @@ mod tests {
             result,
             Err(LocalSendError::InvalidMulticastAddress(_))
         ));
+        assert!(matches!(
+            MulticastConfig::default().with_ipv6_address("fd00::1".parse().unwrap()),
+            Err(LocalSendError::InvalidMulticastAddress(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn the_configured_multicast_ttl_is_set_on_the_sockets() {
+        use crate::discovery::Discovery;
+
+        let port = std::net::UdpSocket::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let device = DeviceInfo::new("local".into(), 53317, Protocol::Http);
+        for (config, ttl) in [
+            (
+                MulticastConfig::new("224.0.0.167".parse().unwrap(), port, None).unwrap(),
+                1,
+            ),
+            (
+                MulticastConfig::new("224.0.0.167".parse().unwrap(), port, None)
+                    .unwrap()
+                    .with_multicast_ttl(3),
+                3,
+            ),
+        ] {
+            let mut discovery =
+                MulticastDiscovery::new_with_device_and_config(device.clone(), config)
+                    .unwrap()
+                    .with_interface_source(|| Ok(vec![Ipv4Addr::LOCALHOST]));
+            discovery.start().await.expect("join on loopback");
+            let socket = discovery.sockets.read().unwrap()[0].clone();
+            assert_eq!(socket.multicast_ttl_v4().unwrap(), ttl);
+            discovery.stop();
+        }
+    }
+
+    #[tokio::test]
+    async fn announcements_arrive_over_the_ipv6_group() {
+        use crate::discovery::Discovery;
+
+        // No IPv6 on this host: nothing to test.
+        let Ok(probe) = std::net::UdpSocket::bind("[::1]:0") else {
+            return;
+        };
+        let port = probe.local_addr().unwrap().port();
+        drop(probe);
+        let config = MulticastConfig::new("224.0.0.167".parse().unwrap(), port, None)
+            .unwrap()
+            .with_ipv6_address("ff05::167".parse().unwrap())
+            .unwrap();
+        let local = DeviceInfo {
+            fingerprint: "local-fingerprint".into(),
+            ..DeviceInfo::new("local".into(), 53317, Protocol::Http)
+        };
+        let mut discovery = MulticastDiscovery::new_with_device_and_config(local, config)
+            .unwrap()
+            .with_interface_source(|| Ok(vec![Ipv4Addr::LOCALHOST]));
+        discovery.start().await.expect("join on loopback");
+        assert_eq!(discovery.sockets.read().unwrap().len(), 2, "IPv4 and IPv6");
+
+        let peer = DeviceInfo {
+            fingerprint: "peer-fingerprint".into(),
+            ..DeviceInfo::new("peer".into(), 53317, Protocol::Http)
+        };
+        let datagram = serde_json::to_vec(&AnnouncementMessage::from_device(&peer, false)).unwrap();
+        let sender = tokio::net::UdpSocket::bind("[::]:0").await.unwrap();
+        let group = SocketAddr::from(("ff05::167".parse::<std::net::Ipv6Addr>().unwrap(), port));
+        // No route for IPv6 multicast on this host: nothing to test either.
+        if sender.send_to(&datagram, group).await.is_err() {
+            discovery.stop();
+            return;
+        }
+        let mut known = Vec::new();
+        for _ in 0..100 {
+            sender.send_to(&datagram, group).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            known = discovery.get_known_devices();
+            if !known.is_empty() {
+                break;
+            }
+        }
+        discovery.stop();
+
+        assert_eq!(known.len(), 1);
+        let source: std::net::IpAddr = known[0].ip.as_deref().unwrap().parse().unwrap();
+        assert!(source.is_ipv6(), "{source}");
     }
 
     #[test]
@@ -548,7 +1403,9 @@ mod tests {
     }
 
     #[cfg(feature = "https")]
-    use crate::{DeviceInfo, LocalSendServer, Protocol};
+    use super::ANNOUNCEMENT_REGISTER_TIMEOUT;
+    #[cfg(feature = "https")]
+    use crate::LocalSendServer;
 
     #[cfg(feature = "https")]
     #[tokio::test]
@@ -566,8 +1423,12 @@ mod tests {
         let mut peer = server.device().clone();
         peer.ip = Some("127.0.0.1".into());
         let local = DeviceInfo::new("discovery client".into(), 0, Protocol::Https);
-        let client = MulticastDiscovery::client_for_announcement(local, &peer)
-            .expect("build a client for the announced peer");
+        let client = MulticastDiscovery::client_for_announcement(
+            local,
+            &peer,
+            ANNOUNCEMENT_REGISTER_TIMEOUT,
+        )
+        .expect("build a client for the announced peer");
 
         client
             .register(&peer)
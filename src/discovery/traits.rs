@@ -1,13 +1,67 @@
 use crate::error::LocalSendError;
 use crate::protocol::DeviceInfo;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
 
+/// Called with every device a backend discovers, as it is discovered.
+pub type DiscoveredCallback = Box<dyn Fn(DeviceInfo) + Send + Sync + 'static>;
+
+/// A discovery backend. Object safe, so callers can hold a
+/// `Box<dyn Discovery>` without caring which backend is behind it.
 #[async_trait::async_trait]
 pub trait Discovery {
     async fn start(&mut self) -> std::result::Result<(), LocalSendError>;
     fn stop(&mut self);
     async fn announce_presence(&self) -> std::result::Result<(), LocalSendError>;
-    fn on_discovered<F>(&mut self, callback: F)
-    where
-        F: Fn(DeviceInfo) + Send + Sync + 'static;
+    fn on_discovered(&mut self, callback: DiscoveredCallback);
+    /// Every peer seen so far, one entry per fingerprint with its most
+    /// recent address. A backend may leave out peers it has not heard from
+    /// in a while, so after a `stop` its list ages out like any other.
     fn get_known_devices(&self) -> Vec<DeviceInfo>;
 }
+
+/// An entry of a backend's known list.
+#[derive(Debug, Clone)]
+pub(crate) struct SeenDevice {
+    pub(crate) device: DeviceInfo,
+    pub(crate) last_seen: Instant,
+}
+
+/// Insert `device` into a backend's known list, replacing an older entry
+/// with the same fingerprint.
+pub(crate) fn remember_device(known: &RwLock<Vec<SeenDevice>>, device: DeviceInfo) {
+    let mut known = known.write().unwrap();
+    let seen = SeenDevice {
+        device,
+        last_seen: Instant::now(),
+    };
+    match known
+        .iter_mut()
+        .find(|existing| existing.device.fingerprint == seen.device.fingerprint)
+    {
+        Some(existing) => *existing = seen,
+        None => known.push(seen),
+    }
+}
+
+/// The devices on a known list, leaving out those not seen within `ttl`.
+pub(crate) fn known_devices(
+    known: &RwLock<Vec<SeenDevice>>,
+    ttl: Option<Duration>,
+) -> Vec<DeviceInfo> {
+    known
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|seen| ttl.is_none_or(|ttl| seen.last_seen.elapsed() < ttl))
+        .map(|seen| seen.device.clone())
+        .collect()
+}
+
+/// Drop the entries not seen within `ttl`.
+pub(crate) fn forget_stale(known: &RwLock<Vec<SeenDevice>>, ttl: Duration) {
+    known
+        .write()
+        .unwrap()
+        .retain(|seen| seen.last_seen.elapsed() < ttl);
+}
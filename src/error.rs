@@ -13,6 +13,15 @@ pub enum LocalSendError {
         source: std::io::Error,
     },
 
+    #[error("Cannot create directory {}: {source}", path.display())]
+    CreateDir {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("Encrypted file error: {message}")]
+    Encryption { message: String },
+
     #[error("Serde JSON error: {source}")]
     Serde {
         #[from]
@@ -43,6 +52,9 @@ pub enum LocalSendError {
     #[error("Invalid multicast address: {0}")]
     InvalidMulticastAddress(String),
 
+    #[error("Invalid size: {0}")]
+    InvalidSize(String),
+
     // ============================================================================
     // Protocol Errors
     // ============================================================================
@@ -55,6 +67,12 @@ pub enum LocalSendError {
     #[error("Protocol version mismatch: expected {expected}, got {actual}")]
     VersionMismatch { expected: String, actual: String },
 
+    #[error("Invalid protocol version: {0}")]
+    InvalidVersion(String),
+
+    #[error("Invalid protocol: {0} (expected http or https)")]
+    InvalidProtocol(String),
+
     #[error("Invalid state transition: {message}")]
     InvalidState { message: String },
 
@@ -145,6 +163,16 @@ impl LocalSendError {
             message: message.into(),
         }
     }
+
+    /// Whether trying again later may succeed: connection trouble, a
+    /// timeout, or a 5xx from the peer. Refusals and bad input are final.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Reqwest { .. } | Self::Network { .. } => true,
+            Self::HttpFailed { status, .. } => *status >= 500,
+            _ => false,
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, LocalSendError>;
@@ -9,23 +9,35 @@ pub mod protocol;
 pub mod server;
 
 // Re-export commonly used types for backwards compatibility
-pub use client::{LocalSendClient, TlsTrustPolicy};
+pub use client::{ClientConfig, LocalSendClient, RetryPolicy, TlsTrustPolicy};
 pub use core::{
     DeviceInfoBuilder, Session, build_file_metadata, build_file_metadata_from_bytes,
-    generate_file_id, get_device_model, get_device_type, get_local_ip, get_mime_type,
+    build_file_metadata_with_hash, generate_file_id, get_device_model, get_device_type,
+    get_local_ip, get_mime_type, get_mime_type_sniffed, parse_size, register_mime_type,
     unique_save_path,
 };
+#[cfg(feature = "thumbnails")]
+pub use core::{build_file_metadata_with_preview, image_preview};
+#[cfg(feature = "encryption")]
+pub use crypto::{AtRestEncryption, AtRestManifest, decrypt_file};
+pub use crypto::{
+    IDENTITY_FILE_NAME, Identity, generate_fingerprint, sha256_from_bytes, sha256_from_file,
+};
 #[cfg(feature = "https")]
 pub use crypto::{TlsCertificate, generate_tls_certificate};
-pub use crypto::{generate_fingerprint, sha256_from_bytes, sha256_from_file};
-pub use discovery::{Discovery, HttpDiscovery, MulticastConfig, MulticastDiscovery};
+pub use discovery::{
+    AdaptiveDiscovery, Discovery, DiscoveryMode, DiscoveryReport, HttpDiscovery, MulticastConfig,
+    MulticastDiscovery,
+};
 pub use error::{LocalSendError, Result};
 pub use protocol::{
     AnnouncementMessage, DEFAULT_HTTP_PORT, DEFAULT_MULTICAST_ADDRESS, DEFAULT_MULTICAST_PORT,
     DeviceInfo, DeviceType, FileId, FileMetadata, PROTOCOL_VERSION, Port, PrepareUploadRequest,
-    PrepareUploadResponse, Protocol, ReceivedFile, RegisterMessage, SessionId, Token,
-    validate_device_info, validate_file_metadata, validate_protocol_version,
+    PrepareUploadResponse, Protocol, ReceivedFile, RegisterMessage, SessionId, Token, UploadQuery,
+    advertised_version, normalize_version, validate_device_info, validate_file_metadata,
+    validate_fingerprint, validate_port, validate_protocol_version,
 };
+pub use protocol::{ApiVersion, NegotiatedVersion, ProtocolVersion};
 pub use server::LocalSendServer;
 
 #[cfg(feature = "cli")]
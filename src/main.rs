@@ -7,10 +7,12 @@ async fn main() -> anyhow::Result<()> {
     }
 
     use clap::Parser;
+    #[cfg(feature = "encryption")]
+    use localsend_rs::cli::run_decrypt;
     #[cfg(feature = "tui")]
     use localsend_rs::cli::run_tui;
     use localsend_rs::cli::{Cli, Commands};
-    use localsend_rs::cli::{run_discover, run_receive, run_send};
+    use localsend_rs::cli::{run_bench, run_discover, run_receive, run_send};
 
     let cli = Cli::parse();
 
@@ -28,6 +30,13 @@ async fn main() -> anyhow::Result<()> {
         Commands::Tui(cmd) => {
             run_tui(cmd).await?;
         }
+        Commands::Bench(cmd) => {
+            run_bench(cmd).await?;
+        }
+        #[cfg(feature = "encryption")]
+        Commands::Decrypt(cmd) => {
+            run_decrypt(cmd).await?;
+        }
     }
 
     Ok(())
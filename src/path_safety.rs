@@ -1,7 +1,21 @@
 use crate::error::{LocalSendError, Result};
 use std::path::{Component, Path, PathBuf};
 
-pub(crate) fn safe_join(base: &Path, remote_name: &str) -> Result<PathBuf> {
+/// Longest path component we create, in bytes. Most filesystems cap a
+/// component at 255; the slack leaves room for a collision suffix like
+/// ` (12)` and the `.lsenc` extension of encrypted files.
+const MAX_COMPONENT_BYTES: usize = 240;
+
+/// An extension longer than this is treated as part of the name when
+/// truncating, rather than being kept whole.
+const MAX_KEPT_EXTENSION_BYTES: usize = 32;
+
+/// The one policy for a sender-supplied `fileName`: `/`-separated subpaths
+/// are allowed (folder transfers) and come back as a relative path, while
+/// absolute names, `..`, drive or backslash forms and empty names are refused.
+/// Over-long components are shortened (see [`MAX_COMPONENT_BYTES`]), keeping
+/// the file's extension.
+pub(crate) fn relative_file_path(remote_name: &str) -> Result<PathBuf> {
     if remote_name.is_empty()
         || remote_name.contains('\0')
         || remote_name.contains('\\')
@@ -22,9 +36,23 @@ pub(crate) fn safe_join(base: &Path, remote_name: &str) -> Result<PathBuf> {
     }
 
     let mut relative = PathBuf::new();
-    for component in remote_path.components() {
+    let mut components = remote_path.components().peekable();
+    while let Some(component) = components.next() {
         match component {
-            Component::Normal(part) => relative.push(part),
+            Component::Normal(part) => {
+                let part = part.to_string_lossy();
+                let last = components.peek().is_none();
+                let fitted = fit_component(&part, last);
+                if fitted.len() != part.len() {
+                    tracing::info!(
+                        "Shortened a {}-byte name component of {:?} to {:?}",
+                        part.len(),
+                        remote_name,
+                        fitted
+                    );
+                }
+                relative.push(fitted);
+            }
             Component::CurDir => {}
             Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
                 return Err(LocalSendError::invalid_file(format!(
@@ -42,14 +70,131 @@ pub(crate) fn safe_join(base: &Path, remote_name: &str) -> Result<PathBuf> {
         )));
     }
 
-    Ok(base.join(relative))
+    Ok(relative)
+}
+
+/// Cut `part` to [`MAX_COMPONENT_BYTES`] on a character boundary. For the
+/// file name itself (`keep_extension`), a reasonable extension survives.
+fn fit_component(part: &str, keep_extension: bool) -> String {
+    if part.len() <= MAX_COMPONENT_BYTES {
+        return part.to_string();
+    }
+    let (stem, extension) = match part.rfind('.') {
+        Some(dot) if keep_extension && dot > 0 && part.len() - dot <= MAX_KEPT_EXTENSION_BYTES => {
+            part.split_at(dot)
+        }
+        _ => (part, ""),
+    };
+    let mut end = MAX_COMPONENT_BYTES - extension.len();
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{extension}", &stem[..end])
+}
+
+/// A sender's alias as a single directory name. Separators, control
+/// characters and the characters Windows refuses become `_`; leading and
+/// trailing dots and spaces are dropped, so the result is never `.` or `..`.
+/// An alias with nothing left is `unknown`.
+pub(crate) fn alias_dir_name(alias: &str) -> String {
+    let replaced: String = alias
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match replaced.trim_matches(|c| c == '.' || c == ' ') {
+        "" => "unknown".to_string(),
+        name => fit_component(name, false),
+    }
+}
+
+/// `remote_name` under `base`, per [`relative_file_path`]. The name alone
+/// cannot escape, but a symlink already inside `base` could redirect it, so
+/// the part of the result that exists on disk must also resolve inside `base`.
+pub(crate) fn safe_join(base: &Path, remote_name: &str) -> Result<PathBuf> {
+    let path = base.join(relative_file_path(remote_name)?);
+    ensure_contained(base, &path)?;
+    Ok(path)
+}
+
+fn ensure_contained(base: &Path, path: &Path) -> Result<()> {
+    // A save directory that does not exist yet has nothing in it to follow.
+    let Ok(root) = base.canonicalize() else {
+        return Ok(());
+    };
+    let Some(existing) = path.ancestors().find(|p| p.symlink_metadata().is_ok()) else {
+        return Ok(());
+    };
+    // A dangling link fails to resolve, and is refused like an escaping one.
+    match existing.canonicalize() {
+        Ok(resolved) if resolved.starts_with(&root) => Ok(()),
+        _ => Err(LocalSendError::invalid_file(format!(
+            "Save path leaves the save directory through a link: {}",
+            path.display()
+        ))),
+    }
+}
+
+/// Resolve a receiver-chosen save directory: absolute paths are used as-is,
+/// relative ones are taken under `base`, and `..` is rejected either way.
+pub(crate) fn resolve_save_dir(base: &Path, dir: &Path) -> Result<PathBuf> {
+    if dir.as_os_str().is_empty()
+        || dir
+            .components()
+            .any(|component| matches!(component, Component::ParentDir))
+    {
+        return Err(LocalSendError::invalid_file(format!(
+            "Unsafe save directory: {}",
+            dir.display()
+        )));
+    }
+    Ok(base.join(dir))
 }
 
 #[cfg(test)]
 mod tests {
-    use super::safe_join;
+    use super::{
+        MAX_COMPONENT_BYTES, alias_dir_name, relative_file_path, resolve_save_dir, safe_join,
+    };
     use std::path::Path;
 
+    #[test]
+    fn aliases_become_single_safe_folder_names() {
+        assert_eq!(alias_dir_name("Alice's Phone"), "Alice's Phone");
+        assert_eq!(alias_dir_name("../etc"), "_etc");
+        assert_eq!(alias_dir_name("a/b\\c:d"), "a_b_c_d");
+        assert_eq!(alias_dir_name(".."), "unknown");
+        assert_eq!(alias_dir_name("  "), "unknown");
+        assert_eq!(alias_dir_name(&"x".repeat(500)).len(), MAX_COMPONENT_BYTES);
+    }
+
+    #[test]
+    fn over_long_names_are_shortened_keeping_the_extension() {
+        let name = format!("{}.jpeg", "é".repeat(500));
+        assert_eq!(name.chars().count(), 505);
+        let fitted = relative_file_path(&format!("{}/{name}", "d".repeat(1000))).unwrap();
+
+        let components: Vec<_> = fitted.iter().map(|c| c.to_str().unwrap()).collect();
+        assert_eq!(components.len(), 2);
+        assert!(components.iter().all(|c| c.len() <= MAX_COMPONENT_BYTES));
+        assert!(components[1].ends_with(".jpeg"));
+        assert!(components[1].starts_with("éé"));
+
+        // The result is actually creatable.
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(&fitted);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, b"x").unwrap();
+
+        assert_eq!(
+            relative_file_path("short.txt").unwrap(),
+            Path::new("short.txt")
+        );
+    }
+
     #[test]
     fn allows_nested_relative_paths() {
         let base = Path::new("/tmp/localsend");
@@ -58,6 +203,16 @@ mod tests {
         assert_eq!(path, base.join("nested").join("file.txt"));
     }
 
+    #[test]
+    fn relative_file_path_drops_current_dir_components() {
+        assert_eq!(
+            relative_file_path("./a/./b/c.txt").unwrap(),
+            Path::new("a/b/c.txt")
+        );
+        assert!(relative_file_path(".").is_err());
+        assert!(relative_file_path("a/../../x").is_err());
+    }
+
     #[test]
     fn rejects_parent_directory_escape() {
         let base = Path::new("/tmp/localsend");
@@ -66,6 +221,28 @@ mod tests {
         assert!(safe_join(base, "nested/../../evil.txt").is_err());
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn rejects_names_that_escape_through_a_symlink() {
+        let base = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), base.path().join("out")).unwrap();
+        std::os::unix::fs::symlink(
+            outside.path().join("missing.txt"),
+            base.path().join("dangling.txt"),
+        )
+        .unwrap();
+        std::fs::create_dir(base.path().join("inside")).unwrap();
+
+        assert!(safe_join(base.path(), "out/evil.txt").is_err());
+        assert!(safe_join(base.path(), "dangling.txt").is_err());
+        assert_eq!(
+            safe_join(base.path(), "inside/ok.txt").unwrap(),
+            base.path().join("inside/ok.txt")
+        );
+        assert!(safe_join(&base.path().join("not-yet"), "a/b.txt").is_ok());
+    }
+
     #[test]
     fn rejects_absolute_paths() {
         let base = Path::new("/tmp/localsend");
@@ -80,4 +257,20 @@ mod tests {
         assert!(safe_join(base, "C:\\Users\\evil.txt").is_err());
         assert!(safe_join(base, "nested\\evil.txt").is_err());
     }
+
+    #[test]
+    fn save_dir_override_is_relative_to_base_unless_absolute() {
+        let base = Path::new("/tmp/localsend");
+
+        assert_eq!(
+            resolve_save_dir(base, Path::new("Desktop")).unwrap(),
+            base.join("Desktop")
+        );
+        assert_eq!(
+            resolve_save_dir(base, Path::new("/srv/inbox")).unwrap(),
+            Path::new("/srv/inbox")
+        );
+        assert!(resolve_save_dir(base, Path::new("../escape")).is_err());
+        assert!(resolve_save_dir(base, Path::new("")).is_err());
+    }
 }
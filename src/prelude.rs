@@ -17,7 +17,9 @@ pub use crate::protocol::{
 };
 
 // Crypto
-pub use crate::crypto::{generate_fingerprint, sha256_from_bytes, sha256_from_file};
+pub use crate::crypto::{
+    IDENTITY_FILE_NAME, Identity, generate_fingerprint, sha256_from_bytes, sha256_from_file,
+};
 
 #[cfg(feature = "https")]
 pub use crate::crypto::{TlsCertificate, generate_tls_certificate};
@@ -27,7 +29,7 @@ pub use crate::client::LocalSendClient;
 pub use crate::server::LocalSendServer;
 
 // Discovery
-pub use crate::discovery::{Discovery, HttpDiscovery, MulticastDiscovery};
+pub use crate::discovery::{AdaptiveDiscovery, Discovery, HttpDiscovery, MulticastDiscovery};
 
 // Error handling
 pub use crate::error::{LocalSendError, Result};
@@ -1,12 +1,18 @@
 pub mod constants;
 pub mod types;
 pub mod validation;
+pub mod version;
 
 pub use constants::{
     DEFAULT_HTTP_PORT, DEFAULT_MULTICAST_ADDRESS, DEFAULT_MULTICAST_PORT, PROTOCOL_VERSION,
 };
 pub use types::{
     AnnouncementMessage, DeviceInfo, DeviceType, FileId, FileMetadata, Port, PrepareUploadRequest,
-    PrepareUploadResponse, Protocol, ReceivedFile, RegisterMessage, SessionId, Token,
+    PrepareUploadResponse, Protocol, ReceivedFile, RegisterMessage, SessionId, Token, UploadQuery,
+    UploadStatus,
 };
-pub use validation::{validate_device_info, validate_file_metadata, validate_protocol_version};
+pub use validation::{
+    advertised_version, normalize_version, validate_device_info, validate_file_metadata,
+    validate_fingerprint, validate_port, validate_protocol_version,
+};
+pub use version::{ApiVersion, NegotiatedVersion, ProtocolVersion, negotiate};
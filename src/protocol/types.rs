@@ -30,21 +30,22 @@ impl fmt::Display for Protocol {
     }
 }
 
-impl From<&str> for Protocol {
-    fn from(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "https" => Protocol::Https,
-            _ => Protocol::Http,
+/// Case-insensitive `http` or `https`; anything else is an error rather than
+/// a silent fallback to plain HTTP.
+impl std::str::FromStr for Protocol {
+    type Err = crate::error::LocalSendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("https") {
+            Ok(Protocol::Https)
+        } else if s.eq_ignore_ascii_case("http") {
+            Ok(Protocol::Http)
+        } else {
+            Err(crate::error::LocalSendError::InvalidProtocol(s.to_string()))
         }
     }
 }
 
-impl From<String> for Protocol {
-    fn from(s: String) -> Self {
-        Protocol::from(s.as_str())
-    }
-}
-
 /// Session identifier for file transfer sessions
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(transparent)]
@@ -273,6 +274,29 @@ pub struct PrepareUploadResponse {
     pub files: HashMap<FileId, Token>,
 }
 
+/// Query string of `POST /api/localsend/v2/upload`. Sent through reqwest's
+/// `query()` so every value is percent-encoded, and parsed back by the server.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadQuery {
+    #[serde(rename = "sessionId")]
+    pub session_id: SessionId,
+    #[serde(rename = "fileId")]
+    pub file_id: FileId,
+    pub token: Token,
+    /// Bytes of the file the receiver already holds from an interrupted
+    /// upload (see [`UploadStatus`]); the body carries the rest. Not part of
+    /// the LocalSend protocol, so only sent when resuming.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<u64>,
+}
+
+/// Body of `GET /api/localsend/v2/upload-status`, an extension of this
+/// crate: how much of a file the receiver kept from an interrupted upload.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadStatus {
+    pub received: u64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ReceivedFile {
     pub file_name: String,
@@ -281,12 +305,23 @@ pub struct ReceivedFile {
     pub time: String,
     /// Absolute path the file was written to (post-collision-rename).
     pub path: std::path::PathBuf,
+    /// The contents matched the sha256 the sender declared.
+    #[serde(default)]
+    pub verified: bool,
     /// Present when the item is a text message; the inbox shows this inline.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub message_text: Option<String>,
 }
 
+/// The multicast discovery payload.
+///
+/// Protocol v1 flagged announcements with `announcement`; v2 renamed it to
+/// `announce`. Only `announce` is ever emitted. On the way in, `announce`
+/// decides when present and `announcement` is consulted only when it is
+/// absent, so v1-style and v2-style peers both read correctly and a
+/// message carrying neither is a reply.
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "WireAnnouncement")]
 pub struct AnnouncementMessage {
     pub alias: String,
     pub version: String,
@@ -299,10 +334,79 @@ pub struct AnnouncementMessage {
     pub protocol: Protocol,
     #[serde(default)]
     pub download: bool,
-    #[serde(default)]
+    /// `true` for an announcement that expects a reply, `false` for a reply.
     pub announce: bool,
+    /// Extension: the address peers should reach us on, for multi-homed
+    /// hosts whose multicast source address is the wrong interface. Other
+    /// clients ignore it and keep using the source address; this crate uses
+    /// it only on the source's network (see [`DeviceInfo::from_announcement`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip: Option<String>,
+}
+
+/// What a peer may put on the wire, before the two flags are folded into
+/// [`AnnouncementMessage::announce`].
+#[derive(Deserialize)]
+struct WireAnnouncement {
+    alias: String,
+    version: String,
+    #[serde(rename = "deviceModel", default)]
+    device_model: Option<String>,
+    #[serde(rename = "deviceType", default)]
+    device_type: Option<DeviceType>,
+    fingerprint: String,
+    port: u16,
+    protocol: Protocol,
+    #[serde(default)]
+    download: bool,
+    #[serde(default)]
+    announce: Option<bool>,
     #[serde(default)]
-    pub announcement: Option<bool>,
+    announcement: Option<bool>,
+    #[serde(default)]
+    ip: Option<String>,
+}
+
+impl From<WireAnnouncement> for AnnouncementMessage {
+    fn from(wire: WireAnnouncement) -> Self {
+        Self {
+            alias: wire.alias,
+            version: wire.version,
+            device_model: wire.device_model,
+            device_type: wire.device_type,
+            fingerprint: wire.fingerprint,
+            port: wire.port,
+            protocol: wire.protocol,
+            download: wire.download,
+            announce: wire.announce.or(wire.announcement).unwrap_or(false),
+            ip: wire.ip,
+        }
+    }
+}
+
+impl AnnouncementMessage {
+    /// Build the multicast payload advertising `device`. `announce` is `true`
+    /// for an announcement and `false` for a reply.
+    pub fn from_device(device: &DeviceInfo, announce: bool) -> Self {
+        Self {
+            alias: device.alias.clone(),
+            version: device.version.clone(),
+            device_model: device.device_model.clone(),
+            device_type: device.device_type,
+            fingerprint: device.fingerprint.clone(),
+            port: device.port,
+            protocol: device.protocol,
+            download: device.download,
+            announce,
+            ip: None,
+        }
+    }
+
+    /// Advertise `ip` in place of the datagram's source address.
+    pub fn with_ip(mut self, ip: Option<std::net::Ipv4Addr>) -> Self {
+        self.ip = ip.map(|ip| ip.to_string());
+        self
+    }
 }
 
 pub type RegisterMessage = DeviceInfo;
@@ -311,7 +415,7 @@ impl DeviceInfo {
     pub fn new(alias: String, port: u16, protocol: Protocol) -> Self {
         Self {
             alias,
-            version: crate::protocol::constants::PROTOCOL_VERSION.to_string(),
+            version: crate::protocol::advertised_version(),
             device_model: None,
             device_type: None,
             fingerprint: String::new(),
@@ -322,14 +426,60 @@ impl DeviceInfo {
         }
     }
 
-    // This function is added based on the provided "Code Edit" and instruction.
-    // It assumes `_src` is a type that has an `ip()` method returning an IP address.
-    // For example, `std::net::SocketAddr` or similar.
+    /// The peer behind an announcement, addressed by the datagram's source
+    /// `src`. An advertised `ip` replaces it only when both sit on the same
+    /// network as one of this host's IPv4 interfaces: neither field is
+    /// authenticated, and the address is where the register request (and any
+    /// transfer) then goes, so an announcement must not be able to point us at
+    /// an arbitrary host.
     pub fn from_announcement<T: std::net::ToSocketAddrs>(
         announcement: AnnouncementMessage,
-        _src: T,
+        src: T,
     ) -> Self {
-        let socket_addr = _src.to_socket_addrs().ok().and_then(|mut i| i.next());
+        let source = src
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .map(|addr| addr.ip());
+        let local_networks: Vec<_> = if_addrs::get_if_addrs()
+            .map(|interfaces| {
+                interfaces
+                    .into_iter()
+                    .filter_map(|interface| match interface.addr {
+                        if_addrs::IfAddr::V4(address) => Some((address.ip, address.netmask)),
+                        if_addrs::IfAddr::V6(_) => None,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self::from_announcement_on(announcement, source, &local_networks)
+    }
+
+    /// [`Self::from_announcement`] against the given local `(address,
+    /// netmask)` networks.
+    pub(crate) fn from_announcement_on(
+        announcement: AnnouncementMessage,
+        source: Option<std::net::IpAddr>,
+        local_networks: &[(std::net::Ipv4Addr, std::net::Ipv4Addr)],
+    ) -> Self {
+        let advertised = announcement
+            .ip
+            .as_deref()
+            .and_then(|ip| ip.parse::<std::net::Ipv4Addr>().ok())
+            .filter(|advertised| match source {
+                Some(std::net::IpAddr::V4(source)) => {
+                    on_shared_network(*advertised, source, local_networks)
+                }
+                _ => false,
+            });
+        if advertised.is_none()
+            && let Some(ip) = &announcement.ip
+        {
+            tracing::debug!(
+                "Ignoring advertised address {ip} of {}: not on the network of {source:?}",
+                announcement.alias
+            );
+        }
         Self {
             alias: announcement.alias,
             version: announcement.version,
@@ -339,14 +489,48 @@ impl DeviceInfo {
             port: announcement.port,
             protocol: announcement.protocol,
             download: announcement.download,
-            ip: socket_addr.map(|s| s.ip().to_string()),
+            ip: advertised
+                .map(std::net::IpAddr::V4)
+                .or(source)
+                .map(|ip| ip.to_string()),
         }
     }
 }
 
+/// Whether `advertised` is a unicast address on the same local network as
+/// `source`.
+fn on_shared_network(
+    advertised: std::net::Ipv4Addr,
+    source: std::net::Ipv4Addr,
+    local_networks: &[(std::net::Ipv4Addr, std::net::Ipv4Addr)],
+) -> bool {
+    if advertised.is_loopback()
+        || advertised.is_unspecified()
+        || advertised.is_multicast()
+        || advertised.is_broadcast()
+    {
+        return false;
+    }
+    local_networks.iter().any(|(address, netmask)| {
+        let mask = u32::from(*netmask);
+        let network = u32::from(*address) & mask;
+        mask != 0 && u32::from(source) & mask == network && u32::from(advertised) & mask == network
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{DeviceInfo, DeviceType};
+    use super::{AnnouncementMessage, DeviceInfo, DeviceType, Protocol};
+
+    #[test]
+    fn protocol_parses_strictly() {
+        assert_eq!("https".parse::<Protocol>().unwrap(), Protocol::Https);
+        assert_eq!("HTTP".parse::<Protocol>().unwrap(), Protocol::Http);
+        assert!(matches!(
+            "ftp".parse::<Protocol>(),
+            Err(crate::error::LocalSendError::InvalidProtocol(name)) if name == "ftp"
+        ));
+    }
 
     #[test]
     fn known_device_types_round_trip() {
@@ -396,4 +580,61 @@ mod tests {
         assert_eq!(device.device_type, Some(DeviceType::Desktop));
         assert_eq!(device.fingerprint.len(), 64);
     }
+
+    #[test]
+    fn announcement_from_device_copies_every_field() {
+        let device = DeviceInfo {
+            alias: "Desk".into(),
+            version: "2.1".into(),
+            device_model: Some("Linux".into()),
+            device_type: Some(DeviceType::Headless),
+            fingerprint: "fp".into(),
+            port: 4000,
+            protocol: Protocol::Http,
+            download: true,
+            ip: Some("192.0.2.1".into()),
+        };
+
+        let message = AnnouncementMessage::from_device(&device, true);
+        assert_eq!(message.alias, device.alias);
+        assert_eq!(message.version, device.version);
+        assert_eq!(message.device_model, device.device_model);
+        assert_eq!(message.device_type, device.device_type);
+        assert_eq!(message.fingerprint, device.fingerprint);
+        assert_eq!(message.port, device.port);
+        assert_eq!(message.protocol, device.protocol);
+        assert!(message.download);
+        assert!(message.announce);
+
+        let reply = AnnouncementMessage::from_device(&device, false);
+        assert!(!reply.announce);
+    }
+
+    #[test]
+    fn announce_flag_is_read_from_either_field_and_emitted_once() {
+        let cases = [
+            (r#""announce": true"#, true),
+            (r#""announce": false"#, false),
+            (r#""announcement": true"#, true),
+            (r#""announcement": false"#, false),
+            (r#""announce": true, "announcement": true"#, true),
+            (r#""announce": false, "announcement": false"#, false),
+            // The v2 field wins when a peer sends both and they disagree.
+            (r#""announce": false, "announcement": true"#, false),
+            (r#""announce": true, "announcement": false"#, true),
+            ("", false),
+        ];
+        for (flags, expected) in cases {
+            let separator = if flags.is_empty() { "" } else { ", " };
+            let json = format!(
+                r#"{{"alias": "Peer", "version": "2.1", "fingerprint": "abc", "port": 53317, "protocol": "https"{separator}{flags}}}"#
+            );
+            let message: AnnouncementMessage = serde_json::from_str(&json).expect("parses");
+            assert_eq!(message.announce, expected, "flags: {flags:?}");
+
+            let emitted = serde_json::to_value(&message).unwrap();
+            assert_eq!(emitted["announce"], expected);
+            assert!(emitted.get("announcement").is_none());
+        }
+    }
 }
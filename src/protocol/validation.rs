@@ -1,10 +1,11 @@
 use crate::error::{LocalSendError, Result};
-use crate::protocol::{DeviceInfo, FileMetadata, PROTOCOL_VERSION};
+use crate::protocol::{DeviceInfo, FileMetadata, PROTOCOL_VERSION, Port};
 
 /// Validates protocol version compatibility
 ///
 /// LocalSend protocol follows semantic versioning.
 /// Major version must match, minor version can differ.
+/// To talk to a peer of another major, see [`crate::protocol::negotiate`].
 pub fn validate_protocol_version(version: &str) -> Result<()> {
     let parts: Vec<&str> = version.split('.').collect();
     let expected_parts: Vec<&str> = PROTOCOL_VERSION.split('.').collect();
@@ -27,6 +28,40 @@ pub fn validate_protocol_version(version: &str) -> Result<()> {
     Ok(())
 }
 
+/// Normalizes a protocol version to canonical `major.minor`: surrounding
+/// whitespace and a leading `v` are dropped, a bare major gains `.0`, and a
+/// patch component is cut off (`" v2.1.3 "` -> `"2.1"`).
+pub fn normalize_version(version: &str) -> Result<String> {
+    let trimmed = version.trim();
+    let trimmed = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+    let mut parts = trimmed.split('.');
+    let number = |part: Option<&str>| -> Option<u32> {
+        part.filter(|p| !p.is_empty() && p.bytes().all(|b| b.is_ascii_digit()))?
+            .parse()
+            .ok()
+    };
+    let major = number(parts.next());
+    let minor = match parts.next() {
+        None => Some(0),
+        part => number(part),
+    };
+    let patch_ok = match parts.next() {
+        None => true,
+        part => number(part).is_some() && parts.next().is_none(),
+    };
+
+    match (major, minor, patch_ok) {
+        (Some(major), Some(minor), true) => Ok(format!("{major}.{minor}")),
+        _ => Err(LocalSendError::InvalidVersion(version.to_string())),
+    }
+}
+
+/// The version every locally built [`DeviceInfo`] advertises: the normalized
+/// [`PROTOCOL_VERSION`].
+pub fn advertised_version() -> String {
+    normalize_version(PROTOCOL_VERSION).expect("PROTOCOL_VERSION must be a valid major.minor")
+}
+
 /// Validates that device info contains all required fields
 pub fn validate_device_info(device: &DeviceInfo) -> Result<()> {
     if device.alias.trim().is_empty() {
@@ -37,12 +72,42 @@ pub fn validate_device_info(device: &DeviceInfo) -> Result<()> {
 
     validate_protocol_version(&device.version)?;
 
-    if device.fingerprint.trim().is_empty() {
+    validate_port(device.port)?;
+
+    validate_fingerprint(&device.fingerprint)
+}
+
+/// Validates the port a device says it listens on. Port 0 can never be
+/// reached, the same rule as [`Port::new`]; a privileged port (below 1024)
+/// is allowed but logged (at debug level: peers repeat it in every
+/// announcement), as a LocalSend peer rarely runs on one.
+pub fn validate_port(port: u16) -> Result<()> {
+    Port::new(port)?;
+    if port < 1024 {
+        tracing::debug!("Device advertises privileged port {port}");
+    }
+    Ok(())
+}
+
+/// Validates a peer fingerprint before it is used as a discovery identity:
+/// it must be non-blank and free of whitespace/control characters, or every
+/// malformed peer would collapse onto the same de-duplication key.
+pub fn validate_fingerprint(fingerprint: &str) -> Result<()> {
+    if fingerprint.trim().is_empty() {
         return Err(LocalSendError::invalid_device(
             "Device fingerprint cannot be empty",
         ));
     }
 
+    if fingerprint
+        .chars()
+        .any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(LocalSendError::invalid_device(
+            "Device fingerprint contains whitespace or control characters",
+        ));
+    }
+
     Ok(())
 }
 
@@ -69,6 +134,69 @@ pub fn validate_file_metadata(metadata: &FileMetadata) -> Result<()> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_normalize_version() {
+        assert_eq!(normalize_version("2.1").unwrap(), "2.1");
+        assert_eq!(normalize_version(" v2.1.3 ").unwrap(), "2.1");
+        assert_eq!(normalize_version("2").unwrap(), "2.0");
+        assert_eq!(normalize_version("02.10").unwrap(), "2.10");
+        for bad in ["", "v", "2.", "two.one", "2.1.x", "2.1.3.4", "-2.1"] {
+            assert!(
+                matches!(
+                    normalize_version(bad),
+                    Err(LocalSendError::InvalidVersion(_))
+                ),
+                "{bad:?} must be rejected"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn every_local_device_advertises_the_protocol_version() {
+        use crate::core::DeviceInfoBuilder;
+        use crate::discovery::{HttpDiscovery, MulticastDiscovery};
+        use crate::protocol::Protocol;
+        use crate::server::LocalSendServer;
+
+        let save = tempfile::tempdir().unwrap();
+        let (mut server, _events) = LocalSendServer::builder()
+            .port(0)
+            .save_dir(save.path())
+            .build()
+            .await
+            .unwrap();
+
+        let versions = [
+            DeviceInfo::new("a".into(), 53317, Protocol::Http).version,
+            DeviceInfoBuilder::new("b", 53317).build().version,
+            server.device().version.clone(),
+            MulticastDiscovery::new("c".into(), 53317, Protocol::Http)
+                .unwrap()
+                .local_device()
+                .version
+                .clone(),
+            HttpDiscovery::new("d".into(), 53317, Protocol::Http)
+                .unwrap()
+                .local_device()
+                .version
+                .clone(),
+        ];
+        server.stop().await;
+
+        for version in versions {
+            assert_eq!(version, PROTOCOL_VERSION);
+        }
+    }
+
+    #[test]
+    fn test_validate_fingerprint() {
+        assert!(validate_fingerprint("3f2c9a1b").is_ok());
+        assert!(validate_fingerprint("").is_err());
+        assert!(validate_fingerprint("  ").is_err());
+        assert!(validate_fingerprint("abc def").is_err());
+        assert!(validate_fingerprint("abc\0").is_err());
+    }
+
     #[test]
     fn test_validate_protocol_version_compatible() {
         // Same version
@@ -119,6 +247,18 @@ mod tests {
         // Invalid version
         device.version = "3.0".to_string();
         assert!(validate_device_info(&device).is_err());
+        device.version = PROTOCOL_VERSION.to_string();
+
+        // Port 0
+        device.port = 0;
+        assert!(matches!(
+            validate_device_info(&device),
+            Err(LocalSendError::InvalidPort(_))
+        ));
+
+        // Privileged, but allowed
+        device.port = 443;
+        assert!(validate_device_info(&device).is_ok());
     }
 
     #[test]
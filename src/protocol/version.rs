@@ -0,0 +1,156 @@
+use crate::error::{LocalSendError, Result};
+use crate::protocol::{PROTOCOL_VERSION, normalize_version};
+use std::fmt;
+use std::str::FromStr;
+
+/// A LocalSend protocol version as `major.minor`. Ordered by major, then
+/// minor; a patch component is accepted when parsing and dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ProtocolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ProtocolVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// The version this implementation speaks, [`PROTOCOL_VERSION`].
+    pub fn local() -> Self {
+        PROTOCOL_VERSION
+            .parse()
+            .expect("PROTOCOL_VERSION must be a valid major.minor")
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = LocalSendError;
+
+    /// Anything [`normalize_version`] accepts (`"2.1"`, `" v2.1.3 "`, `"2"`).
+    fn from_str(version: &str) -> Result<Self> {
+        let normalized = normalize_version(version)?;
+        let (major, minor) = normalized
+            .split_once('.')
+            .expect("normalize_version yields major.minor");
+        let number = |part: &str| {
+            part.parse()
+                .map_err(|_| LocalSendError::InvalidVersion(version.to_string()))
+        };
+        Ok(Self::new(number(major)?, number(minor)?))
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// The family of HTTP routes to talk to a peer with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ApiVersion {
+    /// `/api/localsend/v1/...`, spoken by protocol 1.x peers.
+    V1,
+    /// `/api/localsend/v2/...`, spoken by protocol 2.x peers.
+    V2,
+}
+
+impl ApiVersion {
+    /// The route prefix, without a trailing slash.
+    pub fn base_path(self) -> &'static str {
+        match self {
+            Self::V1 => "/api/localsend/v1",
+            Self::V2 => "/api/localsend/v2",
+        }
+    }
+}
+
+/// The outcome of [`negotiate`]: the highest version both sides speak, and
+/// the routes that go with it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NegotiatedVersion {
+    pub version: ProtocolVersion,
+    pub api: ApiVersion,
+}
+
+impl NegotiatedVersion {
+    /// Whether both sides speak at least `major.minor`, for gating a feature
+    /// added in a minor release.
+    pub fn supports(&self, major: u32, minor: u32) -> bool {
+        self.version >= ProtocolVersion::new(major, minor)
+    }
+}
+
+/// Pick how to talk to a peer advertising `remote`. The lower of the two
+/// versions wins, on the understanding that a newer side still serves the
+/// routes of the versions before it; so a 3.x peer is reached over v2 by a
+/// 2.x client. Fails when that common version has no routes this crate
+/// knows (a 0.x peer, or a local version past the known ones).
+///
+/// [`validate_protocol_version`](crate::protocol::validate_protocol_version)
+/// remains the stricter same-major check.
+pub fn negotiate(local: ProtocolVersion, remote: ProtocolVersion) -> Result<NegotiatedVersion> {
+    let version = local.min(remote);
+    let api = match version.major {
+        1 => ApiVersion::V1,
+        2 => ApiVersion::V2,
+        _ => {
+            return Err(LocalSendError::VersionMismatch {
+                expected: local.to_string(),
+                actual: remote.to_string(),
+            });
+        }
+    };
+    Ok(NegotiatedVersion { version, api })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ApiVersion, ProtocolVersion, negotiate};
+    use crate::error::LocalSendError;
+
+    fn version(text: &str) -> ProtocolVersion {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn versions_parse_and_order_by_major_then_minor() {
+        assert_eq!(version(" v2.1.3 "), ProtocolVersion::new(2, 1));
+        assert_eq!(version("2"), ProtocolVersion::new(2, 0));
+        assert_eq!(ProtocolVersion::local().to_string(), "2.1");
+        assert!(version("2.10") > version("2.9"));
+        assert!(version("3.0") > version("2.10"));
+        assert!(matches!(
+            "two".parse::<ProtocolVersion>(),
+            Err(LocalSendError::InvalidVersion(_))
+        ));
+    }
+
+    #[test]
+    fn the_lower_version_picks_the_routes() {
+        let local = version("2.1");
+
+        let same = negotiate(local, version("2.1")).unwrap();
+        assert_eq!(same.api, ApiVersion::V2);
+
+        let older_minor = negotiate(local, version("2.0")).unwrap();
+        assert_eq!(older_minor.version, version("2.0"));
+        assert!(older_minor.supports(2, 0));
+        assert!(!older_minor.supports(2, 1));
+
+        let v1 = negotiate(local, version("1.0")).unwrap();
+        assert_eq!(v1.api, ApiVersion::V1);
+        assert_eq!(v1.api.base_path(), "/api/localsend/v1");
+
+        // A future major is met on the routes this side knows.
+        let future = negotiate(local, version("3.0")).unwrap();
+        assert_eq!(future.version, local);
+        assert_eq!(future.api, ApiVersion::V2);
+
+        assert!(matches!(
+            negotiate(local, version("0.9")),
+            Err(LocalSendError::VersionMismatch { .. })
+        ));
+    }
+}
@@ -30,6 +30,14 @@ pub enum ServerEvent {
     WebShareSessionDone {
         session_id: SessionId,
     },
+    /// A file transfer was accepted and its session opened; uploads follow.
+    SessionStarted {
+        session_id: SessionId,
+        sender_alias: String,
+        /// Accepted files only.
+        file_count: usize,
+        total_bytes: u64,
+    },
     /// Cumulative payload bytes written for an active receive session.
     FileReceiveProgress {
         session_id: SessionId,
@@ -40,7 +48,8 @@ pub enum ServerEvent {
         total_bytes: u64,
         file_count: usize,
     },
-    /// One file finished writing to disk.
+    /// One file finished writing to disk (or, in `receive_into_memory` mode,
+    /// was handed to the callback; `path` is then empty).
     FileReceived {
         session_id: SessionId,
         file_id: FileId,
@@ -48,14 +57,111 @@ pub enum ServerEvent {
         path: PathBuf,
         size: u64,
         sender_alias: String,
+        /// The sender declared a sha256 and the received bytes matched it.
+        /// The size is always checked; a file that fails either check is
+        /// discarded and never reported.
+        verified: bool,
         /// Retained for source compatibility. First-class text messages are
         /// emitted as [`ServerEvent::TextReceived`].
         message_text: Option<String>,
     },
+    /// An upload matched a file already in the save directory and was
+    /// discarded under [`crate::server::DuplicatePolicy::SkipIdentical`].
+    /// `path` is the existing file; the session still counts it as received.
+    FileSkipped {
+        session_id: SessionId,
+        file_id: FileId,
+        file_name: String,
+        path: PathBuf,
+        size: u64,
+        sender_alias: String,
+    },
     /// All accepted files of a session arrived (or the session was cancelled).
     SessionDone {
         session_id: SessionId,
     },
+    /// A transfer request was turned away before any session opened.
+    TransferRejected(TransferRejection),
+    /// A peer registered with us over HTTP, typically answering our
+    /// announcement; `ip` is the connection's source address. Pass it to
+    /// [`crate::discovery::MulticastDiscovery::remember`] so a peer that only
+    /// answers, and rarely announces itself, stays on the known list.
+    DeviceRegistered(DeviceInfo),
+}
+
+/// Why a `prepare-upload` did not open a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectionReason {
+    /// The consumer declined, accepted no files, or no consumer was listening.
+    Declined,
+    /// Nobody answered within the accept timeout.
+    Timeout,
+    /// Another session was already active (HTTP 409).
+    Blocked,
+    /// The server runs with receiving disabled (HTTP 403).
+    ReceiveDisabled,
+    /// The sender's fingerprint is blocked, or missing from the allowlist
+    /// (HTTP 403).
+    SenderNotAllowed,
+    /// The offered files add up to more than the receiver's size limit
+    /// (HTTP 403).
+    TooLarge,
+}
+
+/// Audit record of a rejected transfer, tying the reason to the sender.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferRejection {
+    pub sender_fingerprint: String,
+    pub sender_alias: String,
+    pub file_count: usize,
+    /// Sum of the declared sizes of every offered file.
+    pub total_size: u64,
+    pub reason: RejectionReason,
+}
+
+/// What a receive run took in, folded from its [`ServerEvent`]s so a caller
+/// can report a structured result when the server stops.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReceiveSummary {
+    pub files_received: usize,
+    pub total_bytes: u64,
+    /// Uploads discarded as identical to a file already on disk.
+    pub duplicates_skipped: usize,
+    /// Sender aliases in first-seen order, without repeats.
+    pub senders: Vec<String>,
+    #[serde(rename = "durationSecs", serialize_with = "serialize_secs")]
+    pub duration: std::time::Duration,
+}
+
+impl ReceiveSummary {
+    /// Count a completed or skipped file; every other event is ignored.
+    pub fn record(&mut self, event: &ServerEvent) {
+        let sender_alias = match event {
+            ServerEvent::FileReceived {
+                size, sender_alias, ..
+            } => {
+                self.files_received += 1;
+                self.total_bytes = self.total_bytes.saturating_add(*size);
+                sender_alias
+            }
+            ServerEvent::FileSkipped { sender_alias, .. } => {
+                self.duplicates_skipped += 1;
+                sender_alias
+            }
+            _ => return,
+        };
+        if !self.senders.contains(sender_alias) {
+            self.senders.push(sender_alias.clone());
+        }
+    }
+}
+
+fn serialize_secs<S: serde::Serializer>(
+    duration: &std::time::Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
 }
 
 #[derive(Clone, Debug)]
@@ -83,6 +189,9 @@ impl PendingWebShareRequest {
 pub enum TransferDecision {
     Accept,
     AcceptFiles(Vec<FileId>),
+    /// Accept every offered file into this directory instead of the server's
+    /// save directory. A relative path is taken under the save directory.
+    AcceptInto(PathBuf),
     Decline,
 }
 
@@ -120,14 +229,28 @@ impl PendingRequest {
         &self.files
     }
 
-    /// Accept every offered file. No-op if the sender already timed out.
-    pub fn accept(self) {
-        let _ = self.responder.send(TransferDecision::Accept);
+    /// Accept every offered file. Returns `false` if the request had already
+    /// timed out, in which case the sender was turned away and nothing opens.
+    pub fn accept(self) -> bool {
+        self.responder.send(TransferDecision::Accept).is_ok()
+    }
+
+    /// Accept a subset of the offered files (empty = decline). Returns
+    /// `false` if the request had already timed out.
+    pub fn accept_files(self, ids: Vec<FileId>) -> bool {
+        self.responder
+            .send(TransferDecision::AcceptFiles(ids))
+            .is_ok()
     }
 
-    /// Accept a subset of the offered files (empty = decline).
-    pub fn accept_files(self, ids: Vec<FileId>) {
-        let _ = self.responder.send(TransferDecision::AcceptFiles(ids));
+    /// Accept every offered file, saving them under `dir` for this session
+    /// only. The directory is created if it does not exist; one that is
+    /// unsafe (`..`) or cannot be created refuses the transfer with 403.
+    /// Returns `false` if the request had already timed out.
+    pub fn accept_into(self, dir: impl Into<PathBuf>) -> bool {
+        self.responder
+            .send(TransferDecision::AcceptInto(dir.into()))
+            .is_ok()
     }
 
     pub fn decline(self) {
@@ -156,6 +279,16 @@ mod tests {
         assert!(matches!(rx.await, Ok(TransferDecision::Accept)));
     }
 
+    #[tokio::test]
+    async fn accept_into_carries_the_directory() {
+        let (r, rx) = req();
+        r.accept_into("Desktop");
+        assert_eq!(
+            rx.await.unwrap(),
+            TransferDecision::AcceptInto(PathBuf::from("Desktop"))
+        );
+    }
+
     #[tokio::test]
     async fn decline_sends_decline_decision() {
         let (r, rx) = req();
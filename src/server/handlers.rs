@@ -2,9 +2,16 @@ use super::crosscopy_authorized::{
     CROSSCOPY_FILE_V3_HANDOFF_HEADER, CrossCopyAuthorizedHandoff, CrossCopyAuthorizedPrepare,
     CrossCopyAuthorizedPrepareMetadata, CrossCopyAuthorizedUpload, CrossCopyAuthorizedUploadBody,
 };
-use super::events::ServerEvent;
-use super::state::{CrossCopyAuthorizedSession, ServerState, write_body_to_file_with_progress};
-use crate::protocol::{DeviceInfo, FileId, PrepareUploadRequest, PrepareUploadResponse, SessionId};
+use super::events::{RejectionReason, ServerEvent, TransferRejection};
+use super::server::DuplicatePolicy;
+use super::state::{
+    CrossCopyAuthorizedSession, ServerState, UploadSink, write_body_with_progress,
+    write_multipart_with_progress,
+};
+use crate::protocol::{
+    DeviceInfo, FileId, PrepareUploadRequest, PrepareUploadResponse, SessionId, UploadQuery,
+    UploadStatus, validate_fingerprint, validate_port,
+};
 use axum::{
     Json,
     body::Body,
@@ -15,10 +22,14 @@ use axum::{
 use futures_util::StreamExt;
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::RwLock;
 
+/// How often a streaming upload refreshes its session's last activity.
+const SESSION_TOUCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
 pub(crate) async fn handle_info(State(state): State<Arc<RwLock<ServerState>>>) -> Response {
     let state = state.read().await;
     Json(state.device.clone()).into_response()
@@ -26,10 +37,27 @@ pub(crate) async fn handle_info(State(state): State<Arc<RwLock<ServerState>>>) -
 
 pub(crate) async fn handle_register(
     State(state): State<Arc<RwLock<ServerState>>>,
-    Json(remote_device): Json<DeviceInfo>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    Json(mut remote_device): Json<DeviceInfo>,
 ) -> Response {
     tracing::debug!("Register request from {:?}", remote_device.alias);
+    remote_device.ip = Some(peer.ip().to_string());
     let state = state.read().await;
+    // The same checks as an announcement before the peer is passed on to be
+    // listed; it is answered either way.
+    match validate_fingerprint(&remote_device.fingerprint)
+        .and_then(|()| validate_port(remote_device.port))
+    {
+        Ok(()) => {
+            let _ = state
+                .events_tx
+                .try_send(ServerEvent::DeviceRegistered(remote_device));
+        }
+        Err(error) => tracing::debug!(
+            "Not listing {} ({peer}) from its register request: {error}",
+            remote_device.alias
+        ),
+    }
     Json(state.device.clone()).into_response()
 }
 
@@ -148,13 +176,46 @@ async fn handle_crosscopy_authorized_prepare(
     Json(response).into_response()
 }
 
-async fn handle_standard_prepare_upload(
+pub(crate) async fn handle_standard_prepare_upload(
     state_ref: Arc<RwLock<ServerState>>,
     peer: std::net::SocketAddr,
     params: PrepareUploadParams,
     request: PrepareUploadRequest,
 ) -> Response {
-    // PIN gate runs first, before any session/event work -- a locked-out or
+    // A receive-disabled device, or a sender it does not take transfers
+    // from, is refused before the PIN check: no PIN could unlock it, and a
+    // sender should not be prompted for one.
+    {
+        let state = state_ref.read().await;
+        if state.receive_disabled {
+            record_rejection(&state.events_tx, &request, RejectionReason::ReceiveDisabled);
+            return (
+                StatusCode::FORBIDDEN,
+                "Receiving is disabled on this device",
+            )
+                .into_response();
+        }
+        let fingerprint = &request.info.fingerprint;
+        let allowed = !state.blocked_fingerprints.contains(fingerprint)
+            && state
+                .allowed_fingerprints
+                .as_ref()
+                .is_none_or(|allowed| allowed.contains(fingerprint));
+        if !allowed {
+            tracing::warn!(
+                "Refused prepare-upload from {} ({peer}): sender fingerprint {fingerprint} is not allowed",
+                request.info.alias
+            );
+            record_rejection(
+                &state.events_tx,
+                &request,
+                RejectionReason::SenderNotAllowed,
+            );
+            return StatusCode::FORBIDDEN.into_response();
+        }
+    }
+
+    // PIN gate runs next, before any session/event work -- a locked-out or
     // unauthenticated peer must never reach the accept flow (which would
     // otherwise answer with 403/409 instead of the correct 401/429).
     {
@@ -177,56 +238,116 @@ async fn handle_standard_prepare_upload(
         return StatusCode::NO_CONTENT.into_response();
     }
 
-    // LocalSend represents a text message as exactly one small offered item
-    // whose non-empty `preview` is the complete body. Mixed/multi-file offers
-    // remain ordinary file transfers even if one item happens to have preview
-    // metadata.
+    // Refuse unsafe names before anyone is asked to accept them; the upload
+    // path applies the same policy when it joins the name under save_dir.
+    if let Some(error) = request
+        .files
+        .values()
+        .find_map(|file| crate::path_safety::relative_file_path(&file.file_name).err())
+    {
+        tracing::warn!("Prepare-upload rejected: {}", error);
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+
+    // An offer past the size limit is refused before anyone is asked to
+    // accept it; each upload is later held to its declared size.
+    let total_size = request
+        .files
+        .values()
+        .map(|file| file.size)
+        .fold(0_u64, u64::saturating_add);
+    {
+        let state = state_ref.read().await;
+        if let Some(max) = state.max_total_size.filter(|max| total_size > *max) {
+            tracing::warn!("Prepare-upload rejected: {total_size} bytes offered, limit is {max}");
+            record_rejection(&state.events_tx, &request, RejectionReason::TooLarge);
+            return (
+                StatusCode::FORBIDDEN,
+                "Transfer is larger than this device accepts",
+            )
+                .into_response();
+        }
+    }
+
+    // LocalSend represents a text message as exactly one small `text/*`
+    // offered item whose non-empty `preview` is the complete body. Anything
+    // else with a preview (an image and its thumbnail) and mixed/multi-file
+    // offers remain ordinary file transfers.
     let message_text = if request.files.len() == 1 {
         request.files.values().next().and_then(|file| {
             file.preview
                 .as_ref()
-                .filter(|text| !text.is_empty() && file.size < 1024 * 1024)
+                .filter(|text| {
+                    !text.is_empty()
+                        && file.size < 1024 * 1024
+                        && file.file_type.starts_with("text/")
+                })
                 .cloned()
         })
     } else {
         None
     };
 
-    // Short lock: reject a conflicting session, reserve this one with a
+    // Short lock: reject once every session slot is taken, reserve this one with a
     // placeholder session over the *offered* files (replaced below with the
     // real session, built from the accepted files only, once the accept
     // decision is in), and pull out the config needed to make that decision.
     // Never hold this guard across the `timeout(...).await` below -- that
     // would deadlock every other concurrent request (including the upload
     // that follows acceptance).
-    let (events_tx, auto_accept, accept_timeout) = {
+    let (reservation_id, events_tx, auto_accept, auto_decline, accept_timeout, clock) = {
         let mut state = state_ref.write().await;
 
-        // Check for existing session timeout (e.g. 5 minutes or session finished)
-        if let Some(session) = &state.current_session {
-            if session.is_timed_out(300) {
-                state.current_session = None;
-            } else {
-                tracing::warn!("Session already exists, rejecting new session");
-                return StatusCode::CONFLICT.into_response();
+        // Timed-out sessions give their slot up first (e.g. a sender that
+        // vanished mid-transfer), then a full house is a conflict.
+        let timeout = state.session_timeout;
+        let stale: Vec<SessionId> = state
+            .sessions
+            .values()
+            .filter(|session| session.is_idle_for(timeout))
+            .map(|session| session.id.clone())
+            .collect();
+        for id in stale {
+            if let Some(session) = state.sessions.remove(&id) {
+                tokio::spawn(remove_partials(session));
             }
         }
+        if state.sessions.len() >= state.max_sessions {
+            tracing::warn!(
+                "{} session(s) already active, rejecting new session",
+                state.sessions.len()
+            );
+            record_rejection(&state.events_tx, &request, RejectionReason::Blocked);
+            return StatusCode::CONFLICT.into_response();
+        }
 
-        state.current_session = Some(crate::core::Session::new(
+        let reservation = crate::core::Session::with_clock(
             request.info.alias.clone(),
             request.files.clone(),
-        ));
+            state.clock.clone(),
+        );
+        let reservation_id = reservation.id.clone();
+        state.sessions.insert(reservation_id.clone(), reservation);
 
         (
+            reservation_id,
             state.events_tx.clone(),
-            state.auto_accept.load(std::sync::atomic::Ordering::Relaxed),
+            state.auto_accept.load(std::sync::atomic::Ordering::Relaxed)
+                || state
+                    .auto_accept_below
+                    .is_some_and(|limit| total_size < limit),
+            state.auto_decline,
             state.accept_timeout,
+            state.clock.clone(),
         )
     };
 
-    // Decide: auto-accept, or ask the event consumer.
+    // Decide: auto-accept, auto-decline, or ask the event consumer.
+    let mut rejection_reason = RejectionReason::Declined;
     let decision = if auto_accept {
         crate::server::events::TransferDecision::Accept
+    } else if auto_decline {
+        crate::server::events::TransferDecision::Decline
     } else {
         let (pending_request, decision_rx) =
             crate::server::events::PendingRequest::new(request.info.clone(), request.files.clone());
@@ -240,26 +361,43 @@ async fn handle_standard_prepare_upload(
             // No consumer listening -> decline.
             crate::server::events::TransferDecision::Decline
         } else {
-            match tokio::time::timeout(accept_timeout, decision_rx).await {
+            let mut decision_rx = decision_rx;
+            match tokio::time::timeout(accept_timeout, &mut decision_rx).await {
                 Ok(Ok(d)) => d,
-                _ => crate::server::events::TransferDecision::Decline, // dropped or timed out
+                // The request was dropped unanswered.
+                Ok(Err(_)) => crate::server::events::TransferDecision::Decline,
+                // Close the channel before giving up, so an answer racing the
+                // deadline either landed already (and wins) or is refused --
+                // `PendingRequest::accept` then reports that it lost.
+                Err(_) => {
+                    decision_rx.close();
+                    decision_rx.try_recv().unwrap_or_else(|_| {
+                        rejection_reason = RejectionReason::Timeout;
+                        crate::server::events::TransferDecision::Decline
+                    })
+                }
             }
         }
     };
 
+    let mut save_dir_override = None;
     let accepted_ids: Vec<FileId> = match decision {
         crate::server::events::TransferDecision::Accept => request.files.keys().cloned().collect(),
         crate::server::events::TransferDecision::AcceptFiles(ids) => ids
             .into_iter()
             .filter(|id| request.files.contains_key(id))
             .collect(),
+        crate::server::events::TransferDecision::AcceptInto(dir) => {
+            save_dir_override = Some(dir);
+            request.files.keys().cloned().collect()
+        }
         crate::server::events::TransferDecision::Decline => Vec::new(),
     };
 
     if accepted_ids.is_empty() {
         let mut state = state_ref.write().await;
-        state.current_session = None;
-        tracing::info!("Transfer declined (or timed out)");
+        release_reservation(&mut state, &reservation_id);
+        record_rejection(&state.events_tx, &request, rejection_reason);
         return StatusCode::FORBIDDEN.into_response();
     }
 
@@ -272,13 +410,53 @@ async fn handle_standard_prepare_upload(
         .filter(|(id, _)| accepted_ids.contains(id))
         .map(|(id, meta)| (id.clone(), meta.clone()))
         .collect();
-    let session = crate::core::Session::new(request.info.alias.clone(), accepted_files);
+    let mut session =
+        crate::core::Session::with_clock(request.info.alias.clone(), accepted_files, clock);
+    if let Some(dir) = save_dir_override {
+        let default_dir = state_ref.read().await.save_dir.clone();
+        let resolved = match crate::path_safety::resolve_save_dir(&default_dir, &dir) {
+            Ok(resolved) => resolved,
+            Err(e) => {
+                tracing::warn!("Rejected save directory override: {}", e);
+                release_reservation(&mut *state_ref.write().await, &reservation_id);
+                return StatusCode::FORBIDDEN.into_response();
+            }
+        };
+        // The receiver's choice, not a fault on this side: the transfer is
+        // refused as if it had been declined.
+        if let Err(e) = crate::core::ensure_dir(&resolved).await {
+            tracing::warn!("Rejected save directory override: {}", e);
+            release_reservation(&mut *state_ref.write().await, &reservation_id);
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        session.save_dir = Some(resolved);
+    }
     let session_id = session.id.clone();
     let files_map = session.tokens.clone();
 
+    // The reservation is the single authority for this request: if it was
+    // cancelled or replaced while we waited, the accept arrived too late.
     {
         let mut state = state_ref.write().await;
-        state.current_session = Some(session);
+        if !holds_reservation(&state, &reservation_id) {
+            tracing::info!("Transfer accepted after its reservation ended; rejecting");
+            record_rejection(&state.events_tx, &request, RejectionReason::Timeout);
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        if message_text.is_none() {
+            let _ = state.events_tx.try_send(ServerEvent::SessionStarted {
+                session_id: session_id.clone(),
+                sender_alias: session.sender_alias.clone(),
+                file_count: session.files.len(),
+                total_bytes: session
+                    .files
+                    .values()
+                    .map(|file| file.size)
+                    .fold(0_u64, u64::saturating_add),
+            });
+        }
+        state.sessions.remove(&reservation_id);
+        state.sessions.insert(session_id.clone(), session);
     }
 
     // If it's a message, return 204 No Content
@@ -298,7 +476,7 @@ async fn handle_standard_prepare_upload(
                 session_id: session_id.clone(),
             });
 
-        state.current_session = None;
+        state.sessions.remove(&session_id);
         return StatusCode::NO_CONTENT.into_response();
     }
 
@@ -309,14 +487,281 @@ async fn handle_standard_prepare_upload(
     .into_response()
 }
 
-#[derive(Deserialize)]
-pub(crate) struct UploadParams {
-    #[serde(rename = "sessionId")]
-    session_id: SessionId,
-    #[serde(rename = "fileId")]
-    file_id: FileId,
-    #[serde(rename = "token")]
-    token: crate::protocol::Token,
+fn holds_reservation(state: &ServerState, reservation_id: &SessionId) -> bool {
+    state.sessions.contains_key(reservation_id)
+}
+
+/// Drop the placeholder session if it is still there (a cancel may already
+/// have removed it).
+fn release_reservation(state: &mut ServerState, reservation_id: &SessionId) {
+    state.sessions.remove(reservation_id);
+}
+
+/// Log a turned-away `prepare-upload` with its sender and offer, and publish
+/// the same record as a [`ServerEvent::TransferRejected`].
+fn record_rejection(
+    events_tx: &tokio::sync::mpsc::Sender<ServerEvent>,
+    request: &PrepareUploadRequest,
+    reason: RejectionReason,
+) {
+    let rejection = TransferRejection {
+        sender_fingerprint: request.info.fingerprint.clone(),
+        sender_alias: request.info.alias.clone(),
+        file_count: request.files.len(),
+        total_size: request
+            .files
+            .values()
+            .map(|file| file.size)
+            .fold(0_u64, u64::saturating_add),
+        reason,
+    };
+    tracing::info!(
+        sender_fingerprint = %rejection.sender_fingerprint,
+        sender_alias = %rejection.sender_alias,
+        file_count = rejection.file_count,
+        total_size = rejection.total_size,
+        reason = ?rejection.reason,
+        "Transfer rejected"
+    );
+    let _ = events_tx.try_send(ServerEvent::TransferRejected(rejection));
+}
+
+/// Strict-mode framing check: the body must announce its length up front, so
+/// a chunked stream cannot run past the declared size. A multipart length
+/// also covers the form framing, so it only has to be present.
+fn check_content_length(headers: &HeaderMap, declared_size: u64) -> Result<(), StatusCode> {
+    let Some(value) = headers.get(axum::http::header::CONTENT_LENGTH) else {
+        tracing::warn!("Upload rejected: missing Content-Length in strict mode");
+        return Err(StatusCode::LENGTH_REQUIRED);
+    };
+    let Some(length) = value.to_str().ok().and_then(|v| v.parse::<u64>().ok()) else {
+        tracing::warn!("Upload rejected: unparsable Content-Length {:?}", value);
+        return Err(StatusCode::BAD_REQUEST);
+    };
+    if !is_multipart(headers) && length != declared_size {
+        tracing::warn!(
+            "Upload rejected: Content-Length {} does not match declared size {}",
+            length,
+            declared_size
+        );
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(())
+}
+
+/// Whether `existing` holds the same bytes as the just-written `received`.
+/// A verified `declared_sha` stands in for hashing the new copy; any I/O
+/// error counts as "different", so the upload is kept rather than lost.
+async fn same_contents(existing: &Path, received: &Path, declared_sha: Option<&str>) -> bool {
+    let Ok(existing_sha) = crate::sha256_from_file(existing).await else {
+        return false;
+    };
+    match declared_sha {
+        Some(sha) => existing_sha.eq_ignore_ascii_case(sha),
+        None => crate::sha256_from_file(received)
+            .await
+            .is_ok_and(|sha| sha == existing_sha),
+    }
+}
+
+/// Copy a received file to the same relative path under `mirror_dir`
+/// (renamed around anything already there), with its at-rest manifest if it
+/// has one. Failures are only logged: the upload itself already succeeded.
+async fn mirror_received(save_dir: PathBuf, mirror_dir: PathBuf, path: PathBuf) {
+    let relative = path
+        .strip_prefix(&save_dir)
+        .unwrap_or(&path)
+        .iter()
+        .map(|part| part.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let target = match crate::core::unique_save_path(&mirror_dir, &relative) {
+        Ok(target) => target,
+        Err(e) => {
+            tracing::warn!("Not mirroring {:?}: {}", path, e);
+            return;
+        }
+    };
+    if let Some(parent) = target.parent()
+        && let Err(e) = crate::core::ensure_dir(parent).await
+    {
+        tracing::warn!("Failed to mirror {:?}: {}", path, e);
+        return;
+    }
+    if let Err(e) = tokio::fs::copy(&path, &target).await {
+        tracing::warn!("Failed to mirror {:?} to {:?}: {}", path, target, e);
+        return;
+    }
+    #[cfg(feature = "encryption")]
+    {
+        use crate::crypto::at_rest::manifest_path;
+        let manifest = manifest_path(&path);
+        if manifest.is_file()
+            && let Err(e) = tokio::fs::copy(&manifest, manifest_path(&target)).await
+        {
+            tracing::warn!("Failed to mirror the manifest of {:?}: {}", path, e);
+        }
+    }
+    tracing::debug!("Mirrored {:?} to {:?}", path, target);
+}
+
+/// Give a saved file the modified time its sender declared. Best effort: a
+/// file whose time cannot be set keeps its arrival time.
+async fn set_modified_time(path: &Path, modified: std::time::SystemTime) {
+    let target = path.to_path_buf();
+    let result = tokio::task::spawn_blocking(move || {
+        std::fs::File::options()
+            .write(true)
+            .open(&target)?
+            .set_modified(modified)
+    })
+    .await;
+    if let Ok(Err(e)) = result {
+        tracing::warn!("Failed to restore the modified time of {:?}: {}", path, e);
+    }
+}
+
+/// LocalSend uploads are a raw body; a `multipart/form-data` content type marks
+/// a third-party client posting the file as a form part instead.
+fn is_multipart(headers: &HeaderMap) -> bool {
+    headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("multipart/form-data")
+        })
+}
+
+/// Appended to the name of an interrupted upload kept for resuming.
+const PARTIAL_EXTENSION: &str = "lspart";
+
+/// Set an interrupted upload aside as `<name>.lspart` and record it on its
+/// session, for the sender to resume. A session that has since ended has
+/// no one to resume it, so the partial is deleted instead.
+async fn keep_partial(
+    state_ref: &Arc<RwLock<ServerState>>,
+    session_id: &SessionId,
+    file_id: &FileId,
+    save_path: &Path,
+) {
+    let mut partial = save_path.as_os_str().to_owned();
+    partial.push(format!(".{PARTIAL_EXTENSION}"));
+    let partial = PathBuf::from(partial);
+    if tokio::fs::rename(save_path, &partial).await.is_err() {
+        let _ = tokio::fs::remove_file(save_path).await;
+        return;
+    }
+    let mut state = state_ref.write().await;
+    match state.sessions.get_mut(session_id) {
+        Some(session) => {
+            tracing::info!("Kept {:?} for the sender to resume", partial);
+            session.partials.insert(file_id.clone(), partial);
+        }
+        None => {
+            drop(state);
+            let _ = tokio::fs::remove_file(&partial).await;
+        }
+    }
+}
+
+/// Put a partial taken for resuming back on its session, when the attempt
+/// failed before any of it was used; without the session, delete it.
+async fn restore_partial(
+    state_ref: &Arc<RwLock<ServerState>>,
+    session_id: &SessionId,
+    file_id: &FileId,
+    partial: Option<PathBuf>,
+) {
+    let Some(partial) = partial else {
+        return;
+    };
+    let mut state = state_ref.write().await;
+    match state.sessions.get_mut(session_id) {
+        Some(session) => {
+            session.partials.insert(file_id.clone(), partial);
+        }
+        None => {
+            drop(state);
+            let _ = tokio::fs::remove_file(&partial).await;
+        }
+    }
+}
+
+/// Delete the partials an ended session kept; nobody will resume them.
+pub(crate) async fn remove_partials(session: crate::core::Session) {
+    for partial in session.partials.into_values() {
+        let _ = tokio::fs::remove_file(&partial).await;
+    }
+}
+
+/// Move a kept partial back to the upload's save path and open it for
+/// appending. A partial that is not `offset` bytes long does not match what
+/// the sender is about to send, so it is discarded (409) and the sender
+/// starts over.
+async fn reopen_partial(
+    partial: &Path,
+    save_path: &Path,
+    offset: u64,
+) -> Result<tokio::fs::File, StatusCode> {
+    let kept = tokio::fs::metadata(partial)
+        .await
+        .map(|meta| meta.len())
+        .ok();
+    if kept != Some(offset) {
+        tracing::warn!(
+            "Resume of {:?} rejected: {:?} bytes kept, sender offset {}",
+            partial,
+            kept,
+            offset
+        );
+        let _ = tokio::fs::remove_file(partial).await;
+        return Err(StatusCode::CONFLICT);
+    }
+    let reopened = async {
+        tokio::fs::rename(partial, save_path).await?;
+        tokio::fs::OpenOptions::new()
+            .append(true)
+            .open(save_path)
+            .await
+    };
+    reopened.await.map_err(|e| {
+        tracing::error!("Failed to resume {:?}: {}", partial, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// `GET /api/localsend/v2/upload-status`: how many bytes of a file this
+/// receiver kept from an interrupted upload. Answers 404 unless
+/// [`crate::server::LocalSendServerBuilder::resumable_uploads`] is on, like
+/// a receiver that has no such endpoint.
+pub(crate) async fn handle_upload_status(
+    State(state_ref): State<Arc<RwLock<ServerState>>>,
+    Query(params): Query<UploadQuery>,
+) -> Response {
+    let partial = {
+        let state = state_ref.read().await;
+        if !state.resumable_uploads {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        let Some(session) = state.sessions.get(&params.session_id) else {
+            return StatusCode::FORBIDDEN.into_response();
+        };
+        if !session.verify_token(&params.file_id, &params.token) {
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        session.partials.get(&params.file_id).cloned()
+    };
+    let received = match partial {
+        Some(partial) => tokio::fs::metadata(&partial)
+            .await
+            .map(|meta| meta.len())
+            .unwrap_or(0),
+        None => 0,
+    };
+    Json(UploadStatus { received }).into_response()
 }
 
 #[derive(Clone)]
@@ -370,7 +815,8 @@ impl ReceiveProgressContext {
 #[axum::debug_handler]
 pub(crate) async fn handle_upload(
     State(state_ref): State<Arc<RwLock<ServerState>>>,
-    Query(params): Query<UploadParams>,
+    Query(params): Query<UploadQuery>,
+    headers: HeaderMap,
     body: Body,
 ) -> Response {
     let mut state = state_ref.write().await;
@@ -433,6 +879,8 @@ pub(crate) async fn handle_upload(
                         path: receipt.path().clone(),
                         size: receipt.size(),
                         sender_alias,
+                        // The gate owns these bytes; nothing was hashed here.
+                        verified: false,
                         message_text: None,
                     })
                     .await;
@@ -448,6 +896,8 @@ pub(crate) async fn handle_upload(
     // Verify session
     let (
         file_name,
+        save_name,
+        save_dir,
         session_id,
         sender_alias,
         declared_size,
@@ -457,16 +907,7 @@ pub(crate) async fn handle_upload(
         total_bytes,
         file_count,
         receive_rate_limit_bytes_per_second,
-    ) = if let Some(session) = &state.current_session {
-        if session.id != params.session_id {
-            tracing::warn!(
-                "Upload rejected: Session ID mismatch. Expected {}, got {}",
-                session.id,
-                params.session_id
-            );
-            return StatusCode::FORBIDDEN.into_response();
-        }
-
+    ) = if let Some(session) = state.sessions.get(&params.session_id) {
         // Verify token against the session's random per-file token (R6) --
         // never re-derive it, only compare against what was issued.
         if !session.verify_token(&params.file_id, &params.token) {
@@ -478,6 +919,14 @@ pub(crate) async fn handle_upload(
         if let Some(meta) = session.files.get(&params.file_id) {
             (
                 meta.file_name.clone(),
+                session
+                    .save_name(&params.file_id)
+                    .unwrap_or(&meta.file_name)
+                    .to_string(),
+                session
+                    .save_dir
+                    .clone()
+                    .unwrap_or_else(|| state.save_dir.clone()),
                 session.id.clone(),
                 session.sender_alias.clone(),
                 meta.size,
@@ -500,11 +949,85 @@ pub(crate) async fn handle_upload(
             return StatusCode::NOT_FOUND.into_response();
         }
     } else {
-        tracing::warn!("Upload rejected: No active session");
+        tracing::warn!("Upload rejected: No session {}", params.session_id);
         return StatusCode::FORBIDDEN.into_response();
     };
 
-    let save_path = match crate::core::unique_save_path(&state.save_dir, &file_name) {
+    // Only a partial this receiver kept can be resumed. It leaves the session
+    // once nothing but opening the file can turn this attempt away, and goes
+    // back if this attempt is cut off as well; a fresh start (no offset)
+    // discards it.
+    let offset = params.offset.unwrap_or(0);
+    let has_partial = state
+        .sessions
+        .get(&session_id)
+        .is_some_and(|session| session.partials.contains_key(&params.file_id));
+    if offset > 0 && !has_partial {
+        tracing::warn!(
+            "Upload rejected: offset {} but no partial kept for {}",
+            offset,
+            params.file_id
+        );
+        return StatusCode::CONFLICT.into_response();
+    }
+
+    if state.strict_content_length
+        && let Err(status) = check_content_length(&headers, declared_size.saturating_sub(offset))
+    {
+        return status.into_response();
+    }
+
+    // The layout folder sits under the save directory; the mirror copy keeps
+    // the same relative path.
+    let file_dir =
+        match state
+            .save_layout
+            .dir_for(&save_dir, &sender_alias, state.clock.wall_time())
+        {
+            Ok(dir) => dir,
+            Err(e) => {
+                tracing::warn!("Upload rejected: {}", e);
+                return StatusCode::BAD_REQUEST.into_response();
+            }
+        };
+
+    // Under SkipIdentical, remember the same-named file this upload would
+    // otherwise be renamed around; it is compared once the bytes are in.
+    let memory = state.memory_receiver.clone().zip(
+        state
+            .sessions
+            .get(&session_id)
+            .and_then(|session| session.files.get(&params.file_id))
+            .cloned(),
+    );
+
+    // Encrypted copies never match a fresh upload byte for byte, so
+    // encryption at rest turns the duplicate check off.
+    #[cfg(feature = "encryption")]
+    let at_rest = state.at_rest.clone().filter(|_| memory.is_none());
+    // Without the feature nothing is ever encrypted; the type keeps the
+    // `Some` arms below compiled out.
+    #[cfg(not(feature = "encryption"))]
+    let at_rest: Option<std::convert::Infallible> = None;
+
+    let existing = (memory.is_none()
+        && at_rest.is_none()
+        && state.duplicate_policy == DuplicatePolicy::SkipIdentical)
+        .then(|| crate::path_safety::safe_join(&file_dir, &save_name).ok())
+        .flatten()
+        .filter(|path| path.is_file());
+
+    #[cfg(feature = "encryption")]
+    let stored_name = match &at_rest {
+        Some(_) => format!(
+            "{save_name}.{}",
+            crate::crypto::at_rest::ENCRYPTED_EXTENSION
+        ),
+        None => save_name.clone(),
+    };
+    #[cfg(not(feature = "encryption"))]
+    let stored_name = save_name.clone();
+    let mut save_path = match crate::core::unique_save_path(&file_dir, &stored_name) {
         Ok(path) => path,
         Err(e) => {
             tracing::warn!("Upload rejected: {}", e);
@@ -512,16 +1035,91 @@ pub(crate) async fn handle_upload(
         }
     };
 
+    let idle_timeout = Some(state.upload_idle_timeout);
+    let activity_clock = state.clock.clone();
+    let mirror_dir = state.mirror_dir.clone();
+    let history = state.history.clone();
+    let resumable = state.resumable_uploads && memory.is_none() && at_rest.is_none();
+    let modified_time = if state.preserve_modified_time {
+        state
+            .sessions
+            .get(&session_id)
+            .and_then(|session| session.files.get(&params.file_id))
+            .and_then(|meta| meta.metadata.as_ref()?.modified.as_deref())
+            .and_then(crate::core::file::parse_timestamp)
+    } else {
+        None
+    };
+
+    let partial = state
+        .sessions
+        .get_mut(&session_id)
+        .and_then(|session| session.partials.remove(&params.file_id));
     // Release the lock before async I/O operations
     drop(state);
 
-    // Ensure parent directory exists (async)
-    if let Some(parent) = save_path.parent()
-        && let Err(e) = tokio::fs::create_dir_all(parent).await
-    {
-        tracing::error!("Failed to create directory {:?}: {}", parent, e);
-        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
-    }
+    let resume_from = match partial {
+        Some(partial) if offset == 0 => {
+            let _ = tokio::fs::remove_file(&partial).await;
+            None
+        }
+        partial => partial,
+    };
+
+    // In memory mode nothing touches the disk; otherwise open the save path.
+    let mut sink = if memory.is_some() {
+        UploadSink::Memory(Vec::new())
+    } else {
+        if let Some(parent) = save_path.parent()
+            && let Err(e) = crate::core::ensure_dir(parent).await
+        {
+            tracing::error!("{}", e);
+            restore_partial(&state_ref, &session_id, &params.file_id, resume_from).await;
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+        let file = match crate::core::file::create_unique_file(&file_dir, &stored_name).await {
+            Ok((path, file)) => {
+                save_path = path;
+                file
+            }
+            Err(e) => {
+                tracing::error!("Failed to create {:?}: {}", save_path, e);
+                restore_partial(&state_ref, &session_id, &params.file_id, resume_from).await;
+                return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+            }
+        };
+        let file = match resume_from {
+            Some(partial) => {
+                drop(file);
+                match reopen_partial(&partial, &save_path, offset).await {
+                    Ok(file) => file,
+                    Err(status) => {
+                        let _ = tokio::fs::remove_file(&save_path).await;
+                        // Still there when it could not be moved into place.
+                        let partial = tokio::fs::try_exists(&partial)
+                            .await
+                            .unwrap_or(false)
+                            .then_some(partial);
+                        restore_partial(&state_ref, &session_id, &params.file_id, partial).await;
+                        return status.into_response();
+                    }
+                }
+            }
+            None => file,
+        };
+        match &at_rest {
+            #[cfg(feature = "encryption")]
+            Some(at_rest) => match at_rest.writer(file) {
+                Ok(writer) => UploadSink::Encrypted(Box::new(writer)),
+                Err(e) => {
+                    tracing::error!("{}", e);
+                    let _ = tokio::fs::remove_file(&save_path).await;
+                    return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+                }
+            },
+            _ => UploadSink::File(file),
+        }
+    };
 
     let progress = ReceiveProgressContext {
         session_id: session_id.clone(),
@@ -534,26 +1132,103 @@ pub(crate) async fn handle_upload(
         received_bytes,
     };
     let callback_progress = progress.clone();
-    let file_reported = Arc::new(AtomicU64::new(0));
+    // A resumed upload counts the bytes already kept from the start.
+    progress.add(offset);
+    let file_reported = Arc::new(AtomicU64::new(offset));
     let callback_file_reported = file_reported.clone();
-    let mut previous_file_bytes = 0_u64;
-    let body_len = match write_body_to_file_with_progress(
-        body,
-        &save_path,
-        receive_rate_limit_bytes_per_second,
-        move |file_bytes| {
-            let delta = file_bytes.saturating_sub(previous_file_bytes);
-            previous_file_bytes = file_bytes;
-            callback_file_reported.store(file_bytes, Ordering::Relaxed);
-            callback_progress.add(delta);
-        },
-    )
-    .await
-    {
-        Ok(bytes_written) => bytes_written,
+    let mut previous_file_bytes = offset;
+    // A body that streams for longer than the session TTL must keep its
+    // session (and the files still to come in it) from being swept.
+    let activity_state = state_ref.clone();
+    let activity_session_id = session_id.clone();
+    let mut last_touch: Option<std::time::Instant> = None;
+    let on_progress = move |file_bytes: u64| {
+        let file_bytes = offset.saturating_add(file_bytes);
+        let delta = file_bytes.saturating_sub(previous_file_bytes);
+        previous_file_bytes = file_bytes;
+        callback_file_reported.store(file_bytes, Ordering::Relaxed);
+        callback_progress.add(delta);
+
+        let now = activity_clock.now();
+        if last_touch.is_some_and(|at| now.saturating_duration_since(at) < SESSION_TOUCH_INTERVAL) {
+            return;
+        }
+        // Never wait for the lock from inside the body loop; a chunk that
+        // finds it taken just leaves the touch to the next one.
+        if let Ok(mut state) = activity_state.try_write() {
+            if let Some(session) = state.sessions.get_mut(&activity_session_id) {
+                session.touch();
+            }
+            last_touch = Some(now);
+        }
+    };
+    let written = if is_multipart(&headers) {
+        write_multipart_with_progress(
+            body,
+            headers,
+            &mut sink,
+            receive_rate_limit_bytes_per_second,
+            idle_timeout,
+            Some(declared_size.saturating_sub(offset)),
+            on_progress,
+        )
+        .await
+    } else {
+        write_body_with_progress(
+            body,
+            &mut sink,
+            receive_rate_limit_bytes_per_second,
+            idle_timeout,
+            Some(declared_size.saturating_sub(offset)),
+            on_progress,
+        )
+        .await
+    };
+    let body_len = match written {
+        Ok(bytes_written) => offset.saturating_add(bytes_written),
+        Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+            progress.rollback(file_reported.load(Ordering::Relaxed));
+            tracing::warn!("Upload of {:?} stalled ({}); aborting", save_path, e);
+            // A stall is what resuming is for: keep the bytes and the session,
+            // which the sweep closes if the sender never comes back.
+            if resumable {
+                keep_partial(&state_ref, &session_id, &params.file_id, &save_path).await;
+                return StatusCode::REQUEST_TIMEOUT.into_response();
+            }
+            // A stalled sender would otherwise hold the session forever:
+            // discard the partial and close the session so a new one can open.
+            let _ = tokio::fs::remove_file(&save_path).await;
+            let mut state = state_ref.write().await;
+            if state.sessions.remove(&session_id).is_some() {
+                let _ = state
+                    .events_tx
+                    .try_send(ServerEvent::SessionDone { session_id });
+            }
+            return StatusCode::REQUEST_TIMEOUT.into_response();
+        }
+        // A write that fails part way (the sender hung up, the disk filled)
+        // must not leave a truncated file behind.
+        Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+            progress.rollback(file_reported.load(Ordering::Relaxed));
+            tracing::warn!("Malformed multipart upload for {:?}: {}", save_path, e);
+            let _ = tokio::fs::remove_file(&save_path).await;
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+        // Cut off at the declared size, before the excess reaches the disk.
+        Err(e) if e.kind() == std::io::ErrorKind::FileTooLarge => {
+            progress.rollback(file_reported.load(Ordering::Relaxed));
+            tracing::warn!("Upload of {:?} is larger than declared: {}", save_path, e);
+            let _ = tokio::fs::remove_file(&save_path).await;
+            return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+        }
         Err(e) => {
             progress.rollback(file_reported.load(Ordering::Relaxed));
             tracing::error!("Failed to save file to {:?}: {}", save_path, e);
+            if resumable {
+                keep_partial(&state_ref, &session_id, &params.file_id, &save_path).await;
+            } else {
+                let _ = tokio::fs::remove_file(&save_path).await;
+            }
             return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
@@ -574,16 +1249,26 @@ pub(crate) async fn handle_upload(
             declared_size,
             body_len
         );
-        let _ = tokio::fs::remove_file(&save_path).await;
+        if resumable {
+            keep_partial(&state_ref, &session_id, &params.file_id, &save_path).await;
+        } else {
+            let _ = tokio::fs::remove_file(&save_path).await;
+        }
         return StatusCode::INTERNAL_SERVER_ERROR.into_response();
     }
 
     // When the sender advertised a sha256, verify the bytes on disk match it
     // (case-insensitive hex). Size can be right while the contents are
     // corrupt; reject those the same way.
-    if let Some(expected_sha) = declared_sha {
-        match crate::sha256_from_file(&save_path).await {
-            Ok(actual) if actual.eq_ignore_ascii_case(&expected_sha) => {}
+    if let Some(expected_sha) = &declared_sha {
+        let computed = match &sink {
+            UploadSink::Memory(bytes) => Ok(crate::sha256_from_bytes(bytes)),
+            UploadSink::File(_) => crate::sha256_from_file(&save_path).await,
+            #[cfg(feature = "encryption")]
+            UploadSink::Encrypted(writer) => Ok(writer.plaintext_sha256()),
+        };
+        match computed {
+            Ok(actual) if actual.eq_ignore_ascii_case(expected_sha) => {}
             Ok(actual) => {
                 progress.rollback(body_len);
                 tracing::warn!(
@@ -604,7 +1289,82 @@ pub(crate) async fn handle_upload(
         }
     }
 
-    tracing::info!("Received file: {:?} for session {}", save_path, session_id);
+    #[cfg(feature = "encryption")]
+    if let UploadSink::Encrypted(writer) = &sink
+        && let Err(e) = writer.write_manifest(&save_path, &file_name).await
+    {
+        progress.rollback(body_len);
+        tracing::error!("Failed to write the manifest for {:?}: {}", save_path, e);
+        let _ = tokio::fs::remove_file(&save_path).await;
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    }
+
+    // Flushed first, so no write still in flight bumps the time again.
+    if let Some(modified) = modified_time
+        && !matches!(sink, UploadSink::Memory(_))
+    {
+        if let Err(e) = tokio::io::AsyncWriteExt::flush(&mut sink).await {
+            tracing::warn!("Failed to flush {:?}: {}", save_path, e);
+        }
+        set_modified_time(&save_path, modified).await;
+    }
+
+    let duplicate_of = match existing {
+        Some(existing) if same_contents(&existing, &save_path, declared_sha.as_deref()).await => {
+            tracing::info!(
+                "Upload {:?} is identical to {:?}; discarding the new copy",
+                save_path,
+                existing
+            );
+            let _ = tokio::fs::remove_file(&save_path).await;
+            Some(existing)
+        }
+        _ => None,
+    };
+    let save_path = match (memory, sink) {
+        (Some((receiver, metadata)), UploadSink::Memory(bytes)) => {
+            tracing::info!(
+                "Received {:?} ({} bytes) into memory for session {}",
+                file_name,
+                body_len,
+                session_id
+            );
+            receiver(metadata, bytes);
+            PathBuf::new()
+        }
+        _ => {
+            if duplicate_of.is_none() {
+                tracing::info!("Received file: {:?} for session {}", save_path, session_id);
+                if let Some(mirror_dir) = mirror_dir {
+                    tokio::spawn(mirror_received(save_dir, mirror_dir, save_path.clone()));
+                }
+                if let Some(history) = history {
+                    let entry = crate::server::history::HistoryEntry {
+                        file_name: save_path
+                            .file_name()
+                            .map(|n| n.to_string_lossy().into_owned())
+                            .unwrap_or_else(|| file_name.clone()),
+                        size: body_len,
+                        sender_alias: sender_alias.clone(),
+                        received_at: chrono::Utc::now(),
+                        sha256: declared_sha.clone(),
+                        path: save_path.clone(),
+                    };
+                    // Awaited, so the entry is on disk before the sender
+                    // hears back; a failure is logged, the file is still saved.
+                    if let Err(e) = history.append(&entry).await {
+                        tracing::warn!(
+                            "Failed to record {:?} in {:?}: {}",
+                            save_path,
+                            history.path(),
+                            e
+                        );
+                    }
+                }
+            }
+            save_path
+        }
+    };
 
     // Reacquire lock for state updates
     let mut state = state_ref.write().await;
@@ -616,19 +1376,15 @@ pub(crate) async fn handle_upload(
     // upload must never be recorded against a different session's
     // accounting, since a foreign file id could otherwise push an unrelated
     // session to "all done".
-    let still_current = state
-        .current_session
-        .as_ref()
-        .map(|session| session.id == session_id)
-        .unwrap_or(false);
+    let still_current = state.sessions.contains_key(&session_id);
 
     // Record this file as received on the (still-current) session; a
     // multi-file transfer only closes once every accepted file has arrived,
     // not after the first one (R5).
     let all_done = if still_current {
         state
-            .current_session
-            .as_mut()
+            .sessions
+            .get_mut(&session_id)
             .map(|session| session.mark_received(&params.file_id))
             .unwrap_or(false)
     } else {
@@ -647,28 +1403,39 @@ pub(crate) async fn handle_upload(
     // *final* on-disk name -- unique_save_path may have renamed the file on
     // collision, and a consumer needs to see where the bytes actually went,
     // not the name originally requested by the sender.
-    let final_file_name = save_path
-        .file_name()
-        .map(|n| n.to_string_lossy().into_owned())
-        .unwrap_or(file_name);
-    let _ = state
-        .events_tx
-        .try_send(crate::server::events::ServerEvent::FileReceived {
+    let event = match duplicate_of {
+        Some(path) => ServerEvent::FileSkipped {
+            session_id: session_id.clone(),
+            file_id: params.file_id.clone(),
+            file_name,
+            path,
+            size: body_len,
+            sender_alias,
+        },
+        None => ServerEvent::FileReceived {
             session_id: session_id.clone(),
             file_id: params.file_id.clone(),
-            file_name: final_file_name,
+            file_name: save_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or(file_name),
             path: save_path,
             size: body_len,
             sender_alias,
+            verified: declared_sha.is_some(),
             // A real binary upload has no inline text body.
             message_text: None,
-        });
+        },
+    };
+    let _ = state.events_tx.try_send(event);
 
     if still_current && all_done {
         let _ = state
             .events_tx
-            .try_send(crate::server::events::ServerEvent::SessionDone { session_id });
-        state.current_session = None;
+            .try_send(crate::server::events::ServerEvent::SessionDone {
+                session_id: session_id.clone(),
+            });
+        state.sessions.remove(&session_id);
     }
 
     StatusCode::OK.into_response()
@@ -677,7 +1444,7 @@ pub(crate) async fn handle_upload(
 #[derive(Deserialize)]
 pub(crate) struct CancelParams {
     #[serde(rename = "sessionId")]
-    session_id: SessionId,
+    pub(crate) session_id: SessionId,
 }
 
 pub(crate) async fn handle_cancel(
@@ -714,16 +1481,15 @@ pub(crate) async fn handle_cancel(
         return StatusCode::OK.into_response();
     }
 
-    if let Some(session) = &state.current_session
-        && session.id.as_str() == params.session_id.as_str()
-    {
+    if let Some(session) = state.sessions.remove(&params.session_id) {
         let _ = state
             .events_tx
             .try_send(crate::server::events::ServerEvent::SessionDone {
                 session_id: params.session_id.clone(),
             });
-        state.current_session = None;
         tracing::info!("Session {} cancelled", params.session_id);
+        drop(state);
+        remove_partials(session).await;
     }
 
     StatusCode::OK.into_response()
@@ -0,0 +1,150 @@
+//! Durable, append-only record of received files.
+
+use crate::error::LocalSendError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+pub type Result<T> = std::result::Result<T, LocalSendError>;
+
+/// The index of received files a receiver keeps in its save directory when
+/// asked to (`receive --index`, `tui --index`).
+pub const INDEX_FILE_NAME: &str = ".localsend-received.jsonl";
+
+/// One line of the history file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    /// Final on-disk name, after any collision rename.
+    pub file_name: String,
+    pub size: u64,
+    pub sender_alias: String,
+    pub received_at: DateTime<Utc>,
+    /// As declared by the sender (and verified) when it sent one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    pub path: PathBuf,
+}
+
+/// A JSON-lines history file. Every [`append`](Self::append) is its own
+/// write, synced to disk before it returns: a receiver that is killed right
+/// after a transfer still has that transfer on record.
+#[derive(Debug)]
+pub struct ReceiveHistory {
+    path: PathBuf,
+    // Serialises appends, so two uploads finishing together never interleave.
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl ReceiveHistory {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            write_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Add `entry` as a line of its own. A torn tail left by a crash is
+    /// ended first, so the new line does not run on from it.
+    pub async fn append(&self, entry: &HistoryEntry) -> Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+
+        let _guard = self.write_lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        if file.metadata().await?.len() > 0 {
+            file.seek(std::io::SeekFrom::End(-1)).await?;
+            if file.read_u8().await? != b'\n' {
+                line.insert(0, b'\n');
+            }
+        }
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    /// Every entry on record, oldest first. A missing file is an empty
+    /// history; a line that does not parse (the torn tail of a write cut off
+    /// by a crash) is skipped.
+    pub async fn load(&self) -> Result<Vec<HistoryEntry>> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => return Err(error.into()),
+        };
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(error) => {
+                    tracing::debug!(
+                        "Skipping unreadable history line in {:?}: {error}",
+                        self.path
+                    );
+                    None
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HistoryEntry, ReceiveHistory};
+
+    fn entry(name: &str) -> HistoryEntry {
+        HistoryEntry {
+            file_name: name.into(),
+            size: 3,
+            sender_alias: "peer".into(),
+            received_at: chrono::Utc::now(),
+            sha256: None,
+            path: name.into(),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_torn_last_line_does_not_hide_earlier_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = ReceiveHistory::new(dir.path().join("history.jsonl"));
+        assert!(history.load().await.unwrap().is_empty());
+
+        history.append(&entry("a.txt")).await.unwrap();
+        history.append(&entry("b.txt")).await.unwrap();
+        // A crash halfway through a third write.
+        let mut contents = std::fs::read(history.path()).unwrap();
+        contents.extend_from_slice(br#"{"fileName":"c.t"#);
+        std::fs::write(history.path(), contents).unwrap();
+
+        let names: Vec<_> = history
+            .load()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.file_name)
+            .collect();
+        assert_eq!(names, ["a.txt", "b.txt"]);
+
+        // The next entry is not glued onto the torn line.
+        history.append(&entry("d.txt")).await.unwrap();
+        let names: Vec<_> = history
+            .load()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.file_name)
+            .collect();
+        assert_eq!(names, ["a.txt", "b.txt", "d.txt"]);
+    }
+}
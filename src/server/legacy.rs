@@ -0,0 +1,205 @@
+//! The LocalSend v1 API (`/api/localsend/v1/...`), for senders that never
+//! moved to v2. Off unless [`crate::server::LocalSendServerBuilder::enable_v1`]
+//! is set, in which case each request runs through the v2 session flow:
+//!
+//! - `info` and `register` answer with this device's v2 info; v1 peers read
+//!   `alias`, `deviceModel` and `deviceType` from it and ignore the rest.
+//! - `send-request` is v2's `prepare-upload`. The sender's `info` carries no
+//!   `version`, `port` or `protocol`; it is taken as version `1.0` on the
+//!   default port over HTTP. A missing `fingerprint` is left empty, so an
+//!   allow-list never matches it. Each file's `fileType` is a coarse kind
+//!   (`image`, `video`, `pdf`, `text`, `apk`, `other`) rather than a MIME
+//!   type, and is replaced by the type guessed from `fileName`.
+//! - The answer is the bare `{fileId: token}` map; v1 has no session id.
+//! - `send?fileId=&token=` goes to whichever session issued that token.
+//! - `cancel` has no parameters and ends the v1 sessions opened from the
+//!   caller's address.
+
+use super::handlers::{
+    CancelParams, PrepareUploadParams, handle_cancel, handle_info, handle_standard_prepare_upload,
+    handle_upload,
+};
+use super::state::ServerState;
+use crate::protocol::{
+    DEFAULT_HTTP_PORT, DeviceInfo, DeviceType, FileId, FileMetadata, PrepareUploadRequest,
+    PrepareUploadResponse, Protocol, Token, UploadQuery,
+};
+use axum::{
+    Json,
+    body::Body,
+    extract::{ConnectInfo, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// The protocol version a v1 sender is recorded with.
+const V1_VERSION: &str = "1.0";
+
+/// A v1 `info` DTO: no version, port or protocol, and (in early releases)
+/// no fingerprint.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LegacyDeviceInfo {
+    alias: String,
+    #[serde(default)]
+    device_model: Option<String>,
+    #[serde(default)]
+    device_type: Option<DeviceType>,
+    #[serde(default)]
+    fingerprint: Option<String>,
+}
+
+impl From<LegacyDeviceInfo> for DeviceInfo {
+    fn from(info: LegacyDeviceInfo) -> Self {
+        DeviceInfo {
+            alias: info.alias,
+            version: V1_VERSION.to_string(),
+            device_model: info.device_model,
+            device_type: info.device_type,
+            fingerprint: info.fingerprint.unwrap_or_default(),
+            port: DEFAULT_HTTP_PORT,
+            protocol: Protocol::Http,
+            download: false,
+            ip: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LegacySendRequest {
+    info: LegacyDeviceInfo,
+    files: HashMap<FileId, FileMetadata>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct LegacySendParams {
+    #[serde(rename = "fileId")]
+    file_id: FileId,
+    token: Token,
+}
+
+pub(crate) async fn handle_v1_info(State(state_ref): State<Arc<RwLock<ServerState>>>) -> Response {
+    if !state_ref.read().await.enable_v1 {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    handle_info(State(state_ref)).await
+}
+
+pub(crate) async fn handle_v1_register(
+    State(state_ref): State<Arc<RwLock<ServerState>>>,
+    Json(remote_device): Json<LegacyDeviceInfo>,
+) -> Response {
+    let state = state_ref.read().await;
+    if !state.enable_v1 {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    tracing::debug!("v1 register request from {:?}", remote_device.alias);
+    Json(state.device.clone()).into_response()
+}
+
+pub(crate) async fn handle_v1_send_request(
+    State(state_ref): State<Arc<RwLock<ServerState>>>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+    Query(params): Query<PrepareUploadParams>,
+    Json(request): Json<LegacySendRequest>,
+) -> Response {
+    if !state_ref.read().await.enable_v1 {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let files = request
+        .files
+        .into_iter()
+        .map(|(id, mut file)| {
+            file.file_type = crate::core::get_mime_type(std::path::Path::new(&file.file_name));
+            (id, file)
+        })
+        .collect();
+    let request = PrepareUploadRequest {
+        info: request.info.into(),
+        files,
+    };
+
+    let response = handle_standard_prepare_upload(state_ref.clone(), peer, params, request).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let Ok(body) = axum::body::to_bytes(response.into_body(), usize::MAX).await else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let Ok(prepared) = serde_json::from_slice::<PrepareUploadResponse>(&body) else {
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+
+    let mut state = state_ref.write().await;
+    let ServerState {
+        sessions,
+        legacy_sessions,
+        ..
+    } = &mut *state;
+    legacy_sessions.retain(|session_id, _| sessions.contains_key(session_id));
+    legacy_sessions.insert(prepared.session_id, peer.ip());
+    Json(prepared.files).into_response()
+}
+
+pub(crate) async fn handle_v1_send(
+    State(state_ref): State<Arc<RwLock<ServerState>>>,
+    Query(params): Query<LegacySendParams>,
+    headers: HeaderMap,
+    body: Body,
+) -> Response {
+    let session_id = {
+        let state = state_ref.read().await;
+        if !state.enable_v1 {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        let issued_by = state
+            .legacy_sessions
+            .keys()
+            .filter_map(|session_id| state.sessions.get(session_id))
+            .find(|session| session.verify_token(&params.file_id, &params.token));
+        match issued_by {
+            Some(session) => session.id.clone(),
+            None => {
+                tracing::warn!("v1 upload rejected: no session issued that token");
+                return StatusCode::FORBIDDEN.into_response();
+            }
+        }
+    };
+    let query = UploadQuery {
+        session_id,
+        file_id: params.file_id,
+        token: params.token,
+        offset: None,
+    };
+    handle_upload(State(state_ref), Query(query), headers, body).await
+}
+
+pub(crate) async fn handle_v1_cancel(
+    State(state_ref): State<Arc<RwLock<ServerState>>>,
+    ConnectInfo(peer): ConnectInfo<std::net::SocketAddr>,
+) -> Response {
+    let cancelled: Vec<_> = {
+        let mut state = state_ref.write().await;
+        if !state.enable_v1 {
+            return StatusCode::NOT_FOUND.into_response();
+        }
+        let from_peer: Vec<_> = state
+            .legacy_sessions
+            .iter()
+            .filter(|(_, ip)| **ip == peer.ip())
+            .map(|(session_id, _)| session_id.clone())
+            .collect();
+        for session_id in &from_peer {
+            state.legacy_sessions.remove(session_id);
+        }
+        from_peer
+    };
+    for session_id in cancelled {
+        handle_cancel(State(state_ref.clone()), Query(CancelParams { session_id })).await;
+    }
+    StatusCode::OK.into_response()
+}
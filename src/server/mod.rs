@@ -2,10 +2,12 @@
 
 pub mod crosscopy_authorized;
 pub mod events;
+pub mod history;
 pub mod server;
 pub mod web_share;
 
 pub(crate) mod handlers;
+pub(crate) mod legacy;
 pub(crate) mod pin;
 pub(crate) mod routes;
 pub(crate) mod state;
@@ -16,6 +18,14 @@ pub use crosscopy_authorized::{
     CrossCopyAuthorizedUploadError, CrossCopyAuthorizedUploadGate, CrossCopyAuthorizedUploadOwner,
     CrossCopyAuthorizedUploadReceipt,
 };
-pub use events::{PendingRequest, PendingWebShareRequest, ServerEvent, TransferDecision};
-pub use server::{LocalSendServer, LocalSendServerBuilder};
+pub use events::{
+    PendingRequest, PendingWebShareRequest, ReceiveSummary, RejectionReason, ServerEvent,
+    TransferDecision, TransferRejection,
+};
+pub use history::{HistoryEntry, INDEX_FILE_NAME, ReceiveHistory};
+pub use server::{
+    DEFAULT_SESSION_TIMEOUT, DEFAULT_UPLOAD_IDLE_TIMEOUT, DEFAULT_WEB_CLIENT_ORIGIN,
+    DuplicatePolicy, LocalSendServer, LocalSendServerBuilder, MIN_SESSION_TIMEOUT, MemoryReceiver,
+    SaveLayout,
+};
 pub use web_share::{WebShareFile, WebShareSource};
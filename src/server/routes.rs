@@ -1,24 +1,35 @@
 use super::CROSSCOPY_FILE_V3_HANDOFF_HEADER;
 use super::handlers::{
     handle_cancel, handle_info, handle_prepare_upload, handle_register, handle_upload,
+    handle_upload_status,
+};
+use super::legacy::{
+    handle_v1_cancel, handle_v1_info, handle_v1_register, handle_v1_send, handle_v1_send_request,
 };
 use super::state::ServerState;
 use super::web_share::{
-    handle_download, handle_prepare_download, handle_web_i18n, handle_web_index, handle_web_js,
+    handle_download, handle_prepare_download, handle_shared_files, handle_web_i18n,
+    handle_web_index, handle_web_js,
 };
 use axum::{
     Router,
     extract::Request,
-    http::StatusCode,
+    http::{Method, StatusCode, header},
     middleware::{self, Next},
     response::{IntoResponse, Response},
     routing::{get, post},
 };
 use std::sync::Arc;
 use tokio::sync::RwLock;
+use tower_http::cors::{AllowOrigin, CorsLayer};
 
-pub(crate) fn create_router(state: Arc<RwLock<ServerState>>) -> Router {
-    Router::new()
+/// The API's routes; `web_client_origins` are the browser origins answered
+/// with CORS headers, none for no CORS at all.
+pub(crate) fn create_router(
+    state: Arc<RwLock<ServerState>>,
+    web_client_origins: &[String],
+) -> Router {
+    let router = Router::new()
         .route("/api/localsend/v2/info", get(handle_info))
         .route("/api/localsend/v2/register", post(handle_register))
         .route(
@@ -26,12 +37,23 @@ pub(crate) fn create_router(state: Arc<RwLock<ServerState>>) -> Router {
             post(handle_prepare_upload),
         )
         .route("/api/localsend/v2/upload", post(handle_upload))
+        .route("/api/localsend/v2/upload-status", get(handle_upload_status))
         .route("/api/localsend/v2/cancel", post(handle_cancel))
         .route(
             "/api/localsend/v2/prepare-download",
             post(handle_prepare_download),
         )
         .route("/api/localsend/v2/download", get(handle_download))
+        .route("/api/localsend/v2/shared", get(handle_shared_files))
+        // Legacy v1, answered only with `enable_v1`.
+        .route("/api/localsend/v1/info", get(handle_v1_info))
+        .route("/api/localsend/v1/register", post(handle_v1_register))
+        .route(
+            "/api/localsend/v1/send-request",
+            post(handle_v1_send_request),
+        )
+        .route("/api/localsend/v1/send", post(handle_v1_send))
+        .route("/api/localsend/v1/cancel", post(handle_v1_cancel))
         .route("/", get(handle_web_index))
         .route("/main.js", get(handle_web_js))
         .route("/i18n.json", get(handle_web_i18n))
@@ -39,7 +61,27 @@ pub(crate) fn create_router(state: Arc<RwLock<ServerState>>) -> Router {
         // prepare route.  Never let another LocalSend handler silently ignore
         // it and fall back into standard behavior.
         .layer(middleware::from_fn(reject_crosscopy_header_on_other_routes))
-        .with_state(state)
+        .with_state(state);
+    if web_client_origins.is_empty() {
+        return router;
+    }
+    let allow_origin = if web_client_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        // The builder has checked that every origin parses.
+        AllowOrigin::list(
+            web_client_origins
+                .iter()
+                .filter_map(|origin| origin.parse().ok()),
+        )
+    };
+    // Outermost, so preflights are answered before any other layer.
+    router.layer(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers([header::CONTENT_TYPE]),
+    )
 }
 
 async fn reject_crosscopy_header_on_other_routes(request: Request, next: Next) -> Response {
@@ -1,7 +1,9 @@
 use super::events::ServerEvent;
 use super::state::ServerState;
+use crate::core::clock::{Clock, SystemClock};
 use crate::protocol::{DeviceInfo, Protocol};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
@@ -12,6 +14,71 @@ use tokio::task::JoinHandle;
 #[cfg(feature = "https")]
 use axum_server::tls_rustls::RustlsConfig;
 
+/// How long an upload body may go without delivering a byte before the
+/// receiver aborts it and frees the session.
+pub const DEFAULT_UPLOAD_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long a session may sit idle before the receiver drops it and takes
+/// offers from other senders again.
+pub const DEFAULT_SESSION_TIMEOUT: Duration = Duration::from_secs(300);
+/// Where the official LocalSend web client is served from; the origin
+/// [`LocalSendServerBuilder::web_client`] lets in.
+pub const DEFAULT_WEB_CLIENT_ORIGIN: &str = "https://web.localsend.org";
+
+/// The shortest session timeout the builder takes.
+pub const MIN_SESSION_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Consumer of uploads received in memory; see
+/// [`LocalSendServerBuilder::receive_into_memory`].
+pub type MemoryReceiver = Arc<dyn Fn(crate::protocol::FileMetadata, Vec<u8>) + Send + Sync>;
+
+/// How the receiver treats an upload whose file name already exists in the
+/// save directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicatePolicy {
+    /// Keep both, saving the new one as `name (1).ext`.
+    #[default]
+    Rename,
+    /// Compare SHA-256 digests (the sender's declared `sha256` when present,
+    /// otherwise a hash of the received bytes); when they match, discard the
+    /// new copy and report [`ServerEvent::FileSkipped`]. Differing contents
+    /// still fall back to renaming.
+    SkipIdentical,
+}
+
+/// Where under the save directory received files go.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SaveLayout {
+    /// Straight into the save directory.
+    #[default]
+    Flat,
+    /// Into a folder named after the sender's alias, made safe to use as a
+    /// directory name.
+    BySender,
+    /// Into a folder for the local date the file arrived, `YYYY-MM-DD`.
+    ByDate,
+}
+
+impl SaveLayout {
+    /// The directory an upload from `sender_alias` arriving at `now` is
+    /// saved in.
+    pub(crate) fn dir_for(
+        self,
+        save_dir: &Path,
+        sender_alias: &str,
+        now: std::time::SystemTime,
+    ) -> crate::Result<PathBuf> {
+        let folder = match self {
+            Self::Flat => return Ok(save_dir.to_path_buf()),
+            Self::BySender => crate::path_safety::alias_dir_name(sender_alias),
+            Self::ByDate => chrono::DateTime::<chrono::Local>::from(now)
+                .format("%Y-%m-%d")
+                .to_string(),
+        };
+        crate::path_safety::safe_join(save_dir, &folder)
+    }
+}
+
 pub struct LocalSendServer {
     device: DeviceInfo,
     save_dir: PathBuf,
@@ -27,10 +94,45 @@ pub struct LocalSendServer {
     auto_accept: Arc<AtomicBool>,
     accept_timeout: Duration,
     receive_rate_limit_bytes_per_second: Option<u64>,
+    upload_idle_timeout: Duration,
+    session_timeout: Duration,
+    max_sessions: usize,
+    /// Reject uploads whose `Content-Length` is missing or disagrees with the
+    /// size declared in prepare-upload.
+    strict_content_length: bool,
+    duplicate_policy: DuplicatePolicy,
+    save_layout: SaveLayout,
+    /// Keep interrupted uploads for the sender to resume.
+    resumable_uploads: bool,
+    /// Set each received file's modified time to the one the sender declared.
+    preserve_modified_time: bool,
+    /// Also answer the legacy v1 API.
+    enable_v1: bool,
+    /// Send CORS headers so browser pages from these origins can call the
+    /// API; none turns CORS off.
+    web_client_origins: Vec<String>,
+    /// Stay discoverable but turn away every standard prepare-upload.
+    receive_disabled: bool,
+    /// Largest total size of a transfer's files that is considered.
+    max_total_size: Option<u64>,
+    /// Sender fingerprints allowed to start a transfer; `None` allows all.
+    allowed_fingerprints: Option<HashSet<String>>,
+    blocked_fingerprints: HashSet<String>,
+    auto_decline: bool,
+    /// Transfers declaring fewer total bytes than this are auto-accepted.
+    auto_accept_below: Option<u64>,
+    /// Second directory every received file is copied into.
+    mirror_dir: Option<PathBuf>,
+    history: Option<Arc<super::history::ReceiveHistory>>,
+    memory_receiver: Option<MemoryReceiver>,
+    #[cfg(feature = "encryption")]
+    at_rest: Option<Arc<crate::crypto::AtRestEncryption>>,
     /// Receiver-side PIN, enforced by `pin::PinGate` in the request handler.
     pin: Option<String>,
     crosscopy_authorized_upload_gate:
         Option<Arc<dyn super::crosscopy_authorized::CrossCopyAuthorizedUploadGate>>,
+    /// Time source for session expiry.
+    clock: Arc<dyn Clock>,
     state: Option<Arc<RwLock<ServerState>>>,
 }
 
@@ -58,8 +160,30 @@ impl LocalSendServer {
             auto_accept: Arc::new(AtomicBool::new(auto_accept)),
             accept_timeout,
             receive_rate_limit_bytes_per_second,
+            upload_idle_timeout: DEFAULT_UPLOAD_IDLE_TIMEOUT,
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+            max_sessions: 1,
+            strict_content_length: false,
+            duplicate_policy: DuplicatePolicy::default(),
+            save_layout: SaveLayout::default(),
+            resumable_uploads: false,
+            preserve_modified_time: false,
+            enable_v1: false,
+            web_client_origins: Vec::new(),
+            receive_disabled: false,
+            max_total_size: None,
+            allowed_fingerprints: None,
+            blocked_fingerprints: HashSet::new(),
+            auto_decline: false,
+            auto_accept_below: None,
+            mirror_dir: None,
+            history: None,
+            memory_receiver: None,
+            #[cfg(feature = "encryption")]
+            at_rest: None,
             pin,
             crosscopy_authorized_upload_gate: None,
+            clock: Arc::new(SystemClock),
             state: None,
         })
     }
@@ -81,11 +205,35 @@ impl LocalSendServer {
             port: crate::protocol::DEFAULT_HTTP_PORT,
             save_dir: PathBuf::from("./downloads"),
             protocol: Protocol::Http,
+            device_model: None,
             pin: None,
             auto_accept: false,
             accept_timeout: Duration::from_secs(60),
             receive_rate_limit_bytes_per_second: None,
+            upload_idle_timeout: DEFAULT_UPLOAD_IDLE_TIMEOUT,
+            session_timeout: DEFAULT_SESSION_TIMEOUT,
+            max_sessions: 1,
+            strict_content_length: false,
+            duplicate_policy: DuplicatePolicy::default(),
+            save_layout: SaveLayout::default(),
+            resumable_uploads: false,
+            preserve_modified_time: false,
+            enable_v1: false,
+            web_client_origins: Vec::new(),
+            receive_disabled: false,
+            max_total_size: None,
+            allowed_fingerprints: None,
+            blocked_fingerprints: HashSet::new(),
+            auto_decline: false,
+            auto_accept_below: None,
+            mirror_dir: None,
+            history: None,
+            memory_receiver: None,
+            #[cfg(feature = "encryption")]
+            at_rest: None,
             crosscopy_authorized_upload_gate: None,
+            clock: None,
+            fingerprint: None,
             #[cfg(feature = "https")]
             tls_certificate: None,
         }
@@ -114,19 +262,115 @@ impl LocalSendServer {
         self.crosscopy_authorized_upload_gate = gate;
     }
 
+    /// Serve `cert` from the next [`start`](Self::start) on. Over HTTPS the
+    /// advertised fingerprint becomes the certificate's.
     #[cfg(feature = "https")]
     pub fn set_tls_certificate(&mut self, cert: crate::crypto::TlsCertificate) {
+        if self.device.protocol == Protocol::Https {
+            self.device.fingerprint = cert.fingerprint.clone();
+        }
         self.tls_cert = Some(cert);
     }
 
+    /// [`set_tls_certificate`](Self::set_tls_certificate) with a PEM
+    /// certificate and key kept from an earlier run, for a fingerprint that
+    /// stays the same across restarts. See
+    /// [`TlsCertificate::from_pem`](crate::crypto::TlsCertificate::from_pem).
+    #[cfg(feature = "https")]
+    pub fn set_tls_certificate_from_pem(
+        &mut self,
+        cert_pem: impl Into<String>,
+        key_pem: impl Into<String>,
+    ) -> crate::Result<()> {
+        let cert = crate::crypto::TlsCertificate::from_pem(cert_pem, key_pem)?;
+        self.set_tls_certificate(cert);
+        Ok(())
+    }
+
+    /// Fresh per-listener state; called once the real port is known.
+    fn new_state(&self, events_tx: mpsc::Sender<ServerEvent>) -> ServerState {
+        ServerState {
+            device: self.device.clone(),
+            sessions: HashMap::new(),
+            save_dir: self.save_dir.clone(),
+            events_tx,
+            auto_accept: self.auto_accept.clone(),
+            accept_timeout: self.accept_timeout,
+            receive_rate_limit_bytes_per_second: self.receive_rate_limit_bytes_per_second,
+            upload_idle_timeout: self.upload_idle_timeout,
+            session_timeout: self.session_timeout,
+            max_sessions: self.max_sessions,
+            strict_content_length: self.strict_content_length,
+            duplicate_policy: self.duplicate_policy,
+            save_layout: self.save_layout,
+            resumable_uploads: self.resumable_uploads,
+            preserve_modified_time: self.preserve_modified_time,
+            enable_v1: self.enable_v1,
+            legacy_sessions: HashMap::new(),
+            receive_disabled: self.receive_disabled,
+            max_total_size: self.max_total_size,
+            allowed_fingerprints: self.allowed_fingerprints.clone(),
+            blocked_fingerprints: self.blocked_fingerprints.clone(),
+            auto_decline: self.auto_decline,
+            auto_accept_below: self.auto_accept_below,
+            mirror_dir: self.mirror_dir.clone(),
+            history: self.history.clone(),
+            memory_receiver: self.memory_receiver.clone(),
+            #[cfg(feature = "encryption")]
+            at_rest: self.at_rest.clone(),
+            pin_gate: crate::server::pin::PinGate::new(self.pin.clone()),
+            clock: self.clock.clone(),
+            web_share: None,
+            crosscopy_authorized_upload_gate: self.crosscopy_authorized_upload_gate.clone(),
+            crosscopy_authorized_session: None,
+            crosscopy_authorized_active_upload: None,
+            crosscopy_authorized_stopping: false,
+        }
+    }
+
     pub async fn start(&mut self) -> std::result::Result<(), crate::error::LocalSendError> {
         let (events_tx, events_rx) = mpsc::channel(64);
         self.events_rx = Some(events_rx);
+        self.listen(events_tx).await
+    }
 
-        let addr = format!("0.0.0.0:{}", self.device.port);
-        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
-        self.shutdown_tx = Some(shutdown_tx);
+    /// Move the listener to `port` (`0` for an OS-assigned one). Identity,
+    /// settings and the event channel carry over, so the receiver returned by
+    /// `build()` keeps working; [`Self::port`] and `/info` report the new
+    /// port. The new port is bound before the old listener stops, so when
+    /// that fails the server keeps serving where it was. An open session or
+    /// web share does not survive the restart.
+    pub async fn restart(&mut self, port: u16) -> crate::Result<()> {
+        let events_tx = match &self.state {
+            Some(state) => state.read().await.events_tx.clone(),
+            None => {
+                let (events_tx, events_rx) = mpsc::channel(64);
+                self.events_rx = Some(events_rx);
+                events_tx
+            }
+        };
+        // The old listener holds the port it is asked to move to; only then
+        // does it have to go first.
+        let same_port = port != 0 && port == self.device.port && self.handle.is_some();
+        if same_port {
+            self.stop().await;
+        }
+        let listener = self.bind(port).await?;
+        if !same_port {
+            self.stop().await;
+        }
+        self.serve(listener, events_tx)
+    }
+
+    async fn listen(&mut self, events_tx: mpsc::Sender<ServerEvent>) -> crate::Result<()> {
+        let listener = self.bind(self.device.port).await?;
+        self.serve(listener, events_tx)
+    }
 
+    /// Bind `port` and, for HTTPS, load the certificate: everything that may
+    /// fail before a listener is running.
+    async fn bind(&self, port: u16) -> crate::Result<BoundListener> {
+        let addr = format!("0.0.0.0:{port}");
         if self.https {
             #[cfg(feature = "https")]
             {
@@ -150,30 +394,40 @@ impl LocalSendServer {
                             ))
                         })?;
 
-                // Bind before spawn so the real (possibly OS-assigned) port is
-                // known before the ServerState/router are built.
                 let std_listener = std::net::TcpListener::bind(&addr)?;
                 std_listener.set_nonblocking(true)?;
+                Ok(BoundListener::Https(std_listener, tls_config))
+            }
+            #[cfg(not(feature = "https"))]
+            {
+                Err(crate::error::LocalSendError::network(
+                    "HTTPS support not enabled. Please build with --features https",
+                ))
+            }
+        } else {
+            Ok(BoundListener::Http(TcpListener::bind(&addr).await?))
+        }
+    }
+
+    fn serve(
+        &mut self,
+        listener: BoundListener,
+        events_tx: mpsc::Sender<ServerEvent>,
+    ) -> crate::Result<()> {
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        self.shutdown_tx = Some(shutdown_tx);
+
+        match listener {
+            #[cfg(feature = "https")]
+            BoundListener::Https(std_listener, tls_config) => {
+                // Bound before spawn so the real (possibly OS-assigned) port
+                // is known before the ServerState/router are built.
                 let bound_port = std_listener.local_addr()?.port();
                 self.device.port = bound_port;
 
-                let state = Arc::new(RwLock::new(ServerState {
-                    device: self.device.clone(),
-                    current_session: None,
-                    save_dir: self.save_dir.clone(),
-                    events_tx,
-                    auto_accept: self.auto_accept.clone(),
-                    accept_timeout: self.accept_timeout,
-                    receive_rate_limit_bytes_per_second: self.receive_rate_limit_bytes_per_second,
-                    pin_gate: crate::server::pin::PinGate::new(self.pin.clone()),
-                    web_share: None,
-                    crosscopy_authorized_upload_gate: self.crosscopy_authorized_upload_gate.clone(),
-                    crosscopy_authorized_session: None,
-                    crosscopy_authorized_active_upload: None,
-                    crosscopy_authorized_stopping: false,
-                }));
+                let state = Arc::new(RwLock::new(self.new_state(events_tx)));
                 self.state = Some(state.clone());
-                let router = super::routes::create_router(state.clone());
+                let router = super::routes::create_router(state.clone(), &self.web_client_origins);
 
                 let server = axum_server::from_tcp_rustls(std_listener, tls_config)
                     .map_err(|e| {
@@ -203,55 +457,35 @@ impl LocalSendServer {
                 self.sweep_handle = Some(spawn_session_sweep(state));
                 Ok(())
             }
-            #[cfg(not(feature = "https"))]
-            {
-                Err(crate::error::LocalSendError::network(
-                    "HTTPS support not enabled. Please build with --features https",
-                ))
-            }
-        } else {
-            // Bind before spawn so the real (possibly OS-assigned) port is
-            // known before the ServerState/router are built.
-            let listener = TcpListener::bind(&addr).await?;
-            let bound_port = listener.local_addr()?.port();
-            self.device.port = bound_port;
-            tracing::info!("Starting HTTP server on port {}", bound_port);
-
-            let state = Arc::new(RwLock::new(ServerState {
-                device: self.device.clone(),
-                current_session: None,
-                save_dir: self.save_dir.clone(),
-                events_tx,
-                auto_accept: self.auto_accept.clone(),
-                accept_timeout: self.accept_timeout,
-                receive_rate_limit_bytes_per_second: self.receive_rate_limit_bytes_per_second,
-                pin_gate: crate::server::pin::PinGate::new(self.pin.clone()),
-                web_share: None,
-                crosscopy_authorized_upload_gate: self.crosscopy_authorized_upload_gate.clone(),
-                crosscopy_authorized_session: None,
-                crosscopy_authorized_active_upload: None,
-                crosscopy_authorized_stopping: false,
-            }));
-            self.state = Some(state.clone());
-            let router = super::routes::create_router(state.clone());
-
-            let handle = tokio::spawn(async move {
-                let server = axum::serve(
-                    listener,
-                    router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
-                )
-                .with_graceful_shutdown(async {
-                    let _ = shutdown_rx.await;
-                });
+            BoundListener::Http(listener) => {
+                // Bound before spawn so the real (possibly OS-assigned) port
+                // is known before the ServerState/router are built.
+                let bound_port = listener.local_addr()?.port();
+                self.device.port = bound_port;
+                tracing::info!("Starting HTTP server on port {}", bound_port);
 
-                if let Err(e) = server.await {
-                    tracing::error!("HTTP server error: {}", e);
-                }
-            });
+                let state = Arc::new(RwLock::new(self.new_state(events_tx)));
+                self.state = Some(state.clone());
+                let router = super::routes::create_router(state.clone(), &self.web_client_origins);
 
-            self.handle = Some(handle);
-            self.sweep_handle = Some(spawn_session_sweep(state));
-            Ok(())
+                let handle = tokio::spawn(async move {
+                    let server = axum::serve(
+                        listener,
+                        router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+                    )
+                    .with_graceful_shutdown(async {
+                        let _ = shutdown_rx.await;
+                    });
+
+                    if let Err(e) = server.await {
+                        tracing::error!("HTTP server error: {}", e);
+                    }
+                });
+
+                self.handle = Some(handle);
+                self.sweep_handle = Some(spawn_session_sweep(state));
+                Ok(())
+            }
         }
     }
 
@@ -294,6 +528,9 @@ impl LocalSendServer {
         }
         if let Some(handle) = self.handle.take() {
             handle.abort();
+            // Wait for the task to drop its listener, so the port is free
+            // again by the time `stop` returns.
+            let _ = handle.await;
         }
     }
 
@@ -323,6 +560,31 @@ impl LocalSendServer {
         Ok(())
     }
 
+    /// Host `files` for pull under the ids given, for a headless node: a
+    /// [`Self::start_web_share`] with no PIN that approves every peer's
+    /// prepare-download. Each file is offered under its own file name.
+    pub async fn register_shared_files(
+        &mut self,
+        files: HashMap<crate::protocol::FileId, PathBuf>,
+    ) -> crate::Result<()> {
+        let mut shared = Vec::with_capacity(files.len());
+        for (id, path) in files {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .ok_or_else(|| {
+                    crate::error::LocalSendError::invalid_file(format!(
+                        "Not a file to share: {}",
+                        path.display()
+                    ))
+                })?;
+            let mut file = super::web_share::WebShareFile::path(path, name).await?;
+            file.metadata.id = id;
+            shared.push(file);
+        }
+        self.start_web_share(shared, None, true).await
+    }
+
     pub async fn stop_web_share(&mut self) -> crate::Result<()> {
         let state = self
             .state
@@ -361,24 +623,46 @@ impl LocalSendServer {
     }
 }
 
-/// Every 60s, reclaim a session that's been idle past its 300s TTL (R5: a
-/// sender that vanishes mid-transfer must not permanently wedge the single
-/// upload slot). The lock is only held for the duration of the check itself
-/// -- no `.await` happens while it's held.
+/// A listener bound by [`LocalSendServer::bind`], not yet serving.
+enum BoundListener {
+    Http(TcpListener),
+    #[cfg(feature = "https")]
+    Https(std::net::TcpListener, RustlsConfig),
+}
+
+/// Reclaim a session that's been idle past the session timeout (R5: a
+/// sender that vanishes mid-transfer must not permanently hold one
+/// of the session slots), checking twice per timeout and at most once a minute. The
+/// lock is only held for the duration of the check itself -- no `.await`
+/// happens while it's held.
 fn spawn_session_sweep(state: Arc<RwLock<ServerState>>) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let mut tick = tokio::time::interval(Duration::from_secs(60));
+        let timeout = state.read().await.session_timeout;
+        let mut tick = tokio::time::interval(
+            (timeout / 2).clamp(Duration::from_secs(1), Duration::from_secs(60)),
+        );
         loop {
             tick.tick().await;
-            let protected_owner = {
+            let (expired, protected_owner) = {
                 let mut s = state.write().await;
-                if let Some(session) = &s.current_session
-                    && session.is_timed_out(300)
-                {
-                    tracing::info!("Sweeping timed-out session {}", session.id);
-                    s.current_session = None;
+                let expired_ids: Vec<_> = s
+                    .sessions
+                    .values()
+                    .filter(|session| session.is_idle_for(timeout))
+                    .map(|session| session.id.clone())
+                    .collect();
+                let mut expired = Vec::with_capacity(expired_ids.len());
+                for id in expired_ids {
+                    if let Some(session) = s.sessions.remove(&id) {
+                        tracing::info!("Sweeping timed-out session {}", session.id);
+                        let _ = s.events_tx.try_send(ServerEvent::SessionDone {
+                            session_id: session.id.clone(),
+                        });
+                        expired.push(session);
+                    }
                 }
-                if s.crosscopy_authorized_session
+                let protected_owner = if s
+                    .crosscopy_authorized_session
                     .as_ref()
                     .is_some_and(|session| session.is_timed_out(300))
                 {
@@ -387,8 +671,12 @@ fn spawn_session_sweep(state: Arc<RwLock<ServerState>>) -> JoinHandle<()> {
                         .map(|session| session.owner)
                 } else {
                     None
-                }
+                };
+                (expired, protected_owner)
             };
+            for session in expired {
+                super::handlers::remove_partials(session).await;
+            }
             if let Some(owner) = protected_owner {
                 owner.cancel().await;
             }
@@ -407,12 +695,36 @@ pub struct LocalSendServerBuilder {
     port: u16,
     save_dir: PathBuf,
     protocol: Protocol,
+    device_model: Option<String>,
     pin: Option<String>,
     auto_accept: bool,
     accept_timeout: Duration,
     receive_rate_limit_bytes_per_second: Option<u64>,
+    upload_idle_timeout: Duration,
+    session_timeout: Duration,
+    max_sessions: usize,
+    strict_content_length: bool,
+    duplicate_policy: DuplicatePolicy,
+    save_layout: SaveLayout,
+    resumable_uploads: bool,
+    preserve_modified_time: bool,
+    enable_v1: bool,
+    web_client_origins: Vec<String>,
+    receive_disabled: bool,
+    max_total_size: Option<u64>,
+    allowed_fingerprints: Option<HashSet<String>>,
+    blocked_fingerprints: HashSet<String>,
+    auto_decline: bool,
+    auto_accept_below: Option<u64>,
+    mirror_dir: Option<PathBuf>,
+    history: Option<Arc<super::history::ReceiveHistory>>,
+    memory_receiver: Option<MemoryReceiver>,
+    #[cfg(feature = "encryption")]
+    at_rest: Option<Arc<crate::crypto::AtRestEncryption>>,
     crosscopy_authorized_upload_gate:
         Option<Arc<dyn super::crosscopy_authorized::CrossCopyAuthorizedUploadGate>>,
+    clock: Option<Arc<dyn Clock>>,
+    fingerprint: Option<String>,
     #[cfg(feature = "https")]
     tls_certificate: Option<crate::crypto::TlsCertificate>,
 }
@@ -438,6 +750,16 @@ impl LocalSendServerBuilder {
         self
     }
 
+    /// Advertise `model` in `/info` and announcements instead of the bare OS
+    /// name from [`crate::core::device::get_device_model`].
+    pub fn device_model(mut self, model: impl Into<String>) -> Self {
+        self.device_model = Some(model.into());
+        self
+    }
+
+    /// Require senders to pass this PIN to prepare-upload. A missing or wrong
+    /// PIN is answered with 401 before any accept prompt, and repeated
+    /// failures from one address are locked out with 429.
     pub fn pin(mut self, pin: impl Into<String>) -> Self {
         self.pin = Some(pin.into());
         self
@@ -461,6 +783,228 @@ impl LocalSendServerBuilder {
         self
     }
 
+    /// Abort an upload whose body delivers no data for this long, discarding
+    /// the partial file and closing its session (under
+    /// [`Self::resumable_uploads`], keeping both for the sender to resume).
+    /// Defaults to [`DEFAULT_UPLOAD_IDLE_TIMEOUT`].
+    pub fn upload_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.upload_idle_timeout = timeout;
+        self
+    }
+
+    /// Drop a session that has been idle for longer than `timeout`, with any
+    /// partials it kept for resuming, so a sender that vanished does not
+    /// hold the receiver. A body still streaming keeps its session alive.
+    /// Defaults to [`DEFAULT_SESSION_TIMEOUT`]; a timeout under
+    /// [`MIN_SESSION_TIMEOUT`] (zero included) is raised to it, leaving the
+    /// sender time to start uploading once accepted. It is also never shorter
+    /// than [`Self::accept_timeout`], which an offer waiting on the user
+    /// spends holding its session slot.
+    pub fn session_timeout(mut self, timeout: Duration) -> Self {
+        self.session_timeout = timeout.max(MIN_SESSION_TIMEOUT);
+        self
+    }
+
+    /// Let up to `max` senders hold a session at once, each uploading under
+    /// its own session id; one more is answered `409 Conflict`. Defaults to
+    /// 1, a single transfer at a time. Clamped to at least 1.
+    pub fn max_sessions(mut self, max: usize) -> Self {
+        self.max_sessions = max.max(1);
+        self
+    }
+
+    /// Require every upload to carry a `Content-Length`, and for raw bodies
+    /// to match the size declared in prepare-upload, before anything is
+    /// written. Without it a chunked body is read until the sender stops.
+    pub fn strict_content_length(mut self, yes: bool) -> Self {
+        self.strict_content_length = yes;
+        self
+    }
+
+    /// What to do when an upload's name is already taken in the save
+    /// directory. Defaults to [`DuplicatePolicy::Rename`].
+    pub fn duplicate_policy(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = policy;
+        self
+    }
+
+    /// Which subdirectory of the save directory each file lands in.
+    /// Defaults to [`SaveLayout::Flat`].
+    pub fn save_layout(mut self, layout: SaveLayout) -> Self {
+        self.save_layout = layout;
+        self
+    }
+
+    /// Keep an upload that is cut off part way, as `<name>.lspart`, instead
+    /// of deleting it. The sender can ask `upload-status` how much arrived
+    /// and send the rest with an `offset` (see
+    /// [`crate::LocalSendClient::resume_file`]). A partial still held when
+    /// its session is cancelled or times out (see [`Self::session_timeout`])
+    /// is deleted. Uploads received into memory or encrypted at rest
+    /// are never kept.
+    pub fn resumable_uploads(mut self, yes: bool) -> Self {
+        self.resumable_uploads = yes;
+        self
+    }
+
+    /// Give each received file the modified time the sender put in its
+    /// metadata, instead of the time it arrived. A missing or unreadable
+    /// timestamp leaves the file as written.
+    pub fn preserve_modified_time(mut self, yes: bool) -> Self {
+        self.preserve_modified_time = yes;
+        self
+    }
+
+    /// Also accept transfers from senders on the LocalSend v1 API
+    /// (`/api/localsend/v1/...`), translated into the v2 session flow. A v1
+    /// sender is recorded as version 1.0 over HTTP on the default port, and
+    /// its coarse `fileType` gives way to the type guessed from the file
+    /// name. Off by default, when the v1 routes answer 404.
+    pub fn enable_v1(mut self, yes: bool) -> Self {
+        self.enable_v1 = yes;
+        self
+    }
+
+    /// Answer cross-origin requests (CORS, including `OPTIONS` preflights)
+    /// from [`DEFAULT_WEB_CLIENT_ORIGIN`], so the LocalSend web client can
+    /// reach the API; `false` turns CORS off again, whatever origins were
+    /// allowed. Off by default, when a browser on another origin cannot read
+    /// the responses.
+    ///
+    /// Every page served from an allowed origin can then offer files from
+    /// the user's browser, unasked; with [`Self::auto_accept`] they land in
+    /// the save directory. Allow only origins you trust.
+    pub fn web_client(mut self, yes: bool) -> Self {
+        if !yes {
+            self.web_client_origins.clear();
+        } else if !self
+            .web_client_origins
+            .iter()
+            .any(|origin| origin == DEFAULT_WEB_CLIENT_ORIGIN)
+        {
+            self.web_client_origins
+                .push(DEFAULT_WEB_CLIENT_ORIGIN.to_string());
+        }
+        self
+    }
+
+    /// Answer cross-origin requests from these origins too, such as a
+    /// self-hosted web client (`http://192.168.1.20:8080`). `*` allows every
+    /// origin, so any web page the user visits can offer files; see
+    /// [`Self::web_client`]. An origin that is not a valid header value
+    /// fails [`Self::build`]. Calls add up.
+    pub fn web_client_origins<I, S>(mut self, origins: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.web_client_origins
+            .extend(origins.into_iter().map(Into::into));
+        self
+    }
+
+    /// Keep answering `/info` and `/register` (so peers can still find this
+    /// device and it can send), but refuse every prepare-upload with 403 and
+    /// never raise a [`ServerEvent::TransferRequest`].
+    pub fn receive_disabled(mut self, yes: bool) -> Self {
+        self.receive_disabled = yes;
+        self
+    }
+
+    /// Refuse, with 403, a transfer whose files add up to more than `bytes`,
+    /// before raising a [`ServerEvent::TransferRequest`]. Every upload is
+    /// held to the size declared for it regardless: a body running past it
+    /// is cut off with 413 and discarded.
+    pub fn max_total_size(mut self, bytes: u64) -> Self {
+        self.max_total_size = Some(bytes);
+        self
+    }
+
+    /// Only senders with one of these fingerprints may start a transfer;
+    /// any other prepare-upload is refused with 403 before a PIN check or a
+    /// [`ServerEvent::TransferRequest`]. Calls add up.
+    pub fn allowed_fingerprints<I, S>(mut self, fingerprints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_fingerprints
+            .get_or_insert_with(HashSet::new)
+            .extend(fingerprints.into_iter().map(Into::into));
+        self
+    }
+
+    /// Refuse prepare-uploads from senders with any of these fingerprints,
+    /// like a sender missing from [`allowed_fingerprints`](Self::allowed_fingerprints).
+    /// Calls add up.
+    pub fn blocked_fingerprints<I, S>(mut self, fingerprints: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.blocked_fingerprints
+            .extend(fingerprints.into_iter().map(Into::into));
+        self
+    }
+
+    /// For unattended receivers: a transfer that is not auto-accepted is
+    /// declined with 403 straight away, instead of raising a
+    /// [`ServerEvent::TransferRequest`] and waiting out the accept timeout.
+    pub fn auto_decline(mut self, yes: bool) -> Self {
+        self.auto_decline = yes;
+        self
+    }
+
+    /// Auto-accept transfers whose files add up to fewer than `bytes`, and
+    /// handle larger ones as usual: a [`ServerEvent::TransferRequest`], or a
+    /// refusal under [`auto_decline`](Self::auto_decline). Has no effect
+    /// while [`auto_accept`](Self::auto_accept) accepts everything anyway.
+    pub fn auto_accept_below(mut self, bytes: u64) -> Self {
+        self.auto_accept_below = Some(bytes);
+        self
+    }
+
+    /// Copy every received file into `dir` as well, at the same path
+    /// relative to the save directory. The copy runs in the background once
+    /// the upload has been answered; a failed copy is logged and never fails
+    /// the transfer.
+    pub fn mirror_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.mirror_dir = Some(dir.into());
+        self
+    }
+
+    /// Record every received file in a [`ReceiveHistory`] at `path`. Each
+    /// entry is synced to disk before the upload is answered.
+    ///
+    /// [`ReceiveHistory`]: super::history::ReceiveHistory
+    pub fn history_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.history = Some(Arc::new(super::history::ReceiveHistory::new(path)));
+        self
+    }
+
+    /// Deliver each completed upload to `receiver` as its metadata and bytes
+    /// instead of writing it under the save directory. Size and `sha256` are
+    /// verified first, and `FileReceived` still fires, with an empty `path`.
+    /// The whole file is buffered, so this suits message-sized payloads.
+    pub fn receive_into_memory(
+        mut self,
+        receiver: impl Fn(crate::protocol::FileMetadata, Vec<u8>) + Send + Sync + 'static,
+    ) -> Self {
+        self.memory_receiver = Some(Arc::new(receiver));
+        self
+    }
+
+    /// Encrypt every file written under the save directory with a key
+    /// derived from `passphrase`. Files are saved as `<name>.lsenc` with a
+    /// `<name>.lsenc.json` manifest beside them; recover the plaintext with
+    /// [`crate::decrypt_file`]. Duplicate detection is skipped, since the
+    /// stored bytes never match a fresh upload.
+    #[cfg(feature = "encryption")]
+    pub fn encrypt_at_rest(mut self, passphrase: impl Into<String>) -> Self {
+        self.at_rest = Some(Arc::new(crate::crypto::AtRestEncryption::new(passphrase)));
+        self
+    }
+
     /// Enable the optional CrossCopy File-v3 receiver mode on this existing
     /// listener.  This does not create a second socket or discovery identity.
     /// Omitting the hook preserves normal LocalSend behavior and rejects the
@@ -473,6 +1017,22 @@ impl LocalSendServerBuilder {
         self
     }
 
+    /// Measure session idleness on `clock` instead of the system clock, so
+    /// tests can expire a session with [`crate::core::MockClock::advance`].
+    /// [`SaveLayout::ByDate`] folders are dated by its wall time too.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    /// Advertise this fingerprint over HTTP instead of a fresh random one,
+    /// e.g. an [`Identity`](crate::crypto::Identity)'s. Over HTTPS the
+    /// fingerprint is always the certificate's, and this is ignored.
+    pub fn fingerprint(mut self, fingerprint: impl Into<String>) -> Self {
+        self.fingerprint = Some(fingerprint.into());
+        self
+    }
+
     #[cfg(feature = "https")]
     pub fn tls_certificate(mut self, certificate: crate::crypto::TlsCertificate) -> Self {
         self.tls_certificate = Some(certificate);
@@ -504,16 +1064,21 @@ impl LocalSendServerBuilder {
             if let Some(ref cert) = tls_cert {
                 cert.fingerprint.clone()
             } else {
-                crate::crypto::generate_fingerprint()
+                self.fingerprint
+                    .unwrap_or_else(crate::crypto::generate_fingerprint)
             }
             #[cfg(not(feature = "https"))]
-            crate::crypto::generate_fingerprint()
+            self.fingerprint
+                .unwrap_or_else(crate::crypto::generate_fingerprint)
         };
 
         let device = DeviceInfo {
             alias: self.alias,
-            version: crate::protocol::PROTOCOL_VERSION.to_string(),
-            device_model: Some(crate::core::device::get_device_model()),
+            version: crate::protocol::advertised_version(),
+            device_model: Some(
+                self.device_model
+                    .unwrap_or_else(crate::core::device::get_device_model),
+            ),
             device_type: Some(crate::core::device::get_device_type()),
             fingerprint,
             port: self.port,
@@ -532,6 +1097,43 @@ impl LocalSendServerBuilder {
             self.receive_rate_limit_bytes_per_second,
         )?;
         server.set_crosscopy_authorized_upload_gate(self.crosscopy_authorized_upload_gate);
+        server.upload_idle_timeout = self.upload_idle_timeout;
+        // A prompt holds its session slot while it waits, so the sweep must
+        // not reclaim the slot before the prompt itself could time out.
+        server.session_timeout = self.session_timeout.max(self.accept_timeout);
+        server.max_sessions = self.max_sessions;
+        server.strict_content_length = self.strict_content_length;
+        server.duplicate_policy = self.duplicate_policy;
+        server.save_layout = self.save_layout;
+        server.resumable_uploads = self.resumable_uploads;
+        server.preserve_modified_time = self.preserve_modified_time;
+        server.enable_v1 = self.enable_v1;
+        if let Some(origin) = self
+            .web_client_origins
+            .iter()
+            .find(|origin| axum::http::HeaderValue::from_str(origin).is_err())
+        {
+            return Err(crate::error::LocalSendError::invalid_state(format!(
+                "Invalid web client origin {origin:?}"
+            )));
+        }
+        server.web_client_origins = self.web_client_origins;
+        server.receive_disabled = self.receive_disabled;
+        server.max_total_size = self.max_total_size;
+        server.allowed_fingerprints = self.allowed_fingerprints;
+        server.blocked_fingerprints = self.blocked_fingerprints;
+        server.auto_decline = self.auto_decline;
+        server.auto_accept_below = self.auto_accept_below;
+        server.mirror_dir = self.mirror_dir;
+        server.history = self.history;
+        server.memory_receiver = self.memory_receiver;
+        #[cfg(feature = "encryption")]
+        {
+            server.at_rest = self.at_rest;
+        }
+        if let Some(clock) = self.clock {
+            server.clock = clock;
+        }
         #[cfg(feature = "https")]
         if let Some(cert) = tls_cert {
             server.set_tls_certificate(cert);
@@ -5,15 +5,21 @@ use crate::server::crosscopy_authorized::{
 };
 use axum::body::Body;
 use futures_util::StreamExt;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::sync::atomic::AtomicBool;
-use tokio::io::AsyncWriteExt;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
 use tokio_util::sync::CancellationToken;
 
 pub struct ServerState {
     pub device: DeviceInfo,
-    pub current_session: Option<crate::core::Session>,
+    /// Open sessions by id; a placeholder holds a slot while its prepare
+    /// waits for the accept decision.
+    pub sessions: std::collections::HashMap<SessionId, crate::core::Session>,
+    /// See [`crate::server::LocalSendServerBuilder::max_sessions`].
+    pub max_sessions: usize,
     pub save_dir: PathBuf,
     pub events_tx: tokio::sync::mpsc::Sender<crate::server::events::ServerEvent>,
     /// Shared with [`crate::server::LocalSendServer`] so a live
@@ -21,13 +27,54 @@ pub struct ServerState {
     pub auto_accept: Arc<AtomicBool>,
     pub accept_timeout: std::time::Duration,
     pub receive_rate_limit_bytes_per_second: Option<u64>,
+    /// See [`crate::server::LocalSendServerBuilder::upload_idle_timeout`].
+    pub upload_idle_timeout: std::time::Duration,
+    /// See [`crate::server::LocalSendServerBuilder::session_timeout`].
+    pub session_timeout: std::time::Duration,
+    /// See [`crate::server::LocalSendServerBuilder::strict_content_length`].
+    pub strict_content_length: bool,
+    pub duplicate_policy: crate::server::server::DuplicatePolicy,
+    /// See [`crate::server::LocalSendServerBuilder::resumable_uploads`].
+    pub resumable_uploads: bool,
+    /// See [`crate::server::LocalSendServerBuilder::preserve_modified_time`].
+    pub preserve_modified_time: bool,
+    /// See [`crate::server::LocalSendServerBuilder::save_layout`].
+    pub save_layout: crate::server::server::SaveLayout,
+    /// See [`crate::server::LocalSendServerBuilder::enable_v1`].
+    pub enable_v1: bool,
+    /// Sessions opened over the v1 API, with the address that opened them:
+    /// a v1 `cancel` names no session.
+    pub legacy_sessions: std::collections::HashMap<SessionId, std::net::IpAddr>,
+    /// See [`crate::server::LocalSendServerBuilder::receive_disabled`].
+    pub receive_disabled: bool,
+    /// See [`crate::server::LocalSendServerBuilder::max_total_size`].
+    pub max_total_size: Option<u64>,
+    /// See [`crate::server::LocalSendServerBuilder::allowed_fingerprints`].
+    pub allowed_fingerprints: Option<std::collections::HashSet<String>>,
+    /// See [`crate::server::LocalSendServerBuilder::blocked_fingerprints`].
+    pub blocked_fingerprints: std::collections::HashSet<String>,
+    /// See [`crate::server::LocalSendServerBuilder::auto_decline`].
+    pub auto_decline: bool,
+    /// See [`crate::server::LocalSendServerBuilder::auto_accept_below`].
+    pub auto_accept_below: Option<u64>,
+    /// See [`crate::server::LocalSendServerBuilder::mirror_dir`].
+    pub mirror_dir: Option<std::path::PathBuf>,
+    /// See [`crate::server::LocalSendServerBuilder::history_file`].
+    pub history: Option<Arc<crate::server::history::ReceiveHistory>>,
+    /// See [`crate::server::LocalSendServerBuilder::receive_into_memory`].
+    pub memory_receiver: Option<crate::server::server::MemoryReceiver>,
+    /// See [`crate::server::LocalSendServerBuilder::encrypt_at_rest`].
+    #[cfg(feature = "encryption")]
+    pub at_rest: Option<Arc<crate::crypto::AtRestEncryption>>,
     pub pin_gate: crate::server::pin::PinGate,
+    /// Time source for new sessions (and so for the stale-session checks).
+    pub clock: Arc<dyn crate::core::Clock>,
     pub web_share: Option<crate::server::web_share::WebShareState>,
     /// Optional host-owned gate for File-v3 protected prepares.  `None` is the
     /// production-compatible default and makes the reserved header fail closed.
     pub crosscopy_authorized_upload_gate:
         Option<std::sync::Arc<dyn crate::server::CrossCopyAuthorizedUploadGate>>,
-    /// A protected session is deliberately separate from `current_session` so
+    /// A protected session is deliberately separate from `sessions` so
     /// standard and CrossCopy-issued LocalSend tokens can never be confused.
     pub crosscopy_authorized_session: Option<CrossCopyAuthorizedSession>,
     /// An upload whose owner was moved into `receive`. It remains cancellable
@@ -73,25 +120,166 @@ impl CrossCopyAuthorizedSession {
     }
 }
 
-pub(crate) async fn write_body_to_file_with_progress<F>(
+/// Where an upload body goes: the save path, a buffer handed to the
+/// builder's `receive_into_memory` callback, or (with `encrypt_at_rest`) the
+/// save path through an encrypting writer.
+pub(crate) enum UploadSink {
+    File(tokio::fs::File),
+    Memory(Vec<u8>),
+    #[cfg(feature = "encryption")]
+    Encrypted(Box<crate::crypto::at_rest::EncryptingWriter<tokio::fs::File>>),
+}
+
+impl tokio::io::AsyncWrite for UploadSink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Self::File(file) => Pin::new(file).poll_write(cx, buf),
+            Self::Memory(bytes) => Pin::new(bytes).poll_write(cx, buf),
+            #[cfg(feature = "encryption")]
+            Self::Encrypted(writer) => Pin::new(writer.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::File(file) => Pin::new(file).poll_flush(cx),
+            Self::Memory(bytes) => Pin::new(bytes).poll_flush(cx),
+            #[cfg(feature = "encryption")]
+            Self::Encrypted(writer) => Pin::new(writer.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::File(file) => Pin::new(file).poll_shutdown(cx),
+            Self::Memory(bytes) => Pin::new(bytes).poll_shutdown(cx),
+            #[cfg(feature = "encryption")]
+            Self::Encrypted(writer) => Pin::new(writer.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Stream `body` into `sink`. A body that runs past `max_bytes` fails with
+/// [`std::io::ErrorKind::FileTooLarge`] as soon as it does.
+pub(crate) async fn write_body_with_progress<W, F>(
+    body: Body,
+    sink: &mut W,
+    rate_limit_bytes_per_second: Option<u64>,
+    idle_timeout: Option<std::time::Duration>,
+    max_bytes: Option<u64>,
+    progress: F,
+) -> std::io::Result<u64>
+where
+    W: AsyncWrite + Unpin,
+    F: FnMut(u64),
+{
+    write_stream_with_progress(
+        body.into_data_stream(),
+        sink,
+        rate_limit_bytes_per_second,
+        idle_timeout,
+        max_bytes,
+        progress,
+    )
+    .await
+}
+
+/// Save the first file part of a `multipart/form-data` upload, as sent by some
+/// third-party clients instead of LocalSend's raw body. A form without a file
+/// part fails with [`std::io::ErrorKind::InvalidData`]; `max_bytes` caps the
+/// file part as in [`write_body_with_progress`].
+pub(crate) async fn write_multipart_with_progress<W, F>(
     body: Body,
-    path: &Path,
+    headers: axum::http::HeaderMap,
+    sink: &mut W,
     rate_limit_bytes_per_second: Option<u64>,
+    idle_timeout: Option<std::time::Duration>,
+    max_bytes: Option<u64>,
+    progress: F,
+) -> std::io::Result<u64>
+where
+    W: AsyncWrite + Unpin,
+    F: FnMut(u64),
+{
+    let invalid = |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+    // Driven directly rather than through axum's `Multipart` extractor, whose
+    // default body limit would cut off any file over 2 MiB; the size cap here
+    // is `max_bytes`.
+    let boundary = headers
+        .get(axum::http::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| invalid("multipart upload has no content type".to_string()))
+        .and_then(|content_type| {
+            multer::parse_boundary(content_type).map_err(|error| invalid(error.to_string()))
+        })?;
+    let mut multipart = multer::Multipart::new(body.into_data_stream(), boundary);
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|error| invalid(error.to_string()))?
+    {
+        if field.file_name().is_some() || field.name() == Some("file") {
+            return write_stream_with_progress(
+                field,
+                sink,
+                rate_limit_bytes_per_second,
+                idle_timeout,
+                max_bytes,
+                progress,
+            )
+            .await;
+        }
+    }
+    Err(invalid("multipart upload has no file part".to_string()))
+}
+
+async fn write_stream_with_progress<S, E, W, F>(
+    stream: S,
+    sink: &mut W,
+    rate_limit_bytes_per_second: Option<u64>,
+    idle_timeout: Option<std::time::Duration>,
+    max_bytes: Option<u64>,
     mut progress: F,
 ) -> std::io::Result<u64>
 where
+    S: futures_util::Stream<Item = Result<bytes::Bytes, E>>,
+    E: std::fmt::Display,
+    W: AsyncWrite + Unpin,
     F: FnMut(u64),
 {
-    let mut file = tokio::fs::File::create(path).await?;
     let mut bytes_written = 0u64;
-    let mut stream = body.into_data_stream();
+    let mut stream = std::pin::pin!(stream);
     let started_at = tokio::time::Instant::now();
     let rate_limit_bytes_per_second = rate_limit_bytes_per_second.filter(|rate| *rate > 0);
 
-    while let Some(chunk) = stream.next().await {
+    loop {
+        let next = match idle_timeout {
+            Some(idle) => tokio::time::timeout(idle, stream.next())
+                .await
+                .map_err(|_| {
+                    std::io::Error::new(
+                        std::io::ErrorKind::TimedOut,
+                        format!("no upload data for {idle:?}"),
+                    )
+                })?,
+            None => stream.next().await,
+        };
+        let Some(chunk) = next else {
+            break;
+        };
         let chunk = chunk.map_err(|e| std::io::Error::other(e.to_string()))?;
         bytes_written += chunk.len() as u64;
-        file.write_all(&chunk).await?;
+        if let Some(max) = max_bytes.filter(|max| bytes_written > *max) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::FileTooLarge,
+                format!("upload runs past {max} bytes"),
+            ));
+        }
+        sink.write_all(&chunk).await?;
         if let Some(rate) = rate_limit_bytes_per_second {
             let target = std::time::Duration::from_secs_f64(bytes_written as f64 / rate as f64);
             let delay = target.saturating_sub(started_at.elapsed());
@@ -102,13 +290,14 @@ where
         progress(bytes_written);
     }
 
-    file.flush().await?;
+    // Shutdown rather than flush: an encrypting sink seals its last chunk here.
+    sink.shutdown().await?;
     Ok(bytes_written)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::write_body_to_file_with_progress;
+    use super::write_body_with_progress;
     use axum::body::{Body, Bytes};
     use futures_util::stream;
     use std::convert::Infallible;
@@ -121,7 +310,8 @@ mod tests {
         ));
         let body = Body::from("streamed upload content");
 
-        let bytes_written = write_body_to_file_with_progress(body, &path, None, |_| {})
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        let bytes_written = write_body_with_progress(body, &mut file, None, None, None, |_| {})
             .await
             .expect("body should stream to file");
 
@@ -148,11 +338,13 @@ mod tests {
         let body = Body::from_stream(chunks);
         let mut samples = Vec::new();
 
-        let bytes_written = write_body_to_file_with_progress(body, &path, None, |cumulative| {
-            samples.push(cumulative);
-        })
-        .await
-        .expect("body should stream with progress");
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        let bytes_written =
+            write_body_with_progress(body, &mut file, None, None, None, |cumulative| {
+                samples.push(cumulative);
+            })
+            .await
+            .expect("body should stream with progress");
 
         assert_eq!(samples, vec![3, 5, 9]);
         assert_eq!(bytes_written, 9);
@@ -170,9 +362,11 @@ mod tests {
         let body = Body::from(vec![0_u8; 4_096]);
         let started_at = tokio::time::Instant::now();
 
-        let bytes_written = write_body_to_file_with_progress(body, &path, Some(8_192), |_| {})
-            .await
-            .expect("throttled body should stream to file");
+        let mut file = tokio::fs::File::create(&path).await.unwrap();
+        let bytes_written =
+            write_body_with_progress(body, &mut file, Some(8_192), None, None, |_| {})
+                .await
+                .expect("throttled body should stream to file");
 
         assert_eq!(bytes_written, 4_096);
         assert!(started_at.elapsed() >= std::time::Duration::from_millis(450));
@@ -213,6 +213,39 @@ fn prepare_response(
     .into_response()
 }
 
+#[derive(Deserialize)]
+pub(crate) struct SharedFilesQuery {
+    pin: Option<String>,
+}
+
+/// `GET /api/localsend/v2/shared`: the files on offer while download mode is
+/// on, sorted by name, so a peer can look before it asks to download. Guarded
+/// by the Web Share PIN, but it opens no session.
+pub(crate) async fn handle_shared_files(
+    State(state_ref): State<Arc<RwLock<ServerState>>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    Query(query): Query<SharedFilesQuery>,
+) -> Response {
+    let mut state = state_ref.write().await;
+    let Some(web) = state.web_share.as_mut() else {
+        return (StatusCode::FORBIDDEN, "Web share not initialized").into_response();
+    };
+
+    match web.pin_gate.check(query.pin.as_deref(), peer.ip()) {
+        PinVerdict::Ok => {}
+        PinVerdict::Unauthorized => return StatusCode::UNAUTHORIZED.into_response(),
+        PinVerdict::LockedOut => return StatusCode::TOO_MANY_REQUESTS.into_response(),
+    }
+
+    let mut files: Vec<FileMetadata> = web
+        .files
+        .values()
+        .map(|file| file.metadata.clone())
+        .collect();
+    files.sort_by(|a, b| a.file_name.cmp(&b.file_name));
+    Json(files).into_response()
+}
+
 #[derive(Deserialize)]
 pub(crate) struct DownloadQuery {
     #[serde(rename = "sessionId")]
@@ -1,15 +1,18 @@
 //! Main TUI application with async event loop.
 
 use crate::client::LocalSendClient;
-use crate::crypto::generate_fingerprint;
+use crate::crypto::{Identity, generate_fingerprint};
 use crate::discovery::{Discovery, MulticastDiscovery};
-use crate::protocol::{DeviceInfo, DeviceType, PROTOCOL_VERSION, Protocol, ReceivedFile};
-use crate::server::{LocalSendServer, ServerEvent};
+use crate::protocol::{DeviceInfo, DeviceType, Protocol, ReceivedFile, SessionId};
+use crate::server::{LocalSendServer, ReceiveHistory, ServerEvent};
 
 use super::popup::{MessageLevel, Popup};
+use super::screens::receive::{
+    INBOX_CAPACITY, TIME_FORMAT, history_entry, inbox_item, push_capped,
+};
 use super::screens::{
     Screen, receive::ReceiveScreen, send_file::SendFileScreen, send_text::SendTextScreen,
-    settings::SettingsScreen,
+    settings::SettingsScreen, transfers::TransfersScreen,
 };
 use super::theme::THEME;
 
@@ -28,6 +31,7 @@ use std::sync::Arc;
 use strum::IntoEnumIterator;
 use tokio::sync::RwLock;
 use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
 use tui_input::backend::crossterm::EventHandler;
 
 /// Which send flow a background task belongs to, so its result updates the right screen.
@@ -60,6 +64,62 @@ enum SendUpdate {
     NeedsPin { generation: u64, kind: SendKind },
 }
 
+/// One incoming session as the status bar reports it, folded from the
+/// server's progress events. The `App` keeps one per session in flight.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct ReceiveStatus {
+    files_done: usize,
+    file_count: usize,
+    bytes_received: u64,
+    total_bytes: u64,
+}
+
+impl ReceiveStatus {
+    /// e.g. `Receiving 2/3 files, 45%`.
+    fn label(&self) -> String {
+        let percent = (self.bytes_received.min(self.total_bytes) * 100)
+            .checked_div(self.total_bytes)
+            .unwrap_or(0);
+        let noun = if self.file_count == 1 {
+            "file"
+        } else {
+            "files"
+        };
+        format!(
+            "Receiving {}/{} {noun}, {percent}%",
+            self.files_done, self.file_count
+        )
+    }
+
+    /// One more file of `session_id` has landed (saved or skipped).
+    fn count_file(statuses: &mut HashMap<SessionId, Self>, session_id: &SessionId) {
+        if let Some(status) = statuses.get_mut(session_id) {
+            status.files_done = (status.files_done + 1).min(status.file_count);
+        }
+    }
+
+    /// The status bar text for every session in flight: one session's own
+    /// label, or the sum of several prefixed with how many there are.
+    fn summary(statuses: &HashMap<SessionId, Self>) -> Option<String> {
+        match statuses.len() {
+            0 => None,
+            1 => statuses.values().next().map(Self::label),
+            sessions => {
+                let total = statuses
+                    .values()
+                    .fold(Self::default(), |total, status| Self {
+                        files_done: total.files_done + status.files_done,
+                        file_count: total.file_count + status.file_count,
+                        bytes_received: total.bytes_received
+                            + status.bytes_received.min(status.total_bytes),
+                        total_bytes: total.total_bytes + status.total_bytes,
+                    });
+                Some(format!("{sessions} transfers: {}", total.label()))
+            }
+        }
+    }
+}
+
 /// Main TUI application state.
 pub struct App {
     // Mode
@@ -88,6 +148,7 @@ pub struct App {
     send_text: SendTextScreen,
     send_file: SendFileScreen,
     receive: ReceiveScreen,
+    transfers: TransfersScreen,
     settings: SettingsScreen,
 
     // Status message
@@ -104,6 +165,9 @@ pub struct App {
     // generation (a cancelled/abandoned task) are ignored so they can't clobber
     // a newer send or wedge `is_sending`.
     send_generation: u64,
+    // Cancelled when the user backs out of the send in flight, which then
+    // cancels its session on the receiver instead of leaving it to time out.
+    send_cancel: CancellationToken,
 
     // Last device count we could read, shown when the devices lock is
     // momentarily held by the discovery writer (fallback instead of panic).
@@ -121,27 +185,51 @@ pub struct App {
     // Previous scanning state, so the scanning→done edge forces one repaint
     // (otherwise the last "Scanning…" frame lingers when nothing else changes).
     was_scanning: bool,
+    // Aggregate progress of the session currently being received, if any.
+    receive_status: HashMap<SessionId, ReceiveStatus>,
+    // Where items evicted from the Inbox go.
+    history: Option<Arc<ReceiveHistory>>,
+    // Feeds the one task that appends evicted items to `history`, started on
+    // the first eviction; a single writer keeps the file oldest first.
+    archive_tx: Option<tokio::sync::mpsc::UnboundedSender<Vec<crate::server::HistoryEntry>>>,
+    // Keep the received-files index in the save directory and start the
+    // Inbox from it.
+    index: bool,
+    // Attach a thumbnail to each image sent; see `with_previews`.
+    previews: bool,
+    // The kept identity's certificate, served on every (re)start.
+    #[cfg(feature = "https")]
+    tls_certificate: Option<crate::crypto::TlsCertificate>,
 }
 
 impl App {
-    /// Create a new App instance.
+    /// Create a new App instance. With an `identity` the device keeps that
+    /// fingerprint (and certificate), and its alias unless `alias` is given;
+    /// without one it is a new device each run.
     pub fn new(
         port: u16,
         alias: Option<String>,
         https: bool,
         pin: Option<String>,
         auto_accept: bool,
+        identity: Option<Identity>,
     ) -> Result<Self> {
-        let device_name = alias.unwrap_or_else(|| {
-            format!("LocalSend-Rust-{}", &uuid::Uuid::new_v4().to_string()[..4])
-        });
+        let device_name = alias
+            .or_else(|| identity.as_ref().map(|identity| identity.alias.clone()))
+            .unwrap_or_else(|| {
+                format!("LocalSend-Rust-{}", &uuid::Uuid::new_v4().to_string()[..4])
+            });
+        let fingerprint = identity
+            .as_ref()
+            .map(|identity| identity.fingerprint.clone())
+            .unwrap_or_else(generate_fingerprint);
 
         let device_info = DeviceInfo {
             alias: device_name,
-            version: PROTOCOL_VERSION.to_string(),
+            version: crate::protocol::advertised_version(),
             device_model: Some(crate::core::device::get_device_model()),
             device_type: Some(DeviceType::Desktop),
-            fingerprint: generate_fingerprint(),
+            fingerprint,
             port,
             protocol: if https {
                 Protocol::Https
@@ -178,6 +266,7 @@ impl App {
             send_text: SendTextScreen::new(devices.clone()),
             send_file: SendFileScreen::new(devices.clone()),
             receive: ReceiveScreen::new(received_files.clone(), port, save_dir.clone()),
+            transfers: TransfersScreen::default(),
             settings,
             status_message: None,
             discovery: None,
@@ -185,19 +274,28 @@ impl App {
             send_tx,
             send_rx,
             send_generation: 0,
+            send_cancel: CancellationToken::new(),
             last_device_count: 0,
             scan_deadline: std::time::Instant::now() + Duration::from_secs(3),
             dirty: true,
             pending_pin_kind: None,
             was_scanning: true,
+            receive_status: HashMap::new(),
+            history: None,
+            archive_tx: None,
+            index: false,
+            previews: false,
+            #[cfg(feature = "https")]
+            tls_certificate: identity.and_then(|identity| identity.certificate),
         })
     }
 
     /// Run the TUI application.
     pub async fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         // Start background services
-        self.start_discovery().await?;
+        // The server first: discovery announces the identity it settles on.
         self.start_server().await?;
+        self.start_discovery().await?;
 
         // Main event loop. We poll for input on a short tick but only repaint
         // when something actually changed (`self.dirty`), so an idle TUI does
@@ -258,7 +356,7 @@ impl App {
 
         let mut discovery = MulticastDiscovery::new_with_device(device_info.clone());
 
-        discovery.on_discovered(move |device: DeviceInfo| {
+        discovery.on_discovered(Box::new(move |device: DeviceInfo| {
             // Skip self
             if device.fingerprint == device_info.fingerprint {
                 return;
@@ -276,7 +374,7 @@ impl App {
             if !exists {
                 devices_guard.push(device);
             }
-        });
+        }));
 
         discovery.start().await?;
         discovery.announce_presence().await?;
@@ -288,10 +386,7 @@ impl App {
 
     /// Start receiver server in background.
     async fn start_server(&mut self) -> Result<()> {
-        // Ensure save directory exists
-        if !self.save_dir.exists() {
-            std::fs::create_dir_all(&self.save_dir)?;
-        }
+        crate::core::ensure_dir(&self.save_dir).await?;
 
         let protocol = if self.https {
             Protocol::Https
@@ -304,11 +399,32 @@ impl App {
             .port(self.port)
             .save_dir(self.save_dir.clone())
             .protocol(protocol)
+            .fingerprint(self.device_info.fingerprint.clone())
             .auto_accept(self.initial_auto_accept);
+        #[cfg(feature = "https")]
+        if let Some(certificate) = self.tls_certificate.clone() {
+            builder = builder.tls_certificate(certificate);
+        }
         if let Some(ref pin) = self.pin {
             builder = builder.pin(pin.clone());
         }
+        if self.index {
+            let index = self.save_dir.join(crate::server::INDEX_FILE_NAME);
+            match ReceiveHistory::new(&index).load().await {
+                Ok(entries) => {
+                    let skip = entries.len().saturating_sub(INBOX_CAPACITY);
+                    let mut files = self.received_files.write().await;
+                    files.splice(0..0, entries.into_iter().skip(skip).map(inbox_item));
+                }
+                Err(e) => tracing::warn!("Failed to load {:?}: {}", index, e),
+            }
+            builder = builder.history_file(index);
+        }
         let (server, events) = builder.build().await?;
+        // Over HTTPS the fingerprint is the served certificate's SHA-256, the
+        // one peers compute from the handshake; announce that, not ours.
+        self.device_info.fingerprint = server.device().fingerprint.clone();
+        self.settings.device_info.fingerprint = self.device_info.fingerprint.clone();
 
         self.events_rx = Some(events);
         self.server = Some(server);
@@ -316,6 +432,63 @@ impl App {
         Ok(())
     }
 
+    /// Keep the Inbox's prior items in a history file; the oldest ones are
+    /// moved there once the Inbox is full.
+    pub fn with_history_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.history = Some(Arc::new(ReceiveHistory::new(path)));
+        self
+    }
+
+    /// Record received files in the save directory's
+    /// [`INDEX_FILE_NAME`](crate::server::INDEX_FILE_NAME), and show the
+    /// newest of those already on record in the Inbox once the receiver
+    /// starts.
+    pub fn with_index(mut self) -> Self {
+        self.index = true;
+        self
+    }
+
+    /// Attach a small thumbnail to each image sent, for the receiver's accept
+    /// prompt.
+    #[cfg(feature = "thumbnails")]
+    pub fn with_previews(mut self) -> Self {
+        self.previews = true;
+        self
+    }
+
+    /// Append items evicted from the Inbox to the history, off the UI thread
+    /// and in eviction order.
+    fn archive(
+        history: Option<&Arc<ReceiveHistory>>,
+        archive_tx: &mut Option<
+            tokio::sync::mpsc::UnboundedSender<Vec<crate::server::HistoryEntry>>,
+        >,
+        evicted: Vec<ReceivedFile>,
+    ) {
+        let Some(history) = history else {
+            return;
+        };
+        let entries: Vec<_> = evicted.iter().filter_map(history_entry).collect();
+        if entries.is_empty() {
+            return;
+        }
+        let tx = archive_tx.get_or_insert_with(|| {
+            let history = history.clone();
+            let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<Vec<_>>();
+            tokio::spawn(async move {
+                while let Some(entries) = rx.recv().await {
+                    for entry in entries {
+                        if let Err(e) = history.append(&entry).await {
+                            tracing::warn!("Failed to archive {:?}: {}", entry.path, e);
+                        }
+                    }
+                }
+            });
+            tx
+        });
+        let _ = tx.send(entries);
+    }
+
     /// Drain pending `ServerEvent`s and react (show popup, record received files).
     fn poll_server_events(&mut self) {
         let Some(rx) = self.events_rx.as_mut() else {
@@ -323,6 +496,7 @@ impl App {
         };
         while let Ok(ev) = rx.try_recv() {
             self.dirty = true; // any server event may change what's on screen
+            self.transfers.apply(&ev);
             match ev {
                 ServerEvent::TransferRequest(request) => match self.popup {
                     // Free slot: show the confirm dialog.
@@ -349,30 +523,61 @@ impl App {
                 ServerEvent::WebShareRequest(_)
                 | ServerEvent::WebShareDownloadProgress { .. }
                 | ServerEvent::WebShareSessionDone { .. } => {}
-                ServerEvent::FileReceiveProgress { .. } => {}
+                ServerEvent::FileReceiveProgress {
+                    session_id,
+                    bytes_received,
+                    total_bytes,
+                    file_count,
+                    ..
+                } => {
+                    let status = self.receive_status.entry(session_id).or_default();
+                    status.file_count = file_count;
+                    status.bytes_received = bytes_received;
+                    status.total_bytes = total_bytes;
+                }
+                ServerEvent::FileSkipped { session_id, .. } => {
+                    ReceiveStatus::count_file(&mut self.receive_status, &session_id)
+                }
+                ServerEvent::TransferRejected(_) | ServerEvent::SessionStarted { .. } => {}
+                // Official clients answer our announcements this way and
+                // announce themselves rarely; without it they would age out.
+                ServerEvent::DeviceRegistered(device) => {
+                    if let Some(discovery) = &self.discovery {
+                        discovery.remember(device);
+                    }
+                }
                 ServerEvent::FileReceived {
+                    session_id,
                     file_name,
                     path,
                     size,
                     sender_alias,
+                    verified,
                     message_text,
                     ..
                 } => {
+                    ReceiveStatus::count_file(&mut self.receive_status, &session_id);
+                    let file = ReceivedFile {
+                        file_name,
+                        size,
+                        sender: sender_alias,
+                        time: chrono::Local::now().format(TIME_FORMAT).to_string(),
+                        path,
+                        verified,
+                        message_text,
+                    };
                     // A momentary write-lock contention must not crash the UI;
                     // if we can't record right now, drop this one row rather
                     // than panic (the event is advisory, not load-bearing).
-                    if let Ok(mut files) = self.received_files.try_write() {
-                        files.push(ReceivedFile {
-                            file_name,
-                            size,
-                            sender: sender_alias,
-                            time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                            path,
-                            message_text,
-                        });
-                    }
+                    // The guard lives for the push only, never the history I/O.
+                    let evicted = match self.received_files.try_write() {
+                        Ok(mut files) => push_capped(&mut files, file, INBOX_CAPACITY),
+                        Err(_) => Vec::new(),
+                    };
+                    Self::archive(self.history.as_ref(), &mut self.archive_tx, evicted);
                 }
-                ServerEvent::SessionDone { .. } => {
+                ServerEvent::SessionDone { session_id } => {
+                    self.receive_status.remove(&session_id);
                     self.status_message =
                         Some(("✓ Transfer complete".to_string(), MessageLevel::Success));
                 }
@@ -395,11 +600,11 @@ impl App {
                         continue;
                     }
                     let ratio = if total > 0 {
-                        (sent as f64 / total as f64).clamp(0.0, 1.0)
+                        sent as f64 / total as f64
                     } else {
                         0.0
                     };
-                    self.send_file.progress = ratio;
+                    self.send_file.progress = crate::tui::screens::send_file::clamp_ratio(ratio);
                 }
                 SendUpdate::Finished {
                     generation,
@@ -445,6 +650,18 @@ impl App {
     /// discovery task, off our input path) and while discovery is still warming
     /// up (so the "Scanning…" placeholder and its transition are drawn).
     fn refresh_device_count(&mut self) {
+        // Drop the peers discovery has stopped hearing from, so the list and
+        // the status bar count only devices that are still around.
+        if let Some(discovery) = &self.discovery {
+            let present = discovery.get_known_devices();
+            if let Ok(mut devices) = self.devices.try_write() {
+                devices.retain(|device| {
+                    present
+                        .iter()
+                        .any(|peer| peer.fingerprint == device.fingerprint)
+                });
+            }
+        }
         if let Ok(devices) = self.devices.try_read() {
             let count = devices.len();
             if count != self.last_device_count {
@@ -546,6 +763,8 @@ impl App {
             Screen::SendText => self.handle_send_text_key(key),
             Screen::SendFile => self.handle_send_file_key(key),
             Screen::Receive => self.handle_receive_key(key),
+            // Read-only view; Tab/q are handled above.
+            Screen::Transfers => {}
             Screen::Settings => self.handle_settings_key(key),
         }
 
@@ -614,7 +833,7 @@ impl App {
                     self.popup = None;
                     self.pending_pin_kind = None;
                     // Supersede any straggler from the failed attempt.
-                    self.send_generation = self.send_generation.wrapping_add(1);
+                    self.abandon_send();
                     self.status_message = Some(("Send cancelled".into(), MessageLevel::Info));
                 }
                 _ => {
@@ -693,7 +912,7 @@ impl App {
                 KeyCode::Esc => {
                     // Leaving cancels any in-flight send (its result is ignored).
                     if self.send_text.is_sending {
-                        self.send_generation = self.send_generation.wrapping_add(1);
+                        self.abandon_send();
                         self.send_text.is_sending = false;
                         self.status_message = Some(("Send cancelled".into(), MessageLevel::Info));
                     }
@@ -733,7 +952,7 @@ impl App {
                     // Leaving cancels any in-flight send (its result is ignored),
                     // so a stalled upload never wedges the screen.
                     if self.send_file.is_sending {
-                        self.send_generation = self.send_generation.wrapping_add(1);
+                        self.abandon_send();
                         self.status_message = Some(("Send cancelled".into(), MessageLevel::Info));
                     }
                     self.send_file.clear();
@@ -757,6 +976,13 @@ impl App {
         }
     }
 
+    /// Give up on the send in flight: its late updates are ignored, and it
+    /// cancels its session on the receiver.
+    fn abandon_send(&mut self) {
+        self.send_generation = self.send_generation.wrapping_add(1);
+        self.send_cancel.cancel();
+    }
+
     /// Spawn a text-message send (optionally carrying a `pin`), reporting
     /// progress/result/needs-pin back over the send channel.
     fn spawn_text_send(&mut self, pin: Option<String>) {
@@ -771,13 +997,16 @@ impl App {
         let tx = self.send_tx.clone();
         self.send_generation = self.send_generation.wrapping_add(1);
         let generation = self.send_generation;
+        self.send_cancel = CancellationToken::new();
+        let cancel = self.send_cancel.clone();
 
         self.send_text.is_sending = true;
         self.status_message = Some(("Sending message...".into(), MessageLevel::Info));
 
         tokio::spawn(async move {
             let client = LocalSendClient::new(device_info);
-            let result = send_text_message(&client, &target, &message, pin.as_deref()).await;
+            let result =
+                send_text_message(&client, &target, &message, pin.as_deref(), &cancel).await;
             let _ = tx.send(send_update_from_result(
                 generation,
                 SendKind::Text,
@@ -805,6 +1034,9 @@ impl App {
         let tx = self.send_tx.clone();
         self.send_generation = self.send_generation.wrapping_add(1);
         let generation = self.send_generation;
+        self.send_cancel = CancellationToken::new();
+        let cancel = self.send_cancel.clone();
+        let previews = self.previews;
         let label = file_path
             .file_name()
             .map(|s| s.to_string_lossy().into_owned())
@@ -828,7 +1060,16 @@ impl App {
                         total,
                     });
                 });
-            let result = send_file(&client, &target, &file_path, Some(cb), pin).await;
+            let result = send_file(
+                &client,
+                &target,
+                &file_path,
+                previews,
+                Some(cb),
+                pin,
+                &cancel,
+            )
+            .await;
             let _ = tx.send(send_update_from_result(
                 generation,
                 SendKind::File,
@@ -913,6 +1154,7 @@ impl App {
             Screen::SendText => self.send_text.render(layout[1], frame.buffer_mut()),
             Screen::SendFile => self.send_file.render(layout[1], frame.buffer_mut()),
             Screen::Receive => self.receive.render(layout[1], frame.buffer_mut()),
+            Screen::Transfers => self.transfers.render(layout[1], frame.buffer_mut()),
             Screen::Settings => frame.render_widget(&self.settings, layout[1]),
         }
 
@@ -946,6 +1188,7 @@ impl App {
                 Screen::SendText => "📝 Text".to_string(),
                 Screen::SendFile => "📁 File".to_string(),
                 Screen::Receive => "📥 Inbox".to_string(),
+                Screen::Transfers => "🔄 Transfers".to_string(),
                 Screen::Settings => "⚙️ Settings".to_string(),
             })
             .collect();
@@ -980,6 +1223,11 @@ impl App {
             Span::styled(format!("🟢 Listening on {} ", self.port), THEME.status_bar),
         ];
 
+        if let Some(label) = ReceiveStatus::summary(&self.receive_status) {
+            spans.push(Span::raw("| "));
+            spans.push(Span::styled(format!("📥 {label} "), THEME.status_info));
+        }
+
         if let Some((ref msg, level)) = self.status_message {
             spans.push(Span::raw("| "));
             let style = match level {
@@ -1032,6 +1280,7 @@ async fn send_text_message(
     target: &DeviceInfo,
     message: &str,
     pin: Option<&str>,
+    cancel: &CancellationToken,
 ) -> anyhow::Result<()> {
     use crate::core::file::{build_file_metadata_from_bytes, generate_file_id};
 
@@ -1050,68 +1299,85 @@ async fn send_text_message(
     let mut files = HashMap::new();
     files.insert(metadata.id.clone(), metadata.clone());
 
-    let response = client.prepare_upload(target, files, pin).await?;
-
-    if response.session_id.is_empty() {
+    let Some(response) = client.prepare_upload(target, files, pin).await? else {
         // 204 No Content - text message sent via preview
         return Ok(());
-    }
+    };
 
-    // Write to temp file and upload
     if let Some(token) = response.files.get(&metadata.id) {
-        let temp_path = std::env::temp_dir().join(format!("localsend_text_{}.txt", metadata.id));
-        tokio::fs::write(&temp_path, &file_data).await?;
-
-        client
-            .upload_file(
-                target,
-                &response.session_id,
-                &metadata.id,
-                token,
-                &temp_path,
-                None,
-            )
-            .await?;
-
-        let _ = tokio::fs::remove_file(temp_path).await;
+        let upload =
+            client.upload_bytes(target, &response.session_id, &metadata.id, token, file_data);
+        upload_unless_cancelled(client, target, &response.session_id, cancel, upload).await?;
     }
 
     Ok(())
 }
 
+/// Run `upload` unless the user backs out first, in which case the session
+/// is cancelled on the receiver rather than left open until it times out.
+/// Backing out while the offer still awaits an answer cancels the session
+/// as soon as one is granted.
+async fn upload_unless_cancelled(
+    client: &LocalSendClient,
+    target: &DeviceInfo,
+    session_id: &SessionId,
+    cancel: &CancellationToken,
+    upload: impl std::future::Future<Output = crate::Result<()>>,
+) -> anyhow::Result<()> {
+    tokio::select! {
+        biased;
+        _ = cancel.cancelled() => {
+            client.cancel(target, session_id).await?;
+            Ok(())
+        }
+        result = upload => Ok(result?),
+    }
+}
+
 /// Send a file to a device, reporting per-chunk progress through `progress`.
 async fn send_file(
     client: &LocalSendClient,
     target: &DeviceInfo,
     file_path: &Path,
+    previews: bool,
     progress: Option<crate::client::client::ProgressCallback>,
     pin: Option<String>,
+    cancel: &CancellationToken,
 ) -> anyhow::Result<()> {
-    use crate::core::file::build_file_metadata;
-
-    let metadata = build_file_metadata(file_path).await?;
+    #[cfg(feature = "thumbnails")]
+    let metadata = if previews {
+        crate::core::file::build_file_metadata_with_preview(file_path).await?
+    } else {
+        crate::core::file::build_file_metadata(file_path).await?
+    };
+    // Only `App::with_previews` turns them on, and it needs the feature.
+    #[cfg(not(feature = "thumbnails"))]
+    let metadata = {
+        debug_assert!(!previews);
+        crate::core::file::build_file_metadata(file_path).await?
+    };
 
     let mut files = HashMap::new();
     files.insert(metadata.id.clone(), metadata.clone());
 
-    let response = client.prepare_upload(target, files, pin.as_deref()).await?;
+    let response = client
+        .prepare_upload(target, files, pin.as_deref())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("receiver answered the file offer with no session"))?;
 
     let token = response.files.get(&metadata.id).ok_or_else(|| {
         anyhow::anyhow!("receiver declined the file (no upload token was issued)")
     })?;
 
-    client
-        .upload_file(
-            target,
-            &response.session_id,
-            &metadata.id,
-            token,
-            file_path,
-            progress,
-        )
-        .await?;
-
-    Ok(())
+    let upload = client.upload_file(
+        target,
+        &response.session_id,
+        &metadata.id,
+        token,
+        file_path,
+        progress,
+    );
+    upload_unless_cancelled(client, target, &response.session_id, cancel, upload).await
 }
 
 /// Open the platform file manager focused on `path`. Best-effort: spawns the
@@ -1140,20 +1406,12 @@ fn reveal_in_file_manager(path: &Path) -> std::io::Result<()> {
     Ok(())
 }
 
-/// Main entry point for the TUI.
-pub async fn run_tui(
-    port: u16,
-    alias: Option<String>,
-    https: bool,
-    pin: Option<String>,
-    auto_accept: bool,
-) -> Result<()> {
+/// Main entry point for the TUI: run `app` until the user quits.
+pub async fn run_tui(app: App) -> Result<()> {
     color_eyre::install()?;
 
     let terminal = ratatui::init();
-    let app_result = App::new(port, alias, https, pin, auto_accept)?
-        .run(terminal)
-        .await;
+    let app_result = app.run(terminal).await;
     ratatui::restore();
 
     app_result
@@ -1161,13 +1419,225 @@ pub async fn run_tui(
 
 #[cfg(test)]
 mod tests {
-    use super::{App, SendKind, SendUpdate, send_update_from_result};
+    use super::{App, ReceiveStatus, SendKind, SendUpdate, send_update_from_result};
     use crate::tui::popup::{MessageLevel, Popup};
 
     // App::new does no I/O (it binds nothing until run()), so it's safe to build
     // one for pure state-machine assertions.
     fn test_app() -> App {
-        App::new(0, Some("t".to_string()), false, None, false).expect("build app")
+        App::new(0, Some("t".to_string()), false, None, false, None).expect("build app")
+    }
+
+    #[test]
+    fn receive_status_label_reports_files_and_percent() {
+        let mut status = ReceiveStatus {
+            files_done: 2,
+            file_count: 3,
+            bytes_received: 450,
+            total_bytes: 1_000,
+        };
+        assert_eq!(status.label(), "Receiving 2/3 files, 45%");
+
+        status.file_count = 1;
+        status.files_done = 0;
+        status.total_bytes = 0;
+        assert_eq!(status.label(), "Receiving 0/1 file, 0%");
+    }
+
+    #[test]
+    fn receive_status_follows_progress_events_until_the_session_closes() {
+        use crate::protocol::{FileId, SessionId};
+        use crate::server::events::ServerEvent;
+
+        let mut app = test_app();
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        app.events_rx = Some(rx);
+        let session_id = SessionId::new();
+        let progress = |bytes_received| ServerEvent::FileReceiveProgress {
+            session_id: session_id.clone(),
+            file_id: FileId::new(),
+            file_name: "a.bin".into(),
+            sender_alias: "peer".into(),
+            bytes_received,
+            total_bytes: 200,
+            file_count: 2,
+        };
+        tx.try_send(progress(100)).unwrap();
+        tx.try_send(ServerEvent::FileReceived {
+            session_id: session_id.clone(),
+            file_id: FileId::new(),
+            file_name: "a.bin".into(),
+            path: "a.bin".into(),
+            size: 100,
+            sender_alias: "peer".into(),
+            verified: false,
+            message_text: None,
+        })
+        .unwrap();
+        tx.try_send(progress(150)).unwrap();
+        app.poll_server_events();
+        assert_eq!(
+            ReceiveStatus::summary(&app.receive_status).as_deref(),
+            Some("Receiving 1/2 files, 75%")
+        );
+
+        // A second sender at once: each session keeps its own count, and one
+        // finishing leaves the other on the status bar.
+        let other = SessionId::new();
+        tx.try_send(ServerEvent::FileReceiveProgress {
+            session_id: other.clone(),
+            file_id: FileId::new(),
+            file_name: "b.bin".into(),
+            sender_alias: "other".into(),
+            bytes_received: 50,
+            total_bytes: 200,
+            file_count: 1,
+        })
+        .unwrap();
+        app.poll_server_events();
+        assert_eq!(
+            ReceiveStatus::summary(&app.receive_status).as_deref(),
+            Some("2 transfers: Receiving 1/3 files, 50%")
+        );
+
+        tx.try_send(ServerEvent::SessionDone { session_id })
+            .unwrap();
+        app.poll_server_events();
+        assert_eq!(
+            app.receive_status.get(&other).map(ReceiveStatus::label),
+            Some("Receiving 0/1 file, 25%".to_string())
+        );
+        assert_eq!(app.receive_status.len(), 1);
+
+        tx.try_send(ServerEvent::SessionDone { session_id: other })
+            .unwrap();
+        app.poll_server_events();
+        assert!(app.receive_status.is_empty());
+    }
+
+    #[tokio::test]
+    async fn inbox_is_capped_and_evicted_items_go_to_history() {
+        use crate::protocol::{FileId, SessionId};
+        use crate::server::ReceiveHistory;
+        use crate::server::events::ServerEvent;
+        use crate::tui::screens::receive::INBOX_CAPACITY;
+
+        let dir = tempfile::tempdir().unwrap();
+        let history_path = dir.path().join("history.jsonl");
+        let mut app = test_app().with_history_file(&history_path);
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        app.events_rx = Some(rx);
+        let session_id = SessionId::new();
+        // Enough one-item evictions for out-of-order appends to show.
+        let overflow = 40;
+        for i in 0..INBOX_CAPACITY + overflow {
+            tx.try_send(ServerEvent::FileReceived {
+                session_id: session_id.clone(),
+                file_id: FileId::new(),
+                file_name: format!("{i}.bin"),
+                path: format!("{i}.bin").into(),
+                size: i as u64,
+                sender_alias: "peer".into(),
+                verified: false,
+                message_text: None,
+            })
+            .unwrap();
+            app.poll_server_events();
+        }
+
+        {
+            let files = app.received_files.read().await;
+            assert_eq!(files.len(), INBOX_CAPACITY);
+            assert_eq!(files[0].file_name, format!("{overflow}.bin"));
+        }
+
+        let history = ReceiveHistory::new(&history_path);
+        let mut archived = Vec::new();
+        for _ in 0..500 {
+            archived = history.load().await.unwrap();
+            if archived.len() == overflow {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let names: Vec<_> = archived.into_iter().map(|entry| entry.file_name).collect();
+        let oldest_first: Vec<_> = (0..overflow).map(|i| format!("{i}.bin")).collect();
+        assert_eq!(names, oldest_first);
+    }
+
+    #[tokio::test]
+    async fn the_inbox_starts_from_the_index_in_the_save_dir() {
+        use crate::server::{HistoryEntry, INDEX_FILE_NAME, ReceiveHistory};
+
+        let dir = tempfile::tempdir().unwrap();
+        let index = ReceiveHistory::new(dir.path().join(INDEX_FILE_NAME));
+        for (name, sha256) in [("old.bin", None), ("checked.bin", Some("ab".to_string()))] {
+            index
+                .append(&HistoryEntry {
+                    file_name: name.into(),
+                    size: 3,
+                    sender_alias: "peer".into(),
+                    received_at: chrono::Utc::now(),
+                    sha256,
+                    path: dir.path().join(name),
+                })
+                .await
+                .unwrap();
+        }
+
+        let mut app = test_app().with_index();
+        app.save_dir = dir.path().to_path_buf();
+        app.start_server().await.expect("server starts");
+
+        {
+            let files = app.received_files.read().await;
+            let names: Vec<_> = files.iter().map(|file| file.file_name.as_str()).collect();
+            assert_eq!(names, ["old.bin", "checked.bin"]);
+            assert!(!files[0].verified);
+            assert!(files[1].verified);
+        }
+        app.server.as_mut().unwrap().stop().await;
+    }
+
+    #[tokio::test]
+    async fn announced_fingerprint_is_the_servers() {
+        let https = cfg!(feature = "https");
+        let mut app =
+            App::new(0, Some("t".to_string()), https, None, false, None).expect("build app");
+        let dir = tempfile::tempdir().unwrap();
+        app.save_dir = dir.path().to_path_buf();
+        let random = app.device_info.fingerprint.clone();
+
+        app.start_server().await.expect("server starts");
+        let server = app.server.as_mut().unwrap();
+        assert_eq!(app.device_info.fingerprint, server.device().fingerprint);
+        if https {
+            assert_ne!(app.device_info.fingerprint, random);
+            assert_eq!(app.device_info.fingerprint.len(), 64, "a SHA-256 digest");
+        }
+        server.stop().await;
+    }
+
+    #[tokio::test]
+    async fn a_kept_identity_names_the_device() {
+        let https = cfg!(feature = "https");
+        let protocol = if https {
+            crate::protocol::Protocol::Https
+        } else {
+            crate::protocol::Protocol::Http
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let identity = crate::crypto::Identity::load_or_create(dir.path(), protocol).unwrap();
+        let (alias, fingerprint) = (identity.alias.clone(), identity.fingerprint.clone());
+
+        let mut app = App::new(0, None, https, None, false, Some(identity)).expect("build app");
+        app.save_dir = dir.path().join("downloads");
+        assert_eq!(app.device_info.alias, alias);
+        app.start_server().await.expect("server starts");
+        let server = app.server.as_mut().unwrap();
+        assert_eq!(server.device().fingerprint, fingerprint);
+        assert_eq!(app.device_info.fingerprint, fingerprint);
+        server.stop().await;
     }
 
     #[test]
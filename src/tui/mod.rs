@@ -7,4 +7,4 @@ mod popup;
 mod screens;
 mod theme;
 
-pub use app::run_tui;
+pub use app::{App, run_tui};
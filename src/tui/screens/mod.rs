@@ -4,6 +4,7 @@ pub mod receive;
 pub mod send_file;
 pub mod send_text;
 pub mod settings;
+pub mod transfers;
 
 use strum::{Display, EnumIter, FromRepr};
 
@@ -15,5 +16,6 @@ pub enum Screen {
 
     SendFile,
     Receive,
+    Transfers,
     Settings,
 }
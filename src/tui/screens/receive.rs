@@ -12,6 +12,64 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::protocol::ReceivedFile;
+use crate::server::HistoryEntry;
+
+/// Received items the Inbox keeps in memory. Past this the oldest are
+/// evicted, to the history file when one is configured.
+pub const INBOX_CAPACITY: usize = 500;
+
+/// How the Inbox timestamps an item (local time).
+pub const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Append `file`, then drop the oldest items beyond `capacity` and return them.
+pub fn push_capped(
+    files: &mut Vec<ReceivedFile>,
+    file: ReceivedFile,
+    capacity: usize,
+) -> Vec<ReceivedFile> {
+    files.push(file);
+    let excess = files.len().saturating_sub(capacity);
+    files.drain(..excess).collect()
+}
+
+/// A history line for an evicted Inbox item. Text messages are never
+/// persisted, so they have none.
+pub fn history_entry(file: &ReceivedFile) -> Option<HistoryEntry> {
+    if file.message_text.is_some() {
+        return None;
+    }
+    let received_at = chrono::NaiveDateTime::parse_from_str(&file.time, TIME_FORMAT)
+        .ok()
+        .and_then(|time| time.and_local_timezone(chrono::Local).single())
+        .map(|time| time.to_utc())
+        .unwrap_or_else(chrono::Utc::now);
+    Some(HistoryEntry {
+        file_name: file.file_name.clone(),
+        size: file.size,
+        sender_alias: file.sender.clone(),
+        received_at,
+        sha256: None,
+        path: file.path.clone(),
+    })
+}
+
+/// An Inbox item for a file on record from an earlier run.
+pub fn inbox_item(entry: HistoryEntry) -> ReceivedFile {
+    ReceivedFile {
+        file_name: entry.file_name,
+        size: entry.size,
+        sender: entry.sender_alias,
+        time: entry
+            .received_at
+            .with_timezone(&chrono::Local)
+            .format(TIME_FORMAT)
+            .to_string(),
+        path: entry.path,
+        // The receiver only records a sha256 it checked.
+        verified: entry.sha256.is_some(),
+        message_text: None,
+    }
+}
 
 /// Receive screen state.
 pub struct ReceiveScreen {
@@ -216,7 +274,7 @@ fn one_line(text: &str, max: usize) -> String {
     }
 }
 
-fn format_size(bytes: u64) -> String {
+pub(super) fn format_size(bytes: u64) -> String {
     const KB: u64 = 1024;
     const MB: u64 = KB * 1024;
     const GB: u64 = MB * 1024;
@@ -13,6 +13,17 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tui_input::Input;
 
+/// A progress fraction fit for [`Gauge::ratio`], which panics outside
+/// `0.0..=1.0`. A sender that over-sends can push `sent / total` past 1, and
+/// NaN (0/0) shows as no progress.
+pub fn clamp_ratio(ratio: f64) -> f64 {
+    if ratio.is_nan() {
+        0.0
+    } else {
+        ratio.clamp(0.0, 1.0)
+    }
+}
+
 /// Stage in send file flow
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SendFileStage {
@@ -69,7 +80,7 @@ impl SendFileScreen {
     #[allow(dead_code)]
     pub fn set_progress(&mut self, file: &str, progress: f64) {
         self.current_file = Some(file.to_string());
-        self.progress = progress;
+        self.progress = clamp_ratio(progress);
     }
 
     pub fn next_device(&mut self) {
@@ -252,15 +263,17 @@ impl SendFileScreen {
 
         // Input or progress
         if self.is_sending {
+            // `progress` is a public field; never trust it to be in range.
+            let progress = clamp_ratio(self.progress);
             let label = format!(
                 "{}: {:.0}%",
                 self.current_file.as_deref().unwrap_or("Uploading"),
-                self.progress * 100.0
+                progress * 100.0
             );
             let gauge = Gauge::default()
                 .block(Block::default().borders(Borders::ALL))
                 .gauge_style(THEME.status_success)
-                .ratio(self.progress)
+                .ratio(progress)
                 .label(label);
             gauge.render(layout[1], buf);
         } else {
@@ -292,3 +305,36 @@ impl SendFileScreen {
         Paragraph::new(help).centered().render(layout[3], buf);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{SendFileScreen, SendFileStage, clamp_ratio};
+    use ratatui::{buffer::Buffer, layout::Rect};
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    #[test]
+    fn ratios_are_clamped_into_the_gauge_range() {
+        assert_eq!(clamp_ratio(1.7), 1.0);
+        assert_eq!(clamp_ratio(-0.2), 0.0);
+        assert_eq!(clamp_ratio(f64::NAN), 0.0);
+        assert_eq!(clamp_ratio(0.4), 0.4);
+    }
+
+    #[test]
+    fn over_full_progress_renders_as_complete() {
+        let mut screen = SendFileScreen::new(Arc::new(RwLock::new(Vec::new())));
+        screen.set_progress("big.iso", 1.5);
+        assert_eq!(screen.progress, 1.0);
+
+        // Even a direct out-of-range write must not reach the gauge.
+        screen.progress = 3.0;
+        screen.stage = SendFileStage::EnterFilePath;
+        screen.is_sending = true;
+        let area = Rect::new(0, 0, 60, 12);
+        let mut buf = Buffer::empty(area);
+        screen.render(area, &mut buf);
+        let text: String = buf.content.iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("big.iso: 100%"), "{text}");
+    }
+}
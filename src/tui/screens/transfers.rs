@@ -0,0 +1,275 @@
+//! Transfers screen: receive sessions in flight and how recent ones ended.
+
+use super::receive::format_size;
+use super::send_file::clamp_ratio;
+use crate::protocol::SessionId;
+use crate::server::ServerEvent;
+use crate::tui::theme::THEME;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    widgets::{Block, Borders, Paragraph, Row, Table, Widget},
+};
+
+/// Finished transfers kept on screen; older ones scroll off.
+const MAX_FINISHED: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferOutcome {
+    Active,
+    /// Every accepted file arrived.
+    Completed,
+    /// The session ended with files still missing.
+    Cancelled,
+}
+
+/// One receive session, as the event stream has described it so far.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransferEntry {
+    pub session_id: SessionId,
+    pub sender_alias: String,
+    pub file_count: usize,
+    pub files_done: usize,
+    pub bytes_received: u64,
+    pub total_bytes: u64,
+    pub outcome: TransferOutcome,
+}
+
+impl TransferEntry {
+    fn progress(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 1.0;
+        }
+        clamp_ratio(self.bytes_received as f64 / self.total_bytes as f64)
+    }
+}
+
+/// Transfers screen state, folded from [`ServerEvent`]s.
+#[derive(Debug, Default)]
+pub struct TransfersScreen {
+    /// Oldest first.
+    entries: Vec<TransferEntry>,
+}
+
+impl TransfersScreen {
+    pub fn apply(&mut self, event: &ServerEvent) {
+        match event {
+            ServerEvent::SessionStarted {
+                session_id,
+                sender_alias,
+                file_count,
+                total_bytes,
+            } => self.entries.push(TransferEntry {
+                session_id: session_id.clone(),
+                sender_alias: sender_alias.clone(),
+                file_count: *file_count,
+                files_done: 0,
+                bytes_received: 0,
+                total_bytes: *total_bytes,
+                outcome: TransferOutcome::Active,
+            }),
+            ServerEvent::FileReceiveProgress {
+                session_id,
+                bytes_received,
+                total_bytes,
+                ..
+            } => {
+                if let Some(entry) = self.active_mut(session_id) {
+                    entry.bytes_received = *bytes_received;
+                    entry.total_bytes = *total_bytes;
+                }
+            }
+            ServerEvent::FileReceived { session_id, .. }
+            | ServerEvent::FileSkipped { session_id, .. } => {
+                if let Some(entry) = self.active_mut(session_id) {
+                    entry.files_done += 1;
+                }
+            }
+            ServerEvent::SessionDone { session_id } => {
+                if let Some(entry) = self.active_mut(session_id) {
+                    entry.outcome = if entry.files_done >= entry.file_count {
+                        TransferOutcome::Completed
+                    } else {
+                        TransferOutcome::Cancelled
+                    };
+                }
+                self.trim_finished();
+            }
+            _ => {}
+        }
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &TransferEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.outcome == TransferOutcome::Active)
+    }
+
+    /// Most recent first.
+    pub fn finished(&self) -> impl Iterator<Item = &TransferEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.outcome != TransferOutcome::Active)
+    }
+
+    fn active_mut(&mut self, session_id: &SessionId) -> Option<&mut TransferEntry> {
+        self.entries.iter_mut().find(|entry| {
+            entry.outcome == TransferOutcome::Active && &entry.session_id == session_id
+        })
+    }
+
+    fn trim_finished(&mut self) {
+        let mut excess = self
+            .entries
+            .iter()
+            .filter(|entry| entry.outcome != TransferOutcome::Active)
+            .count()
+            .saturating_sub(MAX_FINISHED);
+        self.entries.retain(|entry| {
+            if excess > 0 && entry.outcome != TransferOutcome::Active {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+    }
+
+    pub fn render(&self, area: Rect, buf: &mut Buffer) {
+        let block = Block::default()
+            .title(" 🔄 Transfers ")
+            .title_style(THEME.title)
+            .borders(Borders::ALL);
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        if self.entries.is_empty() {
+            Paragraph::new("No transfers yet.")
+                .style(THEME.normal)
+                .centered()
+                .render(inner, buf);
+            return;
+        }
+
+        let rows: Vec<Row> = self
+            .active()
+            .chain(self.finished())
+            .map(|entry| {
+                let status = match entry.outcome {
+                    TransferOutcome::Active => {
+                        format!("{:.0}%", entry.progress() * 100.0)
+                    }
+                    TransferOutcome::Completed => "✓ Done".to_string(),
+                    TransferOutcome::Cancelled => "✗ Cancelled".to_string(),
+                };
+                Row::new(vec![
+                    entry.sender_alias.clone(),
+                    format!("{}/{}", entry.files_done, entry.file_count),
+                    format_size(entry.total_bytes),
+                    status,
+                ])
+            })
+            .collect();
+        let widths = [
+            Constraint::Percentage(40),
+            Constraint::Percentage(15),
+            Constraint::Percentage(20),
+            Constraint::Percentage(25),
+        ];
+        Table::new(rows, widths)
+            .header(
+                Row::new(vec!["From", "Files", "Size", "Status"])
+                    .style(THEME.title)
+                    .bottom_margin(1),
+            )
+            .render(inner, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TransferOutcome, TransfersScreen};
+    use crate::protocol::{FileId, SessionId};
+    use crate::server::ServerEvent;
+    use std::path::PathBuf;
+
+    fn started(session_id: &SessionId, file_count: usize) -> ServerEvent {
+        ServerEvent::SessionStarted {
+            session_id: session_id.clone(),
+            sender_alias: "Phone".into(),
+            file_count,
+            total_bytes: 200,
+        }
+    }
+
+    fn received(session_id: &SessionId) -> ServerEvent {
+        ServerEvent::FileReceived {
+            session_id: session_id.clone(),
+            file_id: FileId::new(),
+            file_name: "a.bin".into(),
+            path: PathBuf::from("a.bin"),
+            size: 100,
+            sender_alias: "Phone".into(),
+            verified: false,
+            message_text: None,
+        }
+    }
+
+    #[test]
+    fn sessions_move_from_active_to_their_outcome() {
+        let mut screen = TransfersScreen::default();
+        let whole = SessionId::new();
+        let cut_short = SessionId::new();
+
+        screen.apply(&started(&whole, 2));
+        screen.apply(&ServerEvent::FileReceiveProgress {
+            session_id: whole.clone(),
+            file_id: FileId::new(),
+            file_name: "a.bin".into(),
+            sender_alias: "Phone".into(),
+            bytes_received: 50,
+            total_bytes: 200,
+            file_count: 2,
+        });
+        let active: Vec<_> = screen.active().collect();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].bytes_received, 50);
+        assert_eq!(active[0].progress(), 0.25);
+
+        screen.apply(&received(&whole));
+        screen.apply(&received(&whole));
+        screen.apply(&ServerEvent::SessionDone {
+            session_id: whole.clone(),
+        });
+
+        screen.apply(&started(&cut_short, 2));
+        screen.apply(&received(&cut_short));
+        screen.apply(&ServerEvent::SessionDone {
+            session_id: cut_short.clone(),
+        });
+
+        assert_eq!(screen.active().count(), 0);
+        let finished: Vec<_> = screen
+            .finished()
+            .map(|entry| (entry.session_id.clone(), entry.files_done, entry.outcome))
+            .collect();
+        assert_eq!(
+            finished,
+            [
+                (cut_short, 1, TransferOutcome::Cancelled),
+                (whole, 2, TransferOutcome::Completed),
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_most_recent_finished_transfers_are_kept() {
+        let mut screen = TransfersScreen::default();
+        for _ in 0..super::MAX_FINISHED + 5 {
+            let session_id = SessionId::new();
+            screen.apply(&started(&session_id, 1));
+            screen.apply(&ServerEvent::SessionDone { session_id });
+        }
+        assert_eq!(screen.finished().count(), super::MAX_FINISHED);
+    }
+}
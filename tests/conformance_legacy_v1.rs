@@ -0,0 +1,145 @@
+mod common;
+
+use localsend_rs::Protocol;
+use localsend_rs::server::LocalSendServer;
+use serde_json::json;
+
+async fn receiver(save: &std::path::Path, enable_v1: bool) -> (LocalSendServer, u16) {
+    let (server, _events) = LocalSendServer::builder()
+        .alias("Legacy Receiver")
+        .port(0)
+        .save_dir(save)
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .enable_v1(enable_v1)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    (server, port)
+}
+
+/// A v1 `send-request`: no version, port, protocol or fingerprint, and a
+/// coarse `fileType`.
+fn send_request(file_name: &str, size: u64) -> serde_json::Value {
+    json!({
+        "info": { "alias": "Old Phone", "deviceModel": "Pixel", "deviceType": "mobile" },
+        "files": {
+            "f1": { "id": "f1", "fileName": file_name, "size": size, "fileType": "image" }
+        }
+    })
+}
+
+#[tokio::test]
+async fn a_v1_sender_delivers_a_file_through_send_request_and_send() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, port) = receiver(save.path(), true).await;
+    let http = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{port}/api/localsend/v1");
+
+    let info: serde_json::Value = http
+        .get(format!("{base}/info"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(info["alias"], "Legacy Receiver");
+
+    let payload = b"v1 bytes".to_vec();
+    let tokens: serde_json::Value = http
+        .post(format!("{base}/send-request"))
+        .json(&send_request("photo.jpg", payload.len() as u64))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    // The bare v1 shape: a token per file, no session id.
+    assert!(tokens.get("sessionId").is_none());
+    let token = tokens["f1"].as_str().expect("a token for f1");
+
+    let wrong = http
+        .post(format!("{base}/send"))
+        .query(&[("fileId", "f1"), ("token", "not-the-token")])
+        .body(payload.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(wrong.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let sent = http
+        .post(format!("{base}/send"))
+        .query(&[("fileId", "f1"), ("token", token)])
+        .body(payload.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(sent.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        std::fs::read(save.path().join("photo.jpg")).unwrap(),
+        payload
+    );
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn a_v1_cancel_frees_the_slot_for_the_next_sender() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, port) = receiver(save.path(), true).await;
+    let http = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{port}/api/localsend/v1");
+
+    let first = http
+        .post(format!("{base}/send-request"))
+        .json(&send_request("a.jpg", 4))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(first.status(), reqwest::StatusCode::OK);
+    let blocked = http
+        .post(format!("{base}/send-request"))
+        .json(&send_request("b.jpg", 4))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(blocked.status(), reqwest::StatusCode::CONFLICT);
+
+    let cancelled = http.post(format!("{base}/cancel")).send().await.unwrap();
+    assert_eq!(cancelled.status(), reqwest::StatusCode::OK);
+    let again = http
+        .post(format!("{base}/send-request"))
+        .json(&send_request("b.jpg", 4))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(again.status(), reqwest::StatusCode::OK);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn v1_routes_are_absent_unless_enabled() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, port) = receiver(save.path(), false).await;
+    let http = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{port}/api/localsend/v1");
+
+    let info = http.get(format!("{base}/info")).send().await.unwrap();
+    assert_eq!(info.status(), reqwest::StatusCode::NOT_FOUND);
+    let prepare = http
+        .post(format!("{base}/send-request"))
+        .json(&send_request("photo.jpg", 4))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(prepare.status(), reqwest::StatusCode::NOT_FOUND);
+
+    server.stop().await;
+}
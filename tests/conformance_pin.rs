@@ -1,7 +1,7 @@
 mod common;
 
 use localsend_rs::Protocol;
-use localsend_rs::server::LocalSendServer;
+use localsend_rs::server::{LocalSendServer, ServerEvent};
 use serde_json::json;
 
 fn minimal_prepare_body() -> serde_json::Value {
@@ -84,3 +84,54 @@ async fn correct_pin_passes() {
     assert!(body["sessionId"].is_string());
     assert!(body["files"]["f1"].is_string());
 }
+
+#[tokio::test]
+async fn missing_pin_is_401_and_correct_pin_reaches_the_accept_prompt() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("Pinned")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .pin("123456")
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    let base = format!("http://127.0.0.1:{port}/api/localsend/v2/prepare-upload");
+    let http = reqwest::Client::new();
+
+    let r = http
+        .post(&base)
+        .json(&minimal_prepare_body())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 401);
+
+    let prepare = tokio::spawn({
+        let http = http.clone();
+        async move {
+            http.post(format!("{base}?pin=123456"))
+                .json(&minimal_prepare_body())
+                .send()
+                .await
+                .unwrap()
+        }
+    });
+    // The first event is the prompt for the second request: the rejected
+    // one never got that far.
+    let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+        .await
+        .expect("accept prompt")
+        .expect("event");
+    match event {
+        ServerEvent::TransferRequest(request) => {
+            assert_eq!(request.sender().alias, "raw-sender");
+            request.accept();
+        }
+        other => panic!("expected an accept prompt, got {other:?}"),
+    }
+    assert_eq!(prepare.await.unwrap().status(), 200);
+}
@@ -1,8 +1,10 @@
 mod common;
 
 use localsend_rs::Protocol;
-use localsend_rs::server::LocalSendServer;
+use localsend_rs::core::MockClock;
+use localsend_rs::server::{LocalSendServer, RejectionReason, SaveLayout, ServerEvent};
 use serde_json::json;
+use std::sync::Arc;
 
 #[tokio::test]
 async fn empty_files_map_returns_204() {
@@ -34,3 +36,450 @@ async fn empty_files_map_returns_204() {
         .unwrap();
     assert_eq!(r.status(), 204);
 }
+
+#[tokio::test]
+async fn receive_disabled_answers_info_but_refuses_prepare_upload() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("Send Only")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .receive_disabled(true)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let client = reqwest::Client::new();
+    let info: serde_json::Value = client
+        .get(format!("http://127.0.0.1:{port}/api/localsend/v2/info"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(info["alias"], "Send Only");
+
+    let body = json!({
+        "info": { "alias": "raw", "version": "2.1", "deviceType": "headless",
+                  "fingerprint": "fp", "port": 53317, "protocol": "http", "download": false },
+        "files": { "f1": { "id": "f1", "fileName": "a.txt", "size": 1,
+                           "fileType": "text/plain" } }
+    });
+    let r = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"
+        ))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 403);
+    assert!(r.text().await.unwrap().contains("disabled"));
+
+    // No accept prompt, only the audit record.
+    match events.try_recv() {
+        Ok(ServerEvent::TransferRejected(rejection)) => {
+            assert_eq!(rejection.reason, RejectionReason::ReceiveDisabled);
+        }
+        other => panic!("expected a TransferRejected event, got {other:?}"),
+    }
+}
+
+fn offer(file_name: &str) -> serde_json::Value {
+    json!({
+        "info": { "alias": "raw", "version": "2.1", "deviceType": "headless",
+                  "fingerprint": "fp", "port": 53317, "protocol": "http", "download": false },
+        "files": { "f1": { "id": "f1", "fileName": file_name, "size": 5,
+                           "fileType": "text/plain" } }
+    })
+}
+
+#[tokio::test]
+async fn only_allowed_and_unblocked_senders_reach_the_accept_flow() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("Kiosk")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .allowed_fingerprints(["trusted", "revoked"])
+        .blocked_fingerprints(["revoked"])
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let client = reqwest::Client::new();
+    for (fingerprint, status) in [("trusted", 200), ("stranger", 403), ("revoked", 403)] {
+        let mut body = offer("a.txt");
+        body["info"]["fingerprint"] = fingerprint.into();
+        let r = client
+            .post(format!(
+                "http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"
+            ))
+            .json(&body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(r.status(), status, "sender {fingerprint}");
+        if status == 200 {
+            // Let the next offer open a session of its own.
+            let session: serde_json::Value = r.json().await.unwrap();
+            let session_id = session["sessionId"].as_str().unwrap().to_string();
+            client
+                .post(format!(
+                    "http://127.0.0.1:{port}/api/localsend/v2/cancel?sessionId={session_id}"
+                ))
+                .send()
+                .await
+                .unwrap();
+        }
+    }
+
+    let mut refused = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        if let ServerEvent::TransferRejected(rejection) = event {
+            assert_eq!(rejection.reason, RejectionReason::SenderNotAllowed);
+            refused.push(rejection.sender_fingerprint);
+        }
+    }
+    assert_eq!(refused, ["stranger", "revoked"]);
+}
+
+#[tokio::test]
+async fn offers_over_the_size_limit_are_refused_and_uploads_held_to_their_size() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("Small")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .max_total_size(8)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let client = reqwest::Client::new();
+    let prepare = format!("http://127.0.0.1:{port}/api/localsend/v2/prepare-upload");
+    // Five bytes each: one fits, two together do not.
+    let mut two_files = offer("a.txt");
+    two_files["files"]["f2"] = json!({ "id": "f2", "fileName": "b.txt", "size": 5,
+                                       "fileType": "text/plain" });
+    let r = client.post(&prepare).json(&two_files).send().await.unwrap();
+    assert_eq!(r.status(), 403);
+    match events.try_recv() {
+        Ok(ServerEvent::TransferRejected(rejection)) => {
+            assert_eq!(rejection.reason, RejectionReason::TooLarge);
+        }
+        other => panic!("expected a TransferRejected event, got {other:?}"),
+    }
+    // Declared sizes that overflow when added up are still just too large.
+    two_files["files"]["f2"]["size"] = json!(u64::MAX);
+    let r = client.post(&prepare).json(&two_files).send().await.unwrap();
+    assert_eq!(r.status(), 403);
+    match events.try_recv() {
+        Ok(ServerEvent::TransferRejected(rejection)) => {
+            assert_eq!(rejection.reason, RejectionReason::TooLarge);
+            assert_eq!(rejection.total_size, u64::MAX);
+        }
+        other => panic!("expected a TransferRejected event, got {other:?}"),
+    }
+
+    let prep: serde_json::Value = client
+        .post(&prepare)
+        .json(&offer("a.txt"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    // The sender declared 5 bytes and sends more.
+    let r = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={}&fileId=f1&token={}",
+            prep["sessionId"].as_str().unwrap(),
+            prep["files"]["f1"].as_str().unwrap()
+        ))
+        .body("hello, world")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 413);
+    assert!(!save.path().join("a.txt").exists());
+}
+
+#[tokio::test]
+async fn nested_file_names_land_in_subdirectories() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let client = reqwest::Client::new();
+    let prep: serde_json::Value = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"
+        ))
+        .json(&offer("a/b/c.txt"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let r = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={}&fileId=f1&token={}",
+            prep["sessionId"].as_str().unwrap(),
+            prep["files"]["f1"].as_str().unwrap()
+        ))
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 200);
+    assert_eq!(
+        std::fs::read(save.path().join("a").join("b").join("c.txt")).unwrap(),
+        b"hello"
+    );
+}
+
+#[tokio::test]
+async fn the_by_sender_layout_files_uploads_under_a_safe_alias_folder() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .save_layout(SaveLayout::BySender)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let client = reqwest::Client::new();
+    let mut body = offer("a.txt");
+    body["info"]["alias"] = "../Bob's Phone".into();
+    let prep: serde_json::Value = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"
+        ))
+        .json(&body)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let r = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={}&fileId=f1&token={}",
+            prep["sessionId"].as_str().unwrap(),
+            prep["files"]["f1"].as_str().unwrap()
+        ))
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 200);
+    assert_eq!(
+        std::fs::read(save.path().join("_Bob's Phone").join("a.txt")).unwrap(),
+        b"hello"
+    );
+}
+
+#[tokio::test]
+async fn the_by_date_layout_dates_the_folder_by_the_servers_clock() {
+    // Noon, so the local date is the 5th in every time zone within ±11h.
+    let arrival = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_709_640_000);
+    let save = tempfile::tempdir().unwrap();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .save_layout(SaveLayout::ByDate)
+        .clock(Arc::new(MockClock::new().with_wall_time(arrival)))
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let client = reqwest::Client::new();
+    let prep: serde_json::Value = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"
+        ))
+        .json(&offer("a.txt"))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let r = client
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={}&fileId=f1&token={}",
+            prep["sessionId"].as_str().unwrap(),
+            prep["files"]["f1"].as_str().unwrap()
+        ))
+        .body("hello")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 200);
+    assert_eq!(
+        std::fs::read(save.path().join("2024-03-05").join("a.txt")).unwrap(),
+        b"hello"
+    );
+}
+
+#[tokio::test]
+async fn traversal_file_names_are_rejected_before_any_prompt() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path().join("inbox"))
+        .protocol(Protocol::Http)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    for name in ["../x", "a/../../x", "/etc/passwd", "a\\..\\x", "C:x"] {
+        let r = reqwest::Client::new()
+            .post(format!(
+                "http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"
+            ))
+            .json(&offer(name))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(r.status(), 400, "{name:?} must be refused");
+    }
+    assert!(
+        events.try_recv().is_err(),
+        "no accept prompt for unsafe names"
+    );
+    assert!(!save.path().join("x").exists());
+}
+
+#[tokio::test]
+async fn the_web_client_option_answers_cors_preflights() {
+    let save = tempfile::tempdir().unwrap();
+    let preflight = |port: u16, origin: &'static str| {
+        reqwest::Client::new()
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .header("Access-Control-Request-Headers", "content-type")
+            .send()
+    };
+
+    for web_client in [true, false] {
+        let (mut server, _events) = LocalSendServer::builder()
+            .port(0)
+            .save_dir(save.path())
+            .protocol(Protocol::Http)
+            .web_client(web_client)
+            .build()
+            .await
+            .unwrap();
+        let port = server.port();
+        common::wait_for_http_info(port).await;
+
+        let response = preflight(port, "https://web.localsend.org").await.unwrap();
+        let allowed_origin = response.headers().get("access-control-allow-origin");
+        if web_client {
+            assert!(response.status().is_success(), "{}", response.status());
+            assert_eq!(allowed_origin.unwrap(), "https://web.localsend.org");
+            let methods = response.headers()["access-control-allow-methods"]
+                .to_str()
+                .unwrap();
+            assert!(methods.contains("POST"), "{methods}");
+        } else {
+            assert!(allowed_origin.is_none());
+        }
+        // Any other page is not let in.
+        let response = preflight(port, "https://attacker.example").await.unwrap();
+        assert!(
+            response
+                .headers()
+                .get("access-control-allow-origin")
+                .is_none()
+        );
+
+        server.stop().await;
+    }
+}
+
+#[tokio::test]
+async fn web_client_origins_are_an_allow_list() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, _events) = LocalSendServer::builder()
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .web_client_origins(["http://192.168.1.20:8080"])
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    for (origin, allowed) in [
+        ("http://192.168.1.20:8080", true),
+        ("https://web.localsend.org", false),
+    ] {
+        let response = reqwest::Client::new()
+            .request(
+                reqwest::Method::OPTIONS,
+                format!("http://127.0.0.1:{port}/api/localsend/v2/prepare-upload"),
+            )
+            .header("Origin", origin)
+            .header("Access-Control-Request-Method", "POST")
+            .send()
+            .await
+            .unwrap();
+        let allowed_origin = response.headers().get("access-control-allow-origin");
+        assert_eq!(allowed_origin.is_some(), allowed, "{origin}");
+    }
+    server.stop().await;
+
+    let invalid = LocalSendServer::builder()
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .web_client_origins(["http://bad\norigin"])
+        .build()
+        .await;
+    assert!(invalid.is_err());
+}
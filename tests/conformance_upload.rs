@@ -12,6 +12,8 @@ fn assert_rejected_upload_only_emits_rolled_back_progress(
     while let Ok(event) = events.try_recv() {
         match event {
             ServerEvent::FileReceiveProgress { bytes_received, .. } => samples.push(bytes_received),
+            // Emitted by the accepted prepare-upload, before the upload.
+            ServerEvent::SessionStarted { .. } => {}
             ServerEvent::FileReceived { .. } | ServerEvent::SessionDone { .. } => {
                 panic!("rejected upload must not emit completion events")
             }
@@ -100,6 +102,67 @@ async fn short_body_is_rejected_and_partial_discarded() {
     assert_rejected_upload_only_emits_rolled_back_progress(&mut events);
 }
 
+/// A sender that disconnects part way through the body must not leave a
+/// truncated file behind.
+#[tokio::test]
+async fn dropped_connection_mid_body_removes_the_partial_file() {
+    use tokio::io::AsyncWriteExt;
+
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (session_id, token) = prepare_single(port, 200_000, None).await;
+    let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+        .await
+        .unwrap();
+    let head = format!(
+        "POST /api/localsend/v2/upload?sessionId={session_id}&fileId=f1&token={token} HTTP/1.1\r\n\
+         Host: 127.0.0.1\r\nContent-Length: 200000\r\n\r\n"
+    );
+    stream.write_all(head.as_bytes()).await.unwrap();
+    stream.write_all(&[7u8; 50_000]).await.unwrap();
+    stream.flush().await.unwrap();
+
+    // Wait until the bytes are on disk, then hang up.
+    let mut received = 0;
+    while received == 0 {
+        if let Some(ServerEvent::FileReceiveProgress { bytes_received, .. }) = events.recv().await {
+            received = bytes_received;
+        }
+    }
+    drop(stream);
+
+    // The failed write rolls the progress back to zero...
+    loop {
+        match tokio::time::timeout(std::time::Duration::from_secs(5), events.recv()).await {
+            Ok(Some(ServerEvent::FileReceiveProgress {
+                bytes_received: 0, ..
+            })) => break,
+            Ok(Some(_)) => continue,
+            other => panic!("no rollback after the disconnect: {other:?}"),
+        }
+    }
+    // ...and the partial file goes with it.
+    let partial = save.path().join("big.bin");
+    for _ in 0..50 {
+        if !partial.exists() {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("partial upload must be deleted");
+}
+
 /// When the metadata carries a sha256, a full-length body whose contents
 /// hash to a different digest must be rejected the same way.
 #[tokio::test]
@@ -138,3 +201,333 @@ async fn sha256_mismatch_is_rejected_and_partial_discarded() {
     );
     assert_rejected_upload_only_emits_rolled_back_progress(&mut events);
 }
+
+/// Some third-party clients POST the file as a `multipart/form-data` part
+/// instead of LocalSend's raw body; the file part is extracted and saved, and
+/// an empty part still produces a zero-byte file.
+#[tokio::test]
+async fn multipart_upload_saves_the_file_part() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    for (payload, saved_name) in [
+        (&b"hello multipart"[..], "big.bin"),
+        (&b""[..], "big (1).bin"),
+    ] {
+        let (session_id, token) =
+            prepare_single(port, payload.len() as u64, Some(sha256_from_bytes(payload))).await;
+        let boundary = "localsend-test-boundary";
+        let mut body = format!(
+            "--{boundary}\r\nContent-Disposition: form-data; name=\"note\"\r\n\r\nignored\r\n\
+             --{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"big.bin\"\r\n\
+             Content-Type: application/octet-stream\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(payload);
+        body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+        let r = reqwest::Client::new()
+            .post(format!(
+                "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={session_id}&fileId=f1&token={token}"
+            ))
+            .header(
+                "content-type",
+                format!("multipart/form-data; boundary={boundary}"),
+            )
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(r.status(), 200);
+        assert_eq!(
+            std::fs::read(save.path().join(saved_name)).unwrap(),
+            payload
+        );
+    }
+}
+
+/// A multipart file is not held to a request-size limit: anything up to the
+/// declared size is taken, as with a raw body.
+#[tokio::test]
+async fn multipart_uploads_over_2_mib_are_saved() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let payload: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let (session_id, token) = prepare_single(
+        port,
+        payload.len() as u64,
+        Some(sha256_from_bytes(&payload)),
+    )
+    .await;
+    let boundary = "localsend-test-boundary";
+    let mut body = format!(
+        "--{boundary}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"big.bin\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n"
+    )
+    .into_bytes();
+    body.extend_from_slice(&payload);
+    body.extend_from_slice(format!("\r\n--{boundary}--\r\n").as_bytes());
+
+    let r = reqwest::Client::new()
+        .post(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={session_id}&fileId=f1&token={token}"
+        ))
+        .header(
+            "content-type",
+            format!("multipart/form-data; boundary={boundary}"),
+        )
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 200);
+    assert_eq!(std::fs::read(save.path().join("big.bin")).unwrap(), payload);
+}
+
+/// Strict mode refuses a chunked body that never states its length (it could
+/// stream past the declared size indefinitely) but still takes a normal
+/// upload that carries a matching `Content-Length`.
+#[tokio::test]
+async fn strict_mode_requires_content_length() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .strict_content_length(true)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let payload = b"hello".to_vec();
+    let (session_id, token) = prepare_single(port, payload.len() as u64, None).await;
+    let url = format!(
+        "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={session_id}&fileId=f1&token={token}"
+    );
+    let client = reqwest::Client::new();
+
+    let chunks = futures_util::stream::iter([Ok::<_, std::io::Error>(payload.clone())]);
+    let chunked = client
+        .post(&url)
+        .body(reqwest::Body::wrap_stream(chunks))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(chunked.status(), 411);
+    assert!(!save.path().join("big.bin").exists());
+
+    let sized = client
+        .post(&url)
+        .body(payload.clone())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(sized.status(), 200);
+    assert_eq!(std::fs::read(save.path().join("big.bin")).unwrap(), payload);
+
+    server.stop().await;
+}
+
+/// A sender that opens the upload and then goes silent must not hold the
+/// session: after the idle timeout the upload is aborted with 408, the
+/// partial is discarded, and a fresh prepare-upload is accepted.
+#[tokio::test]
+async fn stalled_upload_is_aborted_after_the_idle_timeout() {
+    use futures_util::StreamExt;
+
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .upload_idle_timeout(std::time::Duration::from_millis(300))
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (session_id, token) = prepare_single(port, 1_000, None).await;
+    let stalled =
+        futures_util::stream::iter([Ok::<_, std::io::Error>(bytes::Bytes::from(vec![0u8; 100]))])
+            .chain(futures_util::stream::pending());
+
+    let started = std::time::Instant::now();
+    let r = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        reqwest::Client::new()
+            .post(format!(
+                "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={session_id}&fileId=f1&token={token}"
+            ))
+            .body(reqwest::Body::wrap_stream(stalled))
+            .send(),
+    )
+    .await
+    .expect("the receiver must give up on a stalled body")
+    .unwrap();
+    assert_eq!(r.status(), 408);
+    assert!(started.elapsed() >= std::time::Duration::from_millis(300));
+    assert!(!save.path().join("big.bin").exists());
+
+    let mut saw_session_done = false;
+    while let Ok(event) = events.try_recv() {
+        saw_session_done |= matches!(event, ServerEvent::SessionDone { .. });
+    }
+    assert!(saw_session_done, "the stalled session must be closed");
+
+    // The slot is free again: a new offer is not turned away with 409.
+    let (next_session, _) = prepare_single(port, 10, None).await;
+    assert_ne!(next_session, session_id);
+}
+
+/// With resumable uploads a stall keeps what arrived and the session, and a
+/// resume turned away before it starts leaves the partial resumable.
+#[tokio::test]
+async fn a_stalled_resumable_upload_keeps_its_partial_for_the_resume() {
+    use futures_util::StreamExt;
+
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .resumable_uploads(true)
+        .strict_content_length(true)
+        .upload_idle_timeout(std::time::Duration::from_millis(300))
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (session_id, token) = prepare_single(port, 1_000, None).await;
+    let upload = |offset: u64| {
+        format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={session_id}&fileId=f1&token={token}&offset={offset}"
+        )
+    };
+    let status = || async {
+        let status: serde_json::Value = reqwest::get(format!(
+            "http://127.0.0.1:{port}/api/localsend/v2/upload-status?sessionId={session_id}&fileId=f1&token={token}"
+        ))
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+        status["received"].as_u64().unwrap()
+    };
+    let client = reqwest::Client::new();
+
+    let stalled =
+        futures_util::stream::iter([Ok::<_, std::io::Error>(bytes::Bytes::from(vec![0u8; 100]))])
+            .chain(futures_util::stream::pending());
+    let r = client
+        .post(upload(0))
+        .header(reqwest::header::CONTENT_LENGTH, 1_000)
+        .body(reqwest::Body::wrap_stream(stalled))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 408);
+    assert_eq!(status().await, 100);
+    while let Ok(event) = events.try_recv() {
+        assert!(
+            !matches!(event, ServerEvent::SessionDone { .. }),
+            "the session stays open for the resume"
+        );
+    }
+
+    // The wrong length is refused before the partial is touched.
+    let r = client
+        .post(upload(100))
+        .body(vec![0u8; 10])
+        .send()
+        .await
+        .unwrap();
+    assert!(r.status().is_client_error(), "{}", r.status());
+    assert_eq!(status().await, 100);
+
+    let r = client
+        .post(upload(100))
+        .body(vec![0u8; 900])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(r.status(), 200);
+    assert_eq!(
+        std::fs::read(save.path().join("big.bin")).unwrap(),
+        [0u8; 1_000]
+    );
+    assert!(!save.path().join("big.bin.lspart").exists());
+
+    server.stop().await;
+}
+
+/// A file is reported verified only when a declared sha256 was checked.
+#[tokio::test]
+async fn received_files_say_whether_their_sha256_was_checked() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let payload = b"checked";
+    for (sha256, want) in [(Some(sha256_from_bytes(payload)), true), (None, false)] {
+        let (session_id, token) = prepare_single(port, payload.len() as u64, sha256).await;
+        let r = reqwest::Client::new()
+            .post(format!(
+                "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={session_id}&fileId=f1&token={token}"
+            ))
+            .body(&payload[..])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(r.status(), 200);
+
+        let verified = loop {
+            match events.recv().await.expect("event stream open") {
+                ServerEvent::FileReceived { verified, .. } => break verified,
+                _ => continue,
+            }
+        };
+        assert_eq!(verified, want);
+    }
+}
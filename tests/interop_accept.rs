@@ -1,8 +1,9 @@
 mod common;
 
-use localsend_rs::server::{LocalSendServer, ServerEvent};
+use localsend_rs::server::{LocalSendServer, RejectionReason, ServerEvent};
 use localsend_rs::{DeviceInfo, LocalSendClient, LocalSendError, Protocol, build_file_metadata};
 use std::collections::HashMap;
+use std::sync::atomic::Ordering;
 
 async fn start_receiver(
     save_dir: std::path::PathBuf,
@@ -63,7 +64,8 @@ async fn event_consumer_can_accept_a_transfer() {
     let prep = client
         .prepare_upload(&target, files, None)
         .await
-        .expect("accepted");
+        .expect("accepted")
+        .expect("a session");
     let token = prep.files.get(&id).unwrap().clone();
     client
         .upload_file(&target, &prep.session_id, &id, &token, &path, None)
@@ -73,6 +75,114 @@ async fn event_consumer_can_accept_a_transfer() {
     server.stop().await;
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn a_prompt_outlasting_the_session_timeout_is_not_swept() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Receiver")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(false)
+        .session_timeout(std::time::Duration::from_secs(1))
+        .accept_timeout(std::time::Duration::from_secs(10))
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let swept = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let seen = swept.clone();
+    tokio::spawn(async move {
+        while let Some(ev) = events.recv().await {
+            match ev {
+                // The user takes longer to answer than the session timeout.
+                ServerEvent::TransferRequest(req) => {
+                    tokio::time::sleep(std::time::Duration::from_millis(2_500)).await;
+                    req.accept();
+                }
+                ServerEvent::SessionDone { .. } => seen.store(true, Ordering::SeqCst),
+                _ => {}
+            }
+        }
+    });
+
+    let (files, _, _) = one_file(src.path());
+    let client = LocalSendClient::new(DeviceInfo::new("Sender".to_string(), 0, Protocol::Http));
+    client
+        .prepare_upload(&common::target_device(port), files, None)
+        .await
+        .expect("accepted after the slow prompt")
+        .expect("a session");
+    assert!(!swept.load(Ordering::SeqCst), "the prompt was swept");
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn accepting_into_a_directory_overrides_the_save_dir() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = start_receiver(save.path().to_path_buf()).await;
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    tokio::spawn(async move {
+        while let Some(ev) = events.recv().await {
+            if let ServerEvent::TransferRequest(req) = ev {
+                req.accept_into("Desktop/batch");
+            }
+        }
+    });
+
+    let (files, id, path) = one_file(src.path());
+    let mut dev = DeviceInfo::new("Sender".to_string(), 0, Protocol::Http);
+    dev.fingerprint = "sender-fp".to_string();
+    let client = LocalSendClient::new(dev);
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, files, None)
+        .await
+        .expect("accepted")
+        .expect("a session");
+    let token = prep.files.get(&id).unwrap().clone();
+    client
+        .upload_file(&target, &prep.session_id, &id, &token, &path, None)
+        .await
+        .unwrap();
+
+    assert!(save.path().join("Desktop/batch/a.bin").exists());
+    assert!(!save.path().join("a.bin").exists());
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn an_unsafe_save_dir_override_refuses_the_transfer() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = start_receiver(save.path().to_path_buf()).await;
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    tokio::spawn(async move {
+        while let Some(ev) = events.recv().await {
+            if let ServerEvent::TransferRequest(req) = ev {
+                req.accept_into("../outside");
+            }
+        }
+    });
+
+    let (files, _id, _path) = one_file(src.path());
+    let client = LocalSendClient::new(DeviceInfo::new("Sender".to_string(), 0, Protocol::Http));
+    let err = client
+        .prepare_upload(&common::target_device(port), files, None)
+        .await
+        .expect_err("refused");
+    assert!(matches!(err, LocalSendError::Rejected { status: 403 }));
+    server.stop().await;
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn event_consumer_can_decline_a_transfer() {
     let save = tempfile::tempdir().unwrap();
@@ -102,6 +212,53 @@ async fn event_consumer_can_decline_a_transfer() {
     server.stop().await;
 }
 
+/// A declined offer leaves an audit record tying the reason to the sender.
+#[tokio::test(flavor = "multi_thread")]
+async fn declined_transfer_emits_a_structured_rejection() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = start_receiver(save.path().to_path_buf()).await;
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (rejection_tx, rejection_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let mut rejection_tx = Some(rejection_tx);
+        while let Some(ev) = events.recv().await {
+            match ev {
+                ServerEvent::TransferRequest(req) => req.decline(),
+                ServerEvent::TransferRejected(rejection) => {
+                    if let Some(tx) = rejection_tx.take() {
+                        let _ = tx.send(rejection);
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+
+    let (files, _id, _path) = one_file(src.path());
+    let mut dev = DeviceInfo::new("Sender".to_string(), 0, Protocol::Http);
+    dev.fingerprint = "sender-fp".to_string();
+    let client = LocalSendClient::new(dev);
+    let target = common::target_device(port);
+    client
+        .prepare_upload(&target, files, None)
+        .await
+        .expect_err("declined");
+
+    let rejection = tokio::time::timeout(std::time::Duration::from_secs(5), rejection_rx)
+        .await
+        .expect("rejection record in time")
+        .expect("rejection record");
+    assert_eq!(rejection.sender_fingerprint, "sender-fp");
+    assert_eq!(rejection.sender_alias, "Sender");
+    assert_eq!(rejection.file_count, 1);
+    assert_eq!(rejection.total_size, 512);
+    assert_eq!(rejection.reason, RejectionReason::Declined);
+    server.stop().await;
+}
+
 /// Per-file accept: the consumer answers `accept_files` with a subset, and the
 /// server issues tokens only for the accepted ids (this is the exact library
 /// path the TUI's interactive confirm popup drives). The skipped file gets no
@@ -140,7 +297,8 @@ async fn event_consumer_can_accept_a_subset_of_files() {
     let prep = client
         .prepare_upload(&target, files, None)
         .await
-        .expect("subset accepted");
+        .expect("subset accepted")
+        .expect("a session");
 
     assert_eq!(prep.files.len(), 1, "only the kept file should get a token");
     assert!(prep.files.contains_key(&keep_id));
@@ -198,7 +356,8 @@ async fn set_auto_accept_true_takes_effect_live() {
     let prep = client
         .prepare_upload(&target, files, None)
         .await
-        .expect("auto-accept toggled on → accepted despite the declining consumer");
+        .expect("auto-accept toggled on → accepted despite the declining consumer")
+        .expect("a session");
     let token = prep.files.get(&id).unwrap().clone();
     client
         .upload_file(&target, &prep.session_id, &id, &token, &path, None)
@@ -207,3 +366,174 @@ async fn set_auto_accept_true_takes_effect_live() {
     assert!(save.path().join("a.bin").exists());
     server.stop().await;
 }
+
+/// Answers landing on either side of the accept deadline must resolve to one
+/// outcome: the consumer's `accept()` result matches what the sender was told,
+/// and no request leaves a session behind that blocks the next one.
+#[tokio::test(flavor = "multi_thread")]
+async fn accept_racing_the_timeout_has_one_winner() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Receiver")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .accept_timeout(std::time::Duration::from_millis(30))
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (won_tx, mut won_rx) = tokio::sync::mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let mut round = 0_u64;
+        while let Some(ev) = events.recv().await {
+            if let ServerEvent::TransferRequest(req) = ev {
+                // Sweep the answer across the 30 ms deadline.
+                let delay = 20 + round % 21;
+                round += 1;
+                tokio::time::sleep(std::time::Duration::from_millis(delay)).await;
+                let _ = won_tx.send(req.accept());
+            }
+        }
+    });
+
+    let (files, _, _) = one_file(src.path());
+    let mut dev = DeviceInfo::new("Sender".to_string(), 0, Protocol::Http);
+    dev.fingerprint = "sender-fp".to_string();
+    let client = LocalSendClient::new(dev);
+    let target = common::target_device(port);
+    let http = reqwest::Client::new();
+
+    for _ in 0..42 {
+        let result = client.prepare_upload(&target, files.clone(), None).await;
+        let accept_won = won_rx.recv().await.expect("consumer answered");
+        match result {
+            Ok(Some(prep)) => {
+                assert!(accept_won, "sender got a session the consumer lost");
+                http.post(format!(
+                    "http://127.0.0.1:{port}/api/localsend/v2/cancel?sessionId={}",
+                    prep.session_id
+                ))
+                .send()
+                .await
+                .unwrap();
+            }
+            Err(LocalSendError::Rejected { status: 403 }) => {
+                assert!(
+                    !accept_won,
+                    "consumer's accept won but the sender was refused"
+                );
+            }
+            Ok(None) => panic!("a file offer was answered without a session"),
+            Err(other) => panic!("inconsistent state after a race: {other:?}"),
+        }
+    }
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn auto_decline_rejects_without_waiting_for_an_answer() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Unattended")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_decline(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (files, _, _) = one_file(src.path());
+    let client = LocalSendClient::new(DeviceInfo::new("Stranger".to_string(), 0, Protocol::Http));
+    let started = std::time::Instant::now();
+    let result = client
+        .prepare_upload(&common::target_device(port), files, None)
+        .await;
+
+    assert!(matches!(
+        result,
+        Err(LocalSendError::Rejected { status: 403 })
+    ));
+    assert!(
+        started.elapsed() < std::time::Duration::from_secs(5),
+        "declined after {:?}",
+        started.elapsed()
+    );
+    let event = events.try_recv().expect("rejection is recorded");
+    assert!(matches!(
+        event,
+        ServerEvent::TransferRejected(ref rejection) if rejection.reason == RejectionReason::Declined
+    ));
+    assert!(events.try_recv().is_err(), "no TransferRequest was raised");
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn size_threshold_accepts_small_transfers_and_asks_about_large_ones() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Threshold")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept_below(10 * 1024 * 1024)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    let client = LocalSendClient::new(DeviceInfo::new("Sender".to_string(), 0, Protocol::Http));
+    let target = common::target_device(port);
+
+    // 1 KB: accepted without a prompt.
+    let (path, _) = common::make_random_file(src.path(), "small.bin", 1024);
+    let small = build_file_metadata(&path).await.unwrap();
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(small.id.clone(), small)]), None)
+        .await
+        .expect("small transfer is auto-accepted")
+        .expect("a session");
+    assert!(
+        !std::iter::from_fn(|| events.try_recv().ok())
+            .any(|event| matches!(event, ServerEvent::TransferRequest(_))),
+        "no confirmation was asked for"
+    );
+    client
+        .cancel(&target, &prep.session_id)
+        .await
+        .expect("free the session");
+
+    // 1 GB (declared only): goes through the confirmation flow.
+    let mut large = build_file_metadata(&path).await.unwrap();
+    large.size = 1024 * 1024 * 1024;
+    let prompt = tokio::spawn(async move {
+        loop {
+            match events.recv().await.expect("event stream") {
+                ServerEvent::TransferRequest(request) => {
+                    let total: u64 = request.files().values().map(|file| file.size).sum();
+                    request.decline();
+                    return total;
+                }
+                _ => continue,
+            }
+        }
+    });
+    let result = client
+        .prepare_upload(&target, HashMap::from([(large.id.clone(), large)]), None)
+        .await;
+    assert!(matches!(
+        result,
+        Err(LocalSendError::Rejected { status: 403 })
+    ));
+    assert_eq!(prompt.await.unwrap(), 1024 * 1024 * 1024);
+
+    server.stop().await;
+}
@@ -1,7 +1,7 @@
 mod common;
 
-use localsend_rs::Protocol;
-use localsend_rs::server::LocalSendServer;
+use localsend_rs::server::{LocalSendServer, ServerEvent};
+use localsend_rs::{AnnouncementMessage, DeviceInfo, LocalSendClient, Protocol};
 
 #[tokio::test]
 async fn builder_starts_on_ephemeral_port_and_reports_it() {
@@ -26,3 +26,148 @@ async fn builder_starts_on_ephemeral_port_and_reports_it() {
     assert_eq!(info["version"], "2.1");
     assert_eq!(info["port"], serde_json::json!(port));
 }
+
+#[tokio::test]
+async fn configured_device_model_is_advertised_in_info_and_announcements() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, _events) = LocalSendServer::builder()
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .device_model("ThinkPad X1 / Fedora")
+        .build()
+        .await
+        .expect("build");
+
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    let url = format!("http://127.0.0.1:{port}/api/localsend/v2/info");
+    let info: serde_json::Value = reqwest::get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(info["deviceModel"], "ThinkPad X1 / Fedora");
+
+    // Discovery announces the server's own identity.
+    let announcement = AnnouncementMessage::from_device(server.device(), true);
+    let announcement = serde_json::to_value(&announcement).unwrap();
+    assert_eq!(announcement["deviceModel"], "ThinkPad X1 / Fedora");
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn restart_moves_the_listener_to_the_new_port() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Restart Test")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .build()
+        .await
+        .expect("build");
+    let old_port = server.port();
+    common::wait_for_http_info(old_port).await;
+    let fingerprint = server.device().fingerprint.clone();
+
+    let new_port = common::free_port();
+    server.restart(new_port).await.expect("restart");
+    assert_eq!(server.port(), new_port);
+    assert_ne!(new_port, old_port);
+
+    common::wait_for_http_info(new_port).await;
+    let url = format!("http://127.0.0.1:{new_port}/api/localsend/v2/info");
+    let info: serde_json::Value = reqwest::get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(info["port"], serde_json::json!(new_port));
+    assert_eq!(info["fingerprint"], serde_json::json!(fingerprint));
+
+    let old_url = format!("http://127.0.0.1:{old_port}/api/localsend/v2/info");
+    assert!(
+        reqwest::get(&old_url).await.is_err(),
+        "the old port must stop answering"
+    );
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn a_restart_onto_a_taken_port_keeps_the_old_listener() {
+    let save = tempfile::tempdir().unwrap();
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Restart Test")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .build()
+        .await
+        .expect("build");
+    let old_port = server.port();
+    common::wait_for_http_info(old_port).await;
+
+    let taken = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+    let taken_port = taken.local_addr().unwrap().port();
+    assert!(server.restart(taken_port).await.is_err());
+    assert_eq!(server.port(), old_port);
+
+    let url = format!("http://127.0.0.1:{old_port}/api/localsend/v2/info");
+    let info: serde_json::Value = reqwest::get(&url).await.unwrap().json().await.unwrap();
+    assert_eq!(info["port"], serde_json::json!(old_port));
+
+    drop(taken);
+    server.restart(taken_port).await.expect("restart once free");
+    assert_eq!(server.port(), taken_port);
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn a_register_request_is_reported_with_the_peers_address() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("Receiver")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let mut sender = DeviceInfo::new("Sender".into(), 53317, Protocol::Http);
+    sender.fingerprint = "sender-fp".into();
+    sender.ip = Some("10.9.9.9".into());
+    LocalSendClient::new(sender)
+        .register(&common::target_device(port))
+        .await
+        .unwrap();
+
+    let event = tokio::time::timeout(std::time::Duration::from_secs(5), events.recv())
+        .await
+        .unwrap();
+    let Some(ServerEvent::DeviceRegistered(device)) = event else {
+        panic!("expected DeviceRegistered, got {event:?}");
+    };
+    assert_eq!(device.fingerprint, "sender-fp");
+    assert_eq!(device.ip.as_deref(), Some("127.0.0.1"));
+}
+
+#[tokio::test]
+async fn a_register_request_with_an_unusable_port_is_not_reported() {
+    let save = tempfile::tempdir().unwrap();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("Receiver")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let mut sender = DeviceInfo::new("Sender".into(), 0, Protocol::Http);
+    sender.fingerprint = "sender-fp".into();
+    LocalSendClient::new(sender)
+        .register(&common::target_device(port))
+        .await
+        .expect("still answered");
+    assert!(events.try_recv().is_err(), "port 0 cannot be listed");
+}
@@ -290,7 +290,8 @@ async fn one_listener_serves_protected_and_standard_modes_without_token_confusio
             None,
         )
         .await
-        .expect("standard prepare on same listener");
+        .expect("standard prepare on same listener")
+        .expect("a session");
     let standard_token = prepared_standard.files.get(&standard_id).expect("token");
     client
         .upload_file(
@@ -624,7 +625,8 @@ async fn standard_and_protected_upload_tokens_cannot_cross_modes() {
             None,
         )
         .await
-        .expect("standard prepare");
+        .expect("standard prepare")
+        .expect("a session");
     assert_eq!(
         http.post(format!(
             "http://127.0.0.1:{port}/api/localsend/v2/upload?sessionId={}&fileId={}&token={}",
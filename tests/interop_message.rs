@@ -62,15 +62,12 @@ async fn message_path_emits_text_received_without_saving_and_session_done() {
         .await
         .expect("prepare-upload should succeed for a message-shaped offer");
 
-    // The message path answers 204: session_id comes back empty and the
-    // client must NOT call `upload` (the content already rode along in
-    // `preview`).
+    // The message path answers 204: there is no session and the client must
+    // NOT call `upload` (the content already rode along in `preview`).
     assert!(
-        prep.session_id.as_str().is_empty(),
-        "message-shaped prepare-upload should return 204 (empty session id), got {:?}",
-        prep.session_id
+        prep.is_none(),
+        "message-shaped prepare-upload should return 204 (no session), got {prep:?}"
     );
-    assert!(prep.files.is_empty());
 
     // Collect events with a bounded wait -- the handler emits them via
     // `try_send` synchronously before responding, but give the receiver
@@ -116,3 +113,54 @@ async fn message_path_emits_text_received_without_saving_and_session_done() {
 
     server.stop().await;
 }
+
+/// An image offered with its thumbnail in `preview` (as `send --previews`
+/// does) is a file, not a text message: it gets a session and is saved.
+#[tokio::test]
+async fn a_single_small_image_with_a_preview_is_still_uploaded() {
+    let save = tempfile::tempdir().expect("save dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Receiver")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let image = b"\x89PNG\r\n\x1a\n not really an image".to_vec();
+    let file_id = localsend_rs::FileId::new();
+    let metadata = FileMetadata {
+        id: file_id.clone(),
+        file_name: "photo.png".to_string(),
+        size: image.len() as u64,
+        file_type: "image/png".to_string(),
+        sha256: None,
+        preview: Some("data:image/jpeg;base64,/9j/4AAQSkZJRg==".to_string()),
+        metadata: None,
+    };
+    let client = LocalSendClient::new(DeviceInfo::new("Sender".to_string(), 0, Protocol::Http));
+    let target = common::target_device(port);
+
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), metadata)]), None)
+        .await
+        .expect("prepare-upload")
+        .expect("an image offer opens a session");
+    client
+        .upload_bytes(
+            &target,
+            &prep.session_id,
+            &file_id,
+            &prep.files[&file_id],
+            image.clone(),
+        )
+        .await
+        .expect("upload");
+
+    assert_eq!(std::fs::read(save.path().join("photo.png")).unwrap(), image);
+    server.stop().await;
+}
@@ -1,5 +1,6 @@
 mod common;
 
+use localsend_rs::core::MockClock;
 use localsend_rs::server::LocalSendServer;
 use localsend_rs::{DeviceInfo, LocalSendClient, Protocol, build_file_metadata};
 use std::collections::HashMap;
@@ -44,7 +45,11 @@ async fn multi_file_session_completes_and_frees_the_slot() {
         shas.insert(name.to_string(), sha);
         files.insert(m.id.clone(), m);
     }
-    let prep = c.prepare_upload(&target, files, None).await.unwrap();
+    let prep = c
+        .prepare_upload(&target, files, None)
+        .await
+        .unwrap()
+        .unwrap();
     assert_eq!(prep.files.len(), 3);
     for (id, token) in &prep.files {
         c.upload_file(&target, &prep.session_id, id, token, &paths[id], None)
@@ -82,7 +87,7 @@ async fn concurrent_second_session_is_blocked_with_409() {
     let m = build_file_metadata(&p).await.unwrap();
     let mut f = HashMap::new();
     f.insert(m.id.clone(), m);
-    let _prep = c.prepare_upload(&target, f, None).await.unwrap(); // session open, file NOT uploaded
+    let _prep = c.prepare_upload(&target, f, None).await.unwrap().unwrap(); // session open, file NOT uploaded
 
     let (p2, _) = common::make_random_file(src.path(), "blocked.bin", 128);
     let m2 = build_file_metadata(&p2).await.unwrap();
@@ -95,6 +100,180 @@ async fn concurrent_second_session_is_blocked_with_409() {
     assert!(matches!(err, localsend_rs::LocalSendError::SessionBlocked));
 }
 
+#[tokio::test]
+async fn sessions_up_to_the_cap_transfer_side_by_side() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .max_sessions(2)
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    let c = client();
+    let target = common::target_device(port);
+
+    let mut sent = Vec::new();
+    for name in ["first.bin", "second.bin"] {
+        let (p, sha) = common::make_random_file(src.path(), name, 4096);
+        let m = build_file_metadata(&p).await.unwrap();
+        let id = m.id.clone();
+        let prep = c
+            .prepare_upload(&target, HashMap::from([(id.clone(), m)]), None)
+            .await
+            .unwrap()
+            .unwrap();
+        sent.push((name, p, sha, id, prep));
+    }
+    assert_ne!(sent[0].4.session_id, sent[1].4.session_id);
+
+    // Both slots are taken.
+    let (p3, _) = common::make_random_file(src.path(), "third.bin", 128);
+    let m3 = build_file_metadata(&p3).await.unwrap();
+    assert!(matches!(
+        c.prepare_upload(&target, HashMap::from([(m3.id.clone(), m3.clone())]), None)
+            .await,
+        Err(localsend_rs::LocalSendError::SessionBlocked)
+    ));
+
+    // Each upload is matched to its own session, in either order.
+    for (_, path, _, id, prep) in sent.iter().rev() {
+        c.upload_file(&target, &prep.session_id, id, &prep.files[id], path, None)
+            .await
+            .unwrap();
+    }
+    for (name, _, sha, _, _) in &sent {
+        let got = localsend_rs::sha256_from_file(&save.path().join(name))
+            .await
+            .unwrap();
+        assert_eq!(&got, sha);
+    }
+
+    // Both sessions closed on their last file, freeing the slots.
+    assert!(
+        c.prepare_upload(&target, HashMap::from([(m3.id.clone(), m3)]), None)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn stale_session_is_replaced_once_the_clock_passes_the_ttl() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let clock = MockClock::new();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .clock(std::sync::Arc::new(clock.clone()))
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    let c = client();
+    let target = common::target_device(port);
+
+    let offer = |name: &str| {
+        let (p, _) = common::make_random_file(src.path(), name, 128);
+        async move {
+            let m = build_file_metadata(&p).await.unwrap();
+            HashMap::from([(m.id.clone(), m)])
+        }
+    };
+    c.prepare_upload(&target, offer("idle.bin").await, None)
+        .await
+        .unwrap()
+        .unwrap(); // session open, file never uploaded
+    assert!(matches!(
+        c.prepare_upload(&target, offer("early.bin").await, None)
+            .await,
+        Err(localsend_rs::LocalSendError::SessionBlocked)
+    ));
+
+    // Past the 300 s idle TTL without any real waiting.
+    clock.advance(std::time::Duration::from_secs(301));
+    assert!(
+        c.prepare_upload(&target, offer("late.bin").await, None)
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn the_sweeper_drops_an_idle_session_and_its_partials() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let clock = MockClock::new();
+    let (server, mut events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .resumable_uploads(true)
+        .session_timeout(std::time::Duration::from_secs(2))
+        // The session timeout never undercuts the accept timeout.
+        .accept_timeout(std::time::Duration::from_secs(2))
+        .clock(std::sync::Arc::new(clock.clone()))
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    let c = client();
+    let target = common::target_device(port);
+
+    let (p, _) = common::make_random_file(src.path(), "cut.bin", 4096);
+    let m = build_file_metadata(&p).await.unwrap();
+    let id = m.id.clone();
+    let prep = c
+        .prepare_upload(&target, HashMap::from([(id.clone(), m)]), None)
+        .await
+        .unwrap()
+        .unwrap();
+    // The sender gets 100 bytes in, then disappears.
+    let head = std::fs::read(&p).unwrap()[..100].to_vec();
+    assert!(
+        c.upload_bytes(&target, &prep.session_id, &id, &prep.files[&id], head)
+            .await
+            .is_err()
+    );
+    let partial = save.path().join("cut.bin.lspart");
+    assert!(partial.exists());
+
+    clock.advance(std::time::Duration::from_secs(3));
+    let swept = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            if let Some(localsend_rs::server::ServerEvent::SessionDone { session_id }) =
+                events.recv().await
+            {
+                break session_id;
+            }
+        }
+    })
+    .await
+    .expect("the sweeper ends the session");
+    assert_eq!(swept, prep.session_id);
+    // The partial goes right after the event.
+    for _ in 0..50 {
+        if !partial.exists() {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    panic!("the swept session's partial must be deleted");
+}
+
 #[tokio::test]
 async fn same_filename_twice_keeps_both_copies() {
     let save = tempfile::tempdir().unwrap();
@@ -111,7 +290,7 @@ async fn same_filename_twice_keeps_both_copies() {
         let id = m.id.clone();
         let mut f = HashMap::new();
         f.insert(id.clone(), m);
-        let prep = c.prepare_upload(&target, f, None).await.unwrap();
+        let prep = c.prepare_upload(&target, f, None).await.unwrap().unwrap();
         let token = prep.files.get(&id).unwrap().clone();
         c.upload_file(&target, &prep.session_id, &id, &token, &p, None)
             .await
@@ -133,7 +312,7 @@ async fn cancel_frees_the_session_slot() {
     let m = build_file_metadata(&p).await.unwrap();
     let mut f = HashMap::new();
     f.insert(m.id.clone(), m);
-    let prep = c.prepare_upload(&target, f, None).await.unwrap();
+    let prep = c.prepare_upload(&target, f, None).await.unwrap().unwrap();
 
     c.cancel(&target, &prep.session_id)
         .await
@@ -161,10 +340,10 @@ async fn upload_reports_monotonic_progress_up_to_total() {
     let id = m.id.clone();
     let mut f = HashMap::new();
     f.insert(id.clone(), m);
-    let prep = c.prepare_upload(&target, f, None).await.unwrap();
+    let prep = c.prepare_upload(&target, f, None).await.unwrap().unwrap();
     let token = prep.files.get(&id).unwrap().clone();
 
-    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::<(u64, u64)>::new()));
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::<(u64, u64, f64)>::new()));
     let seen_cb = seen.clone();
     c.upload_file(
         &target,
@@ -172,8 +351,8 @@ async fn upload_reports_monotonic_progress_up_to_total() {
         &id,
         &token,
         &p,
-        Some(Box::new(move |sent, total, _elapsed| {
-            seen_cb.lock().unwrap().push((sent, total));
+        Some(Box::new(move |sent, total, ratio| {
+            seen_cb.lock().unwrap().push((sent, total, ratio));
         })),
     )
     .await
@@ -189,6 +368,150 @@ async fn upload_reports_monotonic_progress_up_to_total() {
         seen.windows(2).all(|w| w[0].0 <= w[1].0),
         "progress must be monotonic"
     );
-    assert_eq!(seen.last().unwrap().0, SIZE as u64);
-    assert!(seen.iter().all(|(_, t)| *t == SIZE as u64));
+    assert_eq!(seen.first(), Some(&(0, SIZE as u64, 0.0)));
+    assert_eq!(seen.last(), Some(&(SIZE as u64, SIZE as u64, 1.0)));
+    assert!(seen.iter().all(|(_, t, _)| *t == SIZE as u64));
+    assert!(
+        seen.iter()
+            .all(|(sent, total, ratio)| (*ratio - *sent as f64 / *total as f64).abs() < 1e-9),
+        "ratio must be bytes_sent / total_bytes"
+    );
+}
+
+#[tokio::test]
+async fn empty_file_upload_still_reports_start_and_completion() {
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let (_server, port) = receiver(save.path()).await;
+    let c = client();
+    let target = common::target_device(port);
+
+    let p = src.path().join("empty.txt");
+    std::fs::write(&p, b"").unwrap();
+    let m = build_file_metadata(&p).await.unwrap();
+    let id = m.id.clone();
+    let prep = c
+        .prepare_upload(&target, HashMap::from([(id.clone(), m)]), None)
+        .await
+        .unwrap()
+        .unwrap();
+    let token = prep.files.get(&id).unwrap().clone();
+
+    let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::<(u64, u64, f64)>::new()));
+    let seen_cb = seen.clone();
+    c.upload_file(
+        &target,
+        &prep.session_id,
+        &id,
+        &token,
+        &p,
+        Some(Box::new(move |sent, total, ratio| {
+            seen_cb.lock().unwrap().push((sent, total, ratio));
+        })),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(*seen.lock().unwrap(), [(0, 0, 0.0), (0, 0, 1.0)]);
+}
+
+#[tokio::test]
+async fn a_body_streaming_past_the_ttl_keeps_its_multi_file_session() {
+    use tokio::io::AsyncWriteExt;
+
+    let save = tempfile::tempdir().unwrap();
+    let src = tempfile::tempdir().unwrap();
+    let clock = MockClock::new();
+    let (server, _events) = LocalSendServer::builder()
+        .alias("R")
+        .port(0)
+        .save_dir(save.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .clock(std::sync::Arc::new(clock.clone()))
+        .build()
+        .await
+        .unwrap();
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+    let c = client();
+    let target = common::target_device(port);
+
+    const SIZE: usize = 64 * 1024;
+    let (slow_path, _) = common::make_random_file(src.path(), "slow.bin", SIZE);
+    let (fast_path, _) = common::make_random_file(src.path(), "fast.bin", 512);
+    let slow = build_file_metadata(&slow_path).await.unwrap();
+    let fast = build_file_metadata(&fast_path).await.unwrap();
+    let (slow_id, fast_id) = (slow.id.clone(), fast.id.clone());
+    let prep = c
+        .prepare_upload(
+            &target,
+            HashMap::from([(slow_id.clone(), slow), (fast_id.clone(), fast)]),
+            None,
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+    let contents = std::fs::read(&slow_path).unwrap();
+    let (mut feed, reader) = tokio::io::duplex(1024);
+    let upload = {
+        let c = c.clone();
+        let target = target.clone();
+        let session_id = prep.session_id.clone();
+        let token = prep.files[&slow_id].clone();
+        let slow_id = slow_id.clone();
+        tokio::spawn(async move {
+            c.upload_reader(
+                &target,
+                &session_id,
+                &slow_id,
+                &token,
+                reader,
+                SIZE as u64,
+                None,
+            )
+            .await
+        })
+    };
+
+    feed.write_all(&contents[..SIZE / 2]).await.unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    // The session is older than the TTL, but its body is still arriving.
+    clock.advance(std::time::Duration::from_secs(301));
+    feed.write_all(&contents[SIZE / 2..SIZE * 3 / 4])
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+    let (other, _) = common::make_random_file(src.path(), "other.bin", 128);
+    let other = build_file_metadata(&other).await.unwrap();
+    assert!(matches!(
+        c.prepare_upload(&target, HashMap::from([(other.id.clone(), other)]), None)
+            .await,
+        Err(localsend_rs::LocalSendError::SessionBlocked)
+    ));
+
+    feed.write_all(&contents[SIZE * 3 / 4..]).await.unwrap();
+    drop(feed);
+    upload.await.unwrap().unwrap();
+    c.upload_file(
+        &target,
+        &prep.session_id,
+        &fast_id,
+        &prep.files[&fast_id],
+        &fast_path,
+        None,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read(save.path().join("slow.bin")).unwrap(),
+        contents
+    );
+    assert_eq!(
+        std::fs::read(save.path().join("fast.bin")).unwrap(),
+        std::fs::read(&fast_path).unwrap()
+    );
 }
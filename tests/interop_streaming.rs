@@ -0,0 +1,107 @@
+//! Memory bounds of a large transfer. This binary installs a counting
+//! allocator, so it holds only tests that measure heap use.
+
+mod common;
+
+use localsend_rs::server::LocalSendServer;
+use localsend_rs::{DeviceInfo, LocalSendClient, Protocol, build_file_metadata};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct PeakTracking;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakTracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let now = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(now, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: PeakTracking = PeakTracking;
+
+const FILE_SIZE: usize = 100 * 1024 * 1024;
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_100_mb_upload_runs_in_bounded_memory() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+
+    // Written in chunks: the fixture itself must not hold 100 MB either.
+    let path = src_dir.path().join("video.bin");
+    let mut file = std::fs::File::create(&path).unwrap();
+    let chunk: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+    for _ in 0..FILE_SIZE / chunk.len() {
+        file.write_all(&chunk).unwrap();
+    }
+    drop(file);
+
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Streaming Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let meta = build_file_metadata(&path).await.expect("metadata");
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Streaming Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    client
+        .upload_file(
+            &target,
+            &prep.session_id,
+            &file_id,
+            &prep.files[&file_id],
+            &path,
+            None,
+        )
+        .await
+        .expect("upload");
+    let growth = PEAK.load(Ordering::Relaxed).saturating_sub(baseline);
+
+    assert_eq!(
+        std::fs::metadata(save_dir.path().join("video.bin"))
+            .unwrap()
+            .len(),
+        FILE_SIZE as u64
+    );
+    // Sender and receiver together, in one process.
+    assert!(
+        growth < FILE_SIZE / 10,
+        "heap grew by {growth} bytes while moving {FILE_SIZE}"
+    );
+
+    server.stop().await;
+}
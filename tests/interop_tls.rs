@@ -165,3 +165,79 @@ async fn http_requests_bypass_the_tls_verifier() {
         .expect("HTTP should not invoke TLS verification");
     server.stop().await;
 }
+
+#[tokio::test]
+async fn a_certificate_loaded_from_pem_keeps_the_pinned_identity_across_restarts() {
+    let output = tempfile::tempdir().expect("output directory");
+    let saved = generate_tls_certificate().expect("certificate");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Stable receiver")
+        .port(0)
+        .save_dir(output.path())
+        .protocol(Protocol::Https)
+        .build()
+        .await
+        .expect("start HTTPS receiver");
+    assert_ne!(server.device().fingerprint, saved.fingerprint);
+
+    server
+        .set_tls_certificate_from_pem(saved.cert_pem.clone(), saved.key_pem.clone())
+        .expect("a valid certificate and key");
+    assert_eq!(server.device().fingerprint, saved.fingerprint);
+    server
+        .restart(0)
+        .await
+        .expect("restart with the saved certificate");
+
+    let mut target = server.device().clone();
+    target.ip = Some("127.0.0.1".into());
+    let client = LocalSendClient::with_trust_policy(
+        DeviceInfo::new("Pinned sender".into(), 0, Protocol::Https),
+        TlsTrustPolicy::new([saved.fingerprint.clone()]),
+    )
+    .expect("pinned client");
+    client
+        .register(&target)
+        .await
+        .expect("the saved certificate is served");
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn the_generated_certificate_passes_name_checks_by_ip() {
+    let output = tempfile::tempdir().expect("output directory");
+    let certificate = generate_tls_certificate().expect("certificate");
+    let root = reqwest::Certificate::from_pem(certificate.cert_pem.as_bytes()).expect("root");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Verified receiver")
+        .port(0)
+        .save_dir(output.path())
+        .protocol(Protocol::Https)
+        .tls_certificate(certificate)
+        .build()
+        .await
+        .expect("start HTTPS receiver");
+
+    // Trusts the certificate but still checks it names the address dialled.
+    let client = reqwest::Client::builder()
+        .tls_certs_only([root])
+        .build()
+        .expect("verifying client");
+    let info_url = format!("https://127.0.0.1:{}/api/localsend/v2/info", server.port());
+    let mut last_error = None;
+    for _ in 0..50 {
+        match client.get(&info_url).send().await {
+            Ok(response) => {
+                assert!(response.status().is_success());
+                last_error = None;
+                break;
+            }
+            Err(error) => last_error = Some(error),
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(last_error.is_none(), "{last_error:?}");
+
+    server.stop().await;
+}
@@ -1,6 +1,9 @@
 mod common;
 
-use localsend_rs::server::{LocalSendServer, ServerEvent};
+use localsend_rs::client::ProgressUpdate;
+use localsend_rs::server::{
+    DuplicatePolicy, LocalSendServer, ReceiveHistory, ReceiveSummary, ServerEvent,
+};
 use localsend_rs::{DeviceInfo, LocalSendClient, Protocol, build_file_metadata, sha256_from_file};
 use std::collections::HashMap;
 
@@ -38,7 +41,8 @@ async fn uploads_a_file_byte_for_byte_rs_to_rs() {
     let prep = client
         .prepare_upload(&target, files, None)
         .await
-        .expect("prepare");
+        .expect("prepare")
+        .expect("a session");
     let token = prep.files.get(&file_id).expect("token").clone();
     client
         .upload_file(
@@ -126,7 +130,8 @@ async fn upload_completes_when_progress_event_channel_is_not_drained() {
     let prep = client
         .prepare_upload(&target, files, None)
         .await
-        .expect("prepare");
+        .expect("prepare")
+        .expect("a session");
     let token = prep.files.get(&file_id).expect("token").clone();
 
     tokio::time::timeout(
@@ -150,3 +155,818 @@ async fn upload_completes_when_progress_event_channel_is_not_drained() {
     assert_eq!(got_sha, want_sha);
     server.stop().await;
 }
+
+#[tokio::test]
+async fn progress_channel_reports_increasing_samples_then_completion() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Test Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    const SIZE: usize = 2 * 1024 * 1024; // several chunks
+    let (file_path, _) = common::make_random_file(src_dir.path(), "chan.bin", SIZE);
+    let meta = build_file_metadata(&file_path).await.expect("metadata");
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Test Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+    let token = prep.files.get(&file_id).expect("token").clone();
+
+    let (upload, mut progress) =
+        client.upload_file_with_progress(&target, &prep.session_id, &file_id, &token, &file_path);
+    let drain = async {
+        let mut updates = Vec::new();
+        while let Some(update) = progress.recv().await {
+            updates.push(update);
+        }
+        updates
+    };
+    let (result, updates) = tokio::join!(upload, drain);
+    result.expect("upload");
+
+    let (last, samples) = updates.split_last().expect("at least one update");
+    assert_eq!(
+        *last,
+        ProgressUpdate::Completed {
+            total_bytes: SIZE as u64
+        }
+    );
+    let sent: Vec<u64> = samples
+        .iter()
+        .map(|update| match update {
+            ProgressUpdate::Sent {
+                bytes_sent,
+                total_bytes,
+            } => {
+                assert_eq!(*total_bytes, SIZE as u64);
+                *bytes_sent
+            }
+            other => panic!("completion must be last, got {other:?}"),
+        })
+        .collect();
+    assert!(!sent.is_empty(), "expected per-chunk samples");
+    assert!(
+        sent.windows(2).all(|w| w[0] < w[1]),
+        "progress must increase"
+    );
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn a_progress_upload_completes_without_anyone_reading_the_channel() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Test Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    // Far more samples than the channel holds.
+    const SIZE: usize = 2 * 1024 * 1024;
+    let (file_path, _) = common::make_random_file(src_dir.path(), "undrained.bin", SIZE);
+    let meta = build_file_metadata(&file_path).await.expect("metadata");
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Test Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+    let token = prep.files.get(&file_id).expect("token").clone();
+
+    let (upload, mut progress) =
+        client.upload_file_with_progress(&target, &prep.session_id, &file_id, &token, &file_path);
+    tokio::time::timeout(std::time::Duration::from_secs(30), upload)
+        .await
+        .expect("the upload must not wait on the progress channel")
+        .expect("upload");
+
+    let mut last = None;
+    while let Some(update) = progress.recv().await {
+        last = Some(update);
+    }
+    assert_eq!(
+        last,
+        Some(ProgressUpdate::Completed {
+            total_bytes: SIZE as u64
+        })
+    );
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn receive_summary_counts_every_file_of_a_session() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Summary Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let mut paths = HashMap::new();
+    let mut files = HashMap::new();
+    for (name, size) in [("one.bin", 300), ("two.bin", 700)] {
+        let (path, _) = common::make_random_file(src_dir.path(), name, size);
+        let meta = build_file_metadata(&path).await.expect("metadata");
+        paths.insert(meta.id.clone(), path);
+        files.insert(meta.id.clone(), meta);
+    }
+
+    let mut sender_dev = DeviceInfo::new("Summary Sender".to_string(), 0, Protocol::Http);
+    sender_dev.fingerprint = "summary-sender-fp".to_string();
+    let client = LocalSendClient::new(sender_dev);
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, files, None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+    for (file_id, token) in &prep.files {
+        client
+            .upload_file(
+                &target,
+                &prep.session_id,
+                file_id,
+                token,
+                &paths[file_id],
+                None,
+            )
+            .await
+            .expect("upload");
+    }
+
+    let mut summary = ReceiveSummary::default();
+    loop {
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), events.recv())
+            .await
+            .expect("receiver event should arrive")
+            .expect("receiver event stream should stay open");
+        summary.record(&event);
+        if matches!(event, ServerEvent::SessionDone { .. }) {
+            break;
+        }
+    }
+
+    assert_eq!(summary.files_received, 2);
+    assert_eq!(summary.total_bytes, 1_000);
+    assert_eq!(summary.senders, ["Summary Sender"]);
+
+    server.stop().await;
+}
+
+/// Send `path` as a one-file session and return the receiver's events up to
+/// and including `SessionDone`.
+async fn send_one(
+    client: &LocalSendClient,
+    target: &DeviceInfo,
+    events: &mut tokio::sync::mpsc::Receiver<ServerEvent>,
+    path: &std::path::Path,
+    declare_sha: bool,
+) -> Vec<ServerEvent> {
+    let mut meta = build_file_metadata(path).await.expect("metadata");
+    if declare_sha {
+        meta.sha256 = Some(sha256_from_file(path).await.expect("hash"));
+    }
+    let file_id = meta.id.clone();
+    let prep = client
+        .prepare_upload(target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+    client
+        .upload_file(
+            target,
+            &prep.session_id,
+            &file_id,
+            &prep.files[&file_id],
+            path,
+            None,
+        )
+        .await
+        .expect("upload");
+
+    let mut seen = Vec::new();
+    loop {
+        let event = tokio::time::timeout(std::time::Duration::from_secs(2), events.recv())
+            .await
+            .expect("receiver event should arrive")
+            .expect("receiver event stream should stay open");
+        let done = matches!(event, ServerEvent::SessionDone { .. });
+        seen.push(event);
+        if done {
+            return seen;
+        }
+    }
+}
+
+#[tokio::test]
+async fn re_receiving_an_identical_file_is_recorded_as_a_skip() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Dedup Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .duplicate_policy(DuplicatePolicy::SkipIdentical)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, _) = common::make_random_file(src_dir.path(), "photo.jpg", 2_048);
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Dedup Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+
+    let mut summary = ReceiveSummary::default();
+    // First copy lands; the repeats are discarded whether the sender declares
+    // a sha256 or the receiver has to hash the upload itself.
+    for declare_sha in [false, false, true] {
+        for event in send_one(&client, &target, &mut events, &path, declare_sha).await {
+            summary.record(&event);
+        }
+    }
+    assert_eq!(summary.files_received, 1);
+    assert_eq!(summary.duplicates_skipped, 2);
+
+    // Same name, different bytes: still renamed, never skipped.
+    let (changed, _) = common::make_random_file(src_dir.path(), "photo.jpg", 4_096);
+    let seen = send_one(&client, &target, &mut events, &changed, true).await;
+    assert!(seen.iter().any(|event| matches!(
+        event,
+        ServerEvent::FileReceived { file_name, .. } if file_name == "photo (1).jpg"
+    )));
+
+    let mut names: Vec<_> = std::fs::read_dir(save_dir.path())
+        .expect("read save dir")
+        .map(|entry| entry.expect("entry").file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["photo (1).jpg", "photo.jpg"]);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn in_memory_mode_hands_the_bytes_to_the_callback_and_writes_nothing() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let delivered = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = delivered.clone();
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Memory Receiver")
+        .port(0)
+        .save_dir(save_dir.path().join("never-created"))
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .receive_into_memory(move |metadata, bytes| {
+            sink.lock().unwrap().push((metadata, bytes));
+        })
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, _) = common::make_random_file(src_dir.path(), "payload.bin", 70_000);
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Memory Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let seen = send_one(&client, &target, &mut events, &path, true).await;
+
+    let delivered = std::mem::take(&mut *delivered.lock().unwrap());
+    assert_eq!(delivered.len(), 1);
+    let (metadata, bytes) = &delivered[0];
+    assert_eq!(metadata.file_name, "payload.bin");
+    assert_eq!(bytes, &std::fs::read(&path).unwrap());
+    assert!(seen.iter().any(|event| matches!(
+        event,
+        ServerEvent::FileReceived { size: 70_000, path, .. } if path.as_os_str().is_empty()
+    )));
+    assert!(!save_dir.path().join("never-created").exists());
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn two_files_declaring_the_same_name_are_saved_side_by_side() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Same Name Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    // Two different photos from two folders, both offered as "photo.jpg".
+    let mut sources = Vec::new();
+    let mut files = HashMap::new();
+    for (folder, size) in [("front", 40_000), ("back", 50_000)] {
+        let dir = src_dir.path().join(folder);
+        std::fs::create_dir_all(&dir).unwrap();
+        let (path, _) = common::make_random_file(&dir, "photo.jpg", size);
+        let meta = build_file_metadata(&path).await.expect("metadata");
+        sources.push((meta.id.clone(), path));
+        files.insert(meta.id.clone(), meta);
+    }
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Same Name Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, files, None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+
+    // Upload both at once, so neither can rely on seeing the other on disk.
+    let uploads = sources.iter().map(|(file_id, path)| {
+        client.upload_file(
+            &target,
+            &prep.session_id,
+            file_id,
+            &prep.files[file_id],
+            path,
+            None,
+        )
+    });
+    for result in futures_util::future::join_all(uploads).await {
+        result.expect("upload");
+    }
+
+    let mut saved: Vec<_> = std::fs::read_dir(save_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    saved.sort();
+    let names: Vec<_> = saved
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, ["photo (1).jpg", "photo.jpg"]);
+    let mut sizes: Vec<_> = saved
+        .iter()
+        .map(|path| std::fs::metadata(path).unwrap().len())
+        .collect();
+    sizes.sort();
+    assert_eq!(sizes, [40_000, 50_000]);
+
+    server.stop().await;
+}
+
+#[cfg(feature = "encryption")]
+#[tokio::test]
+async fn encrypted_at_rest_upload_decrypts_back_to_the_original_bytes() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Encrypting Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .encrypt_at_rest("hunter2")
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, _) = common::make_random_file(src_dir.path(), "notes.bin", 200_000);
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Plain Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let seen = send_one(&client, &target, &mut events, &path, true).await;
+
+    let stored = save_dir.path().join("notes.bin.lsenc");
+    assert!(seen.iter().any(|event| matches!(
+        event,
+        ServerEvent::FileReceived { path, .. } if path == &stored
+    )));
+    assert!(!save_dir.path().join("notes.bin").exists());
+    let original = std::fs::read(&path).unwrap();
+    assert_ne!(std::fs::read(&stored).unwrap(), original);
+
+    let restored = save_dir.path().join("notes.bin");
+    let size = localsend_rs::decrypt_file(&stored, "hunter2", &restored, false)
+        .await
+        .expect("decrypt");
+    assert_eq!(size, original.len() as u64);
+    assert_eq!(std::fs::read(&restored).unwrap(), original);
+    assert!(
+        localsend_rs::decrypt_file(&stored, "wrong", &restored, true)
+            .await
+            .is_err()
+    );
+    assert_eq!(std::fs::read(&restored).unwrap(), original);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn mirror_dir_receives_a_copy_of_every_file() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let mirror_dir = tempfile::tempdir().expect("mirror dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Mirroring Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .mirror_dir(mirror_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, want_sha) = common::make_random_file(src_dir.path(), "backup.bin", 20_000);
+    let meta = build_file_metadata(&path).await.expect("metadata");
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Mirror Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+    client
+        .upload_file(
+            &target,
+            &prep.session_id,
+            &file_id,
+            &prep.files[&file_id],
+            &path,
+            None,
+        )
+        .await
+        .expect("upload");
+
+    let primary = save_dir.path().join("backup.bin");
+    assert_eq!(sha256_from_file(&primary).await.unwrap(), want_sha);
+
+    // The mirror copy is made in the background after the upload answers.
+    let mirrored = mirror_dir.path().join("backup.bin");
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    while std::fs::metadata(&mirrored).ok().map(|m| m.len()) != Some(20_000) {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "mirror copy never appeared"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert_eq!(sha256_from_file(&mirrored).await.unwrap(), want_sha);
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn history_entry_survives_a_receiver_that_never_shuts_down() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let history_path = save_dir.path().join("history.jsonl");
+    let (server, events) = LocalSendServer::builder()
+        .alias("Crashing Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .history_file(&history_path)
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, want_sha) = common::make_random_file(src_dir.path(), "last.bin", 4_096);
+    let mut meta = build_file_metadata(&path).await.expect("metadata");
+    meta.sha256 = Some(want_sha.clone());
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "History Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+    client
+        .upload_file(
+            &target,
+            &prep.session_id,
+            &file_id,
+            &prep.files[&file_id],
+            &path,
+            None,
+        )
+        .await
+        .expect("upload");
+
+    // A crash: no stop(), no destructors.
+    std::mem::forget(server);
+    std::mem::forget(events);
+
+    let entries = ReceiveHistory::new(&history_path)
+        .load()
+        .await
+        .expect("read history");
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].file_name, "last.bin");
+    assert_eq!(entries[0].size, 4_096);
+    assert_eq!(entries[0].sender_alias, "History Sender");
+    assert_eq!(entries[0].sha256.as_deref(), Some(want_sha.as_str()));
+    assert_eq!(entries[0].path, save_dir.path().join("last.bin"));
+}
+
+#[tokio::test]
+async fn request_timeout_does_not_cut_off_a_long_upload() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Patient Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, want_sha) = common::make_random_file(src_dir.path(), "slow.bin", 64 * 1024);
+    let meta = build_file_metadata(&path).await.expect("metadata");
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Slow Sender".to_string(),
+        0,
+        Protocol::Http,
+    ))
+    .with_config(
+        localsend_rs::ClientConfig::default()
+            .with_request_timeout(std::time::Duration::from_millis(200)),
+    );
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+
+    // About half a second at 128 KiB/s, well past the request timeout.
+    client
+        .upload_file_with_rate_limit(
+            &target,
+            &prep.session_id,
+            &file_id,
+            &prep.files[&file_id],
+            &path,
+            None,
+            Some(128 * 1024),
+        )
+        .await
+        .expect("the upload outlives the request timeout");
+    assert_eq!(
+        sha256_from_file(&save_dir.path().join("slow.bin"))
+            .await
+            .unwrap(),
+        want_sha
+    );
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn upload_bytes_sends_an_in_memory_buffer() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Bytes Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    // No preview, so the receiver opens a session instead of taking the
+    // message inline.
+    let body = b"a note that is uploaded, not previewed".to_vec();
+    let meta = localsend_rs::build_file_metadata_from_bytes(
+        localsend_rs::generate_file_id(),
+        "note.txt".to_string(),
+        "text/plain".to_string(),
+        body.clone(),
+    );
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Bytes Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+
+    client
+        .upload_bytes(
+            &target,
+            &prep.session_id,
+            &file_id,
+            &prep.files[&file_id],
+            body.clone(),
+        )
+        .await
+        .expect("upload");
+
+    assert_eq!(
+        std::fs::read(save_dir.path().join("note.txt")).unwrap(),
+        body
+    );
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn a_cut_off_upload_resumes_to_a_byte_identical_file() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Resuming Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .resumable_uploads(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, want_sha) = common::make_random_file(src_dir.path(), "big.bin", 200_000);
+    let mut meta = build_file_metadata(&path).await.expect("metadata");
+    meta.sha256 = Some(want_sha.clone());
+    let file_id = meta.id.clone();
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Resuming Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(port);
+    let prep = client
+        .prepare_upload(&target, HashMap::from([(file_id.clone(), meta)]), None)
+        .await
+        .expect("prepare")
+        .expect("a session");
+    let token = &prep.files[&file_id];
+
+    // The first attempt ends 50 000 bytes in.
+    let head = std::fs::read(&path).unwrap()[..50_000].to_vec();
+    let err = client
+        .upload_bytes(&target, &prep.session_id, &file_id, token, head)
+        .await
+        .expect_err("a short body is refused");
+    assert!(
+        matches!(
+            err,
+            localsend_rs::LocalSendError::HttpFailed { status: 500, .. }
+        ),
+        "{err}"
+    );
+    assert!(!save_dir.path().join("big.bin").exists());
+    assert_eq!(
+        client
+            .upload_status(&target, &prep.session_id, &file_id, token)
+            .await
+            .expect("status"),
+        50_000
+    );
+
+    client
+        .resume_file(&target, &prep.session_id, &file_id, token, &path, None)
+        .await
+        .expect("resume");
+
+    let saved = save_dir.path().join("big.bin");
+    assert_eq!(sha256_from_file(&saved).await.unwrap(), want_sha);
+    assert!(!save_dir.path().join("big.bin.lspart").exists());
+
+    server.stop().await;
+}
+
+#[tokio::test]
+async fn the_senders_modified_time_is_restored_when_asked() {
+    let save_dir = tempfile::tempdir().expect("save dir");
+    let src_dir = tempfile::tempdir().expect("src dir");
+    let (mut server, mut events) = LocalSendServer::builder()
+        .alias("Dated Receiver")
+        .port(0)
+        .save_dir(save_dir.path())
+        .protocol(Protocol::Http)
+        .auto_accept(true)
+        .preserve_modified_time(true)
+        .build()
+        .await
+        .expect("build");
+    let port = server.port();
+    common::wait_for_http_info(port).await;
+
+    let (path, _) = common::make_random_file(src_dir.path(), "old-photo.jpg", 4096);
+    let taken = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_500_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(taken)
+        .unwrap();
+
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Dated Sender".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    send_one(
+        &client,
+        &common::target_device(port),
+        &mut events,
+        &path,
+        false,
+    )
+    .await;
+
+    let received = std::fs::metadata(save_dir.path().join("old-photo.jpg")).unwrap();
+    assert_eq!(received.modified().unwrap(), taken);
+
+    server.stop().await;
+}
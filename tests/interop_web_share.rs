@@ -1,7 +1,7 @@
 mod common;
 
-use localsend_rs::Protocol;
 use localsend_rs::server::{LocalSendServer, ServerEvent, WebShareFile};
+use localsend_rs::{DeviceInfo, LocalSendClient, LocalSendError, Protocol};
 
 #[tokio::test(flavor = "multi_thread")]
 async fn approved_browser_session_downloads_exact_bytes() {
@@ -252,3 +252,103 @@ async fn browser_share_streams_disk_files_and_rejects_unknown_file_ids() {
     );
     server.stop().await;
 }
+
+#[tokio::test]
+async fn peer_can_list_the_shared_files() {
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Sharing sender")
+        .port(0)
+        .protocol(Protocol::Http)
+        .build()
+        .await
+        .expect("server starts");
+    common::wait_for_http_info(server.port()).await;
+
+    let client = LocalSendClient::new(DeviceInfo::new(
+        "Browsing peer".to_string(),
+        0,
+        Protocol::Http,
+    ));
+    let target = common::target_device(server.port());
+    assert!(matches!(
+        client.fetch_shared_files(&target, None).await,
+        Err(LocalSendError::Rejected { status: 403 })
+    ));
+
+    server
+        .start_web_share(
+            vec![
+                WebShareFile::inline("b.txt", b"second".to_vec()),
+                WebShareFile::inline("a.txt", b"first!".to_vec()),
+            ],
+            Some("1234".to_string()),
+            true,
+        )
+        .await
+        .expect("web share starts");
+
+    assert!(matches!(
+        client.fetch_shared_files(&target, None).await,
+        Err(LocalSendError::InvalidPin)
+    ));
+    let shared = client
+        .fetch_shared_files(&target, Some("1234"))
+        .await
+        .expect("list shared files");
+    let names: Vec<_> = shared.iter().map(|file| file.file_name.as_str()).collect();
+    assert_eq!(names, ["a.txt", "b.txt"]);
+    assert_eq!(shared[0].size, 6);
+
+    server.stop().await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn registered_shared_files_are_offered_under_their_ids() {
+    let dir = tempfile::tempdir().expect("share dir");
+    let path = dir.path().join("notes.md");
+    std::fs::write(&path, b"# shared from disk").expect("fixture");
+    let (mut server, _events) = LocalSendServer::builder()
+        .alias("Headless host")
+        .port(0)
+        .protocol(Protocol::Http)
+        .build()
+        .await
+        .expect("server starts");
+    common::wait_for_http_info(server.port()).await;
+
+    let id = localsend_rs::protocol::FileId::new();
+    server
+        .register_shared_files(std::collections::HashMap::from([(id.clone(), path)]))
+        .await
+        .expect("files registered");
+    assert!(server.device().download);
+
+    let client = reqwest::Client::new();
+    let base = format!("http://127.0.0.1:{}", server.port());
+    let body: serde_json::Value = client
+        .post(format!("{base}/api/localsend/v2/prepare-download"))
+        .send()
+        .await
+        .expect("prepare request")
+        .error_for_status()
+        .expect("approved without a prompt")
+        .json()
+        .await
+        .expect("prepare JSON");
+    assert_eq!(body["files"][id.as_str()]["fileName"], "notes.md");
+    let session_id = body["sessionId"].as_str().expect("session id");
+
+    let bytes = client
+        .get(format!(
+            "{base}/api/localsend/v2/download?sessionId={session_id}&fileId={id}"
+        ))
+        .send()
+        .await
+        .expect("download request")
+        .error_for_status()
+        .expect("download accepted")
+        .bytes()
+        .await
+        .expect("download bytes");
+    assert_eq!(bytes.as_ref(), b"# shared from disk");
+}